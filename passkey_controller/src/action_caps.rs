@@ -0,0 +1,72 @@
+use crate::*;
+
+/// Per-`ActionType` limits on what a single action may use. Unset limits don't apply.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ActionCaps {
+    pub max_gas: Option<Gas>,
+    pub max_deposit: Option<U128>, // yoctoNEAR, as counted by `attached_value`
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[near]
+impl PasskeyController {
+    /// Caps actions of `action_type`, e.g. Transfer at 10 NEAR, or disables them on this
+    /// deployment. `None` removes the caps. ChargeReverie actions are `record_spend` calls
+    /// and fall under the RecordSpend caps.
+    pub fn set_action_caps(&mut self, action_type: ActionType, caps: Option<ActionCaps>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set action caps"
+        );
+        assert!(
+            !matches!(action_type, ActionType::ChargeReverie { .. }),
+            "ChargeReverie actions are capped by the RecordSpend caps"
+        );
+        match caps {
+            Some(caps) => self.action_caps.insert(action_type, caps),
+            None => self.action_caps.remove(&action_type),
+        };
+    }
+
+    pub fn get_action_caps(&self, action_type: ActionType) -> Option<ActionCaps> {
+        self.action_caps.get(&action_type).cloned()
+    }
+
+    pub fn get_all_action_caps(&self) -> Vec<(ActionType, ActionCaps)> {
+        self.action_caps
+            .iter()
+            .map(|(action_type, caps)| (action_type.clone(), caps.clone()))
+            .collect()
+    }
+}
+
+impl PasskeyController {
+    // internal method rejecting actions of a disabled type or above their type's caps
+    pub(crate) fn assert_within_action_caps(&self, action: &SerializableAction) {
+        if let Some(error) = self.action_caps_error(action) {
+            panic!("{}", error);
+        }
+    }
+
+    // internal method returning why an action is disabled or above its type's caps, if it is
+    pub(crate) fn action_caps_error(&self, action: &SerializableAction) -> Option<&'static str> {
+        let action_type = match &action.action_type {
+            ActionType::ChargeReverie { .. } => &ActionType::RecordSpend,
+            action_type => action_type,
+        };
+        let caps = self.action_caps.get(action_type)?;
+        if caps.disabled {
+            return Some("ERR_ACTION_TYPE_DISABLED");
+        }
+        if caps.max_gas.is_some_and(|max_gas| self.delegated_action_gas(action) > max_gas) {
+            return Some("ERR_ACTION_GAS_CAP_EXCEEDED");
+        }
+        if caps.max_deposit.is_some_and(|max_deposit| action.attached_value() > max_deposit.0) {
+            return Some("ERR_ACTION_DEPOSIT_CAP_EXCEEDED");
+        }
+        None
+    }
+}
@@ -0,0 +1,70 @@
+use crate::*;
+
+#[near]
+impl PasskeyController {
+    /// Allows FunctionCall actions, delegated or direct, to call `method_names` on `receiver_id`,
+    /// adding to any methods already allowed for that receiver.
+    pub fn add_allowed_call(&mut self, receiver_id: AccountId, method_names: Vec<String>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage allowed calls"
+        );
+        assert!(!method_names.is_empty(), "method_names must not be empty");
+        let mut allowed = self.allowed_calls.get(&receiver_id).cloned().unwrap_or_default();
+        for method_name in method_names {
+            if !allowed.contains(&method_name) {
+                allowed.push(method_name);
+            }
+        }
+        self.allowed_calls.insert(receiver_id, allowed);
+    }
+
+    /// Removes `method_names` from a receiver's allowed calls, or the whole receiver if `None`.
+    pub fn remove_allowed_call(&mut self, receiver_id: AccountId, method_names: Option<Vec<String>>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage allowed calls"
+        );
+        match method_names {
+            Some(method_names) => {
+                if let Some(allowed) = self.allowed_calls.get_mut(&receiver_id) {
+                    allowed.retain(|m| !method_names.contains(m));
+                    if allowed.is_empty() {
+                        self.allowed_calls.remove(&receiver_id);
+                    }
+                }
+            }
+            None => {
+                self.allowed_calls.remove(&receiver_id);
+            }
+        }
+    }
+
+    pub fn get_allowed_calls(&self, receiver_id: AccountId) -> Vec<String> {
+        self.allowed_calls.get(&receiver_id).cloned().unwrap_or_default()
+    }
+
+    pub fn is_call_allowed(&self, receiver_id: AccountId, method_name: String) -> bool {
+        self.allowed_calls
+            .get(&receiver_id)
+            .map_or(false, |allowed| allowed.contains(&method_name))
+    }
+}
+
+impl PasskeyController {
+    // internal method checking a FunctionCall targets an allowed receiver and method, so neither
+    // a compromised relayer nor a direct caller can route the controller into arbitrary contracts
+    pub(crate) fn assert_call_allowed(&self, action: &SerializableAction) {
+        if !matches!(action.action_type, ActionType::FunctionCall) {
+            return;
+        }
+        let receiver_id = action
+            .receiver_id
+            .clone()
+            .unwrap_or_else(|| panic!("receiver_id is required for FunctionCall/Transfer"));
+        let method_name = action.method_name.clone().unwrap_or_default();
+        assert!(self.is_call_allowed(receiver_id, method_name), "ERR_CALL_NOT_ALLOWED");
+    }
+}
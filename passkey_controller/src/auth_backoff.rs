@@ -0,0 +1,115 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// Returned instead of a proposal, schedule or session id by calls rejected under auth backoff.
+pub const REJECTED_ID: U64 = U64(u64::MAX);
+
+/// Refuses calls from an account for `base_cooldown_blocks` once it has failed authorization
+/// `max_failures` times within `window_blocks`. Each further cooldown doubles.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthBackoffConfig {
+    pub max_failures: u32,
+    pub window_blocks: U64,
+    pub base_cooldown_blocks: U64,
+}
+
+/// Recent failed authorizations of one predecessor.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AuthFailures {
+    pub count: u32,           // failures in the current window
+    pub window_start: U64,    // block height
+    pub cooldowns: u32,       // cooldowns served since its last authorized call
+    pub cooldown_until: U64,  // block height
+}
+
+#[near]
+impl PasskeyController {
+    /// While set, relayer calls with an unregistered passkey are logged and counted instead of
+    /// failing, so a relayer forwarding them can be cooled down. Such calls do nothing and return
+    /// the request id they would have had, without a receipt, or `REJECTED_ID`. Calls from
+    /// anyone but the relayer still panic, so they can't grow storage.
+    pub fn set_auth_backoff(&mut self, config: Option<AuthBackoffConfig>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set auth backoff"
+        );
+        if let Some(config) = &config {
+            assert!(config.max_failures > 0, "Max failures must be greater than 0");
+            assert!(config.window_blocks.0 > 0, "Failure window must be greater than 0");
+        }
+        self.auth_backoff = config;
+    }
+
+    pub fn get_auth_backoff(&self) -> Option<AuthBackoffConfig> {
+        self.auth_backoff.clone()
+    }
+
+    pub fn get_auth_failures(&self, account_id: AccountId) -> Option<AuthFailures> {
+        self.auth_failures.get(&account_id).cloned()
+    }
+
+    /// Lifts an account's cooldown and forgets its failures.
+    pub fn clear_auth_failures(&mut self, account_id: AccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can clear auth failures"
+        );
+        self.auth_failures.remove(&account_id);
+    }
+}
+
+impl PasskeyController {
+    // internal method refusing calls from a predecessor serving a cooldown
+    pub(crate) fn assert_not_in_auth_cooldown(&self) {
+        let caller = env::predecessor_account_id();
+        if let Some(failures) = self.auth_failures.get(&caller) {
+            assert!(
+                env::block_height() >= failures.cooldown_until.0,
+                "ERR_AUTH_COOLDOWN: {} can't call until block {}",
+                caller,
+                failures.cooldown_until.0
+            );
+        }
+    }
+
+    // internal method counting an authorization failure of a relayer, cooling it down after
+    // `max_failures` of them within the window
+    pub(crate) fn record_auth_failure(&mut self, relayer_id: AccountId, error: &str) {
+        let Some(config) = self.auth_backoff.clone() else {
+            return;
+        };
+        let now = env::block_height();
+        let mut failures = self.auth_failures.get(&relayer_id).cloned().unwrap_or_default();
+        if now >= failures.window_start.0.saturating_add(config.window_blocks.0) {
+            failures.count = 0;
+            failures.window_start = U64(now);
+        }
+        failures.count += 1;
+        if failures.count >= config.max_failures {
+            let cooldown = config.base_cooldown_blocks.0.saturating_mul(1 << failures.cooldowns.min(32));
+            failures.cooldown_until = U64(now.saturating_add(cooldown));
+            failures.cooldowns += 1;
+            failures.count = 0;
+            failures.window_start = U64(now);
+        }
+        log!("Rejected call from {}: {}", relayer_id, error);
+        self.auth_failures.insert(relayer_id, failures);
+    }
+
+    // internal method forgetting the failures of a relayer whose call was authorized
+    pub(crate) fn clear_relayer_auth_failures(&mut self, relayer_id: &AccountId) {
+        if self.auth_failures.contains_key(relayer_id) {
+            self.auth_failures.remove(relayer_id);
+        }
+    }
+
+    // internal method returning the id a delegated action rejected under auth backoff would have had
+    pub(crate) fn rejected_request_id(&self, passkey_pk: &PublicKey, action: &SerializableAction) -> Base58CryptoHash {
+        let nonce = *self.passkey_nonces.get(passkey_pk).unwrap_or(&0) + 1;
+        Base58CryptoHash::from(receipts::compute_request_id(passkey_pk, nonce, action))
+    }
+}
@@ -0,0 +1,43 @@
+use crate::*;
+
+/// NEAR kept on the controller on top of its storage-staking requirement,
+/// so attached deposits can't drain it into a state where it can no longer write storage.
+pub const DEFAULT_BALANCE_RESERVE: u128 = NearToken::from_millinear(100).as_yoctonear(); // 0.1 NEAR
+
+#[near]
+impl PasskeyController {
+    pub fn set_balance_reserve(&mut self, reserve: U128) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set the balance reserve"
+        );
+        self.balance_reserve = reserve.0;
+    }
+
+    pub fn get_balance_reserve(&self) -> U128 {
+        U128(self.balance_reserve)
+    }
+
+    /// Balance actions can still attach: the account balance minus the storage-staking
+    /// requirement and the configured reserve.
+    pub fn get_spendable_controller_balance(&self) -> U128 {
+        let storage_cost = env::storage_byte_cost().as_yoctonear() * env::storage_usage() as u128;
+        U128(
+            env::account_balance()
+                .as_yoctonear()
+                .saturating_sub(storage_cost)
+                .saturating_sub(self.balance_reserve),
+        )
+    }
+}
+
+impl PasskeyController {
+    // internal method rejecting actions whose attached value would breach the reserve
+    pub(crate) fn assert_within_balance_reserve(&self, action: &SerializableAction) {
+        assert!(
+            action.attached_value() <= self.get_spendable_controller_balance().0,
+            "ERR_BALANCE_RESERVE_BREACHED"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use crate::*;
+use crate::cost_estimate::GAS_FOR_DELEGATED_DISPATCH;
+use crate::policy_hooks::{GAS_FOR_ON_POLICY_CHECKED, GAS_FOR_POLICY_CHECK};
+
+/// Max actions in one `execute_delegated_batch`, to stay well within the gas limit.
+pub const MAX_BATCH_ACTIONS: usize = 10;
+
+/// Where one action of a batch goes. `index` is its position in the batch and in the
+/// request ids `execute_delegated_batch` returns.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedAction {
+    pub index: u32,
+    pub action_type: ActionType,
+    pub target: AccountId,
+    pub gas: Gas, // attached to the action's function call, if any
+}
+
+#[near_sdk::near(serializers = [json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchExecutionPlan {
+    pub actions: Vec<PlannedAction>,
+    /// Gas the relayer should attach to `execute_delegated_batch`.
+    pub required_gas: Gas,
+    /// Hash of the actions and their planned targets and gas, passed back to `execute_delegated_batch`.
+    pub plan_hash: Base58CryptoHash,
+}
+
+#[near]
+impl PasskeyController {
+    /// Resolves where each action of a batch will be sent and the gas it gets, as execution
+    /// would right now. Panics like execution would on actions the controller refuses.
+    pub fn plan_batch(&self, actions: Vec<SerializableAction>) -> BatchExecutionPlan {
+        assert!(!actions.is_empty(), "Batch must contain at least one action");
+        assert!(actions.len() <= MAX_BATCH_ACTIONS, "Batch can contain at most {} actions", MAX_BATCH_ACTIONS);
+        let policy_check_gas = if self.policy_hook.is_some() {
+            GAS_FOR_POLICY_CHECK.saturating_add(GAS_FOR_ON_POLICY_CHECKED)
+        } else {
+            Gas::from_gas(0)
+        };
+        let mut required_gas = GAS_FOR_DELEGATED_DISPATCH;
+        let planned: Vec<PlannedAction> = actions
+            .iter()
+            .enumerate()
+            .map(|(index, action)| {
+                self.assert_call_allowed(action);
+                self.assert_within_action_caps(action);
+                let gas = self.delegated_action_gas(action);
+                required_gas = required_gas
+                    .saturating_add(gas)
+                    .saturating_add(policy_check_gas)
+                    .saturating_add(self.delegated_result_callback_gas());
+                PlannedAction {
+                    index: index as u32,
+                    action_type: action.action_type.clone(),
+                    target: self.delegated_target(action),
+                    gas,
+                }
+            })
+            .collect();
+        let bytes = near_sdk::borsh::to_vec(&(&actions, &planned))
+            .unwrap_or_else(|_| panic!("ERR_PAYLOAD_SERIALIZATION"));
+        BatchExecutionPlan {
+            actions: planned,
+            required_gas,
+            plan_hash: Base58CryptoHash::from(env::sha256_array(&bytes)),
+        }
+    }
+
+    /// Executes `actions` in order with consecutive nonces, after checking they still resolve to
+    /// the plan `plan_hash` came from. Returns the request ids, indexed like the plan.
+    pub fn execute_delegated_batch(
+        &mut self,
+        passkey_pk_used: PublicKey,
+        actions: Vec<SerializableAction>,
+        plan_hash: Base58CryptoHash,
+    ) -> Vec<Base58CryptoHash> {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk_used) {
+            return Vec::new();
+        }
+        assert_eq!(self.plan_batch(actions.clone()).plan_hash, plan_hash, "ERR_BATCH_PLAN_MISMATCH");
+        actions
+            .into_iter()
+            .map(|action| {
+                self.assert_single_passkey_can_execute(&action);
+                let nonce = self.next_passkey_nonce(&passkey_pk_used);
+                self.dispatch_delegated(passkey_pk_used.clone(), nonce, action)
+            })
+            .collect()
+    }
+}
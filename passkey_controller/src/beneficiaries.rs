@@ -0,0 +1,64 @@
+use crate::*;
+use crate::events::ControllerEvent;
+
+#[near]
+impl PasskeyController {
+    /// Allows delegated DeleteAccount actions to send the controller's balance to `beneficiary_id`.
+    /// The owner is always allowed.
+    pub fn add_allowed_beneficiary(&mut self, beneficiary_id: AccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage allowed beneficiaries"
+        );
+        if !self.allowed_beneficiaries.contains(&beneficiary_id) {
+            self.allowed_beneficiaries.push(beneficiary_id);
+        }
+    }
+
+    pub fn remove_allowed_beneficiary(&mut self, beneficiary_id: AccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage allowed beneficiaries"
+        );
+        self.allowed_beneficiaries.retain(|id| id != &beneficiary_id);
+    }
+
+    pub fn get_allowed_beneficiaries(&self) -> Vec<AccountId> {
+        self.allowed_beneficiaries.clone()
+    }
+
+    pub fn is_beneficiary_allowed(&self, beneficiary_id: AccountId) -> bool {
+        beneficiary_id == self.owner_id || self.allowed_beneficiaries.contains(&beneficiary_id)
+    }
+}
+
+impl PasskeyController {
+    // internal method refusing a delegated DeleteAccount to a beneficiary the owner hasn't allowed.
+    // Rejections emit BeneficiaryRejected rather than panicking so the attempt stays on-chain.
+    pub(crate) fn reject_disallowed_beneficiary(
+        &self,
+        request_id: Base58CryptoHash,
+        passkey_pk: &PublicKey,
+        action: &SerializableAction,
+    ) -> bool {
+        if !matches!(action.action_type, ActionType::DeleteAccount) {
+            return false;
+        }
+        let beneficiary_id = action
+            .beneficiary_id
+            .clone()
+            .unwrap_or_else(|| panic!("beneficiary_id is required for DeleteAccount"));
+        if self.is_beneficiary_allowed(beneficiary_id.clone()) {
+            return false;
+        }
+        ControllerEvent::BeneficiaryRejected {
+            request_id,
+            passkey_pk: passkey_pk.clone(),
+            beneficiary_id,
+        }
+        .emit();
+        true
+    }
+}
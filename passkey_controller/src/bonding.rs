@@ -0,0 +1,132 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// Bonding is disabled while `min_bond` is 0.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RelayerBondConfig {
+    pub min_bond: U128, // yoctoNEAR a relayer must keep bonded to execute delegated actions
+    pub unbonding_period_ns: U64,
+}
+
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RelayerBond {
+    pub bonded: U128,
+    pub unbonding: U128,
+    pub unbonding_available_at: U64,
+}
+
+#[near]
+impl PasskeyController {
+    pub fn set_relayer_bond_config(&mut self, config: RelayerBondConfig) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set relayer bond config"
+        );
+        self.relayer_bond_config = config;
+    }
+
+    pub fn get_relayer_bond_config(&self) -> RelayerBondConfig {
+        self.relayer_bond_config.clone()
+    }
+
+    pub fn get_relayer_bond(&self, relayer_id: AccountId) -> RelayerBond {
+        self.relayer_bonds.get(&relayer_id).cloned().unwrap_or_default()
+    }
+
+    /// Locks the attached deposit as the caller's relayer bond.
+    #[payable]
+    pub fn bond_relayer(&mut self) -> RelayerBond {
+        let relayer_id = env::predecessor_account_id();
+        let amount = env::attached_deposit().as_yoctonear();
+        assert!(amount > 0, "Bond amount must be greater than 0");
+
+        let mut bond = self.get_relayer_bond(relayer_id.clone());
+        bond.bonded = U128(bond.bonded.0 + amount);
+        self.relayer_bonds.insert(relayer_id.clone(), bond.clone());
+        log!("Relayer {} bonded {}. Total bonded: {}", relayer_id, amount, bond.bonded.0);
+        bond
+    }
+
+    /// Moves part of the caller's bond into unbonding. The unbonding period restarts
+    /// each time more funds are unbonded.
+    pub fn unbond_relayer(&mut self, amount: U128) -> RelayerBond {
+        let relayer_id = env::predecessor_account_id();
+        let mut bond = self.get_relayer_bond(relayer_id.clone());
+        assert!(amount.0 > 0, "Unbond amount must be greater than 0");
+        assert!(
+            bond.bonded.0 >= amount.0,
+            "Insufficient bond to unbond. Relayer {} has {}, requested {}",
+            relayer_id, bond.bonded.0, amount.0
+        );
+        bond.bonded = U128(bond.bonded.0 - amount.0);
+        bond.unbonding = U128(bond.unbonding.0 + amount.0);
+        bond.unbonding_available_at = U64(clock::block_timestamp() + self.relayer_bond_config.unbonding_period_ns.0);
+        self.relayer_bonds.insert(relayer_id, bond.clone());
+        bond
+    }
+
+    /// Withdraws the caller's unbonded funds once the unbonding period has passed.
+    pub fn withdraw_unbonded(&mut self) -> U128 {
+        let relayer_id = env::predecessor_account_id();
+        let mut bond = self.get_relayer_bond(relayer_id.clone());
+        assert!(bond.unbonding.0 > 0, "Nothing to withdraw");
+        assert!(
+            clock::block_timestamp() >= bond.unbonding_available_at.0,
+            "Unbonding period has not passed. Available at {}",
+            bond.unbonding_available_at.0
+        );
+        let amount = bond.unbonding.0;
+        bond.unbonding = U128(0);
+        if bond.bonded.0 == 0 {
+            self.relayer_bonds.remove(&relayer_id);
+        } else {
+            self.relayer_bonds.insert(relayer_id.clone(), bond);
+        }
+        Promise::new(relayer_id.clone()).transfer(NearToken::from_yoctonear(amount));
+        log!("Relayer {} withdrew {} unbonded", relayer_id, amount);
+        U128(amount)
+    }
+
+    /// Slashes a relayer's bond for misbehavior. Bonded funds are slashed before
+    /// funds that are still unbonding. Slashed funds are sent to the owner.
+    pub fn slash_relayer(&mut self, relayer_id: AccountId, amount: U128, reason: String) -> U128 {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can slash relayers"
+        );
+        let mut bond = self.get_relayer_bond(relayer_id.clone());
+        let from_bonded = amount.0.min(bond.bonded.0);
+        let from_unbonding = (amount.0 - from_bonded).min(bond.unbonding.0);
+        bond.bonded = U128(bond.bonded.0 - from_bonded);
+        bond.unbonding = U128(bond.unbonding.0 - from_unbonding);
+        let slashed = from_bonded + from_unbonding;
+        assert!(slashed > 0, "Relayer {} has no bond to slash", relayer_id);
+
+        if bond.bonded.0 == 0 && bond.unbonding.0 == 0 {
+            self.relayer_bonds.remove(&relayer_id);
+        } else {
+            self.relayer_bonds.insert(relayer_id.clone(), bond);
+        }
+        Promise::new(self.owner_id.clone()).transfer(NearToken::from_yoctonear(slashed));
+        log!("Relayer {} slashed {}. Reason: {}", relayer_id, slashed, reason);
+        U128(slashed)
+    }
+
+    // internal method requiring the relayer to hold the minimum bond
+    pub(crate) fn assert_relayer_bonded(&self, relayer_id: &AccountId) {
+        let min_bond = self.relayer_bond_config.min_bond.0;
+        if min_bond == 0 {
+            return;
+        }
+        let bonded = self.relayer_bonds.get(relayer_id).map(|b| b.bonded.0).unwrap_or(0);
+        assert!(
+            bonded >= min_bond,
+            "ERR_RELAYER_NOT_BONDED. Relayer {} has {} bonded, requires {}",
+            relayer_id, bonded, min_bond
+        );
+    }
+}
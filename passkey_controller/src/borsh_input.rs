@@ -0,0 +1,21 @@
+use crate::*;
+
+/// Arguments of `execute_delegated_actions_borsh`, Borsh-encoded into its `payload`.
+#[near_sdk::near(serializers = [borsh])]
+#[derive(Debug, Clone)]
+pub struct DelegatedActionPayload {
+    pub passkey_pk_used: PublicKey,
+    pub action_to_execute: SerializableAction,
+}
+
+#[near]
+impl PasskeyController {
+    /// Same as `execute_delegated_actions` with Borsh input, which costs far less gas to decode
+    /// than JSON for big payloads like DeployContract `code`. `payload` is a Borsh-encoded
+    /// `DelegatedActionPayload`.
+    pub fn execute_delegated_actions_borsh(&mut self, #[serializer(borsh)] payload: Vec<u8>) -> Base58CryptoHash {
+        let DelegatedActionPayload { passkey_pk_used, action_to_execute } =
+            near_sdk::borsh::from_slice(&payload).unwrap_or_else(|_| env::panic_str("ERR_INVALID_BORSH_PAYLOAD"));
+        self.execute_delegated_actions(passkey_pk_used, action_to_execute)
+    }
+}
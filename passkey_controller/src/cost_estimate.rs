@@ -0,0 +1,74 @@
+use crate::*;
+use crate::policy_hooks::{GAS_FOR_ON_POLICY_CHECKED, GAS_FOR_POLICY_CHECK};
+
+/// Gas `execute_delegated_actions` burns itself: checks, bookkeeping and scheduling the promises.
+pub const GAS_FOR_DELEGATED_DISPATCH: Gas = Gas::from_tgas(10);
+
+/// What a delegated action will cost, as computed at execution time.
+#[near_sdk::near(serializers = [json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostEstimate {
+    /// Gas the relayer should attach to `execute_delegated_actions`.
+    pub required_gas: Gas,
+    /// NEAR the action attaches, debited from the passkey's prepaid balance when prepaid accounting is enabled.
+    pub required_deposit: U128,
+    /// Fee the active relayer earns, debited from the prepaid balance on top of `required_deposit`.
+    pub relayer_fee: U128,
+}
+
+#[near]
+impl PasskeyController {
+    /// Prices a delegated action before it is submitted. Panics like execution would on
+    /// actions the controller refuses (disallowed calls, breaching the balance reserve).
+    pub fn estimate_action_cost(&self, action: SerializableAction) -> CostEstimate {
+        self.assert_call_allowed(&action);
+        self.assert_within_action_caps(&action);
+        self.assert_within_balance_reserve(&action);
+        let attached_value = action.attached_value();
+        let relayer_fee = if self.prepaid_accounting_enabled {
+            self.relayer_fee_configs
+                .get(&self.get_active_relayer())
+                .map(|config| config.fee_for(attached_value))
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        let policy_check_gas = if self.policy_hook.is_some() {
+            GAS_FOR_POLICY_CHECK.saturating_add(GAS_FOR_ON_POLICY_CHECKED)
+        } else {
+            Gas::from_gas(0)
+        };
+        CostEstimate {
+            required_gas: GAS_FOR_DELEGATED_DISPATCH
+                .saturating_add(policy_check_gas)
+                .saturating_add(self.delegated_action_gas(&action))
+                .saturating_add(self.delegated_result_callback_gas()),
+            required_deposit: U128(attached_value),
+            relayer_fee: U128(relayer_fee),
+        }
+    }
+}
+
+impl PasskeyController {
+    // internal method returning the gas `build_delegated_promise` attaches to the action's function call, if any
+    pub(crate) fn delegated_action_gas(&self, action: &SerializableAction) -> Gas {
+        match action.action_type {
+            ActionType::FunctionCall => action.gas.unwrap_or(Gas::from_gas(0)),
+            ActionType::CreateAccount if action.method_name.is_some() => action.gas.unwrap_or(GAS_FOR_NEW_ACCOUNT_INIT),
+            ActionType::AddKey | ActionType::DeleteKey if self.get_key_proxy_target(action).is_some() => {
+                action.gas.unwrap_or(GAS_FOR_KEY_PROXY_CALL)
+            }
+            ActionType::RecordSpend | ActionType::ChargeReverie { .. } | ActionType::ReverieDeposit => action.gas.unwrap_or(GAS_FOR_PAYMENTS_CALL),
+            ActionType::StakeWithPool | ActionType::UnstakeFromPool | ActionType::WithdrawFromPool => {
+                action.gas.unwrap_or(GAS_FOR_STAKING_POOL_CALL)
+            }
+            ActionType::CreateAccount
+            | ActionType::DeployContract
+            | ActionType::Transfer
+            | ActionType::Stake
+            | ActionType::AddKey
+            | ActionType::DeleteKey
+            | ActionType::DeleteAccount => Gas::from_gas(0),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crate::*;
+
+/// Gas for the optional init call of a CreateAccount action that doesn't set `gas`.
+pub const GAS_FOR_NEW_ACCOUNT_INIT: Gas = Gas::from_tgas(30);
+
+// Adds a CreateAccount action's steps to `promise`, which must target the new account:
+// create it, fund it, add its full access key, deploy `code` and call `method_name` to
+// initialize it. They share one receipt, so a failing step reverts the whole creation
+// instead of leaving a half-created account.
+pub(crate) fn create_account_actions(mut promise: Promise, action: &SerializableAction) -> Promise {
+    promise = promise.create_account();
+    if let Some(deposit) = action.initial_deposit_for_new_account.filter(|deposit| deposit.0 > 0) {
+        promise = promise.transfer(NearToken::from_yoctonear(deposit.0));
+    }
+    if let Some(pk) = action.public_key_for_new_account.clone() {
+        promise = promise.add_full_access_key(pk);
+    }
+    if let Some(code) = action.code.clone() {
+        promise = promise.deploy_contract(code.0);
+    }
+    if let Some(method_name) = action.method_name.clone() {
+        assert!(action.code.is_some(), "code is required for CreateAccount init call");
+        promise = promise.function_call(
+            method_name,
+            action.call_args(),
+            NearToken::from_yoctonear(action.deposit.unwrap_or(U128(0)).0),
+            action.gas.unwrap_or(GAS_FOR_NEW_ACCOUNT_INIT),
+        );
+    }
+    promise
+}
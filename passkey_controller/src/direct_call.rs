@@ -0,0 +1,55 @@
+use crate::*;
+
+#[near]
+impl PasskeyController {
+    /// When set, `execute_direct_actions` rejects calls made through another contract,
+    /// where the signer's passkey was not necessarily used to authorize this action.
+    pub fn set_require_direct_call(&mut self, require_direct_call: bool) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set require_direct_call"
+        );
+        self.require_direct_call = require_direct_call;
+    }
+
+    pub fn get_require_direct_call(&self) -> bool {
+        self.require_direct_call
+    }
+
+    /// Approves an audited proxy contract to call `execute_direct_actions_from`.
+    pub fn add_direct_action_proxy(&mut self, proxy_id: AccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage direct action proxies"
+        );
+        if !self.direct_action_proxies.contains(&proxy_id) {
+            self.direct_action_proxies.push(proxy_id);
+        }
+    }
+
+    pub fn remove_direct_action_proxy(&mut self, proxy_id: AccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage direct action proxies"
+        );
+        self.direct_action_proxies.retain(|id| id != &proxy_id);
+    }
+
+    pub fn get_direct_action_proxies(&self) -> Vec<AccountId> {
+        self.direct_action_proxies.clone()
+    }
+
+    /// Direct action relayed by an approved proxy on behalf of `account_id`, which must be the
+    /// transaction signer. Allowed even when `require_direct_call` is set.
+    pub fn execute_direct_actions_from(&mut self, account_id: AccountId, action_to_execute: SerializableAction) {
+        assert!(
+            self.direct_action_proxies.contains(&env::predecessor_account_id()),
+            "ERR_PROXY_NOT_APPROVED"
+        );
+        assert_eq!(env::signer_account_id(), account_id, "ERR_SIGNER_ACCOUNT_MISMATCH");
+        self.internal_execute_direct_actions(action_to_execute);
+    }
+}
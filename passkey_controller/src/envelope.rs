@@ -0,0 +1,115 @@
+use crate::*;
+use near_sdk::json_types::U64;
+use near_sdk::CurveType;
+
+/// A delegated action signed by the user's passkey.
+/// The relayer only forwards the envelope; any change to the action, nonce or
+/// expiry invalidates the signature.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone)]
+pub struct SignedActionEnvelope {
+    pub action: SerializableAction,
+    pub nonce: U64,
+    pub valid_until: U64, // block timestamp in nanoseconds
+    pub signature: Base64VecU8, // ed25519, or secp256k1 (r, s, v) over keccak256, of the canonical payload
+}
+
+/// Canonical payload the passkey signs: the Borsh serialization of this struct.
+/// Binding the controller account and passkey prevents replaying the same
+/// signature against another deployment or another registered key.
+#[near_sdk::near(serializers = [borsh])]
+#[derive(Debug, Clone)]
+pub struct ActionPayload {
+    pub controller_id: AccountId,
+    pub passkey_pk: PublicKey,
+    pub action: SerializableAction,
+    pub nonce: u64,
+    pub valid_until: u64,
+}
+
+impl ActionPayload {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        near_sdk::borsh::to_vec(self).unwrap_or_else(|_| panic!("ERR_PAYLOAD_SERIALIZATION"))
+    }
+}
+
+// Verifies a signature made by `passkey_pk` over `message`: a 64-byte ed25519 signature, or for
+// secp256k1 keys a 65-byte (r, s, v) signature over the keccak256 hash of `message`.
+pub(crate) fn verify_passkey_signature(passkey_pk: &PublicKey, message: &[u8], signature: &[u8]) -> bool {
+    match passkey_pk.curve_type() {
+        CurveType::ED25519 => {
+            let pk_bytes: [u8; 32] = passkey_pk.as_bytes()[1..]
+                .try_into()
+                .unwrap_or_else(|_| panic!("ERR_INVALID_PASSKEY_PK_LENGTH"));
+            let signature_bytes: [u8; 64] = signature
+                .try_into()
+                .unwrap_or_else(|_| panic!("ERR_INVALID_SIGNATURE_LENGTH"));
+            env::ed25519_verify(&signature_bytes, message, &pk_bytes)
+        }
+        CurveType::SECP256K1 => {
+            assert!(signature.len() == 65, "ERR_INVALID_SIGNATURE_LENGTH");
+            let recovered = env::ecrecover(&env::keccak256_array(message), &signature[..64], signature[64], true);
+            recovered.is_some_and(|pk_bytes| pk_bytes[..] == passkey_pk.as_bytes()[1..])
+        }
+    }
+}
+
+#[near]
+impl PasskeyController {
+    /// Returns the last nonce consumed by a signed envelope for this passkey.
+    /// The next envelope must use a strictly greater nonce.
+    pub fn get_passkey_nonce(&self, passkey_pk: PublicKey) -> U64 {
+        U64(*self.passkey_nonces.get(&passkey_pk).unwrap_or(&0))
+    }
+
+    /// Returns the exact bytes a passkey must sign for `execute_signed_envelope`.
+    pub fn get_envelope_payload(
+        &self,
+        passkey_pk: PublicKey,
+        action: SerializableAction,
+        nonce: U64,
+        valid_until: U64,
+    ) -> Base64VecU8 {
+        let payload = ActionPayload {
+            controller_id: env::current_account_id(),
+            passkey_pk,
+            action,
+            nonce: nonce.0,
+            valid_until: valid_until.0,
+        };
+        Base64VecU8(payload.to_bytes())
+    }
+
+    pub fn execute_signed_envelope(
+        &mut self,
+        passkey_pk: PublicKey,
+        envelope: SignedActionEnvelope,
+    ) -> Base58CryptoHash {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk) {
+            return self.rejected_request_id(&passkey_pk, &envelope.action);
+        }
+        assert!(
+            clock::block_timestamp() <= envelope.valid_until.0,
+            "ERR_ENVELOPE_EXPIRED"
+        );
+        let last_nonce = *self.passkey_nonces.get(&passkey_pk).unwrap_or(&0);
+        assert!(envelope.nonce.0 > last_nonce, "ERR_ENVELOPE_NONCE_USED");
+
+        let payload = ActionPayload {
+            controller_id: env::current_account_id(),
+            passkey_pk: passkey_pk.clone(),
+            action: envelope.action,
+            nonce: envelope.nonce.0,
+            valid_until: envelope.valid_until.0,
+        };
+        assert!(
+            verify_passkey_signature(&passkey_pk, &payload.to_bytes(), &envelope.signature.0),
+            "ERR_INVALID_ENVELOPE_SIGNATURE"
+        );
+
+        self.assert_single_passkey_can_execute(&payload.action);
+
+        self.passkey_nonces.insert(passkey_pk.clone(), envelope.nonce.0);
+        self.dispatch_delegated(passkey_pk, payload.nonce, payload.action)
+    }
+}
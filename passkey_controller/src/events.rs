@@ -0,0 +1,102 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+pub const EVENT_STANDARD: &str = "reveries_controller";
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// NEP-297 events emitted by the passkey controller.
+#[near_sdk::near(serializers = [json])]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControllerEvent {
+    RecoveryInitiated {
+        new_passkey_pk: PublicKey,
+        guardian_id: AccountId,
+    },
+    RecoveryApproved {
+        new_passkey_pk: PublicKey,
+        guardian_id: AccountId,
+        approvals: u32,
+    },
+    RecoveryCancelled {
+        new_passkey_pk: PublicKey,
+    },
+    RecoveryCompleted {
+        new_passkey_pk: PublicKey,
+    },
+    ActionScheduled {
+        scheduled_id: U64,
+        passkey_pk: PublicKey,
+        execute_after: U64,
+    },
+    ScheduledActionExecuted {
+        scheduled_id: U64,
+        executor_id: AccountId,
+    },
+    ScheduledActionCancelled {
+        scheduled_id: U64,
+    },
+    PasskeySelfRegistered {
+        passkey_pk: PublicKey,
+        registered_by: AccountId,
+    },
+    SelfRegisteredPasskeyApproved {
+        passkey_pk: PublicKey,
+        approved_by: AccountId,
+    },
+    SessionCreated {
+        session_id: U64,
+        passkey_pk: PublicKey,
+        expires_at: U64,
+    },
+    SessionRevoked {
+        session_id: U64,
+    },
+    DepositRefunded {
+        request_id: Base58CryptoHash,
+        passkey_pk: PublicKey,
+        amount: U128,
+    },
+    ActionRejectedByPolicy {
+        request_id: Base58CryptoHash,
+        passkey_pk: PublicKey,
+    },
+    RelayerFeeCharged {
+        relayer_id: AccountId,
+        passkey_pk: PublicKey,
+        amount: U128,
+    },
+    BeneficiaryRejected {
+        request_id: Base58CryptoHash,
+        passkey_pk: PublicKey,
+        beneficiary_id: AccountId,
+    },
+    ExecutionTagged {
+        request_id: Base58CryptoHash,
+        passkey_pk: PublicKey,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        client_id: Option<String>,
+    },
+    ExecutionResolved {
+        request_id: Base58CryptoHash,
+        succeeded: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        client_id: Option<String>,
+    },
+}
+
+impl ControllerEvent {
+    /// Logs the event as `EVENT_JSON`.
+    pub fn emit(&self) {
+        let mut log = near_sdk::serde_json::to_value(self)
+            .unwrap_or_else(|_| panic!("ERR_EVENT_SERIALIZATION"));
+        let fields = log.as_object_mut().unwrap_or_else(|| panic!("ERR_EVENT_SERIALIZATION"));
+        fields.insert("standard".to_string(), EVENT_STANDARD.into());
+        fields.insert("version".to_string(), EVENT_STANDARD_VERSION.into());
+        env::log_str(&format!("EVENT_JSON:{}", log));
+    }
+}
@@ -0,0 +1,51 @@
+use crate::*;
+use crate::events::ControllerEvent;
+use near_sdk::CryptoHash;
+
+/// Max length of an `ExecutionTag` memo or client id.
+pub const MAX_EXECUTION_TAG_LEN: usize = 256;
+
+/// Opaque attribution a relayer attaches to a delegated execution, e.g. the app that
+/// originated it. The controller never interprets it; it is only logged and kept on the receipt.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExecutionTag {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+}
+
+#[near]
+impl PasskeyController {
+    /// `execute_delegated_actions` with a memo and client id, included in the
+    /// `execution_tagged`/`execution_resolved` events and the execution receipt.
+    pub fn execute_delegated_actions_tagged(
+        &mut self,
+        passkey_pk_used: PublicKey,
+        action_to_execute: SerializableAction,
+        tag: ExecutionTag,
+    ) -> Base58CryptoHash {
+        for (field, value) in [("memo", &tag.memo), ("client_id", &tag.client_id)] {
+            assert!(
+                value.as_ref().map_or(true, |value| value.len() <= MAX_EXECUTION_TAG_LEN),
+                "{} must be at most {} characters",
+                field,
+                MAX_EXECUTION_TAG_LEN
+            );
+        }
+        let request_id = self.execute_delegated_actions(passkey_pk_used.clone(), action_to_execute);
+        let key: CryptoHash = request_id.into();
+        if let Some(receipt) = self.execution_receipts.get_mut(&key) {
+            receipt.tag = Some(tag.clone());
+        }
+        ControllerEvent::ExecutionTagged {
+            request_id,
+            passkey_pk: passkey_pk_used,
+            memo: tag.memo,
+            client_id: tag.client_id,
+        }
+        .emit();
+        request_id
+    }
+}
@@ -0,0 +1,102 @@
+use crate::*;
+use near_sdk::json_types::U64;
+use near_sdk::CryptoHash;
+
+/// A function-call access key the controller added to its own account through a delegated AddKey.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrantedKey {
+    pub public_key: PublicKey,
+    pub receiver_id: AccountId,
+    pub method_names: Vec<String>,
+    pub allowance: Option<U128>, // None for an unlimited allowance
+    pub created_at: U64,
+}
+
+#[near]
+impl PasskeyController {
+    /// Access keys the controller has granted on its own account, in grant order.
+    pub fn get_granted_keys(&self, from_index: u32, limit: u32) -> Vec<GrantedKey> {
+        self.granted_keys
+            .values()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_granted_key(&self, public_key: PublicKey) -> Option<GrantedKey> {
+        self.granted_keys.get(&public_key).cloned()
+    }
+
+    /// Deletes an access key the controller granted and drops it from the registry.
+    pub fn revoke_granted_key(&mut self, public_key: PublicKey) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can revoke granted keys"
+        );
+        self.granted_keys
+            .remove(&public_key)
+            .unwrap_or_else(|| panic!("Key was not granted by the controller"));
+        log!("Revoking granted key {}", String::from(&public_key));
+        Promise::new(env::current_account_id()).delete_key(public_key);
+    }
+}
+
+/// Registry update of a delegated AddKey/DeleteKey on the controller's own account,
+/// applied once the action succeeds.
+#[near_sdk::near(serializers = [borsh])]
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingKeyChange {
+    Add(GrantedKey),
+    Delete(PublicKey),
+}
+
+impl PasskeyController {
+    // internal method recording the registry update a delegated AddKey/DeleteKey on the
+    // controller's own account makes, to be applied by `settle_key_change` on its result
+    pub(crate) fn reserve_key_change(&mut self, request_id: CryptoHash, action: &SerializableAction) {
+        if self.get_key_proxy_target(action).is_some() {
+            return;
+        }
+        let change = match action.action_type {
+            ActionType::AddKey => {
+                let public_key = action.public_key.clone().unwrap_or_else(|| panic!("public_key is required for AddKey"));
+                PendingKeyChange::Add(GrantedKey {
+                    public_key,
+                    receiver_id: action
+                        .receiver_id
+                        .clone()
+                        .unwrap_or_else(|| panic!("receiver_id for allowance scope is required for AddKey")),
+                    method_names: action.method_names.clone().unwrap_or_default(),
+                    allowance: action.allowance.filter(|allowance| allowance.0 > 0),
+                    created_at: U64(0),
+                })
+            }
+            ActionType::DeleteKey => match &action.public_key {
+                Some(public_key) => PendingKeyChange::Delete(public_key.clone()),
+                None => return,
+            },
+            _ => return,
+        };
+        self.pending_key_changes.insert(request_id, change);
+    }
+
+    // internal method applying a delegated AddKey/DeleteKey to the registry once it succeeded;
+    // a failed action leaves the registry untouched
+    pub(crate) fn settle_key_change(&mut self, change: PendingKeyChange, succeeded: bool) {
+        if !succeeded {
+            return;
+        }
+        match change {
+            PendingKeyChange::Add(mut granted_key) => {
+                granted_key.created_at = U64(clock::block_timestamp());
+                self.granted_keys.insert(granted_key.public_key.clone(), granted_key);
+            }
+            PendingKeyChange::Delete(public_key) => {
+                self.granted_keys.remove(&public_key);
+            }
+        }
+    }
+}
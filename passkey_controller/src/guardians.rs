@@ -0,0 +1,137 @@
+use crate::*;
+use crate::events::ControllerEvent;
+use near_sdk::json_types::U64;
+
+/// `threshold` of the `guardians` can add a new passkey once `recovery_delay_ns`
+/// has passed since the threshold was reached, unless the owner cancels first.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuardianConfig {
+    pub guardians: Vec<AccountId>,
+    pub threshold: u32,
+    pub recovery_delay_ns: U64,
+}
+
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingRecovery {
+    pub approvals: Vec<AccountId>,
+    pub initiated_at: U64,
+    pub executable_at: Option<U64>, // set once the approval threshold is reached
+}
+
+#[near]
+impl PasskeyController {
+    pub fn set_guardians(&mut self, config: Option<GuardianConfig>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set guardians"
+        );
+        if let Some(config) = config.as_ref() {
+            assert!(config.threshold > 0, "Guardian threshold must be greater than 0");
+            assert!(
+                config.threshold as usize <= config.guardians.len(),
+                "Guardian threshold exceeds number of guardians"
+            );
+        }
+        self.guardian_config = config;
+    }
+
+    pub fn get_guardians(&self) -> Option<GuardianConfig> {
+        self.guardian_config.clone()
+    }
+
+    pub fn get_pending_recovery(&self, new_passkey_pk: PublicKey) -> Option<PendingRecovery> {
+        self.pending_recoveries.get(&new_passkey_pk).cloned()
+    }
+
+    /// Called by a guardian to start, or approve, recovery onto `new_passkey_pk`.
+    pub fn recover_add_passkey(&mut self, new_passkey_pk: PublicKey) -> PendingRecovery {
+        passkey_keys::assert_valid_passkey_pk(&new_passkey_pk);
+        let config = self
+            .guardian_config
+            .clone()
+            .unwrap_or_else(|| panic!("Guardians are not configured"));
+        let guardian_id = env::predecessor_account_id();
+        assert!(config.guardians.contains(&guardian_id), "Only guardians can approve recovery");
+
+        let now = clock::block_timestamp();
+        let mut recovery = match self.pending_recoveries.get(&new_passkey_pk) {
+            Some(recovery) => recovery.clone(),
+            None => {
+                ControllerEvent::RecoveryInitiated {
+                    new_passkey_pk: new_passkey_pk.clone(),
+                    guardian_id: guardian_id.clone(),
+                }
+                .emit();
+                PendingRecovery { approvals: vec![], initiated_at: U64(now), executable_at: None }
+            }
+        };
+        assert!(!recovery.approvals.contains(&guardian_id), "Guardian has already approved this recovery");
+        recovery.approvals.push(guardian_id.clone());
+
+        let approvals = self.count_guardian_approvals(&config, &recovery);
+        if recovery.executable_at.is_none() && approvals >= config.threshold {
+            recovery.executable_at = Some(U64(now + config.recovery_delay_ns.0));
+        }
+        ControllerEvent::RecoveryApproved {
+            new_passkey_pk: new_passkey_pk.clone(),
+            guardian_id,
+            approvals,
+        }
+        .emit();
+        self.pending_recoveries.insert(new_passkey_pk, recovery.clone());
+        recovery
+    }
+
+    /// Registers the recovered passkey once the threshold was met and the delay has passed.
+    /// Callable by anyone.
+    pub fn finalize_recovery(&mut self, new_passkey_pk: PublicKey) {
+        let config = self
+            .guardian_config
+            .clone()
+            .unwrap_or_else(|| panic!("Guardians are not configured"));
+        let recovery = self
+            .pending_recoveries
+            .get(&new_passkey_pk)
+            .cloned()
+            .unwrap_or_else(|| panic!("No pending recovery for this passkey"));
+        let executable_at = recovery
+            .executable_at
+            .unwrap_or_else(|| panic!("Recovery has not reached the guardian threshold"));
+        assert!(clock::block_timestamp() >= executable_at.0, "Recovery delay has not passed");
+        // Guardians removed since approving no longer count.
+        assert!(
+            self.count_guardian_approvals(&config, &recovery) >= config.threshold,
+            "Recovery has not reached the guardian threshold"
+        );
+
+        self.assert_can_add_passkey(&new_passkey_pk);
+        self.pending_recoveries.remove(&new_passkey_pk);
+        self.registered_passkey_pks.insert(new_passkey_pk.clone());
+        ControllerEvent::RecoveryCompleted { new_passkey_pk }.emit();
+    }
+
+    /// Lets the owner cancel a recovery they didn't ask for.
+    pub fn cancel_recovery(&mut self, new_passkey_pk: PublicKey) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can cancel recovery"
+        );
+        assert!(
+            self.pending_recoveries.remove(&new_passkey_pk).is_some(),
+            "No pending recovery for this passkey"
+        );
+        ControllerEvent::RecoveryCancelled { new_passkey_pk }.emit();
+    }
+
+    fn count_guardian_approvals(&self, config: &GuardianConfig, recovery: &PendingRecovery) -> u32 {
+        recovery
+            .approvals
+            .iter()
+            .filter(|guardian_id| config.guardians.contains(guardian_id))
+            .count() as u32
+    }
+}
@@ -0,0 +1,95 @@
+use crate::*;
+use crate::action_caps::ActionCaps;
+use crate::auth_backoff::AuthBackoffConfig;
+use crate::relayer_failover::RelayerFailoverConfig;
+use crate::relayer_fees::{RelayerFeeConfig, MAX_RELAYER_FEE_BPS};
+
+/// How many registered passkeys `get_config` lists. The rest can be paged through with
+/// `get_passkey_pks`; `get_limits` has the total.
+pub const CONFIG_PASSKEY_LIMIT: u32 = 200;
+
+/// Everything a deployment is initialized with, so it can be reproduced from one JSON
+/// document. Only the owner and the trusted relayer are required; `get_config` returns
+/// the current values in the same shape, listing at most `CONFIG_PASSKEY_LIMIT` passkeys.
+#[near_sdk::near(serializers = [json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitConfig {
+    pub owner_id: AccountId,
+    pub trusted_relayer_account_id: AccountId,
+    #[serde(default)]
+    pub passkey_pks: Vec<PublicKey>,
+    #[serde(default)]
+    pub relayer_failover: Option<RelayerFailoverConfig>,
+    /// Fees of the trusted and backup relayers
+    #[serde(default)]
+    pub relayer_fees: Vec<(AccountId, RelayerFeeConfig)>,
+    #[serde(default)]
+    pub prepaid_accounting_enabled: bool,
+    #[serde(default)]
+    pub require_direct_call: bool,
+    #[serde(default = "default_balance_reserve")]
+    pub balance_reserve: U128,
+    #[serde(default)]
+    pub payments_contract_id: Option<AccountId>,
+    #[serde(default)]
+    pub action_caps: Vec<(ActionType, ActionCaps)>,
+    #[serde(default)]
+    pub default_passkey_quota: Option<u32>,
+    #[serde(default)]
+    pub auth_backoff: Option<AuthBackoffConfig>,
+}
+
+fn default_balance_reserve() -> U128 {
+    U128(balance_reserve::DEFAULT_BALANCE_RESERVE)
+}
+
+#[near]
+impl PasskeyController {
+    #[init]
+    pub fn new_with_config(config: InitConfig) -> Self {
+        let mut contract = Self::new(config.trusted_relayer_account_id, config.owner_id, Some(config.passkey_pks));
+        for (relayer_id, fee_config) in config.relayer_fees {
+            assert!(fee_config.fee_bps <= MAX_RELAYER_FEE_BPS, "fee_bps must be at most {}", MAX_RELAYER_FEE_BPS);
+            contract.relayer_fee_configs.insert(relayer_id, fee_config);
+        }
+        for (action_type, caps) in config.action_caps {
+            contract.action_caps.insert(action_type, caps);
+        }
+        if config.relayer_failover.is_some() {
+            contract.relayer_last_seen.insert(contract.trusted_relayer_account_id.clone(), env::block_height());
+        }
+        contract.relayer_failover = config.relayer_failover;
+        contract.prepaid_accounting_enabled = config.prepaid_accounting_enabled;
+        contract.require_direct_call = config.require_direct_call;
+        contract.balance_reserve = config.balance_reserve.0;
+        contract.payments_contract_id = config.payments_contract_id;
+        contract.default_passkey_quota = config.default_passkey_quota;
+        contract.auth_backoff = config.auth_backoff;
+        contract
+    }
+
+    pub fn get_config(&self) -> InitConfig {
+        let mut relayers = vec![self.trusted_relayer_account_id.clone()];
+        relayers.extend(self.relayer_failover.as_ref().map(|failover| failover.backup_relayer.clone()));
+        InitConfig {
+            owner_id: self.owner_id.clone(),
+            trusted_relayer_account_id: self.trusted_relayer_account_id.clone(),
+            passkey_pks: self.get_passkey_pks(0, CONFIG_PASSKEY_LIMIT),
+            relayer_failover: self.relayer_failover.clone(),
+            relayer_fees: relayers
+                .into_iter()
+                .filter_map(|relayer_id| {
+                    let fee_config = self.relayer_fee_configs.get(&relayer_id).cloned()?;
+                    Some((relayer_id, fee_config))
+                })
+                .collect(),
+            prepaid_accounting_enabled: self.prepaid_accounting_enabled,
+            require_direct_call: self.require_direct_call,
+            balance_reserve: U128(self.balance_reserve),
+            payments_contract_id: self.payments_contract_id.clone(),
+            action_caps: self.get_all_action_caps(),
+            default_passkey_quota: self.default_passkey_quota,
+            auth_backoff: self.auth_backoff.clone(),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+pub const DEFAULT_JOB_TTL_NS: u64 = 24 * 60 * 60 * 1_000_000_000; // 24 hours
+pub const MAX_JOB_ID_LEN: usize = 64;
+
+/// Delegated execution submitted under a client-generated job id.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobRecord {
+    pub request_id: Base58CryptoHash,
+    pub submitted_at: U64,
+}
+
+#[near]
+impl PasskeyController {
+    /// Idempotent `execute_delegated_actions` for relayer retries. The first submission of a
+    /// `job_id` executes the action; repeats within the job TTL are no-ops returning its request id,
+    /// whose receipt is available from `get_execution_receipt`.
+    pub fn execute_delegated_job(
+        &mut self,
+        job_id: String,
+        passkey_pk_used: PublicKey,
+        action_to_execute: SerializableAction,
+    ) -> Base58CryptoHash {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk_used) {
+            return self.rejected_request_id(&passkey_pk_used, &action_to_execute);
+        }
+        assert!(!job_id.is_empty() && job_id.len() <= MAX_JOB_ID_LEN, "ERR_INVALID_JOB_ID");
+        if let Some(job) = self.get_job(job_id.clone()) {
+            log!("Job {} was already submitted as {:?}", job_id, job.request_id);
+            return job.request_id;
+        }
+        self.assert_single_passkey_can_execute(&action_to_execute);
+
+        let nonce = self.next_passkey_nonce(&passkey_pk_used);
+        let request_id = self.dispatch_delegated(passkey_pk_used, nonce, action_to_execute);
+        self.jobs.insert(job_id, JobRecord { request_id, submitted_at: U64(clock::block_timestamp()) });
+        request_id
+    }
+
+    /// The job's record, unless it has outlived the job TTL.
+    pub fn get_job(&self, job_id: String) -> Option<JobRecord> {
+        self.jobs
+            .get(&job_id)
+            .filter(|job| !self.is_job_expired(job))
+            .cloned()
+    }
+
+    pub fn set_job_ttl(&mut self, ttl_ns: U64) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set the job TTL"
+        );
+        self.job_ttl_ns = ttl_ns.0;
+    }
+
+    pub fn get_job_ttl(&self) -> U64 {
+        U64(self.job_ttl_ns)
+    }
+
+    /// Removes up to `limit` expired jobs. Callable by anyone to free storage.
+    pub fn prune_expired_jobs(&mut self, limit: u32) -> u32 {
+        let expired: Vec<String> = self
+            .jobs
+            .iter()
+            .filter(|(_, job)| self.is_job_expired(job))
+            .take(limit as usize)
+            .map(|(job_id, _)| job_id.clone())
+            .collect();
+        for job_id in expired.iter() {
+            self.jobs.remove(job_id);
+        }
+        expired.len() as u32
+    }
+}
+
+impl PasskeyController {
+    fn is_job_expired(&self, job: &JobRecord) -> bool {
+        clock::block_timestamp() > job.submitted_at.0.saturating_add(self.job_ttl_ns)
+    }
+}
@@ -1,70 +1,66 @@
+pub mod action_caps;
+pub mod allowed_calls;
+pub mod auth_backoff;
+pub mod balance_reserve;
+pub mod batch_plans;
+pub mod beneficiaries;
+pub mod bonding;
+pub mod borsh_input;
+pub mod cost_estimate;
+pub mod create_account;
+pub mod direct_call;
+pub mod envelope;
+pub mod events;
+pub mod execution_tags;
+pub mod granted_keys;
+pub mod guardians;
+pub mod init_config;
+pub mod jobs;
+pub mod limits;
+pub mod managed_accounts;
+pub mod migrate;
+pub mod multisig;
+pub mod passkey_expiry;
+pub mod passkey_keys;
+pub mod passkey_metadata;
+pub mod payments_integration;
+pub mod policy_hooks;
+pub mod prepaid;
+pub mod quotas;
+pub mod receipts;
+pub mod relayer_failover;
+pub mod relayer_fees;
+pub mod result_callbacks;
+pub mod scheduler;
+pub mod self_registration;
+pub mod sessions;
+pub mod schema;
+pub mod security_config;
+pub mod staking_pools;
+pub mod templates;
+pub mod top_up;
+pub mod versioned;
+pub mod upgrade;
+pub mod validation;
+pub mod webauthn;
 #[cfg(test)]
 mod tests_passkey_controller;
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::{
     env, near, log,
-    AccountId, PanicOnDefault, PublicKey, Allowance,
+    AccountId, PanicOnDefault, PublicKey,
     Promise, Gas, NearToken,
 };
-use near_sdk::json_types::{U128, Base64VecU8};
+use near_sdk::json_types::{U128, Base64VecU8, Base58CryptoHash};
 use near_sdk::serde::{Deserialize, Serialize};
-use near_sdk::store::IterableSet;
-use std::num::NonZeroU128;
+use near_sdk::store::{IterableMap, IterableSet, LookupMap};
+use payments_integration::GAS_FOR_PAYMENTS_CALL;
+use managed_accounts::GAS_FOR_KEY_PROXY_CALL;
+use staking_pools::{GAS_FOR_ON_POOL_ACTION_RESULT, GAS_FOR_STAKING_POOL_CALL};
+use create_account::GAS_FOR_NEW_ACCOUNT_INIT;
 
-#[near_sdk::near(serializers = [borsh, json])]
-#[derive(Debug, Clone)]
-pub enum ActionType {
-    CreateAccount,
-    DeployContract,
-    FunctionCall,
-    Transfer,
-    Stake,
-    AddKey,
-    DeleteKey,
-    DeleteAccount,
-}
-
-// #[derive(JsonSchema, BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
-// #[serde(crate = "near_sdk::serde")]
-#[near_sdk::near(serializers = [borsh, json])]
-#[derive(Debug, Clone)]
-pub struct SerializableAction {
-    pub action_type: ActionType,
-    // Specific fields for each action type, now optional
-    pub receiver_id: Option<AccountId>,
-    pub method_name: Option<String>,
-    pub args: Option<Base64VecU8>, // JSON string of args, base64 encoded
-    pub deposit: Option<U128>, // yoctoNEAR
-    pub gas: Option<Gas>,
-    // For Transfer
-    pub amount: Option<U128>, // yoctoNEAR
-    // For AddKey/DeleteKey
-    pub public_key: Option<PublicKey>,
-    // For AddKey (FunctionCallAccessKey)
-    pub allowance: Option<U128>, // yoctoNEAR
-    pub method_names: Option<Vec<String>>,
-    // For DeployContract
-    pub code: Option<Base64VecU8>,
-    // For Stake
-    pub stake: Option<U128>, // yoctoNEAR
-    // For DeleteAccount
-    pub beneficiary_id: Option<AccountId>,
-    // For CreateAccount
-    pub initial_deposit_for_new_account: Option<U128>, // yoctoNEAR
-    pub public_key_for_new_account: Option<PublicKey>,
-}
-
-impl SerializableAction {
-    pub fn get_action_allowance(&self) -> Allowance {
-        match self.allowance {
-            Some(allowance_amount) if allowance_amount.0 > 0 => {
-                Allowance::Limited(NonZeroU128::new(allowance_amount.0).unwrap_or_else(|| panic!("Allowance must be non-zero if limited")))
-            }
-            _ => Allowance::Unlimited, // Default to Unlimited if None or 0.
-        }
-    }
-}
+pub use reveries_types::{clock, ActionType, SerializableAction};
 
 #[near(contract_state)]
 #[derive(PanicOnDefault)]
@@ -72,6 +68,60 @@ pub struct PasskeyController {
     trusted_relayer_account_id: AccountId,
     owner_id: AccountId,
     registered_passkey_pks: IterableSet<PublicKey>,
+    passkey_nonces: LookupMap<PublicKey, u64>,
+    multisig_config: Option<multisig::MultisigConfig>,
+    action_proposals: LookupMap<u64, multisig::ActionProposal>,
+    next_proposal_id: u64,
+    relayer_bond_config: bonding::RelayerBondConfig,
+    relayer_bonds: LookupMap<AccountId, bonding::RelayerBond>,
+    execution_receipts: LookupMap<near_sdk::CryptoHash, receipts::ExecutionReceipt>,
+    payments_contract_id: Option<AccountId>,
+    webauthn_challenges: LookupMap<near_sdk::CryptoHash, webauthn::IssuedChallenge>,
+    challenge_counter: u64,
+    challenge_ttl_ns: u64,
+    guardian_config: Option<guardians::GuardianConfig>,
+    pending_recoveries: LookupMap<PublicKey, guardians::PendingRecovery>,
+    scheduled_actions: LookupMap<u64, scheduler::ScheduledAction>,
+    next_scheduled_id: u64,
+    passkey_metadata: LookupMap<PublicKey, passkey_metadata::PasskeyMetadata>,
+    prepaid_accounting_enabled: bool,
+    prepaid_balances: LookupMap<PublicKey, u128>,
+    auto_top_ups: LookupMap<PublicKey, top_up::AutoTopUpConfig>,
+    require_direct_call: bool,
+    direct_action_proxies: Vec<AccountId>,
+    staking_pools: Vec<AccountId>,
+    allowed_calls: LookupMap<AccountId, Vec<String>>,
+    call_templates: LookupMap<String, templates::CallTemplate>,
+    self_registration: Option<self_registration::SelfRegistrationConfig>,
+    passkey_expirations: IterableMap<PublicKey, u64>,
+    managed_accounts: Vec<AccountId>,
+    sessions: IterableMap<u64, sessions::Session>,
+    next_session_id: u64,
+    balance_reserve: u128,
+    policy_hook: Option<policy_hooks::PolicyHookConfig>,
+    jobs: IterableMap<String, jobs::JobRecord>,
+    job_ttl_ns: u64,
+    relayer_fee_configs: LookupMap<AccountId, relayer_fees::RelayerFeeConfig>,
+    relayer_fee_balances: LookupMap<AccountId, u128>,
+    allowed_beneficiaries: Vec<AccountId>,
+    granted_keys: IterableMap<PublicKey, granted_keys::GrantedKey>,
+    result_callbacks: LookupMap<near_sdk::CryptoHash, result_callbacks::ResultCallback>,
+    action_caps: IterableMap<ActionType, action_caps::ActionCaps>,
+    relayer_failover: Option<relayer_failover::RelayerFailoverConfig>,
+    relayer_last_seen: LookupMap<AccountId, u64>,
+    passkey_quotas: LookupMap<PublicKey, u32>,
+    default_passkey_quota: Option<u32>,
+    passkey_quota_usage: LookupMap<PublicKey, quotas::QuotaUsage>,
+    auth_backoff: Option<auth_backoff::AuthBackoffConfig>,
+    auth_failures: LookupMap<AccountId, auth_backoff::AuthFailures>,
+    max_passkeys: Option<u32>,
+    max_active_sessions: Option<u32>,
+    pending_self_registrations: LookupMap<PublicKey, AccountId>,
+    managed_account_passkeys: LookupMap<AccountId, PublicKey>,
+    staked_principal: LookupMap<(PublicKey, AccountId), staking_pools::StakedPrincipal>,
+    pending_pool_actions: LookupMap<near_sdk::CryptoHash, staking_pools::PendingPoolAction>,
+    queued_scheduled_ids: IterableSet<u64>,
+    pending_key_changes: LookupMap<near_sdk::CryptoHash, granted_keys::PendingKeyChange>,
 }
 
 #[near]
@@ -86,14 +136,11 @@ impl PasskeyController {
         let mut pk_set = IterableSet::new(b"p");
         if let Some(keys) = initial_passkey_pks {
             for key in keys {
+                passkey_keys::assert_valid_passkey_pk(&key);
                 pk_set.insert(key);
             }
         }
-        Self {
-            trusted_relayer_account_id,
-            owner_id,
-            registered_passkey_pks: pk_set,
-        }
+        Self::with_passkeys(trusted_relayer_account_id, owner_id, pk_set)
     }
 
     pub fn set_trusted_relayer(&mut self, account_id: AccountId) {
@@ -103,6 +150,7 @@ impl PasskeyController {
             "Only owner can set trusted relayer"
         );
         self.trusted_relayer_account_id = account_id;
+        self.reset_relayer_heartbeat();
     }
 
     pub fn get_trusted_relayer(&self) -> AccountId {
@@ -119,6 +167,8 @@ impl PasskeyController {
             self.trusted_relayer_account_id,
             "Only trusted relayer can add passkey PKs"
         );
+        passkey_keys::assert_valid_passkey_pk(&passkey_pk);
+        self.assert_can_add_passkey(&passkey_pk);
         self.registered_passkey_pks.insert(passkey_pk)
     }
 
@@ -128,6 +178,10 @@ impl PasskeyController {
             self.trusted_relayer_account_id,
             "Only trusted relayer can remove passkey PKs"
         );
+        self.passkey_metadata.remove(&passkey_pk);
+        self.passkey_expirations.remove(&passkey_pk);
+        self.passkey_quotas.remove(&passkey_pk);
+        self.passkey_quota_usage.remove(&passkey_pk);
         self.registered_passkey_pks.remove(&passkey_pk)
     }
 
@@ -135,15 +189,45 @@ impl PasskeyController {
         self.registered_passkey_pks.contains(&passkey_pk)
     }
 
+    pub fn get_passkey_pks(&self, from_index: u32, limit: u32) -> Vec<PublicKey> {
+        self.registered_passkey_pks
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect()
+    }
+
     pub fn execute_direct_actions(
         &mut self,
         action_to_execute: SerializableAction,
+    ) {
+        if self.require_direct_call {
+            assert_eq!(
+                env::predecessor_account_id(),
+                env::signer_account_id(),
+                "ERR_DIRECT_ACTIONS_REQUIRE_DIRECT_CALL"
+            );
+        }
+        self.internal_execute_direct_actions(action_to_execute);
+    }
+
+    // internal method executing a direct action for the transaction signer's passkey.
+    // Callers decide whether the signer can be trusted (see `execute_direct_actions_from`).
+    fn internal_execute_direct_actions(
+        &mut self,
+        action_to_execute: SerializableAction,
     ) {
         let signer_pk = env::signer_account_pk();
         assert!(
             self.registered_passkey_pks.contains(&signer_pk),
             "ERR_SIGNER_PK_NOT_REGISTERED_AS_PASSKEY"
         );
+        self.assert_passkey_not_expired(&signer_pk);
+        self.consume_passkey_quota(&signer_pk);
+        self.assert_call_allowed(&action_to_execute);
+        self.assert_within_action_caps(&action_to_execute);
+        self.assert_within_balance_reserve(&action_to_execute);
 
         let signer_account_id = env::signer_account_id(); // This is Derp's account
         log!(
@@ -164,10 +248,11 @@ impl PasskeyController {
                 })
             }
             ActionType::CreateAccount => {
-                // For CreateAccount, the promise is typically initiated by an existing account (the signer).
                 // The `receiver_id` in `action_data` specifies the new account_id to be created.
-                // The initial promise should be targeted at the signer_account_id, which will then create the new account.
-                signer_account_id.clone()
+                // All of its actions apply to the new account, so the promise targets it.
+                action_data.receiver_id.clone().unwrap_or_else(|| {
+                    panic!("receiver_id (new account_id) is required for CreateAccount")
+                })
             }
             ActionType::DeployContract => {
                 // Deploying to Derp's own account.
@@ -194,24 +279,17 @@ impl PasskeyController {
                 // The beneficiary_id in action_data is where remaining funds go.
                 signer_account_id.clone()
             }
+            ActionType::RecordSpend | ActionType::ChargeReverie { .. } | ActionType::ReverieDeposit => self.get_payments_contract_or_panic(),
+            ActionType::StakeWithPool | ActionType::UnstakeFromPool | ActionType::WithdrawFromPool => {
+                self.get_staking_pool_or_panic(&action_data)
+            }
         };
 
         let mut promise = Promise::new(promise_target_account_id.clone());
 
         match action_data.action_type {
             ActionType::CreateAccount => {
-                let _new_account_id = action_data.receiver_id.clone().unwrap_or_else(|| {
-                    panic!("receiver_id (new account_id) is required for CreateAccount")
-                });
-                promise = promise.create_account();
-                if let Some(deposit) = action_data.initial_deposit_for_new_account {
-                    if deposit.0 > 0 {
-                        promise = promise.transfer(NearToken::from_yoctonear(deposit.0));
-                    }
-                }
-                if let Some(pk) = action_data.public_key_for_new_account.clone() {
-                    promise = promise.add_full_access_key(pk);
-                }
+                promise = create_account::create_account_actions(promise, &action_data);
             }
             ActionType::DeployContract => {
                 promise = promise.deploy_contract(
@@ -223,11 +301,8 @@ impl PasskeyController {
             }
             ActionType::FunctionCall => {
                 promise = promise.function_call(
-                    action_data.method_name.unwrap_or_else(|| String::new()),
-                    action_data
-                        .args
-                        .unwrap_or_else(|| Base64VecU8(vec![]))
-                        .0,
+                    action_data.method_name.clone().unwrap_or_else(|| String::new()),
+                    action_data.call_args(),
                     NearToken::from_yoctonear(action_data.deposit.unwrap_or_else(|| U128(0)).0),
                     action_data.gas.unwrap_or_else(|| Gas::from_gas(5_000_000_000_000)), // Default to 5 TGas
                 );
@@ -286,6 +361,33 @@ impl PasskeyController {
                         .unwrap_or_else(|| panic!("beneficiary_id is required for DeleteAccount")),
                 );
             }
+            ActionType::RecordSpend | ActionType::ChargeReverie { .. } | ActionType::ReverieDeposit => {
+                let (method_name, args, deposit) = action_data.payments_call();
+                promise = promise.function_call(
+                    method_name,
+                    args,
+                    deposit,
+                    action_data.gas.unwrap_or(GAS_FOR_PAYMENTS_CALL),
+                );
+            }
+            ActionType::StakeWithPool | ActionType::UnstakeFromPool | ActionType::WithdrawFromPool => {
+                let pending = self
+                    .reserve_pool_principal(&signer_pk, &action_data, 0)
+                    .unwrap_or_else(|| panic!("{:?} is not a staking pool action", action_data.action_type));
+                let (method_name, args, deposit) = action_data.staking_pool_call();
+                promise = promise
+                    .function_call(
+                        method_name,
+                        args,
+                        deposit,
+                        action_data.gas.unwrap_or(GAS_FOR_STAKING_POOL_CALL),
+                    )
+                    .then(
+                        Self::ext(env::current_account_id())
+                            .with_static_gas(GAS_FOR_ON_POOL_ACTION_RESULT)
+                            .on_direct_pool_action_result(signer_pk.clone(), pending),
+                    );
+            }
         }
         log!(
             "Direct action {:?} prepared by {} for target {}",
@@ -300,53 +402,63 @@ impl PasskeyController {
         &mut self,
         passkey_pk_used: PublicKey,
         action_to_execute: SerializableAction,
-    ) {
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.trusted_relayer_account_id,
-            "Only trusted relayer can execute actions"
-        );
-        assert!(
-            self.registered_passkey_pks.contains(&passkey_pk_used),
-            "Passkey PK not registered"
-        );
+    ) -> Base58CryptoHash {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk_used) {
+            return self.rejected_request_id(&passkey_pk_used, &action_to_execute);
+        }
+        self.assert_single_passkey_can_execute(&action_to_execute);
 
-        let action_data = action_to_execute;
+        let nonce = self.next_passkey_nonce(&passkey_pk_used);
+        self.dispatch_delegated(passkey_pk_used, nonce, action_to_execute)
+    }
 
-        let promise_target_account_id = match action_data.action_type {
-            ActionType::FunctionCall | ActionType::Transfer => {
-                action_data.receiver_id.clone().unwrap_or_else(|| panic!("receiver_id is required for FunctionCall/Transfer"))
-            }
-            ActionType::CreateAccount => {
-                 action_data.receiver_id.clone().unwrap_or_else(|| panic!("receiver_id is required for CreateAccount (as the new account_id)"))
+    // internal method for relayer-submitted calls on behalf of a passkey. Every such call goes
+    // through here. Returns false instead of panicking when auth backoff counted an unregistered
+    // passkey against the relayer, in which case the caller must return without doing anything.
+    #[must_use]
+    pub(crate) fn authorize_relayer_with_registered_passkey(&mut self, passkey_pk_used: &PublicKey) -> bool {
+        self.assert_not_in_auth_cooldown();
+        let relayer_id = self.assert_active_relayer();
+        if !self.registered_passkey_pks.contains(passkey_pk_used) {
+            if self.auth_backoff.is_none() {
+                env::panic_str("Passkey PK not registered");
             }
-            ActionType::DeployContract | ActionType::Stake | ActionType::AddKey | ActionType::DeleteKey | ActionType::DeleteAccount => {
-                env::current_account_id()
-            }
-        };
+            self.record_auth_failure(relayer_id, "Passkey PK not registered");
+            return false;
+        }
+        self.assert_passkey_not_expired(passkey_pk_used);
+        self.assert_relayer_bonded(&relayer_id);
+        self.clear_relayer_auth_failures(&relayer_id);
+        true
+    }
+
+    // internal method that builds the promise for a delegated action.
+    // Delegated actions that don't name a receiver operate on the controller's own account,
+    // except AddKey/DeleteKey with a managed `user_id`, which are proxied to that account.
+    // FunctionCall actions may only target calls allowed with `add_allowed_call`, actions must
+    // stay within their type's `set_action_caps`, and no action may attach more than
+    // `get_spendable_controller_balance`.
+    fn build_delegated_promise(&self, action_data: SerializableAction) -> Promise {
+        self.assert_call_allowed(&action_data);
+        self.assert_within_action_caps(&action_data);
+        self.assert_within_balance_reserve(&action_data);
+        let key_proxy_target = self.get_key_proxy_target(&action_data);
+        let promise_target_account_id = self.delegated_target(&action_data);
 
         let mut promise = Promise::new(promise_target_account_id.clone());
 
         match action_data.action_type {
             ActionType::CreateAccount => {
                 // receiver_id (the new account) is already correctly set as promise_target_account_id for delegated creation.
-                promise = promise.create_account();
-                if let Some(deposit) = action_data.initial_deposit_for_new_account {
-                    if deposit.0 > 0 {
-                        promise = promise.transfer(NearToken::from_yoctonear(deposit.0));
-                    }
-                }
-                if let Some(pk) = action_data.public_key_for_new_account.clone() {
-                    promise = promise.add_full_access_key(pk);
-                }
+                promise = create_account::create_account_actions(promise, &action_data);
             }
             ActionType::DeployContract => {
                 promise = promise.deploy_contract(action_data.code.unwrap_or_else(|| Base64VecU8(vec![])).0);
             }
             ActionType::FunctionCall => {
                 promise = promise.function_call(
-                    action_data.method_name.unwrap_or_else(|| String::new()),
-                    action_data.args.unwrap_or_else(|| Base64VecU8(vec![])).0,
+                    action_data.method_name.clone().unwrap_or_else(|| String::new()),
+                    action_data.call_args(),
                     NearToken::from_yoctonear(action_data.deposit.unwrap_or_else(|| U128(0)).0),
                     action_data.gas.unwrap_or_else(|| Gas::from_gas(0)),
                 );
@@ -360,6 +472,10 @@ impl PasskeyController {
                     action_data.public_key.clone().unwrap_or_else(|| panic!("public_key is required for Stake")).clone(),
                 );
             }
+            ActionType::AddKey | ActionType::DeleteKey if key_proxy_target.is_some() => {
+                let (method_name, args, deposit) = action_data.key_proxy_call();
+                promise = promise.function_call(method_name, args, deposit, action_data.gas.unwrap_or(GAS_FOR_KEY_PROXY_CALL));
+            }
             ActionType::AddKey => {
                 promise = promise.add_access_key_allowance(
                     action_data.public_key.clone().unwrap_or_else(|| panic!("public_key is required for AddKey")).clone(),
@@ -374,7 +490,107 @@ impl PasskeyController {
             ActionType::DeleteAccount => {
                 promise = promise.delete_account(action_data.beneficiary_id.unwrap_or_else(|| panic!("beneficiary_id is required for DeleteAccount")));
             }
+            ActionType::RecordSpend | ActionType::ChargeReverie { .. } | ActionType::ReverieDeposit => {
+                let (method_name, args, deposit) = action_data.payments_call();
+                promise = promise.function_call(method_name, args, deposit, action_data.gas.unwrap_or(GAS_FOR_PAYMENTS_CALL));
+            }
+            ActionType::StakeWithPool | ActionType::UnstakeFromPool | ActionType::WithdrawFromPool => {
+                let (method_name, args, deposit) = action_data.staking_pool_call();
+                promise = promise.function_call(method_name, args, deposit, action_data.gas.unwrap_or(GAS_FOR_STAKING_POOL_CALL));
+            }
         }
         log!("Action {:?} prepared for target {}", action_data.action_type, promise_target_account_id);
+        promise
+    }
+
+    // internal method returning the account a delegated action's promise is sent to
+    pub(crate) fn delegated_target(&self, action: &SerializableAction) -> AccountId {
+        if let Some(key_proxy_target) = self.get_key_proxy_target(action) {
+            return key_proxy_target;
+        }
+        match action.action_type {
+            ActionType::FunctionCall | ActionType::Transfer => {
+                action.receiver_id.clone().unwrap_or_else(|| panic!("receiver_id is required for FunctionCall/Transfer"))
+            }
+            ActionType::CreateAccount => {
+                 action.receiver_id.clone().unwrap_or_else(|| panic!("receiver_id is required for CreateAccount (as the new account_id)"))
+            }
+            ActionType::DeployContract | ActionType::Stake | ActionType::AddKey | ActionType::DeleteKey | ActionType::DeleteAccount => {
+                env::current_account_id()
+            }
+            ActionType::RecordSpend | ActionType::ChargeReverie { .. } | ActionType::ReverieDeposit => self.get_payments_contract_or_panic(),
+            ActionType::StakeWithPool | ActionType::UnstakeFromPool | ActionType::WithdrawFromPool => {
+                self.get_staking_pool_or_panic(action)
+            }
+        }
+    }
+}
+
+impl PasskeyController {
+    // internal method building a controller with default settings around an existing passkey set
+    pub(crate) fn with_passkeys(
+        trusted_relayer_account_id: AccountId,
+        owner_id: AccountId,
+        registered_passkey_pks: IterableSet<PublicKey>,
+    ) -> Self {
+        Self {
+            trusted_relayer_account_id,
+            owner_id,
+            registered_passkey_pks,
+            passkey_nonces: LookupMap::new(b"n"),
+            multisig_config: None,
+            action_proposals: LookupMap::new(b"m"),
+            next_proposal_id: 0,
+            relayer_bond_config: bonding::RelayerBondConfig::default(),
+            relayer_bonds: LookupMap::new(b"d"),
+            execution_receipts: LookupMap::new(b"e"),
+            payments_contract_id: None,
+            webauthn_challenges: LookupMap::new(b"c"),
+            challenge_counter: 0,
+            challenge_ttl_ns: webauthn::DEFAULT_CHALLENGE_TTL_NS,
+            guardian_config: None,
+            pending_recoveries: LookupMap::new(b"g"),
+            scheduled_actions: LookupMap::new(b"s"),
+            next_scheduled_id: 0,
+            passkey_metadata: LookupMap::new(b"a"),
+            prepaid_accounting_enabled: false,
+            prepaid_balances: LookupMap::new(b"f"),
+            auto_top_ups: LookupMap::new(b"t"),
+            require_direct_call: false,
+            direct_action_proxies: Vec::new(),
+            staking_pools: Vec::new(),
+            allowed_calls: LookupMap::new(b"w"),
+            call_templates: LookupMap::new(b"l"),
+            self_registration: None,
+            passkey_expirations: IterableMap::new(b"x"),
+            managed_accounts: Vec::new(),
+            sessions: IterableMap::new(b"y"),
+            next_session_id: 0,
+            balance_reserve: balance_reserve::DEFAULT_BALANCE_RESERVE,
+            policy_hook: None,
+            jobs: IterableMap::new(b"j"),
+            job_ttl_ns: jobs::DEFAULT_JOB_TTL_NS,
+            relayer_fee_configs: LookupMap::new(b"r"),
+            relayer_fee_balances: LookupMap::new(b"k"),
+            allowed_beneficiaries: Vec::new(),
+            granted_keys: IterableMap::new(b"q"),
+            result_callbacks: LookupMap::new(b"h"),
+            action_caps: IterableMap::new(b"v"),
+            relayer_failover: None,
+            relayer_last_seen: LookupMap::new(b"b"),
+            passkey_quotas: LookupMap::new(b"u"),
+            default_passkey_quota: None,
+            passkey_quota_usage: LookupMap::new(b"z"),
+            auth_backoff: None,
+            auth_failures: LookupMap::new(b"i"),
+            max_passkeys: None,
+            max_active_sessions: None,
+            pending_self_registrations: LookupMap::new(b"O"),
+            managed_account_passkeys: LookupMap::new(b"o"),
+            staked_principal: LookupMap::new(b"K"),
+            pending_pool_actions: LookupMap::new(b"P"),
+            queued_scheduled_ids: IterableSet::new(b"Q"),
+            pending_key_changes: LookupMap::new(b"G"),
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,62 @@
+use crate::*;
+
+/// Current passkey and session counts against their caps. `None` caps are unlimited.
+#[near_sdk::near(serializers = [json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControllerLimits {
+    pub passkey_count: u32,
+    pub max_passkeys: Option<u32>,
+    pub session_count: u32,
+    pub max_active_sessions: Option<u32>,
+}
+
+#[near]
+impl PasskeyController {
+    /// Caps the passkeys registered on this account, however they are added.
+    pub fn set_max_passkeys(&mut self, max_passkeys: Option<u32>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set limits"
+        );
+        self.max_passkeys = max_passkeys;
+    }
+
+    /// Caps the stored sessions. Expired sessions count until `prune_expired_sessions` removes them.
+    pub fn set_max_active_sessions(&mut self, max_active_sessions: Option<u32>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set limits"
+        );
+        self.max_active_sessions = max_active_sessions;
+    }
+
+    pub fn get_limits(&self) -> ControllerLimits {
+        ControllerLimits {
+            passkey_count: self.registered_passkey_pks.len(),
+            max_passkeys: self.max_passkeys,
+            session_count: self.sessions.len(),
+            max_active_sessions: self.max_active_sessions,
+        }
+    }
+}
+
+impl PasskeyController {
+    // internal method refusing a new passkey once the cap is reached; re-adding a
+    // registered passkey doesn't grow state and is always allowed
+    pub(crate) fn assert_can_add_passkey(&self, passkey_pk: &PublicKey) {
+        if let Some(max_passkeys) = self.max_passkeys {
+            assert!(
+                self.registered_passkey_pks.contains(passkey_pk) || self.registered_passkey_pks.len() < max_passkeys,
+                "ERR_MAX_PASSKEYS_REACHED"
+            );
+        }
+    }
+
+    pub(crate) fn assert_can_add_session(&self) {
+        if let Some(max_active_sessions) = self.max_active_sessions {
+            assert!(self.sessions.len() < max_active_sessions, "ERR_MAX_SESSIONS_REACHED");
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::*;
+
+pub const GAS_FOR_KEY_PROXY_CALL: Gas = Gas::from_tgas(10);
+
+#[near]
+impl PasskeyController {
+    /// Registers a user account whose keys delegated AddKey/DeleteKey actions may manage
+    /// (via its `add_key`/`delete_key` key proxy methods), and the passkey of its owner, the
+    /// only one allowed to manage them. The owner vouches that the account's proxy accepts
+    /// calls from this controller. Sub-accounts of the controller are managed without
+    /// registration, but still need their owner passkey set here.
+    pub fn add_managed_account(&mut self, account_id: AccountId, owner_passkey_pk: PublicKey) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage accounts"
+        );
+        assert!(self.registered_passkey_pks.contains(&owner_passkey_pk), "ERR_PASSKEY_NOT_REGISTERED");
+        self.managed_account_passkeys.insert(account_id.clone(), owner_passkey_pk);
+        if !account_id.is_sub_account_of(&env::current_account_id()) && !self.managed_accounts.contains(&account_id) {
+            self.managed_accounts.push(account_id);
+        }
+    }
+
+    pub fn remove_managed_account(&mut self, account_id: AccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage accounts"
+        );
+        self.managed_account_passkeys.remove(&account_id);
+        self.managed_accounts.retain(|id| id != &account_id);
+    }
+
+    /// The passkey allowed to manage the keys of a managed account.
+    pub fn get_managed_account_passkey(&self, account_id: AccountId) -> Option<PublicKey> {
+        self.managed_account_passkeys.get(&account_id).cloned()
+    }
+
+    pub fn get_managed_accounts(&self) -> Vec<AccountId> {
+        self.managed_accounts.clone()
+    }
+
+    pub fn is_managed_account(&self, account_id: AccountId) -> bool {
+        account_id.is_sub_account_of(&env::current_account_id()) || self.managed_accounts.contains(&account_id)
+    }
+}
+
+impl PasskeyController {
+    // internal method returning the user account a delegated AddKey/DeleteKey is proxied to,
+    // or None when it operates on the controller's own account
+    pub(crate) fn get_key_proxy_target(&self, action: &SerializableAction) -> Option<AccountId> {
+        if !matches!(action.action_type, ActionType::AddKey | ActionType::DeleteKey) {
+            return None;
+        }
+        let account_id = action.user_id.clone().filter(|id| id != &env::current_account_id())?;
+        assert!(self.is_managed_account(account_id.clone()), "ERR_ACCOUNT_NOT_MANAGED");
+        Some(account_id)
+    }
+
+    // internal method rejecting a delegated AddKey/DeleteKey on a managed account by any
+    // passkey other than the one set for it with `add_managed_account`
+    pub(crate) fn assert_key_proxy_owner(&self, action: &SerializableAction, passkey_pk: &PublicKey) {
+        if let Some(account_id) = self.get_key_proxy_target(action) {
+            assert_eq!(
+                self.managed_account_passkeys.get(&account_id),
+                Some(passkey_pk),
+                "ERR_NOT_MANAGED_ACCOUNT_OWNER"
+            );
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use crate::*;
+
+// State layout of the first deployed controller: relayer, owner and the registered passkeys.
+#[near_sdk::near(serializers = [borsh])]
+struct PasskeyControllerV1 {
+    trusted_relayer_account_id: AccountId,
+    owner_id: AccountId,
+    registered_passkey_pks: IterableSet<PublicKey>,
+}
+
+#[near]
+impl PasskeyController {
+    /// Migrates state written by the first deployed controller, keeping its relayer, owner and
+    /// passkeys and giving every later setting its default. Call through `upgrade(.., Some("migrate"))`.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old: PasskeyControllerV1 = env::state_read().unwrap_or_else(|| env::panic_str("Failed to read old state"));
+        Self::with_passkeys(old.trusted_relayer_account_id, old.owner_id, old.registered_passkey_pks)
+    }
+}
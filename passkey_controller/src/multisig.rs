@@ -0,0 +1,194 @@
+use crate::*;
+use crate::envelope::verify_passkey_signature;
+use near_sdk::json_types::U64;
+
+/// Delegated actions moving at least `value_threshold` yoctoNEAR need
+/// `required_approvals` distinct registered passkeys before they execute.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultisigConfig {
+    pub value_threshold: U128, // yoctoNEAR
+    pub required_approvals: u32,
+    pub proposal_ttl_ns: U64,
+}
+
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone)]
+pub struct ActionProposal {
+    pub action: SerializableAction,
+    pub approvals: Vec<PublicKey>,
+    pub created_at: U64,
+    pub expires_at: U64,
+}
+
+/// Canonical payload a passkey signs to approve a proposal, including the proposing passkey.
+/// Binding the action means a signature over a proposal id can't approve anything else.
+#[near_sdk::near(serializers = [borsh])]
+#[derive(Debug, Clone)]
+pub struct ApprovalPayload {
+    pub controller_id: AccountId,
+    pub passkey_pk: PublicKey,
+    pub proposal_id: u64,
+    pub action: SerializableAction,
+}
+
+impl ApprovalPayload {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        near_sdk::borsh::to_vec(self).unwrap_or_else(|_| panic!("ERR_PAYLOAD_SERIALIZATION"))
+    }
+}
+
+#[near]
+impl PasskeyController {
+    pub fn set_multisig_config(&mut self, config: Option<MultisigConfig>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set multisig config"
+        );
+        if let Some(config) = config.as_ref() {
+            assert!(config.required_approvals >= 2, "Multisig requires at least 2 approvals");
+        }
+        self.multisig_config = config;
+    }
+
+    pub fn get_multisig_config(&self) -> Option<MultisigConfig> {
+        self.multisig_config.clone()
+    }
+
+    pub fn get_action_proposal(&self, proposal_id: U64) -> Option<ActionProposal> {
+        self.action_proposals.get(&proposal_id.0).cloned()
+    }
+
+    /// Id the next `propose_action` creates, which its proposer signs.
+    pub fn get_next_proposal_id(&self) -> U64 {
+        U64(self.next_proposal_id)
+    }
+
+    /// Returns the exact bytes a passkey must sign to propose or approve `action` as `proposal_id`.
+    pub fn get_approval_payload(&self, proposal_id: U64, passkey_pk: PublicKey, action: SerializableAction) -> Base64VecU8 {
+        let payload = ApprovalPayload {
+            controller_id: env::current_account_id(),
+            passkey_pk,
+            proposal_id: proposal_id.0,
+            action,
+        };
+        Base64VecU8(payload.to_bytes())
+    }
+
+    /// Proposes a high-value action. The proposing passkey counts as the first approval, so it
+    /// signs the approval payload of the id the proposal gets (see `get_next_proposal_id`).
+    pub fn propose_action(
+        &mut self,
+        passkey_pk_used: PublicKey,
+        action_to_execute: SerializableAction,
+        signature: Base64VecU8,
+    ) -> U64 {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk_used) {
+            return auth_backoff::REJECTED_ID;
+        }
+        let config = self
+            .multisig_config
+            .clone()
+            .unwrap_or_else(|| panic!("Multisig approval mode is not enabled"));
+
+        let proposal_id = self.next_proposal_id;
+        self.assert_approval_signed(proposal_id, &passkey_pk_used, &action_to_execute, &signature);
+        self.next_proposal_id += 1;
+        let now = clock::block_timestamp();
+        self.action_proposals.insert(
+            proposal_id,
+            ActionProposal {
+                action: action_to_execute,
+                approvals: vec![passkey_pk_used],
+                created_at: U64(now),
+                expires_at: U64(now + config.proposal_ttl_ns.0),
+            },
+        );
+        log!("Action proposal {} created", proposal_id);
+        U64(proposal_id)
+    }
+
+    /// Approves a pending proposal with the passkey's signature over its approval payload.
+    /// Executes the action once the approval threshold is met.
+    /// Returns true if the action was executed by this approval.
+    pub fn approve_action(&mut self, proposal_id: U64, passkey_pk_used: PublicKey, signature: Base64VecU8) -> bool {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk_used) {
+            return false;
+        }
+        let config = self
+            .multisig_config
+            .clone()
+            .unwrap_or_else(|| panic!("Multisig approval mode is not enabled"));
+
+        let mut proposal = self
+            .action_proposals
+            .remove(&proposal_id.0)
+            .unwrap_or_else(|| panic!("Action proposal {} not found", proposal_id.0));
+        if clock::block_timestamp() > proposal.expires_at.0 {
+            // The proposal stays removed so expired actions can never execute.
+            log!("Action proposal {} expired", proposal_id.0);
+            return false;
+        }
+        assert!(
+            !proposal.approvals.contains(&passkey_pk_used),
+            "Passkey has already approved this proposal"
+        );
+        self.assert_approval_signed(proposal_id.0, &passkey_pk_used, &proposal.action, &signature);
+        proposal.approvals.push(passkey_pk_used);
+
+        // Approvals from passkeys removed or expired since proposing no longer count.
+        let valid_approvals = proposal
+            .approvals
+            .iter()
+            .filter(|pk| self.registered_passkey_pks.contains(*pk) && !self.is_passkey_expired(pk))
+            .count() as u32;
+        if valid_approvals >= config.required_approvals {
+            log!("Action proposal {} approved by {} passkeys", proposal_id.0, valid_approvals);
+            let proposer_pk = proposal.approvals[0].clone();
+            let nonce = self.next_passkey_nonce(&proposer_pk);
+            self.dispatch_delegated(proposer_pk, nonce, proposal.action);
+            true
+        } else {
+            self.action_proposals.insert(proposal_id.0, proposal);
+            false
+        }
+    }
+
+    /// Cancels and deletes an expired proposal. Callable by anyone to free storage.
+    pub fn prune_expired_proposal(&mut self, proposal_id: U64) -> bool {
+        let expired = self
+            .action_proposals
+            .get(&proposal_id.0)
+            .map(|proposal| clock::block_timestamp() > proposal.expires_at.0)
+            .unwrap_or(false);
+        if expired {
+            self.action_proposals.remove(&proposal_id.0);
+        }
+        expired
+    }
+
+    // internal method checking a passkey's signature over the approval payload of a proposal
+    fn assert_approval_signed(&self, proposal_id: u64, passkey_pk: &PublicKey, action: &SerializableAction, signature: &Base64VecU8) {
+        let payload = ApprovalPayload {
+            controller_id: env::current_account_id(),
+            passkey_pk: passkey_pk.clone(),
+            proposal_id,
+            action: action.clone(),
+        };
+        assert!(
+            verify_passkey_signature(passkey_pk, &payload.to_bytes(), &signature.0),
+            "ERR_INVALID_APPROVAL_SIGNATURE"
+        );
+    }
+
+    // internal method rejecting single-passkey execution of actions above the multisig threshold
+    pub(crate) fn assert_single_passkey_can_execute(&self, action: &SerializableAction) {
+        if let Some(config) = self.multisig_config.as_ref() {
+            assert!(
+                action.attached_value() < config.value_threshold.0,
+                "ERR_ACTION_REQUIRES_MULTISIG_APPROVAL"
+            );
+        }
+    }
+}
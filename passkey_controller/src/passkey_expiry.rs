@@ -0,0 +1,58 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+#[near]
+impl PasskeyController {
+    /// Sets when a registered passkey stops being usable (block timestamp in nanoseconds),
+    /// e.g. for short-lived session passkeys. `None` makes it permanent again.
+    pub fn set_passkey_expiry(&mut self, passkey_pk: PublicKey, expires_at_ns: Option<U64>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.trusted_relayer_account_id,
+            "Only trusted relayer can set passkey expiry"
+        );
+        assert!(
+            self.registered_passkey_pks.contains(&passkey_pk),
+            "Passkey PK not registered"
+        );
+        match expires_at_ns {
+            Some(expires_at_ns) => self.passkey_expirations.insert(passkey_pk, expires_at_ns.0),
+            None => self.passkey_expirations.remove(&passkey_pk),
+        };
+    }
+
+    pub fn get_passkey_expiry(&self, passkey_pk: PublicKey) -> Option<U64> {
+        self.passkey_expirations.get(&passkey_pk).map(|expires_at| U64(*expires_at))
+    }
+
+    /// Removes up to `limit` expired passkeys with their metadata. Callable by anyone to free storage.
+    pub fn prune_expired_passkeys(&mut self, limit: u32) -> u32 {
+        let now = clock::block_timestamp();
+        let expired: Vec<PublicKey> = self
+            .passkey_expirations
+            .iter()
+            .filter(|(_, expires_at)| now > **expires_at)
+            .take(limit as usize)
+            .map(|(pk, _)| pk.clone())
+            .collect();
+        for passkey_pk in expired.iter() {
+            self.passkey_expirations.remove(passkey_pk);
+            self.passkey_metadata.remove(passkey_pk);
+            self.registered_passkey_pks.remove(passkey_pk);
+        }
+        expired.len() as u32
+    }
+}
+
+impl PasskeyController {
+    pub(crate) fn is_passkey_expired(&self, passkey_pk: &PublicKey) -> bool {
+        self.passkey_expirations
+            .get(passkey_pk)
+            .map_or(false, |expires_at| clock::block_timestamp() > *expires_at)
+    }
+
+    // internal method for execution paths: a passkey that is registered but expired can't act
+    pub(crate) fn assert_passkey_not_expired(&self, passkey_pk: &PublicKey) {
+        assert!(!self.is_passkey_expired(passkey_pk), "ERR_PASSKEY_EXPIRED");
+    }
+}
@@ -0,0 +1,100 @@
+use crate::*;
+use near_sdk::CurveType;
+
+pub const ED25519_KEY_LEN: usize = 32;
+// Uncompressed point without the SEC1 0x04 tag, as NEAR stores secp256k1 keys
+pub const SECP256K1_KEY_LEN: usize = 64;
+const SEC1_UNCOMPRESSED_TAG: u8 = 0x04;
+
+/// Why a passkey public key was rejected. Panics use the `Display` form.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PasskeyKeyError {
+    UnsupportedCurve(String),
+    InvalidLength { curve: String, expected: usize, actual: usize },
+    CompressedSecp256k1,
+    /// All-zero key bytes, which placeholder or uninitialized keys produce. The point itself
+    /// isn't decompressed, so other bytes off the curve are only caught when signatures fail.
+    ZeroKey,
+}
+
+impl std::fmt::Display for PasskeyKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasskeyKeyError::UnsupportedCurve(curve) => write!(f, "ERR_UNSUPPORTED_PASSKEY_CURVE: {}", curve),
+            PasskeyKeyError::InvalidLength { curve, expected, actual } => write!(
+                f,
+                "ERR_INVALID_PASSKEY_PK_LENGTH: {} keys are {} bytes, got {}",
+                curve, expected, actual
+            ),
+            PasskeyKeyError::CompressedSecp256k1 => write!(f, "ERR_COMPRESSED_SECP256K1_PK: submit the uncompressed point"),
+            PasskeyKeyError::ZeroKey => write!(f, "ERR_ZERO_PASSKEY_PK"),
+        }
+    }
+}
+
+/// Builds a passkey `PublicKey` from raw key bytes, normalizing encodings: secp256k1 keys
+/// may carry the SEC1 0x04 uncompressed tag, which is stripped.
+pub fn normalize_passkey_pk(curve: CurveType, key_bytes: &[u8]) -> Result<PublicKey, PasskeyKeyError> {
+    let (curve_name, expected) = match curve {
+        CurveType::ED25519 => ("ed25519", ED25519_KEY_LEN),
+        CurveType::SECP256K1 => ("secp256k1", SECP256K1_KEY_LEN),
+    };
+    let key_bytes = match (curve, key_bytes.len()) {
+        (CurveType::SECP256K1, 65) if key_bytes[0] == SEC1_UNCOMPRESSED_TAG => &key_bytes[1..],
+        (CurveType::SECP256K1, 33) if matches!(key_bytes[0], 0x02 | 0x03) => {
+            return Err(PasskeyKeyError::CompressedSecp256k1)
+        }
+        _ => key_bytes,
+    };
+    if key_bytes.len() != expected {
+        return Err(PasskeyKeyError::InvalidLength {
+            curve: curve_name.to_string(),
+            expected,
+            actual: key_bytes.len(),
+        });
+    }
+    if key_bytes.iter().all(|byte| *byte == 0) {
+        return Err(PasskeyKeyError::ZeroKey);
+    }
+    PublicKey::from_parts(curve, key_bytes.to_vec()).map_err(|_| PasskeyKeyError::InvalidLength {
+        curve: curve_name.to_string(),
+        expected,
+        actual: key_bytes.len(),
+    })
+}
+
+/// Re-checks an already parsed key, e.g. one a relayer sent as `"secp256k1:..."`.
+pub fn validate_passkey_pk(passkey_pk: &PublicKey) -> Result<PublicKey, PasskeyKeyError> {
+    normalize_passkey_pk(passkey_pk.curve_type(), &passkey_pk.as_bytes()[1..])
+}
+
+pub(crate) fn assert_valid_passkey_pk(passkey_pk: &PublicKey) {
+    if let Err(err) = validate_passkey_pk(passkey_pk) {
+        env::panic_str(&err.to_string());
+    }
+}
+
+fn parse_curve(curve: &str) -> Result<CurveType, PasskeyKeyError> {
+    match curve.to_ascii_lowercase().as_str() {
+        "ed25519" => Ok(CurveType::ED25519),
+        "secp256k1" => Ok(CurveType::SECP256K1),
+        _ => Err(PasskeyKeyError::UnsupportedCurve(curve.to_string())),
+    }
+}
+
+#[near]
+impl PasskeyController {
+    /// Normalizes raw key bytes exported by an authenticator (`curve` is "ed25519" or
+    /// "secp256k1") into the `PublicKey` the controller registers.
+    pub fn normalize_passkey_pk(&self, curve: String, key_bytes: Base64VecU8) -> PublicKey {
+        parse_curve(&curve)
+            .and_then(|curve| normalize_passkey_pk(curve, &key_bytes.0))
+            .unwrap_or_else(|err| env::panic_str(&err.to_string()))
+    }
+
+    /// `add_passkey_pk` for raw key bytes, normalized like `normalize_passkey_pk`.
+    pub fn add_passkey_pk_raw(&mut self, curve: String, key_bytes: Base64VecU8) -> bool {
+        let passkey_pk = self.normalize_passkey_pk(curve, key_bytes);
+        self.add_passkey_pk(passkey_pk)
+    }
+}
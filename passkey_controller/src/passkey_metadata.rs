@@ -0,0 +1,38 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// Attestation details recorded when a passkey is registered, so users can tell
+/// their devices apart when revoking one.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasskeyMetadata {
+    pub aaguid: String, // authenticator model, hex encoded
+    pub credential_id: Base64VecU8,
+    pub transports: Vec<String>, // e.g. "internal", "hybrid", "usb"
+    pub created_at: U64, // credential creation timestamp in nanoseconds
+    pub device_label: Option<String>,
+}
+
+#[near]
+impl PasskeyController {
+    /// Registers a passkey together with its attestation metadata.
+    pub fn add_passkey_pk_with_metadata(&mut self, passkey_pk: PublicKey, metadata: PasskeyMetadata) -> bool {
+        let added = self.add_passkey_pk(passkey_pk.clone());
+        self.passkey_metadata.insert(passkey_pk, metadata);
+        added
+    }
+
+    pub fn get_passkey_metadata(&self, passkey_pk: PublicKey) -> Option<PasskeyMetadata> {
+        self.passkey_metadata.get(&passkey_pk).cloned()
+    }
+
+    /// Registered passkeys with their metadata, if any was recorded.
+    pub fn get_passkeys_with_metadata(&self, from_index: u32, limit: u32) -> Vec<(PublicKey, Option<PasskeyMetadata>)> {
+        self.registered_passkey_pks
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .map(|passkey_pk| (passkey_pk.clone(), self.passkey_metadata.get(passkey_pk).cloned()))
+            .collect()
+    }
+}
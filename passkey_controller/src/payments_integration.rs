@@ -0,0 +1,31 @@
+use crate::*;
+
+/// Default gas for RecordSpend/ChargeReverie/ReverieDeposit calls when the action doesn't set `gas`.
+pub const GAS_FOR_PAYMENTS_CALL: Gas = Gas::from_tgas(10);
+
+pub use reveries_types::{DepositForArgs, RecordSpendArgs};
+
+#[near]
+impl PasskeyController {
+    /// Sets the PaymentContract targeted by RecordSpend/ChargeReverie/ReverieDeposit actions.
+    /// RecordSpend and ChargeReverie require this controller to be the payments contract's trusted account.
+    /// ChargeReverie carries its `reverie_id`, `user` and `amount` in the action type itself.
+    pub fn set_payments_contract(&mut self, payments_contract_id: Option<AccountId>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set payments contract"
+        );
+        self.payments_contract_id = payments_contract_id;
+    }
+
+    pub fn get_payments_contract(&self) -> Option<AccountId> {
+        self.payments_contract_id.clone()
+    }
+
+    pub(crate) fn get_payments_contract_or_panic(&self) -> AccountId {
+        self.payments_contract_id
+            .clone()
+            .unwrap_or_else(|| panic!("Payments contract is not configured"))
+    }
+}
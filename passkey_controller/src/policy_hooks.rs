@@ -0,0 +1,132 @@
+use crate::*;
+use crate::events::ControllerEvent;
+use near_sdk::{CryptoHash, PromiseResult};
+
+pub const GAS_FOR_POLICY_CHECK: Gas = Gas::from_tgas(10);
+pub const GAS_FOR_POLICY_POST_HOOK: Gas = Gas::from_tgas(10);
+// Dispatching the approved action from the callback; unused gas is added on top
+pub(crate) const GAS_FOR_ON_POLICY_CHECKED: Gas = Gas::from_tgas(15);
+
+/// External policy contract consulted before every delegated action. It must implement
+/// `check(action, passkey_pk) -> bool` and, with `post_hook`, `on_action_executed(request_id, passkey_pk, succeeded)`.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyHookConfig {
+    pub policy_contract_id: AccountId,
+    pub post_hook: bool,
+}
+
+#[near_sdk::near(serializers = [json])]
+struct PolicyCheckArgs {
+    action: SerializableAction,
+    passkey_pk: PublicKey,
+}
+
+#[near_sdk::near(serializers = [json])]
+struct PolicyPostHookArgs {
+    request_id: Base58CryptoHash,
+    passkey_pk: PublicKey,
+    succeeded: bool,
+}
+
+#[near]
+impl PasskeyController {
+    pub fn set_policy_hook(&mut self, config: Option<PolicyHookConfig>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set the policy hook"
+        );
+        self.policy_hook = config;
+    }
+
+    pub fn get_policy_hook(&self) -> Option<PolicyHookConfig> {
+        self.policy_hook.clone()
+    }
+
+    /// Dispatches the action if the policy contract approved it, otherwise fails its receipt.
+    #[private]
+    pub fn on_policy_checked(&mut self, request_id: Base58CryptoHash, action: SerializableAction) -> bool {
+        let approved = match env::promise_result(0) {
+            PromiseResult::Successful(value) => near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(false),
+            _ => false,
+        };
+        let rejected_by_controller = if approved { self.policy_checked_action_error(&action) } else { None };
+        if let Some(error) = rejected_by_controller {
+            // Panicking here would leave the receipt pending and the prepaid debit unrefunded
+            log!("Delegated execution {:?} no longer allowed: {}", request_id, error);
+            self.resolve_delegated(request_id, false, vec![]);
+            return false;
+        }
+        if approved {
+            self.build_delegated_promise(action).then(self.delegated_result_callback(request_id));
+        } else {
+            let key: CryptoHash = request_id.into();
+            let passkey_pk = self.execution_receipts.get(&key).map(|receipt| receipt.passkey_pk.clone());
+            if let Some(passkey_pk) = passkey_pk {
+                ControllerEvent::ActionRejectedByPolicy { request_id, passkey_pk }.emit();
+            }
+            self.resolve_delegated(request_id, false, vec![]);
+        }
+        approved
+    }
+}
+
+impl PasskeyController {
+    // internal method asking the policy contract to approve a recorded delegated action
+    pub(crate) fn request_policy_check(
+        &self,
+        config: &PolicyHookConfig,
+        request_id: Base58CryptoHash,
+        passkey_pk: &PublicKey,
+        action: SerializableAction,
+    ) {
+        let args = near_sdk::serde_json::to_vec(&PolicyCheckArgs { action: action.clone(), passkey_pk: passkey_pk.clone() })
+            .unwrap_or_else(|_| panic!("ERR_PAYLOAD_SERIALIZATION"));
+        Promise::new(config.policy_contract_id.clone())
+            .function_call("check".to_string(), args, NearToken::from_yoctonear(0), GAS_FOR_POLICY_CHECK)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_POLICY_CHECKED)
+                    .on_policy_checked(request_id, action),
+            )
+    }
+
+    // internal method returning why the controller would now refuse an approved action. The
+    // action was checked before the policy call, so only settings changed since then matter.
+    fn policy_checked_action_error(&self, action: &SerializableAction) -> Option<String> {
+        if let (ActionType::FunctionCall, Some(receiver_id)) = (&action.action_type, &action.receiver_id) {
+            let method_name = action.method_name.clone().unwrap_or_default();
+            if !self.is_call_allowed(receiver_id.clone(), method_name) {
+                return Some("ERR_CALL_NOT_ALLOWED".to_string());
+            }
+        }
+        if let Some(account_id) = action.user_id.as_ref().filter(|_| matches!(action.action_type, ActionType::AddKey | ActionType::DeleteKey)) {
+            if account_id != &env::current_account_id() && !self.is_managed_account(account_id.clone()) {
+                return Some("ERR_ACCOUNT_NOT_MANAGED".to_string());
+            }
+        }
+        if let Some(error) = self.action_caps_error(action) {
+            return Some(error.to_string());
+        }
+        if action.attached_value() > self.get_spendable_controller_balance().0 {
+            return Some("ERR_BALANCE_RESERVE_BREACHED".to_string());
+        }
+        None
+    }
+
+    // internal method reporting a resolved execution to the policy contract, if it asked for it
+    pub(crate) fn maybe_call_policy_post_hook(&self, request_id: Base58CryptoHash, passkey_pk: &PublicKey, succeeded: bool) {
+        let Some(config) = self.policy_hook.as_ref().filter(|config| config.post_hook) else {
+            return;
+        };
+        let args = near_sdk::serde_json::to_vec(&PolicyPostHookArgs { request_id, passkey_pk: passkey_pk.clone(), succeeded })
+            .unwrap_or_else(|_| panic!("ERR_PAYLOAD_SERIALIZATION"));
+        Promise::new(config.policy_contract_id.clone()).function_call(
+            "on_action_executed".to_string(),
+            args,
+            NearToken::from_yoctonear(0),
+            GAS_FOR_POLICY_POST_HOOK,
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use crate::*;
+
+#[near]
+impl PasskeyController {
+    /// When enabled, NEAR attached by delegated actions is debited from the passkey's
+    /// prepaid balance and actions exceeding it are refused.
+    pub fn set_prepaid_accounting(&mut self, enabled: bool) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set prepaid accounting"
+        );
+        self.prepaid_accounting_enabled = enabled;
+    }
+
+    pub fn is_prepaid_accounting_enabled(&self) -> bool {
+        self.prepaid_accounting_enabled
+    }
+
+    /// Adds the attached deposit to a passkey's prepaid balance. Returns the new balance.
+    #[payable]
+    pub fn fund_controller_for(&mut self, passkey_pk: PublicKey) -> U128 {
+        assert!(
+            self.registered_passkey_pks.contains(&passkey_pk),
+            "Passkey PK not registered"
+        );
+        let amount = env::attached_deposit().as_yoctonear();
+        assert!(amount > 0, "Deposit must be greater than 0");
+        let balance = self.prepaid_balances.get(&passkey_pk).unwrap_or(&0) + amount;
+        self.prepaid_balances.insert(passkey_pk, balance);
+        U128(balance)
+    }
+
+    pub fn get_prepaid_balance(&self, passkey_pk: PublicKey) -> U128 {
+        U128(*self.prepaid_balances.get(&passkey_pk).unwrap_or(&0))
+    }
+}
+
+impl PasskeyController {
+    // internal method debiting the value an action attaches. Returns the amount debited,
+    // which is 0 when prepaid accounting is disabled.
+    pub(crate) fn debit_prepaid(&mut self, passkey_pk: &PublicKey, amount: u128) -> u128 {
+        if !self.prepaid_accounting_enabled || amount == 0 {
+            return 0;
+        }
+        let balance = *self.prepaid_balances.get(passkey_pk).unwrap_or(&0);
+        assert!(balance >= amount, "ERR_INSUFFICIENT_PREPAID_BALANCE");
+        if balance == amount {
+            self.prepaid_balances.remove(passkey_pk);
+        } else {
+            self.prepaid_balances.insert(passkey_pk.clone(), balance - amount);
+        }
+        amount
+    }
+
+    // internal method returning a debit, e.g. when the action failed and its deposit was refunded
+    pub(crate) fn credit_prepaid(&mut self, passkey_pk: &PublicKey, amount: u128) {
+        if amount == 0 {
+            return;
+        }
+        let balance = self.prepaid_balances.get(passkey_pk).unwrap_or(&0) + amount;
+        self.prepaid_balances.insert(passkey_pk.clone(), balance);
+    }
+}
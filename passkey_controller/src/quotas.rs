@@ -0,0 +1,67 @@
+use crate::*;
+
+const NS_PER_DAY: u64 = 86_400_000_000_000;
+
+/// Actions a passkey executed on `day` (UTC days since the unix epoch).
+#[near_sdk::near(serializers = [borsh])]
+#[derive(Debug, Clone)]
+pub struct QuotaUsage {
+    pub day: u64,
+    pub count: u32,
+}
+
+#[near]
+impl PasskeyController {
+    /// Caps how many actions `passkey_pk` may execute per UTC day, throttling a leaked
+    /// passkey before funds limits kick in. `None` falls back to the default quota.
+    pub fn set_passkey_quota(&mut self, passkey_pk: PublicKey, max_actions_per_day: Option<u32>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set quotas"
+        );
+        match max_actions_per_day {
+            Some(max_actions_per_day) => self.passkey_quotas.insert(passkey_pk, max_actions_per_day),
+            None => self.passkey_quotas.remove(&passkey_pk),
+        };
+    }
+
+    /// Quota for passkeys without their own. `None` leaves them unlimited.
+    pub fn set_default_passkey_quota(&mut self, max_actions_per_day: Option<u32>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set quotas"
+        );
+        self.default_passkey_quota = max_actions_per_day;
+    }
+
+    /// The quota that applies to `passkey_pk`, if any.
+    pub fn get_passkey_quota(&self, passkey_pk: PublicKey) -> Option<u32> {
+        self.passkey_quotas.get(&passkey_pk).copied().or(self.default_passkey_quota)
+    }
+
+    /// Actions `passkey_pk` has executed today.
+    pub fn get_passkey_quota_usage(&self, passkey_pk: PublicKey) -> u32 {
+        self.passkey_quota_usage
+            .get(&passkey_pk)
+            .filter(|usage| usage.day == current_day())
+            .map_or(0, |usage| usage.count)
+    }
+}
+
+impl PasskeyController {
+    // internal method counting an execution against the passkey's daily quota
+    pub(crate) fn consume_passkey_quota(&mut self, passkey_pk: &PublicKey) {
+        let Some(quota) = self.get_passkey_quota(passkey_pk.clone()) else {
+            return;
+        };
+        let count = self.get_passkey_quota_usage(passkey_pk.clone()) + 1;
+        assert!(count <= quota, "ERR_DAILY_QUOTA_EXCEEDED");
+        self.passkey_quota_usage.insert(passkey_pk.clone(), QuotaUsage { day: current_day(), count });
+    }
+}
+
+fn current_day() -> u64 {
+    clock::block_timestamp() / NS_PER_DAY
+}
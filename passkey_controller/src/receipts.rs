@@ -0,0 +1,206 @@
+use crate::*;
+use near_sdk::json_types::{Base58CryptoHash, U64};
+use crate::events::ControllerEvent;
+use near_sdk::{CryptoHash, PromiseResult};
+use crate::policy_hooks::GAS_FOR_POLICY_POST_HOOK;
+
+const GAS_FOR_ON_DELEGATED_ACTION_RESULT: Gas = Gas::from_tgas(5);
+
+/// Blocks a resolved receipt is kept before `prune_execution_receipt` can delete it: about a day.
+pub const EXECUTION_RECEIPT_RETENTION_BLOCKS: u64 = 86_400;
+
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// Minimal on-chain record of a delegated execution, keyed by its request id.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone)]
+pub struct ExecutionReceipt {
+    pub status: ExecutionStatus,
+    pub passkey_pk: PublicKey,
+    pub nonce: U64,
+    pub block_height: U64, // block the action was submitted in
+    pub resolved_block_height: Option<U64>, // block the result callback ran in
+    pub prepaid_debited: U128, // returned to the prepaid balance if the action fails
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<crate::execution_tags::ExecutionTag>, // set by `execute_delegated_actions_tagged`
+}
+
+/// Deterministic id of a delegated execution: sha256 of the Borsh-serialized
+/// (passkey_pk, nonce, action) tuple.
+pub fn compute_request_id(passkey_pk: &PublicKey, nonce: u64, action: &SerializableAction) -> CryptoHash {
+    let bytes = near_sdk::borsh::to_vec(&(passkey_pk, nonce, action))
+        .unwrap_or_else(|_| panic!("ERR_PAYLOAD_SERIALIZATION"));
+    env::sha256_array(&bytes)
+}
+
+#[near]
+impl PasskeyController {
+    pub fn get_execution_receipt(&self, request_id: Base58CryptoHash) -> Option<ExecutionReceipt> {
+        let request_id: CryptoHash = request_id.into();
+        self.execution_receipts.get(&request_id).cloned()
+    }
+
+    /// Deletes a receipt resolved more than `EXECUTION_RECEIPT_RETENTION_BLOCKS` ago. Callable
+    /// by anyone to free storage. Pending receipts are kept for their result callback. Request
+    /// ids include the passkey's nonce, which is never reused, so a pruned id can't be replayed.
+    pub fn prune_execution_receipt(&mut self, request_id: Base58CryptoHash) -> bool {
+        let request_id: CryptoHash = request_id.into();
+        let expired = self
+            .execution_receipts
+            .get(&request_id)
+            .and_then(|receipt| receipt.resolved_block_height)
+            .is_some_and(|resolved| env::block_height() > resolved.0.saturating_add(EXECUTION_RECEIPT_RETENTION_BLOCKS));
+        if expired {
+            self.execution_receipts.remove(&request_id);
+        }
+        expired
+    }
+
+    /// Resolves a delegated execution. If the action failed, the deposit it attached comes
+    /// back to the controller and is credited to the passkey's prepaid balance. Only prepaid
+    /// debits are credited: without prepaid accounting the controller paid the deposit itself.
+    /// Unused gas is refunded to the transaction signer, not the controller, so isn't credited.
+    #[private]
+    pub fn on_delegated_action_result(&mut self, request_id: Base58CryptoHash) -> bool {
+        let (succeeded, result) = match env::promise_result(0) {
+            PromiseResult::Successful(value) => (true, value),
+            _ => (false, vec![]),
+        };
+        self.resolve_delegated(request_id, succeeded, result);
+        succeeded
+    }
+}
+
+impl PasskeyController {
+    // internal method that records a pending receipt and dispatches the action with a result callback,
+    // after the policy contract's approval when a policy hook is set.
+    // Every delegated execution path goes through here so receipts stay complete.
+    pub(crate) fn dispatch_delegated(
+        &mut self,
+        passkey_pk: PublicKey,
+        nonce: u64,
+        action: SerializableAction,
+    ) -> Base58CryptoHash {
+        self.assert_key_proxy_owner(&action, &passkey_pk);
+        let request_id = compute_request_id(&passkey_pk, nonce, &action);
+        assert!(
+            self.execution_receipts.get(&request_id).is_none(),
+            "ERR_DUPLICATE_REQUEST_ID"
+        );
+        self.consume_passkey_quota(&passkey_pk);
+        self.charge_relayer_fee(&passkey_pk, &action);
+        let prepaid_debited = self.debit_prepaid(&passkey_pk, action.attached_value());
+        self.execution_receipts.insert(
+            request_id,
+            ExecutionReceipt {
+                status: ExecutionStatus::Pending,
+                passkey_pk: passkey_pk.clone(),
+                nonce: U64(nonce),
+                block_height: U64(env::block_height()),
+                resolved_block_height: None,
+                prepaid_debited: U128(prepaid_debited),
+                tag: None,
+            },
+        );
+        if let Some(pending) = self.reserve_pool_principal(&passkey_pk, &action, prepaid_debited) {
+            self.pending_pool_actions.insert(request_id, pending);
+        }
+        self.reserve_key_change(request_id, &action);
+        let request_id = Base58CryptoHash::from(request_id);
+        if self.reject_disallowed_beneficiary(request_id, &passkey_pk, &action) {
+            self.resolve_delegated(request_id, false, vec![]);
+            return request_id;
+        }
+        match self.policy_hook.clone() {
+            Some(config) => {
+                // Fail fast on actions the controller would refuse anyway, so the policy
+                // callback only has to recheck what can change while the check is in flight
+                if let Some(error) = action.validate().errors.first() {
+                    panic!("Invalid {}: {}", error.field, error.message);
+                }
+                self.assert_call_allowed(&action);
+                self.assert_within_action_caps(&action);
+                self.assert_within_balance_reserve(&action);
+                self.request_policy_check(&config, request_id, &passkey_pk, action);
+            }
+            None => {
+                self.build_delegated_promise(action).then(self.delegated_result_callback(request_id));
+            }
+        }
+        request_id
+    }
+
+    // internal method building the `on_delegated_action_result` callback, with gas for the policy post hook
+    pub(crate) fn delegated_result_callback(&self, request_id: Base58CryptoHash) -> Promise {
+        Self::ext(env::current_account_id())
+            .with_static_gas(self.delegated_result_callback_gas().saturating_add(self.result_callback_gas(request_id)))
+            .on_delegated_action_result(request_id)
+    }
+
+    pub(crate) fn delegated_result_callback_gas(&self) -> Gas {
+        let post_hook_gas = match &self.policy_hook {
+            Some(config) if config.post_hook => GAS_FOR_POLICY_POST_HOOK,
+            _ => Gas::from_gas(0),
+        };
+        GAS_FOR_ON_DELEGATED_ACTION_RESULT.saturating_add(post_hook_gas)
+    }
+
+    // internal method resolving a delegated execution's receipt, crediting back a failed action's prepaid debit
+    // and forwarding the action's `result` to its result callback, if any
+    pub(crate) fn resolve_delegated(&mut self, request_id: Base58CryptoHash, succeeded: bool, result: Vec<u8>) {
+        let key: CryptoHash = request_id.into();
+        let mut resolved = None;
+        if let Some(receipt) = self.execution_receipts.get_mut(&key) {
+            receipt.status = if succeeded { ExecutionStatus::Succeeded } else { ExecutionStatus::Failed };
+            receipt.resolved_block_height = Some(U64(env::block_height()));
+            let refund = if succeeded { 0 } else { receipt.prepaid_debited.0 };
+            resolved = Some((receipt.passkey_pk.clone(), refund, receipt.tag.clone()));
+        }
+        let Some((passkey_pk, refund, tag)) = resolved else {
+            return;
+        };
+        if let Some(pending) = self.pending_pool_actions.remove(&key) {
+            self.settle_pool_action(&passkey_pk, pending, succeeded);
+        }
+        if let Some(change) = self.pending_key_changes.remove(&key) {
+            self.settle_key_change(change, succeeded);
+        }
+        if let Some(tag) = tag {
+            ControllerEvent::ExecutionResolved {
+                request_id,
+                succeeded,
+                memo: tag.memo,
+                client_id: tag.client_id,
+            }
+            .emit();
+        }
+        if refund > 0 {
+            // The failed action's deposit was refunded to the controller; return it to the user
+            self.credit_prepaid(&passkey_pk, refund);
+            ControllerEvent::DepositRefunded {
+                request_id,
+                passkey_pk: passkey_pk.clone(),
+                amount: U128(refund),
+            }
+            .emit();
+        }
+        // Only now is the prepaid balance settled: a failed action's debit has been credited back
+        self.maybe_request_top_up(&passkey_pk);
+        self.maybe_call_policy_post_hook(request_id, &passkey_pk, succeeded);
+        self.maybe_forward_result(request_id, succeeded, result);
+        log!("Delegated execution {:?} resolved. Succeeded: {}", request_id, succeeded);
+    }
+
+    // internal method consuming the passkey's next nonce for executions that don't carry one
+    pub(crate) fn next_passkey_nonce(&mut self, passkey_pk: &PublicKey) -> u64 {
+        let nonce = *self.passkey_nonces.get(passkey_pk).unwrap_or(&0) + 1;
+        self.passkey_nonces.insert(passkey_pk.clone(), nonce);
+        nonce
+    }
+}
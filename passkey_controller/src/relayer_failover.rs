@@ -0,0 +1,89 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// Hands execution rights to `backup_relayer` while the trusted relayer is silent: once it
+/// has missed `max_missed_heartbeats` heartbeats of `heartbeat_interval_blocks` each.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayerFailoverConfig {
+    pub backup_relayer: AccountId,
+    pub heartbeat_interval_blocks: U64,
+    pub max_missed_heartbeats: u32,
+}
+
+#[near]
+impl PasskeyController {
+    pub fn set_relayer_failover(&mut self, config: Option<RelayerFailoverConfig>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set relayer failover"
+        );
+        if let Some(config) = &config {
+            assert!(config.heartbeat_interval_blocks.0 > 0, "Heartbeat interval must be greater than 0");
+            assert!(config.max_missed_heartbeats > 0, "Max missed heartbeats must be greater than 0");
+            // The primary gets a full window to send its first heartbeat
+            self.relayer_last_seen.insert(self.trusted_relayer_account_id.clone(), env::block_height());
+        }
+        self.relayer_failover = config;
+    }
+
+    pub fn get_relayer_failover(&self) -> Option<RelayerFailoverConfig> {
+        self.relayer_failover.clone()
+    }
+
+    /// Called periodically by the trusted relayer, and by the backup to show it's ready.
+    pub fn relayer_heartbeat(&mut self) {
+        let caller = env::predecessor_account_id();
+        assert!(
+            caller == self.trusted_relayer_account_id || self.is_backup_relayer(&caller),
+            "Only trusted relayers can send heartbeats"
+        );
+        self.relayer_last_seen.insert(caller, env::block_height());
+    }
+
+    /// Block height of the relayer's last heartbeat.
+    pub fn get_relayer_last_seen(&self, relayer_id: AccountId) -> Option<U64> {
+        self.relayer_last_seen.get(&relayer_id).map(|height| U64(*height))
+    }
+
+    /// The relayer currently holding execution rights: the backup while failed over,
+    /// otherwise the trusted relayer.
+    pub fn get_active_relayer(&self) -> AccountId {
+        match &self.relayer_failover {
+            Some(config) if self.primary_relayer_down(config) => config.backup_relayer.clone(),
+            _ => self.trusted_relayer_account_id.clone(),
+        }
+    }
+}
+
+impl PasskeyController {
+    fn is_backup_relayer(&self, account_id: &AccountId) -> bool {
+        self.relayer_failover.as_ref().is_some_and(|config| &config.backup_relayer == account_id)
+    }
+
+    fn primary_relayer_down(&self, config: &RelayerFailoverConfig) -> bool {
+        let Some(last_seen) = self.relayer_last_seen.get(&self.trusted_relayer_account_id) else {
+            return false;
+        };
+        let window = config.heartbeat_interval_blocks.0.saturating_mul(config.max_missed_heartbeats as u64);
+        env::block_height().saturating_sub(*last_seen) > window
+    }
+
+    // internal check for relayer-submitted executions: the trusted relayer, or the backup
+    // while the trusted relayer is down
+    pub(crate) fn assert_active_relayer(&self) -> AccountId {
+        let caller = env::predecessor_account_id();
+        if caller != self.trusted_relayer_account_id {
+            assert_eq!(caller, self.get_active_relayer(), "Only trusted relayer can execute actions");
+        }
+        caller
+    }
+
+    // internal method restarting the heartbeat window for a newly set trusted relayer
+    pub(crate) fn reset_relayer_heartbeat(&mut self) {
+        if self.relayer_failover.is_some() {
+            self.relayer_last_seen.insert(self.trusted_relayer_account_id.clone(), env::block_height());
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::*;
+use crate::events::ControllerEvent;
+
+pub const MAX_RELAYER_FEE_BPS: u16 = 10_000;
+
+/// Fee a relayer earns per delegated execution: `flat_fee` plus `fee_bps` of the
+/// value the action attaches. Only charged while prepaid accounting is enabled.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RelayerFeeConfig {
+    pub flat_fee: U128,
+    pub fee_bps: u16,
+}
+
+impl RelayerFeeConfig {
+    pub fn fee_for(&self, attached_value: u128) -> u128 {
+        self.flat_fee.0 + attached_value * self.fee_bps as u128 / MAX_RELAYER_FEE_BPS as u128
+    }
+}
+
+#[near]
+impl PasskeyController {
+    /// Sets the fee `relayer_id` earns on the delegated actions it submits. `None` removes it.
+    pub fn set_relayer_fee_config(&mut self, relayer_id: AccountId, config: Option<RelayerFeeConfig>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set relayer fees"
+        );
+        match config {
+            Some(config) => {
+                assert!(config.fee_bps <= MAX_RELAYER_FEE_BPS, "fee_bps must be at most {}", MAX_RELAYER_FEE_BPS);
+                self.relayer_fee_configs.insert(relayer_id, config);
+            }
+            None => {
+                self.relayer_fee_configs.remove(&relayer_id);
+            }
+        }
+    }
+
+    pub fn get_relayer_fee_config(&self, relayer_id: AccountId) -> Option<RelayerFeeConfig> {
+        self.relayer_fee_configs.get(&relayer_id).cloned()
+    }
+
+    pub fn get_relayer_fee_balance(&self, relayer_id: AccountId) -> U128 {
+        U128(*self.relayer_fee_balances.get(&relayer_id).unwrap_or(&0))
+    }
+
+    /// Transfers the caller's earned relayer fees to them.
+    pub fn withdraw_relayer_fees(&mut self) -> U128 {
+        let relayer_id = env::predecessor_account_id();
+        let amount = self
+            .relayer_fee_balances
+            .remove(&relayer_id)
+            .unwrap_or_else(|| panic!("No relayer fees to withdraw"));
+        Promise::new(relayer_id.clone()).transfer(NearToken::from_yoctonear(amount));
+        log!("Relayer {} withdrew {} in fees", relayer_id, amount);
+        U128(amount)
+    }
+}
+
+impl PasskeyController {
+    // internal method moving the submitting relayer's fee from the passkey's prepaid balance
+    // to the relayer's fee balance. The fee is kept even if the action later fails.
+    pub(crate) fn charge_relayer_fee(&mut self, passkey_pk: &PublicKey, action: &SerializableAction) -> u128 {
+        if !self.prepaid_accounting_enabled {
+            return 0;
+        }
+        let relayer_id = env::predecessor_account_id();
+        let Some(config) = self.relayer_fee_configs.get(&relayer_id) else {
+            return 0;
+        };
+        let fee = self.debit_prepaid(passkey_pk, config.fee_for(action.attached_value()));
+        if fee == 0 {
+            return 0;
+        }
+        let balance = self.relayer_fee_balances.get(&relayer_id).unwrap_or(&0) + fee;
+        self.relayer_fee_balances.insert(relayer_id.clone(), balance);
+        ControllerEvent::RelayerFeeCharged {
+            relayer_id,
+            passkey_pk: passkey_pk.clone(),
+            amount: U128(fee),
+        }
+        .emit();
+        fee
+    }
+}
@@ -0,0 +1,80 @@
+use crate::*;
+use near_sdk::CryptoHash;
+
+pub const GAS_FOR_RESULT_CALLBACK: Gas = Gas::from_tgas(10);
+
+/// Contract method told how a delegated action resolved, called with
+/// `{ request_id, succeeded, result }` where `result` is the action's base64 return value.
+/// It must be an allowed call, like a FunctionCall action's target.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultCallback {
+    pub receiver_id: AccountId,
+    pub method_name: String,
+}
+
+#[near(serializers = [json])]
+struct ResultCallbackArgs {
+    request_id: Base58CryptoHash,
+    succeeded: bool,
+    result: Base64VecU8,
+}
+
+#[near]
+impl PasskeyController {
+    /// `execute_delegated_actions`, then forwards the action's outcome to `result_callback`
+    /// so dApps can drive their own state off delegated actions.
+    pub fn execute_delegated_actions_with_callback(
+        &mut self,
+        passkey_pk_used: PublicKey,
+        action_to_execute: SerializableAction,
+        result_callback: ResultCallback,
+    ) -> Base58CryptoHash {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk_used) {
+            return self.rejected_request_id(&passkey_pk_used, &action_to_execute);
+        }
+        self.assert_single_passkey_can_execute(&action_to_execute);
+        assert!(
+            self.is_call_allowed(result_callback.receiver_id.clone(), result_callback.method_name.clone()),
+            "ERR_CALL_NOT_ALLOWED"
+        );
+
+        let nonce = self.next_passkey_nonce(&passkey_pk_used);
+        let request_id = receipts::compute_request_id(&passkey_pk_used, nonce, &action_to_execute);
+        self.result_callbacks.insert(request_id, result_callback);
+        self.dispatch_delegated(passkey_pk_used, nonce, action_to_execute)
+    }
+
+    pub fn get_result_callback(&self, request_id: Base58CryptoHash) -> Option<ResultCallback> {
+        let request_id: CryptoHash = request_id.into();
+        self.result_callbacks.get(&request_id).cloned()
+    }
+}
+
+impl PasskeyController {
+    // internal method returning the extra gas the result callback needs, if one was registered
+    pub(crate) fn result_callback_gas(&self, request_id: Base58CryptoHash) -> Gas {
+        let key: CryptoHash = request_id.into();
+        if self.result_callbacks.contains_key(&key) {
+            GAS_FOR_RESULT_CALLBACK
+        } else {
+            Gas::from_gas(0)
+        }
+    }
+
+    // internal method calling the registered result callback once the action has resolved
+    pub(crate) fn maybe_forward_result(&mut self, request_id: Base58CryptoHash, succeeded: bool, result: Vec<u8>) {
+        let key: CryptoHash = request_id.into();
+        let Some(callback) = self.result_callbacks.remove(&key) else {
+            return;
+        };
+        let args = near_sdk::serde_json::to_vec(&ResultCallbackArgs { request_id, succeeded, result: Base64VecU8(result) })
+            .unwrap_or_else(|_| panic!("ERR_PAYLOAD_SERIALIZATION"));
+        Promise::new(callback.receiver_id).function_call(
+            callback.method_name,
+            args,
+            NearToken::from_yoctonear(0),
+            GAS_FOR_RESULT_CALLBACK,
+        );
+    }
+}
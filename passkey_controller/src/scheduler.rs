@@ -0,0 +1,137 @@
+use crate::*;
+use crate::events::ControllerEvent;
+use near_sdk::json_types::U64;
+
+/// Max tip of a scheduled action, 0.01 NEAR.
+pub const MAX_SCHEDULED_TIP: u128 = 10_000_000_000_000_000_000_000;
+
+/// A delegated action stored by a passkey user to run once `execute_after` has passed.
+/// Whoever triggers execution is paid `tip` from the controller's balance, debited from the
+/// passkey's prepaid balance like the action's own value.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone)]
+pub struct ScheduledAction {
+    pub passkey_pk: PublicKey,
+    pub action: SerializableAction,
+    pub execute_after: U64,
+    pub tip: U128,
+}
+
+#[near]
+impl PasskeyController {
+    /// Stores `action` to be executed by anyone once `execute_after_ns` (block timestamp) has passed.
+    /// `tip` is capped at `MAX_SCHEDULED_TIP`.
+    pub fn schedule_action(
+        &mut self,
+        passkey_pk: PublicKey,
+        action: SerializableAction,
+        execute_after_ns: U64,
+        tip: Option<U128>,
+    ) -> U64 {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk) {
+            return auth_backoff::REJECTED_ID;
+        }
+        self.assert_single_passkey_can_execute(&action);
+        let tip = tip.unwrap_or(U128(0));
+        assert!(tip.0 <= MAX_SCHEDULED_TIP, "Tip can be at most {} yoctoNEAR", MAX_SCHEDULED_TIP);
+        assert!(
+            execute_after_ns.0 > clock::block_timestamp(),
+            "Execution time must be in the future"
+        );
+
+        let id = self.next_scheduled_id;
+        self.next_scheduled_id += 1;
+        self.scheduled_actions.insert(
+            id,
+            ScheduledAction {
+                passkey_pk: passkey_pk.clone(),
+                action,
+                execute_after: execute_after_ns,
+                tip,
+            },
+        );
+        self.queued_scheduled_ids.insert(id);
+        ControllerEvent::ActionScheduled {
+            scheduled_id: U64(id),
+            passkey_pk,
+            execute_after: execute_after_ns,
+        }
+        .emit();
+        U64(id)
+    }
+
+    pub fn get_scheduled_action(&self, scheduled_id: U64) -> Option<ScheduledAction> {
+        self.scheduled_actions.get(&scheduled_id.0).cloned()
+    }
+
+    /// Paginated actions still queued, for monitoring the timelock queue.
+    pub fn get_queued_scheduled_actions(&self, from_index: u32, limit: u32) -> Vec<(U64, ScheduledAction)> {
+        self.queued_scheduled_ids
+            .iter()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .filter_map(|scheduled_id| {
+                let scheduled = self.scheduled_actions.get(scheduled_id)?.clone();
+                Some((U64(*scheduled_id), scheduled))
+            })
+            .collect()
+    }
+
+    pub fn get_queued_scheduled_count(&self) -> u32 {
+        self.queued_scheduled_ids.len()
+    }
+
+    /// Executes a scheduled action whose time has passed. Callable by anyone; the caller earns the tip.
+    pub fn execute_scheduled(&mut self, scheduled_id: U64) -> Base58CryptoHash {
+        let scheduled = self
+            .scheduled_actions
+            .remove(&scheduled_id.0)
+            .unwrap_or_else(|| panic!("Scheduled action not found"));
+        self.queued_scheduled_ids.remove(&scheduled_id.0);
+        assert!(
+            clock::block_timestamp() >= scheduled.execute_after.0,
+            "Scheduled action is not yet executable"
+        );
+        // The passkey may have been removed since scheduling
+        assert!(
+            self.registered_passkey_pks.contains(&scheduled.passkey_pk),
+            "Passkey PK not registered"
+        );
+        self.assert_passkey_not_expired(&scheduled.passkey_pk);
+
+        if scheduled.tip.0 > 0 {
+            // The tip leaves the controller's balance on top of the action's value
+            assert!(
+                scheduled.action.attached_value().saturating_add(scheduled.tip.0) <= self.get_spendable_controller_balance().0,
+                "ERR_BALANCE_RESERVE_BREACHED"
+            );
+            self.debit_prepaid(&scheduled.passkey_pk, scheduled.tip.0);
+            Promise::new(env::predecessor_account_id()).transfer(NearToken::from_yoctonear(scheduled.tip.0));
+        }
+        ControllerEvent::ScheduledActionExecuted {
+            scheduled_id,
+            executor_id: env::predecessor_account_id(),
+        }
+        .emit();
+        let nonce = self.next_passkey_nonce(&scheduled.passkey_pk);
+        self.dispatch_delegated(scheduled.passkey_pk, nonce, scheduled.action)
+    }
+
+    /// Cancels a scheduled action. Callable by the relayer for the scheduling passkey, or the owner.
+    pub fn cancel_scheduled(&mut self, scheduled_id: U64) {
+        let scheduled = self
+            .scheduled_actions
+            .get(&scheduled_id.0)
+            .cloned()
+            .unwrap_or_else(|| panic!("Scheduled action not found"));
+        let caller = env::predecessor_account_id();
+        if caller != self.owner_id {
+            if !self.authorize_relayer_with_registered_passkey(&scheduled.passkey_pk) {
+                return;
+            }
+        }
+        self.scheduled_actions.remove(&scheduled_id.0);
+        self.queued_scheduled_ids.remove(&scheduled_id.0);
+        ControllerEvent::ScheduledActionCancelled { scheduled_id }.emit();
+    }
+}
@@ -0,0 +1,26 @@
+use crate::*;
+use crate::envelope::SignedActionEnvelope;
+use crate::receipts::ExecutionReceipt;
+use crate::versioned::VersionedAction;
+use schemars::schema_for;
+
+#[near]
+impl PasskeyController {
+    /// JSON Schema of `SerializableAction`, for building and validating action payloads client-side.
+    pub fn get_action_schema(&self) -> String {
+        near_sdk::serde_json::to_string(&schema_for!(SerializableAction))
+            .unwrap_or_else(|_| panic!("ERR_SCHEMA_SERIALIZATION"))
+    }
+
+    /// JSON Schemas of the types accepted and returned by the controller's execution methods,
+    /// keyed by type name. The full contract ABI is generated by `cargo near build`.
+    pub fn get_abi(&self) -> String {
+        let schemas = near_sdk::serde_json::json!({
+            "SerializableAction": schema_for!(SerializableAction),
+            "VersionedAction": schema_for!(VersionedAction),
+            "SignedActionEnvelope": schema_for!(SignedActionEnvelope),
+            "ExecutionReceipt": schema_for!(ExecutionReceipt),
+        });
+        schemas.to_string()
+    }
+}
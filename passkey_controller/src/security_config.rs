@@ -0,0 +1,59 @@
+use crate::*;
+use crate::bonding::RelayerBondConfig;
+use crate::guardians::GuardianConfig;
+use crate::init_config::InitConfig;
+use crate::limits::ControllerLimits;
+use crate::multisig::MultisigConfig;
+use crate::policy_hooks::PolicyHookConfig;
+use crate::scheduler::ScheduledAction;
+use crate::self_registration::SelfRegistrationConfig;
+use near_sdk::json_types::U64;
+
+/// How many queued scheduled actions `get_security_config` includes. The rest can be paged
+/// through with `get_queued_scheduled_actions`.
+pub const SECURITY_CONFIG_SCHEDULED_LIMIT: u32 = 200;
+
+/// Every security-relevant setting of the controller in one response, for reviews and
+/// monitoring. Contains `get_config` inline, plus settings that aren't part of initialization.
+#[near_sdk::near(serializers = [json])]
+#[derive(Debug, Clone)]
+pub struct SecurityConfig {
+    #[serde(flatten)]
+    pub config: InitConfig,
+    pub active_relayer: AccountId,
+    pub relayer_bond_config: RelayerBondConfig,
+    pub limits: ControllerLimits,
+    pub multisig_config: Option<MultisigConfig>,
+    pub guardian_config: Option<GuardianConfig>,
+    pub policy_hook: Option<PolicyHookConfig>,
+    pub self_registration: Option<SelfRegistrationConfig>,
+    pub staking_pools: Vec<AccountId>,
+    pub allowed_beneficiaries: Vec<AccountId>,
+    pub direct_action_proxies: Vec<AccountId>,
+    pub managed_accounts: Vec<AccountId>,
+    /// All scheduled actions still queued, of which the first `SECURITY_CONFIG_SCHEDULED_LIMIT` are listed
+    pub scheduled_action_count: u32,
+    pub scheduled_actions: Vec<(U64, ScheduledAction)>,
+}
+
+#[near]
+impl PasskeyController {
+    pub fn get_security_config(&self) -> SecurityConfig {
+        SecurityConfig {
+            config: self.get_config(),
+            active_relayer: self.get_active_relayer(),
+            relayer_bond_config: self.relayer_bond_config.clone(),
+            limits: self.get_limits(),
+            multisig_config: self.multisig_config.clone(),
+            guardian_config: self.guardian_config.clone(),
+            policy_hook: self.policy_hook.clone(),
+            self_registration: self.self_registration.clone(),
+            staking_pools: self.staking_pools.clone(),
+            allowed_beneficiaries: self.allowed_beneficiaries.clone(),
+            direct_action_proxies: self.direct_action_proxies.clone(),
+            managed_accounts: self.managed_accounts.clone(),
+            scheduled_action_count: self.get_queued_scheduled_count(),
+            scheduled_actions: self.get_queued_scheduled_actions(0, SECURITY_CONFIG_SCHEDULED_LIMIT),
+        }
+    }
+}
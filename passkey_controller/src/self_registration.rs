@@ -0,0 +1,164 @@
+use crate::*;
+use crate::envelope::verify_passkey_signature;
+use crate::events::ControllerEvent;
+use near_sdk::CryptoHash;
+
+/// Owner settings for `register_my_passkey`. Self-registration is disabled while unset.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfRegistrationConfig {
+    pub deposit: U128, // anti-spam deposit kept by the controller, in yoctoNEAR
+}
+
+/// Proof of possession of a passkey: its signature over the `RegistrationPayload`
+/// for a challenge issued to the caller by `issue_challenge`.
+#[near_sdk::near(serializers = [json])]
+#[derive(Debug, Clone)]
+pub struct RegistrationProof {
+    pub challenge: Base64VecU8,
+    pub signature: Base64VecU8,
+}
+
+/// Canonical payload the passkey signs for `register_my_passkey`.
+#[near_sdk::near(serializers = [borsh])]
+#[derive(Debug, Clone)]
+pub struct RegistrationPayload {
+    pub controller_id: AccountId,
+    pub account_id: AccountId,
+    pub passkey_pk: PublicKey,
+    pub challenge: CryptoHash,
+}
+
+impl RegistrationPayload {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        near_sdk::borsh::to_vec(self).unwrap_or_else(|_| panic!("ERR_PAYLOAD_SERIALIZATION"))
+    }
+}
+
+#[near]
+impl PasskeyController {
+    /// Enables self-registration with the given anti-spam deposit, or disables it with `None`.
+    pub fn set_self_registration(&mut self, config: Option<SelfRegistrationConfig>) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set self registration"
+        );
+        self.self_registration = config;
+    }
+
+    pub fn get_self_registration(&self) -> Option<SelfRegistrationConfig> {
+        self.self_registration.clone()
+    }
+
+    /// Returns the exact bytes a passkey must sign for `register_my_passkey`.
+    pub fn get_registration_payload(
+        &self,
+        account_id: AccountId,
+        passkey_pk: PublicKey,
+        challenge: Base64VecU8,
+    ) -> Base64VecU8 {
+        let challenge: CryptoHash = challenge.0.try_into().unwrap_or_else(|_| panic!("ERR_INVALID_CHALLENGE_LENGTH"));
+        let payload = RegistrationPayload {
+            controller_id: env::current_account_id(),
+            account_id,
+            passkey_pk,
+            challenge,
+        };
+        Base64VecU8(payload.to_bytes())
+    }
+
+    /// Requests registration of `passkey_pk` without the relayer, given a signature from the
+    /// passkey over a challenge issued to the caller. The challenge is consumed. The passkey
+    /// can't execute anything until the owner or relayer calls `approve_self_registered_passkey`.
+    #[payable]
+    pub fn register_my_passkey(&mut self, passkey_pk: PublicKey, proof: RegistrationProof) -> bool {
+        passkey_keys::assert_valid_passkey_pk(&passkey_pk);
+        let config = self
+            .self_registration
+            .clone()
+            .unwrap_or_else(|| panic!("ERR_SELF_REGISTRATION_DISABLED"));
+        assert!(
+            env::attached_deposit().as_yoctonear() >= config.deposit.0,
+            "ERR_INSUFFICIENT_REGISTRATION_DEPOSIT"
+        );
+
+        let account_id = env::predecessor_account_id();
+        let challenge: CryptoHash = proof
+            .challenge
+            .0
+            .try_into()
+            .unwrap_or_else(|_| panic!("ERR_INVALID_CHALLENGE_LENGTH"));
+        let issued = self
+            .webauthn_challenges
+            .remove(&challenge)
+            .unwrap_or_else(|| panic!("ERR_CHALLENGE_NOT_FOUND"));
+        assert_eq!(issued.issued_to, account_id, "ERR_CHALLENGE_ISSUED_TO_ANOTHER_ACCOUNT");
+        assert!(clock::block_timestamp() <= issued.expires_at.0, "ERR_CHALLENGE_EXPIRED");
+
+        let payload = RegistrationPayload {
+            controller_id: env::current_account_id(),
+            account_id: account_id.clone(),
+            passkey_pk: passkey_pk.clone(),
+            challenge,
+        };
+        assert!(
+            verify_passkey_signature(&passkey_pk, &payload.to_bytes(), &proof.signature.0),
+            "ERR_INVALID_REGISTRATION_SIGNATURE"
+        );
+
+        if self.registered_passkey_pks.contains(&passkey_pk) || self.pending_self_registrations.contains_key(&passkey_pk) {
+            return false;
+        }
+        self.pending_self_registrations.insert(passkey_pk.clone(), account_id.clone());
+        ControllerEvent::PasskeySelfRegistered {
+            passkey_pk,
+            registered_by: account_id,
+        }
+        .emit();
+        true
+    }
+
+    /// Account that requested registration of a passkey still awaiting approval.
+    pub fn get_pending_self_registration(&self, passkey_pk: PublicKey) -> Option<AccountId> {
+        self.pending_self_registrations.get(&passkey_pk).cloned()
+    }
+
+    /// Registers a self-registered passkey. Only the owner or the active relayer can approve.
+    pub fn approve_self_registered_passkey(&mut self, passkey_pk: PublicKey) -> bool {
+        let caller = self.assert_owner_or_active_relayer("Only owner or relayer can approve self registered passkeys");
+        assert!(
+            self.pending_self_registrations.remove(&passkey_pk).is_some(),
+            "ERR_NO_PENDING_SELF_REGISTRATION"
+        );
+        self.assert_can_add_passkey(&passkey_pk);
+        let added = self.registered_passkey_pks.insert(passkey_pk.clone());
+        if added {
+            ControllerEvent::SelfRegisteredPasskeyApproved {
+                passkey_pk,
+                approved_by: caller,
+            }
+            .emit();
+        }
+        added
+    }
+
+    /// Drops a pending self registration. The registration deposit is kept.
+    pub fn reject_self_registered_passkey(&mut self, passkey_pk: PublicKey) {
+        self.assert_owner_or_active_relayer("Only owner or relayer can reject self registered passkeys");
+        self.pending_self_registrations.remove(&passkey_pk);
+    }
+}
+
+impl PasskeyController {
+    // internal method returning the caller if it is the owner or the active relayer
+    fn assert_owner_or_active_relayer(&self, message: &str) -> AccountId {
+        let caller = env::predecessor_account_id();
+        assert!(
+            caller == self.owner_id || caller == self.trusted_relayer_account_id || caller == self.get_active_relayer(),
+            "{}",
+            message
+        );
+        caller
+    }
+}
@@ -0,0 +1,186 @@
+use crate::*;
+use crate::envelope::verify_passkey_signature;
+use crate::events::ControllerEvent;
+use near_sdk::json_types::U64;
+
+/// Longest session a passkey can grant: 24 hours.
+pub const MAX_SESSION_TTL_NS: u64 = 86_400_000_000_000;
+
+/// What a session may do. Sessions only run FunctionCall and Transfer actions to
+/// `receivers`; an empty `method_names` allows any method on those receivers.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionPolicy {
+    pub receivers: Vec<AccountId>,
+    pub method_names: Vec<String>,
+    pub max_amount_per_action: Option<U128>, // yoctoNEAR
+    pub max_total_amount: Option<U128>, // yoctoNEAR across the session
+}
+
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub passkey_pk: PublicKey,
+    pub policy: SessionPolicy,
+    pub expires_at: U64,
+    pub spent: U128,
+}
+
+/// Canonical payload a passkey signs to grant a session.
+#[near_sdk::near(serializers = [borsh])]
+#[derive(Debug, Clone)]
+pub struct SessionPayload {
+    pub controller_id: AccountId,
+    pub passkey_pk: PublicKey,
+    pub policy: SessionPolicy,
+    pub ttl_ns: u64,
+    pub nonce: u64,
+}
+
+impl SessionPayload {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        near_sdk::borsh::to_vec(self).unwrap_or_else(|_| panic!("ERR_PAYLOAD_SERIALIZATION"))
+    }
+}
+
+#[near]
+impl PasskeyController {
+    /// Returns the exact bytes a passkey must sign for `create_session`.
+    pub fn get_session_payload(&self, passkey_pk: PublicKey, policy: SessionPolicy, ttl_ns: U64, nonce: U64) -> Base64VecU8 {
+        let payload = SessionPayload {
+            controller_id: env::current_account_id(),
+            passkey_pk,
+            policy,
+            ttl_ns: ttl_ns.0,
+            nonce: nonce.0,
+        };
+        Base64VecU8(payload.to_bytes())
+    }
+
+    /// Opens a session from a passkey's signature over the policy. The relayer can then run
+    /// actions within the policy with `execute_with_session` until it expires. `nonce` shares
+    /// the passkey's envelope nonces.
+    pub fn create_session(
+        &mut self,
+        passkey_pk: PublicKey,
+        policy: SessionPolicy,
+        ttl_ns: U64,
+        nonce: U64,
+        signature: Base64VecU8,
+    ) -> U64 {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk) {
+            return auth_backoff::REJECTED_ID;
+        }
+        assert!(ttl_ns.0 > 0 && ttl_ns.0 <= MAX_SESSION_TTL_NS, "ERR_INVALID_SESSION_TTL");
+        assert!(!policy.receivers.is_empty(), "ERR_SESSION_POLICY_WITHOUT_RECEIVERS");
+        self.assert_can_add_session();
+        let last_nonce = *self.passkey_nonces.get(&passkey_pk).unwrap_or(&0);
+        assert!(nonce.0 > last_nonce, "ERR_ENVELOPE_NONCE_USED");
+
+        let payload = SessionPayload {
+            controller_id: env::current_account_id(),
+            passkey_pk: passkey_pk.clone(),
+            policy,
+            ttl_ns: ttl_ns.0,
+            nonce: nonce.0,
+        };
+        assert!(
+            verify_passkey_signature(&passkey_pk, &payload.to_bytes(), &signature.0),
+            "ERR_INVALID_SESSION_SIGNATURE"
+        );
+        self.passkey_nonces.insert(passkey_pk.clone(), nonce.0);
+
+        self.next_session_id += 1;
+        let session_id = self.next_session_id;
+        let expires_at = U64(clock::block_timestamp() + ttl_ns.0);
+        self.sessions.insert(
+            session_id,
+            Session { passkey_pk: passkey_pk.clone(), policy: payload.policy, expires_at, spent: U128(0) },
+        );
+        ControllerEvent::SessionCreated { session_id: U64(session_id), passkey_pk, expires_at }.emit();
+        U64(session_id)
+    }
+
+    /// Executes an action within a session's policy without a new passkey signature.
+    pub fn execute_with_session(&mut self, session_id: U64, action: SerializableAction) -> Base58CryptoHash {
+        let mut session = self
+            .sessions
+            .get(&session_id.0)
+            .cloned()
+            .unwrap_or_else(|| panic!("ERR_SESSION_NOT_FOUND"));
+        assert!(clock::block_timestamp() <= session.expires_at.0, "ERR_SESSION_EXPIRED");
+        if !self.authorize_relayer_with_registered_passkey(&session.passkey_pk) {
+            return self.rejected_request_id(&session.passkey_pk, &action);
+        }
+
+        let value = action.attached_value();
+        session.policy.assert_allows(&action);
+        let spent = session.spent.0 + value;
+        if let Some(max_total) = session.policy.max_total_amount {
+            assert!(spent <= max_total.0, "ERR_SESSION_TOTAL_EXCEEDED");
+        }
+        session.spent = U128(spent);
+        let passkey_pk = session.passkey_pk.clone();
+        self.sessions.insert(session_id.0, session);
+
+        self.assert_single_passkey_can_execute(&action);
+        let nonce = self.next_passkey_nonce(&passkey_pk);
+        self.dispatch_delegated(passkey_pk, nonce, action)
+    }
+
+    /// Ends a session early. Callable by the relayer or the owner.
+    pub fn revoke_session(&mut self, session_id: U64) -> bool {
+        let caller = env::predecessor_account_id();
+        assert!(
+            caller == self.trusted_relayer_account_id || caller == self.owner_id,
+            "Only trusted relayer or owner can revoke sessions"
+        );
+        let removed = self.sessions.remove(&session_id.0).is_some();
+        if removed {
+            ControllerEvent::SessionRevoked { session_id }.emit();
+        }
+        removed
+    }
+
+    pub fn get_session(&self, session_id: U64) -> Option<Session> {
+        self.sessions.get(&session_id.0).cloned()
+    }
+
+    /// Removes up to `limit` expired sessions. Callable by anyone to free storage.
+    pub fn prune_expired_sessions(&mut self, limit: u32) -> u32 {
+        let now = clock::block_timestamp();
+        let expired: Vec<u64> = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| now > session.expires_at.0)
+            .take(limit as usize)
+            .map(|(session_id, _)| *session_id)
+            .collect();
+        for session_id in expired.iter() {
+            self.sessions.remove(session_id);
+        }
+        expired.len() as u32
+    }
+}
+
+impl SessionPolicy {
+    // Checks a single action against the policy, excluding the session-wide total
+    pub fn assert_allows(&self, action: &SerializableAction) {
+        assert!(
+            matches!(action.action_type, ActionType::FunctionCall | ActionType::Transfer),
+            "ERR_SESSION_ACTION_NOT_ALLOWED"
+        );
+        let receiver_id = action
+            .receiver_id
+            .as_ref()
+            .unwrap_or_else(|| panic!("receiver_id is required for FunctionCall/Transfer"));
+        assert!(self.receivers.contains(receiver_id), "ERR_SESSION_RECEIVER_NOT_ALLOWED");
+        if matches!(action.action_type, ActionType::FunctionCall) && !self.method_names.is_empty() {
+            let method_name = action.method_name.clone().unwrap_or_default();
+            assert!(self.method_names.contains(&method_name), "ERR_SESSION_METHOD_NOT_ALLOWED");
+        }
+        if let Some(max_amount) = self.max_amount_per_action {
+            assert!(action.attached_value() <= max_amount.0, "ERR_SESSION_AMOUNT_EXCEEDED");
+        }
+    }
+}
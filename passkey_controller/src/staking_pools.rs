@@ -0,0 +1,154 @@
+use crate::*;
+use near_sdk::PromiseResult;
+
+// deposit_and_stake pings the pool and restakes, which needs more than a plain call.
+pub const GAS_FOR_STAKING_POOL_CALL: Gas = Gas::from_tgas(50);
+pub const GAS_FOR_ON_POOL_ACTION_RESULT: Gas = Gas::from_tgas(5);
+
+/// Principal a passkey moved into a staking pool, tracked apart from the controller's balance.
+/// A passkey can only unstake and withdraw its own principal, and withdrawn principal that was
+/// debited from its prepaid balance is credited back to it.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StakedPrincipal {
+    pub staked: U128,
+    pub unstaked: U128, // waiting for WithdrawFromPool
+    pub prepaid_funded: U128, // part of the principal debited from the prepaid balance
+}
+
+/// A pool action in flight. Unstaked and withdrawn amounts are taken from the principal when
+/// the action is sent and put back if it fails; staked amounts are added once it succeeds.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingPoolAction {
+    pub pool_id: AccountId,
+    pub action_type: ActionType,
+    pub amount: U128,
+    pub prepaid_debited: U128,
+}
+
+#[near]
+impl PasskeyController {
+    /// Whitelists a staking pool contract for StakeWithPool/UnstakeFromPool/WithdrawFromPool actions.
+    pub fn add_staking_pool(&mut self, pool_id: AccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage staking pools"
+        );
+        if !self.staking_pools.contains(&pool_id) {
+            self.staking_pools.push(pool_id);
+        }
+    }
+
+    pub fn remove_staking_pool(&mut self, pool_id: AccountId) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage staking pools"
+        );
+        self.staking_pools.retain(|id| id != &pool_id);
+    }
+
+    pub fn get_staking_pools(&self) -> Vec<AccountId> {
+        self.staking_pools.clone()
+    }
+
+    pub fn get_staked_principal(&self, passkey_pk: PublicKey, pool_id: AccountId) -> StakedPrincipal {
+        self.staked_principal.get(&(passkey_pk, pool_id)).cloned().unwrap_or_default()
+    }
+
+    /// Settles the principal of a pool action executed with `execute_direct_actions`.
+    #[private]
+    pub fn on_direct_pool_action_result(&mut self, passkey_pk: PublicKey, pending: PendingPoolAction) -> bool {
+        let succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        self.settle_pool_action(&passkey_pk, pending, succeeded);
+        succeeded
+    }
+}
+
+impl PasskeyController {
+    // internal method returning the action's staking pool, which must be whitelisted
+    pub(crate) fn get_staking_pool_or_panic(&self, action: &SerializableAction) -> AccountId {
+        let pool_id = action
+            .receiver_id
+            .clone()
+            .unwrap_or_else(|| panic!("receiver_id (staking pool) is required for {:?}", action.action_type));
+        assert!(self.staking_pools.contains(&pool_id), "ERR_STAKING_POOL_NOT_WHITELISTED");
+        pool_id
+    }
+
+    // internal method taking an unstake or withdrawal out of the passkey's principal in the pool
+    // as the action is sent. Returns None for actions that aren't pool actions.
+    pub(crate) fn reserve_pool_principal(
+        &mut self,
+        passkey_pk: &PublicKey,
+        action: &SerializableAction,
+        prepaid_debited: u128,
+    ) -> Option<PendingPoolAction> {
+        if !matches!(
+            action.action_type,
+            ActionType::StakeWithPool | ActionType::UnstakeFromPool | ActionType::WithdrawFromPool
+        ) {
+            return None;
+        }
+        // A WithdrawFromPool without an amount would also withdraw other passkeys' principal and the rewards
+        let amount = action
+            .amount
+            .unwrap_or_else(|| panic!("amount is required for {:?}", action.action_type))
+            .0;
+        let pool_id = self.get_staking_pool_or_panic(action);
+        let key = (passkey_pk.clone(), pool_id.clone());
+        let mut principal = self.staked_principal.get(&key).cloned().unwrap_or_default();
+        match action.action_type {
+            ActionType::UnstakeFromPool => {
+                assert!(amount <= principal.staked.0, "ERR_UNSTAKE_EXCEEDS_STAKED_PRINCIPAL");
+                principal.staked = U128(principal.staked.0 - amount);
+            }
+            ActionType::WithdrawFromPool => {
+                assert!(amount <= principal.unstaked.0, "ERR_WITHDRAW_EXCEEDS_UNSTAKED_PRINCIPAL");
+                principal.unstaked = U128(principal.unstaked.0 - amount);
+            }
+            _ => {}
+        }
+        self.store_staked_principal(key, principal);
+        Some(PendingPoolAction {
+            pool_id,
+            action_type: action.action_type.clone(),
+            amount: U128(amount),
+            prepaid_debited: U128(prepaid_debited),
+        })
+    }
+
+    // internal method applying a pool action's result to the passkey's principal
+    pub(crate) fn settle_pool_action(&mut self, passkey_pk: &PublicKey, pending: PendingPoolAction, succeeded: bool) {
+        let key = (passkey_pk.clone(), pending.pool_id);
+        let mut principal = self.staked_principal.get(&key).cloned().unwrap_or_default();
+        let amount = pending.amount.0;
+        let mut prepaid_credit = 0;
+        match (pending.action_type, succeeded) {
+            (ActionType::StakeWithPool, true) => {
+                principal.staked = U128(principal.staked.0 + amount);
+                principal.prepaid_funded = U128(principal.prepaid_funded.0 + pending.prepaid_debited.0);
+            }
+            (ActionType::UnstakeFromPool, true) => principal.unstaked = U128(principal.unstaked.0 + amount),
+            (ActionType::UnstakeFromPool, false) => principal.staked = U128(principal.staked.0 + amount),
+            (ActionType::WithdrawFromPool, true) => {
+                prepaid_credit = amount.min(principal.prepaid_funded.0);
+                principal.prepaid_funded = U128(principal.prepaid_funded.0 - prepaid_credit);
+            }
+            (ActionType::WithdrawFromPool, false) => principal.unstaked = U128(principal.unstaked.0 + amount),
+            _ => {}
+        }
+        self.store_staked_principal(key, principal);
+        self.credit_prepaid(passkey_pk, prepaid_credit);
+    }
+
+    fn store_staked_principal(&mut self, key: (PublicKey, AccountId), principal: StakedPrincipal) {
+        if principal.staked.0 == 0 && principal.unstaked.0 == 0 && principal.prepaid_funded.0 == 0 {
+            self.staked_principal.remove(&key);
+        } else {
+            self.staked_principal.insert(key, principal);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::*;
+
+pub const USER_PLACEHOLDER: &str = "{user}";
+pub const AMOUNT_PLACEHOLDER: &str = "{amount}";
+
+/// Owner-registered FunctionCall with JSON args containing `{user}`/`{amount}` placeholders.
+/// The call must still be allowed with `add_allowed_call` to execute.
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone)]
+pub struct CallTemplate {
+    pub receiver_id: AccountId,
+    pub method_name: String,
+    pub args_template: String,
+    pub deposit: Option<U128>, // yoctoNEAR
+    pub gas: Option<Gas>,
+}
+
+/// Values substituted into a `CallTemplate`'s placeholders.
+#[near_sdk::near(serializers = [json])]
+#[derive(Debug, Clone, Default)]
+pub struct TemplateParams {
+    pub user: Option<AccountId>,
+    pub amount: Option<U128>,
+}
+
+impl CallTemplate {
+    /// Substitutes `params` into the args template and checks the result is valid JSON.
+    pub fn render_args(&self, params: &TemplateParams) -> Vec<u8> {
+        let mut args = self.args_template.clone();
+        if args.contains(USER_PLACEHOLDER) {
+            let user = params.user.as_ref().unwrap_or_else(|| panic!("ERR_TEMPLATE_MISSING_PARAM: user"));
+            args = args.replace(USER_PLACEHOLDER, user.as_str());
+        }
+        if args.contains(AMOUNT_PLACEHOLDER) {
+            let amount = params.amount.unwrap_or_else(|| panic!("ERR_TEMPLATE_MISSING_PARAM: amount"));
+            args = args.replace(AMOUNT_PLACEHOLDER, &amount.0.to_string());
+        }
+        near_sdk::serde_json::from_str::<near_sdk::serde_json::Value>(&args)
+            .unwrap_or_else(|_| panic!("ERR_TEMPLATE_INVALID_ARGS"));
+        args.into_bytes()
+    }
+
+    pub fn to_action(&self, params: &TemplateParams) -> SerializableAction {
+        SerializableAction {
+            action_type: ActionType::FunctionCall,
+            receiver_id: Some(self.receiver_id.clone()),
+            method_name: Some(self.method_name.clone()),
+            args: Some(Base64VecU8(self.render_args(params))),
+            deposit: self.deposit,
+            gas: self.gas,
+            amount: None,
+            public_key: None,
+            allowance: None,
+            method_names: None,
+            code: None,
+            stake: None,
+            beneficiary_id: None,
+            initial_deposit_for_new_account: None,
+            public_key_for_new_account: None,
+            reverie_id: None,
+            user_id: None,
+            args_json: None,
+        }
+    }
+}
+
+#[near]
+impl PasskeyController {
+    pub fn set_call_template(&mut self, name: String, template: CallTemplate) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage call templates"
+        );
+        // Render with sample values so malformed templates are rejected up front
+        template.render_args(&TemplateParams {
+            user: Some(env::current_account_id()),
+            amount: Some(U128(0)),
+        });
+        self.call_templates.insert(name, template);
+    }
+
+    pub fn remove_call_template(&mut self, name: String) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can manage call templates"
+        );
+        self.call_templates.remove(&name);
+    }
+
+    pub fn get_call_template(&self, name: String) -> Option<CallTemplate> {
+        self.call_templates.get(&name).cloned()
+    }
+
+    /// Renders the named template with `params` and executes it as a delegated FunctionCall.
+    pub fn execute_template(
+        &mut self,
+        passkey_pk_used: PublicKey,
+        name: String,
+        params: TemplateParams,
+    ) -> Base58CryptoHash {
+        let template = self
+            .call_templates
+            .get(&name)
+            .unwrap_or_else(|| panic!("ERR_TEMPLATE_NOT_FOUND"));
+        let action = template.to_action(&params);
+        self.execute_delegated_actions(passkey_pk_used, action)
+    }
+}
@@ -1,15 +1,11 @@
 use super::*;
-use near_sdk::test_utils::{VMContextBuilder, accounts};
+use near_sdk::test_utils::accounts;
 use near_sdk::testing_env;
-
-fn get_context(predecessor_account_id: AccountId, current_account_id: AccountId) -> VMContextBuilder {
-    let mut builder = VMContextBuilder::new();
-    builder
-        .current_account_id(current_account_id)
-        .signer_account_id(predecessor_account_id.clone())
-        .predecessor_account_id(predecessor_account_id);
-    builder
-}
+use reveries_test_utils::{
+    charge_reverie_action, empty_action, function_call_action, get_context, passkey_pk, passkey_pk_of, passkey_signing_key, record_spend_action,
+    secp256k1_passkey_pk_of, secp256k1_sign_prehash, secp256k1_signing_key, secp256k1_uncompressed_pk_bytes,
+    set_promise_results, transfer_action,
+};
 
 #[test]
 fn test_new() {
@@ -184,6 +180,9 @@ fn test_execute_delegated_actions_panic_not_relayer() {
         beneficiary_id: None,
         initial_deposit_for_new_account: None,
         public_key_for_new_account: None,
+        reverie_id: None,
+        user_id: None,
+        args_json: None,
     };
 
     let non_relayer = accounts(3);
@@ -221,6 +220,9 @@ fn test_execute_delegated_actions_panic_pk_not_registered() {
         beneficiary_id: None,
         initial_deposit_for_new_account: None,
         public_key_for_new_account: None,
+        reverie_id: None,
+        user_id: None,
+        args_json: None,
     };
 
     let pk_unregistered_bytes: [u8; 32] = [99; 32];
@@ -264,6 +266,9 @@ fn test_execute_delegated_actions_runs_with_registered_pk() {
         beneficiary_id: None, // Not used for Transfer
         initial_deposit_for_new_account: None,
         public_key_for_new_account: None,
+        reverie_id: None,
+        user_id: None,
+        args_json: None,
     };
 
     // This will attempt to create a promise but won't execute it in test_utils.
@@ -310,6 +315,9 @@ fn test_execute_direct_actions_runs_with_registered_pk() {
         beneficiary_id: None,
         initial_deposit_for_new_account: None,
         public_key_for_new_account: None,
+        reverie_id: None,
+        user_id: None,
+        args_json: None,
     };
 
     contract.execute_direct_actions(transfer_action);
@@ -354,6 +362,9 @@ fn test_execute_direct_actions_panic_pk_not_registered() {
         beneficiary_id: None,
         initial_deposit_for_new_account: None,
         public_key_for_new_account: None,
+        reverie_id: None,
+        user_id: None,
+        args_json: None,
     };
 
     contract.execute_direct_actions(dummy_action);
@@ -388,9 +399,12 @@ fn test_execute_direct_actions_create_account() {
         receiver_id: Some(new_account_id.clone()), // This is the new account to be created
         initial_deposit_for_new_account: Some(U128(1_000_000_000_000_000_000_000_000)), // 1 NEAR
         public_key_for_new_account: Some(pk_new_account.clone()),
+        reverie_id: None,
+        user_id: None,
         // Other fields as None or default
         method_name: None,
         args: None,
+        args_json: None,
         deposit: None,
         gas: None,
         amount: None,
@@ -405,4 +419,2504 @@ fn test_execute_direct_actions_create_account() {
     contract.execute_direct_actions(create_account_action);
     // Test succeeds if it doesn't panic and implies promise for CreateAccount, Transfer, AddKey was formed.
     // Verification of actual state changes would require integration tests.
-}
\ No newline at end of file
+}
+// Tests for execute_signed_envelope
+
+fn sign_envelope(
+    contract: &PasskeyController,
+    signing_key: &ed25519_dalek::SigningKey,
+    action: SerializableAction,
+    nonce: u64,
+    valid_until: u64,
+) -> envelope::SignedActionEnvelope {
+    use ed25519_dalek::Signer;
+    let payload = contract.get_envelope_payload(
+        passkey_pk_of(signing_key),
+        action.clone(),
+        near_sdk::json_types::U64(nonce),
+        near_sdk::json_types::U64(valid_until),
+    );
+    envelope::SignedActionEnvelope {
+        action,
+        nonce: near_sdk::json_types::U64(nonce),
+        valid_until: near_sdk::json_types::U64(valid_until),
+        signature: Base64VecU8(signing_key.sign(&payload.0).to_bytes().to_vec()),
+    }
+}
+
+#[test]
+fn test_execute_signed_envelope_consumes_nonce() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    testing_env!(get_context(relayer.clone(), contract_account.clone()).build());
+
+    let signing_key = passkey_signing_key(11);
+    let passkey_pk = passkey_pk_of(&signing_key);
+    let mut contract = PasskeyController::new(relayer.clone(), owner.clone(), Some(vec![passkey_pk.clone()]));
+
+    let envelope = sign_envelope(&contract, &signing_key, transfer_action(accounts(3), 100), 1, u64::MAX);
+    contract.execute_signed_envelope(passkey_pk.clone(), envelope);
+    assert_eq!(contract.get_passkey_nonce(passkey_pk.clone()).0, 1);
+}
+
+#[test]
+#[should_panic(expected = "ERR_ENVELOPE_NONCE_USED")]
+fn test_execute_signed_envelope_panic_replayed() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    testing_env!(get_context(relayer.clone(), contract_account.clone()).build());
+
+    let signing_key = passkey_signing_key(11);
+    let passkey_pk = passkey_pk_of(&signing_key);
+    let mut contract = PasskeyController::new(relayer.clone(), owner.clone(), Some(vec![passkey_pk.clone()]));
+
+    let envelope = sign_envelope(&contract, &signing_key, transfer_action(accounts(3), 100), 1, u64::MAX);
+    contract.execute_signed_envelope(passkey_pk.clone(), envelope.clone());
+    contract.execute_signed_envelope(passkey_pk, envelope);
+}
+
+#[test]
+#[should_panic(expected = "ERR_INVALID_ENVELOPE_SIGNATURE")]
+fn test_execute_signed_envelope_panic_tampered_action() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    testing_env!(get_context(relayer.clone(), contract_account.clone()).build());
+
+    let signing_key = passkey_signing_key(11);
+    let passkey_pk = passkey_pk_of(&signing_key);
+    let mut contract = PasskeyController::new(relayer.clone(), owner.clone(), Some(vec![passkey_pk.clone()]));
+
+    let mut envelope = sign_envelope(&contract, &signing_key, transfer_action(accounts(3), 100), 1, u64::MAX);
+    // Relayer redirects the transfer to itself
+    envelope.action.receiver_id = Some(relayer.clone());
+    contract.execute_signed_envelope(passkey_pk, envelope);
+}
+
+#[test]
+#[should_panic(expected = "ERR_ENVELOPE_EXPIRED")]
+fn test_execute_signed_envelope_panic_expired() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    let mut context = get_context(relayer.clone(), contract_account.clone());
+    context.block_timestamp(1_000);
+    testing_env!(context.build());
+
+    let signing_key = passkey_signing_key(11);
+    let passkey_pk = passkey_pk_of(&signing_key);
+    let mut contract = PasskeyController::new(relayer.clone(), owner.clone(), Some(vec![passkey_pk.clone()]));
+
+    let envelope = sign_envelope(&contract, &signing_key, transfer_action(accounts(3), 100), 1, 999);
+    contract.execute_signed_envelope(passkey_pk, envelope);
+}
+
+// Tests for multisig approval mode
+
+fn contract_with_multisig(relayer: AccountId, owner: AccountId, passkeys: Vec<PublicKey>) -> PasskeyController {
+    let mut contract = PasskeyController::new(relayer.clone(), owner.clone(), Some(passkeys));
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    contract.set_multisig_config(Some(multisig::MultisigConfig {
+        value_threshold: U128(1_000),
+        required_approvals: 2,
+        proposal_ttl_ns: near_sdk::json_types::U64(1_000_000),
+    }));
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract
+}
+
+#[test]
+#[should_panic(expected = "ERR_ACTION_REQUIRES_MULTISIG_APPROVAL")]
+fn test_execute_delegated_actions_panic_above_multisig_threshold() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let pk2 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [2u8; 32].to_vec()).unwrap();
+    let mut contract = contract_with_multisig(relayer, owner, vec![pk1.clone(), pk2]);
+
+    contract.execute_delegated_actions(pk1, transfer_action(accounts(3), 1_000));
+}
+
+fn approval_signature(contract: &PasskeyController, proposal_id: near_sdk::json_types::U64, seed: u8, action: &SerializableAction) -> Base64VecU8 {
+    use ed25519_dalek::Signer;
+    let payload = contract.get_approval_payload(proposal_id, passkey_pk(seed), action.clone());
+    Base64VecU8(passkey_signing_key(seed).sign(&payload.0).to_bytes().to_vec())
+}
+
+#[test]
+fn test_propose_and_approve_action() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+
+    let mut contract = contract_with_multisig(relayer, owner, vec![passkey_pk(1), passkey_pk(2)]);
+    let action = transfer_action(accounts(3), 5_000);
+
+    let signature = approval_signature(&contract, contract.get_next_proposal_id(), 1, &action);
+    let proposal_id = contract.propose_action(passkey_pk(1), action.clone(), signature);
+    assert_eq!(contract.get_action_proposal(proposal_id).unwrap().approvals, vec![passkey_pk(1)]);
+
+    // Second distinct passkey meets the threshold and executes the action
+    let signature = approval_signature(&contract, proposal_id, 2, &action);
+    assert!(contract.approve_action(proposal_id, passkey_pk(2), signature));
+    assert!(contract.get_action_proposal(proposal_id).is_none());
+}
+
+#[test]
+#[should_panic(expected = "ERR_INVALID_APPROVAL_SIGNATURE")]
+fn test_approve_action_panic_signature_of_other_passkey() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+
+    let mut contract = contract_with_multisig(relayer, owner, vec![passkey_pk(1), passkey_pk(2)]);
+    let action = transfer_action(accounts(3), 5_000);
+    let signature = approval_signature(&contract, contract.get_next_proposal_id(), 1, &action);
+    let proposal_id = contract.propose_action(passkey_pk(1), action.clone(), signature.clone());
+    // The relayer can't reuse the proposer's signature to approve as another passkey
+    contract.approve_action(proposal_id, passkey_pk(2), signature);
+}
+
+#[test]
+#[should_panic(expected = "Passkey has already approved this proposal")]
+fn test_approve_action_panic_duplicate_approval() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+
+    let mut contract = contract_with_multisig(relayer, owner, vec![passkey_pk(1)]);
+    let action = transfer_action(accounts(3), 5_000);
+    let signature = approval_signature(&contract, contract.get_next_proposal_id(), 1, &action);
+    let proposal_id = contract.propose_action(passkey_pk(1), action, signature.clone());
+    contract.approve_action(proposal_id, passkey_pk(1), signature);
+}
+
+#[test]
+fn test_approve_action_expired_proposal_is_dropped() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+
+    let mut contract = contract_with_multisig(relayer.clone(), owner, vec![passkey_pk(1), passkey_pk(2)]);
+    let action = transfer_action(accounts(3), 5_000);
+    let signature = approval_signature(&contract, contract.get_next_proposal_id(), 1, &action);
+    let proposal_id = contract.propose_action(passkey_pk(1), action.clone(), signature);
+
+    let mut context = get_context(relayer, accounts(2));
+    context.block_timestamp(2_000_000);
+    testing_env!(context.build());
+    let signature = approval_signature(&contract, proposal_id, 2, &action);
+    assert!(!contract.approve_action(proposal_id, passkey_pk(2), signature));
+    assert!(contract.get_action_proposal(proposal_id).is_none());
+}
+
+// Tests for relayer bonding
+
+fn contract_requiring_bond(relayer: AccountId, owner: AccountId, passkeys: Vec<PublicKey>) -> PasskeyController {
+    let mut contract = PasskeyController::new(relayer, owner.clone(), Some(passkeys));
+    testing_env!(get_context(owner, accounts(2)).build());
+    contract.set_relayer_bond_config(bonding::RelayerBondConfig {
+        min_bond: U128(1_000),
+        unbonding_period_ns: near_sdk::json_types::U64(100),
+    });
+    contract
+}
+
+#[test]
+#[should_panic(expected = "ERR_RELAYER_NOT_BONDED")]
+fn test_execute_delegated_actions_panic_relayer_not_bonded() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = contract_requiring_bond(relayer.clone(), owner, vec![pk1.clone()]);
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(pk1, transfer_action(accounts(3), 10));
+}
+
+#[test]
+fn test_bond_slash_and_unbond_relayer() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = contract_requiring_bond(relayer.clone(), owner.clone(), vec![pk1.clone()]);
+
+    let mut context = get_context(relayer.clone(), accounts(2));
+    context.attached_deposit(NearToken::from_yoctonear(1_500));
+    testing_env!(context.build());
+    contract.bond_relayer();
+    contract.execute_delegated_actions(pk1.clone(), transfer_action(accounts(3), 10));
+
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    assert_eq!(contract.slash_relayer(relayer.clone(), U128(600), "double submission".to_string()), U128(600));
+    assert_eq!(contract.get_relayer_bond(relayer.clone()).bonded, U128(900));
+
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    contract.unbond_relayer(U128(900));
+    let mut context = get_context(relayer.clone(), accounts(2));
+    context.block_timestamp(100);
+    testing_env!(context.build());
+    assert_eq!(contract.withdraw_unbonded(), U128(900));
+    assert_eq!(contract.get_relayer_bond(relayer), bonding::RelayerBond::default());
+}
+
+#[test]
+#[should_panic(expected = "Unbonding period has not passed")]
+fn test_withdraw_unbonded_panic_before_cooldown() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let mut contract = contract_requiring_bond(relayer.clone(), owner, vec![]);
+
+    let mut context = get_context(relayer.clone(), accounts(2));
+    context.attached_deposit(NearToken::from_yoctonear(1_000));
+    testing_env!(context.build());
+    contract.bond_relayer();
+    contract.unbond_relayer(U128(1_000));
+    contract.withdraw_unbonded();
+}
+
+// Tests for execution receipts
+
+#[test]
+fn test_execution_receipt_lifecycle() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    testing_env!(get_context(relayer.clone(), contract_account.clone()).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone()]));
+
+    let action = transfer_action(accounts(3), 10);
+    let request_id = contract.execute_delegated_actions(pk1.clone(), action.clone());
+    assert_eq!(request_id, Base58CryptoHash::from(receipts::compute_request_id(&pk1, 1, &action)));
+
+    let receipt = contract.get_execution_receipt(request_id).unwrap();
+    assert_eq!(receipt.status, receipts::ExecutionStatus::Pending);
+    assert_eq!(receipt.nonce.0, 1);
+
+    set_promise_results(&get_context(contract_account.clone(), contract_account.clone()), vec![near_sdk::PromiseResult::Successful(vec![])]);
+    assert!(contract.on_delegated_action_result(request_id));
+    assert_eq!(contract.get_execution_receipt(request_id).unwrap().status, receipts::ExecutionStatus::Succeeded);
+}
+
+#[test]
+fn test_execution_receipt_failed() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    testing_env!(get_context(relayer.clone(), contract_account.clone()).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone()]));
+
+    let first_id = contract.execute_delegated_actions(pk1.clone(), transfer_action(accounts(3), 10));
+    // Same action again consumes the next nonce, so gets a distinct id
+    let second_id = contract.execute_delegated_actions(pk1.clone(), transfer_action(accounts(3), 10));
+    assert_ne!(first_id, second_id);
+
+    set_promise_results(&get_context(contract_account.clone(), contract_account.clone()), vec![near_sdk::PromiseResult::Failed]);
+    assert!(!contract.on_delegated_action_result(second_id));
+    let receipt = contract.get_execution_receipt(second_id).unwrap();
+    assert_eq!(receipt.status, receipts::ExecutionStatus::Failed);
+    assert!(receipt.resolved_block_height.is_some());
+}
+
+#[test]
+fn test_prune_execution_receipt_after_retention() {
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    testing_env!(get_context(relayer.clone(), contract_account.clone()).block_height(10).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), accounts(0), Some(vec![pk1.clone()]));
+    let request_id = contract.execute_delegated_actions(pk1.clone(), transfer_action(accounts(3), 10));
+    // Pending receipts are kept however old they are
+    testing_env!(get_context(accounts(3), contract_account.clone()).block_height(1_000_000).build());
+    assert!(!contract.prune_execution_receipt(request_id));
+
+    set_promise_results(&get_context(contract_account.clone(), contract_account.clone()).block_height(20), vec![near_sdk::PromiseResult::Successful(vec![])]);
+    contract.on_delegated_action_result(request_id);
+    let retained_until = 20 + receipts::EXECUTION_RECEIPT_RETENTION_BLOCKS;
+    testing_env!(get_context(accounts(3), contract_account.clone()).block_height(retained_until).build());
+    assert!(!contract.prune_execution_receipt(request_id));
+    testing_env!(get_context(accounts(3), contract_account).block_height(retained_until + 1).build());
+    assert!(contract.prune_execution_receipt(request_id));
+    assert!(contract.get_execution_receipt(request_id).is_none());
+}
+
+#[test]
+fn test_get_action_schema() {
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let contract = PasskeyController::new(accounts(1), accounts(0), None);
+
+    let schema: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(&contract.get_action_schema()).unwrap();
+    assert_eq!(schema["title"], "SerializableAction");
+    assert!(schema["properties"]["action_type"].is_object());
+
+    let abi: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(&contract.get_abi()).unwrap();
+    assert!(abi["SignedActionEnvelope"].is_object());
+}
+
+// Tests for PaymentContract actions
+
+#[test]
+fn test_payments_call_builds_typed_args() {
+    let action = record_spend_action("rev1", accounts(3), 42);
+    let (method_name, args, deposit) = action.payments_call();
+    assert_eq!(method_name, "record_spend");
+    assert_eq!(deposit, NearToken::from_yoctonear(0));
+    let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(&args).unwrap();
+    assert_eq!(args, near_sdk::serde_json::json!({
+        "reverie_id": "rev1",
+        "user_id": accounts(3),
+        "amount_to_spend": "42",
+    }));
+
+    // ChargeReverie builds the same call from its typed fields alone
+    let charge_action = charge_reverie_action("rev1", accounts(3), 42);
+    assert!(charge_action.reverie_id.is_none() && charge_action.user_id.is_none() && charge_action.amount.is_none());
+    assert_eq!(charge_action.payments_call(), action.payments_call());
+
+    let deposit_action = SerializableAction { action_type: ActionType::ReverieDeposit, ..action };
+    let (method_name, _, deposit) = deposit_action.payments_call();
+    assert_eq!(method_name, "deposit_for");
+    assert_eq!(deposit, NearToken::from_yoctonear(42));
+}
+
+#[test]
+fn test_execute_delegated_charge_reverie_calls_record_spend() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let pk1 = passkey_pk(1);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone()]));
+    contract.set_payments_contract(Some("payments.testnet".parse().unwrap()));
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(pk1, charge_reverie_action("rev1", accounts(3), 42));
+
+    let receipts = near_sdk::test_utils::get_created_receipts();
+    assert_eq!(receipts[0].receiver_id, "payments.testnet".parse::<AccountId>().unwrap());
+    let near_sdk::mock::MockAction::FunctionCallWeight { method_name, args, .. } = &receipts[0].actions[0] else {
+        panic!("Expected a record_spend call");
+    };
+    assert_eq!(method_name, b"record_spend");
+    let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(args).unwrap();
+    assert_eq!(args, near_sdk::serde_json::json!({
+        "reverie_id": "rev1",
+        "user_id": accounts(3),
+        "amount_to_spend": "42",
+    }));
+}
+
+#[test]
+fn test_charge_reverie_parses_typed_args_and_falls_under_record_spend_caps() {
+    let owner = accounts(0);
+    let pk1 = passkey_pk(1);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), owner, Some(vec![pk1]));
+    contract.set_payments_contract(Some("payments.testnet".parse().unwrap()));
+
+    let action_json = near_sdk::serde_json::json!({
+        "action_type": {"ChargeReverie": {"reverie_id": "rev1", "user": accounts(3), "amount": "42"}},
+    })
+    .to_string();
+    assert!(contract.validate_action_json(action_json.clone()).valid);
+    let action: SerializableAction = near_sdk::serde_json::from_str(&action_json).unwrap();
+    assert_eq!(action.action_type, charge_reverie_action("rev1", accounts(3), 42).action_type);
+
+    contract.set_action_caps(ActionType::RecordSpend, Some(action_caps::ActionCaps { disabled: true, ..Default::default() }));
+    assert_eq!(contract.action_caps_error(&action), Some("ERR_ACTION_TYPE_DISABLED"));
+}
+
+#[test]
+fn test_execute_delegated_record_spend() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner.clone(), Some(vec![pk1.clone()]));
+
+    testing_env!(get_context(owner, accounts(2)).build());
+    contract.set_payments_contract(Some("payments.testnet".parse().unwrap()));
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(pk1, record_spend_action("rev1", accounts(3), 42));
+}
+
+#[test]
+#[should_panic(expected = "Payments contract is not configured")]
+fn test_execute_delegated_record_spend_panic_not_configured() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer, owner, Some(vec![pk1.clone()]));
+    contract.execute_delegated_actions(pk1, record_spend_action("rev1", accounts(3), 42));
+}
+
+// Tests for WebAuthn challenges
+
+fn webauthn_assertion(
+    signing_key: &ed25519_dalek::SigningKey,
+    challenge: &[u8],
+    flags: u8,
+) -> webauthn::WebAuthnAssertion {
+    use base64::Engine;
+    use ed25519_dalek::Signer;
+    let mut authenticator_data = vec![0u8; 32];
+    authenticator_data.push(flags);
+    authenticator_data.extend_from_slice(&1u32.to_be_bytes());
+    let client_data_json = format!(
+        r#"{{"type":"webauthn.get","challenge":"{}","origin":"https://reveries.example"}}"#,
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(challenge)
+    );
+    let mut signed_message = authenticator_data.clone();
+    signed_message.extend_from_slice(&near_sdk::env::sha256(client_data_json.as_bytes()));
+    webauthn::WebAuthnAssertion {
+        authenticator_data: Base64VecU8(authenticator_data),
+        client_data_json,
+        signature: Base64VecU8(signing_key.sign(&signed_message).to_bytes().to_vec()),
+    }
+}
+
+#[test]
+#[should_panic(expected = "ERR_INSUFFICIENT_CHALLENGE_DEPOSIT")]
+fn test_issue_challenge_panic_without_deposit_from_non_relayer() {
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![passkey_pk(21)]));
+    testing_env!(get_context(accounts(4), accounts(2)).build());
+    contract.issue_challenge();
+}
+
+#[test]
+fn test_execute_with_webauthn_consumes_challenge() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let signing_key = passkey_signing_key(21);
+    let passkey_pk = passkey_pk_of(&signing_key);
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![passkey_pk.clone()]));
+
+    let issued = contract.issue_challenge();
+    assert_eq!(contract.get_issued_challenge(issued.clone()).unwrap().issued_to, relayer);
+
+    let action = transfer_action(accounts(3), 10);
+    let challenge = contract.get_webauthn_challenge(issued.clone(), action.clone());
+    let assertion = webauthn_assertion(&signing_key, &challenge.0, 0x01);
+    contract.execute_with_webauthn(passkey_pk, action, assertion);
+    assert!(contract.get_issued_challenge(issued).is_none());
+}
+
+#[test]
+#[should_panic(expected = "ERR_CHALLENGE_NOT_FOUND")]
+fn test_execute_with_webauthn_panic_replayed() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let signing_key = passkey_signing_key(21);
+    let passkey_pk = passkey_pk_of(&signing_key);
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![passkey_pk.clone()]));
+
+    let issued = contract.issue_challenge();
+    let action = transfer_action(accounts(3), 10);
+    let challenge = contract.get_webauthn_challenge(issued, action.clone());
+    let assertion = webauthn_assertion(&signing_key, &challenge.0, 0x01);
+    contract.execute_with_webauthn(passkey_pk.clone(), action.clone(), assertion.clone());
+    contract.execute_with_webauthn(passkey_pk, action, assertion);
+}
+
+#[test]
+#[should_panic(expected = "ERR_CHALLENGE_ACTION_MISMATCH")]
+fn test_execute_with_webauthn_panic_action_swapped() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let signing_key = passkey_signing_key(21);
+    let passkey_pk = passkey_pk_of(&signing_key);
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![passkey_pk.clone()]));
+
+    let issued = contract.issue_challenge();
+    let challenge = contract.get_webauthn_challenge(issued, transfer_action(accounts(3), 10));
+    let assertion = webauthn_assertion(&signing_key, &challenge.0, 0x01);
+    contract.execute_with_webauthn(passkey_pk, transfer_action(relayer, 10), assertion);
+}
+
+#[test]
+#[should_panic(expected = "ERR_CHALLENGE_EXPIRED")]
+fn test_execute_with_webauthn_panic_expired() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let signing_key = passkey_signing_key(21);
+    let passkey_pk = passkey_pk_of(&signing_key);
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![passkey_pk.clone()]));
+
+    let issued = contract.issue_challenge();
+    let action = transfer_action(accounts(3), 10);
+    let challenge = contract.get_webauthn_challenge(issued, action.clone());
+    let assertion = webauthn_assertion(&signing_key, &challenge.0, 0x01);
+
+    let mut context = get_context(relayer, accounts(2));
+    context.block_timestamp(webauthn::DEFAULT_CHALLENGE_TTL_NS + 1);
+    testing_env!(context.build());
+    contract.execute_with_webauthn(passkey_pk, action, assertion);
+}
+
+// Tests for guardian recovery
+
+fn contract_with_guardians(owner: AccountId, relayer: AccountId) -> PasskeyController {
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer, owner, None);
+    contract.set_guardians(Some(guardians::GuardianConfig {
+        guardians: vec![accounts(3), accounts(4), accounts(5)],
+        threshold: 2,
+        recovery_delay_ns: near_sdk::json_types::U64(1_000),
+    }));
+    contract
+}
+
+#[test]
+fn test_guardian_recovery_after_delay() {
+    let owner = accounts(0);
+    let mut contract = contract_with_guardians(owner, accounts(1));
+    let new_pk = PublicKey::from_parts(near_sdk::CurveType::ED25519, [42u8; 32].to_vec()).unwrap();
+
+    testing_env!(get_context(accounts(3), accounts(2)).build());
+    let recovery = contract.recover_add_passkey(new_pk.clone());
+    assert!(recovery.executable_at.is_none());
+
+    testing_env!(get_context(accounts(4), accounts(2)).build());
+    let recovery = contract.recover_add_passkey(new_pk.clone());
+    assert_eq!(recovery.executable_at, Some(near_sdk::json_types::U64(1_000)));
+
+    let mut context = get_context(accounts(5), accounts(2));
+    context.block_timestamp(1_000);
+    testing_env!(context.build());
+    contract.finalize_recovery(new_pk.clone());
+    assert!(contract.is_passkey_pk_registered(new_pk.clone()));
+    assert!(contract.get_pending_recovery(new_pk).is_none());
+}
+
+#[test]
+#[should_panic(expected = "Recovery delay has not passed")]
+fn test_guardian_recovery_panic_before_delay() {
+    let owner = accounts(0);
+    let mut contract = contract_with_guardians(owner, accounts(1));
+    let new_pk = PublicKey::from_parts(near_sdk::CurveType::ED25519, [42u8; 32].to_vec()).unwrap();
+
+    testing_env!(get_context(accounts(3), accounts(2)).build());
+    contract.recover_add_passkey(new_pk.clone());
+    testing_env!(get_context(accounts(4), accounts(2)).build());
+    contract.recover_add_passkey(new_pk.clone());
+    contract.finalize_recovery(new_pk);
+}
+
+#[test]
+#[should_panic(expected = "No pending recovery for this passkey")]
+fn test_owner_cancels_guardian_recovery() {
+    let owner = accounts(0);
+    let mut contract = contract_with_guardians(owner.clone(), accounts(1));
+    let new_pk = PublicKey::from_parts(near_sdk::CurveType::ED25519, [42u8; 32].to_vec()).unwrap();
+
+    testing_env!(get_context(accounts(3), accounts(2)).build());
+    contract.recover_add_passkey(new_pk.clone());
+    testing_env!(get_context(accounts(4), accounts(2)).build());
+    contract.recover_add_passkey(new_pk.clone());
+
+    testing_env!(get_context(owner, accounts(2)).build());
+    contract.cancel_recovery(new_pk.clone());
+    assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains("recovery_cancelled")));
+    contract.finalize_recovery(new_pk);
+}
+
+#[test]
+#[should_panic(expected = "Only guardians can approve recovery")]
+fn test_recover_add_passkey_panic_not_guardian() {
+    let owner = accounts(0);
+    let mut contract = contract_with_guardians(owner, accounts(1));
+    let new_pk = PublicKey::from_parts(near_sdk::CurveType::ED25519, [42u8; 32].to_vec()).unwrap();
+
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    contract.recover_add_passkey(new_pk);
+}
+
+// Tests for scheduled actions
+
+#[test]
+fn test_schedule_and_execute_action_after_time() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    testing_env!(get_context(relayer.clone(), contract_account.clone()).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone()]));
+
+    let action = transfer_action(accounts(3), 10);
+    let id = contract.schedule_action(pk1.clone(), action.clone(), near_sdk::json_types::U64(1_000), Some(U128(1)));
+    assert_eq!(contract.get_scheduled_action(id).unwrap().tip, U128(1));
+
+    let mut context = get_context(accounts(4), contract_account);
+    context.block_timestamp(1_000);
+    testing_env!(context.build());
+    let request_id = contract.execute_scheduled(id);
+    assert_eq!(request_id, Base58CryptoHash::from(receipts::compute_request_id(&pk1, 1, &action)));
+    assert!(contract.get_scheduled_action(id).is_none());
+}
+
+#[test]
+#[should_panic(expected = "Scheduled action is not yet executable")]
+fn test_execute_scheduled_panic_too_early() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone()]));
+
+    let id = contract.schedule_action(pk1, transfer_action(accounts(3), 10), near_sdk::json_types::U64(1_000), None);
+    testing_env!(get_context(accounts(4), accounts(2)).build());
+    contract.execute_scheduled(id);
+}
+
+#[test]
+#[should_panic(expected = "Scheduled action not found")]
+fn test_owner_cancels_scheduled_action() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner.clone(), Some(vec![pk1.clone()]));
+
+    let id = contract.schedule_action(pk1, transfer_action(accounts(3), 10), near_sdk::json_types::U64(1_000), None);
+    testing_env!(get_context(owner, accounts(2)).build());
+    contract.cancel_scheduled(id);
+    contract.execute_scheduled(id);
+}
+
+#[test]
+#[should_panic(expected = "Tip can be at most 10000000000000000000000 yoctoNEAR")]
+fn test_schedule_action_panic_tip_above_cap() {
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer, accounts(0), Some(vec![passkey_pk(1)]));
+    let tip = U128(scheduler::MAX_SCHEDULED_TIP + 1);
+    contract.schedule_action(passkey_pk(1), transfer_action(accounts(3), 10), near_sdk::json_types::U64(1_000), Some(tip));
+}
+
+#[test]
+fn test_execute_scheduled_debits_tip_from_prepaid_balance() {
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    let mut contract = contract_with_prepaid_accounting(relayer.clone(), accounts(0), passkey_pk(1));
+    let mut context = get_context(accounts(3), contract_account.clone());
+    context.attached_deposit(NearToken::from_yoctonear(100));
+    testing_env!(context.build());
+    contract.fund_controller_for(passkey_pk(1));
+
+    testing_env!(get_context(relayer, contract_account.clone()).build());
+    let id = contract.schedule_action(passkey_pk(1), transfer_action(accounts(3), 60), near_sdk::json_types::U64(1_000), Some(U128(5)));
+    testing_env!(get_context(accounts(4), contract_account).block_timestamp(1_000).build());
+    contract.execute_scheduled(id);
+    assert_eq!(contract.get_prepaid_balance(passkey_pk(1)), U128(35));
+}
+
+// Tests for versioned actions
+
+#[test]
+fn test_versioned_action_round_trips_and_rejects_unknown_versions() {
+    let action = versioned::VersionedAction::from(transfer_action(accounts(3), 10));
+    let json = near_sdk::serde_json::to_string(&action).unwrap();
+    assert!(json.starts_with("{\"version\":\"v1\""));
+    let decoded: versioned::VersionedAction = near_sdk::serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded.into_latest().amount, Some(U128(10)));
+
+    let unknown = json.replacen("\"v1\"", "\"v2\"", 1);
+    let err = near_sdk::serde_json::from_str::<versioned::VersionedAction>(&unknown).unwrap_err();
+    assert!(err.to_string().contains("unsupported action version: v2"));
+
+    let mut bytes = near_sdk::borsh::to_vec(&action).unwrap();
+    assert!(near_sdk::borsh::from_slice::<versioned::VersionedAction>(&bytes).is_ok());
+    bytes[0] = 1;
+    let err = near_sdk::borsh::from_slice::<versioned::VersionedAction>(&bytes).unwrap_err();
+    assert!(err.to_string().contains("unsupported action version index: 1"));
+}
+
+#[test]
+fn test_execute_versioned_action() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer, owner, Some(vec![pk1.clone()]));
+
+    let action = transfer_action(accounts(3), 10);
+    let request_id = contract.execute_versioned_action(pk1.clone(), action.clone().into());
+    assert_eq!(request_id, Base58CryptoHash::from(receipts::compute_request_id(&pk1, 1, &action)));
+}
+
+// Tests for passkey metadata
+
+#[test]
+fn test_passkey_metadata_added_and_removed_with_passkey() {
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer, accounts(0), None);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let metadata = passkey_metadata::PasskeyMetadata {
+        aaguid: "adce0002-35bc-c60a-648b-0b25f1f05503".to_string(),
+        credential_id: Base64VecU8(vec![7; 16]),
+        transports: vec!["internal".to_string()],
+        created_at: near_sdk::json_types::U64(1_000),
+        device_label: Some("Laptop".to_string()),
+    };
+
+    assert!(contract.add_passkey_pk_with_metadata(pk1.clone(), metadata.clone()));
+    assert!(contract.is_passkey_pk_registered(pk1.clone()));
+    assert_eq!(contract.get_passkey_metadata(pk1.clone()), Some(metadata.clone()));
+    assert_eq!(contract.get_passkeys_with_metadata(0, 10), vec![(pk1.clone(), Some(metadata))]);
+
+    contract.remove_passkey_pk(pk1.clone());
+    assert!(contract.get_passkey_metadata(pk1).is_none());
+}
+
+// Tests for prepaid accounting
+
+fn contract_with_prepaid_accounting(relayer: AccountId, owner: AccountId, passkey_pk: PublicKey) -> PasskeyController {
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer, owner, Some(vec![passkey_pk]));
+    contract.set_prepaid_accounting(true);
+    contract
+}
+
+#[test]
+fn test_prepaid_balance_debited_and_refunded_on_failure() {
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = contract_with_prepaid_accounting(relayer.clone(), accounts(0), pk1.clone());
+
+    let mut context = get_context(accounts(3), contract_account.clone());
+    context.attached_deposit(NearToken::from_yoctonear(100));
+    testing_env!(context.build());
+    assert_eq!(contract.fund_controller_for(pk1.clone()), U128(100));
+
+    testing_env!(get_context(relayer, contract_account.clone()).build());
+    let request_id = contract.execute_delegated_actions(pk1.clone(), transfer_action(accounts(3), 60));
+    assert_eq!(contract.get_prepaid_balance(pk1.clone()), U128(40));
+
+    set_promise_results(&get_context(contract_account.clone(), contract_account), vec![near_sdk::PromiseResult::Failed]);
+    contract.on_delegated_action_result(request_id);
+    assert_eq!(contract.get_prepaid_balance(pk1), U128(100));
+}
+
+#[test]
+fn test_failed_function_call_deposit_refunded_to_prepaid_balance() {
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = contract_with_prepaid_accounting(relayer.clone(), accounts(0), pk1.clone());
+    contract.add_allowed_call("app.near".parse().unwrap(), vec!["play".to_string()]);
+
+    let mut context = get_context(accounts(3), contract_account.clone());
+    context.attached_deposit(NearToken::from_yoctonear(100));
+    testing_env!(context.build());
+    contract.fund_controller_for(pk1.clone());
+
+    testing_env!(get_context(relayer, contract_account.clone()).build());
+    let action = SerializableAction { deposit: Some(U128(70)), ..function_call_action("app.near", "play") };
+    let request_id = contract.execute_delegated_actions(pk1.clone(), action);
+    assert_eq!(contract.get_prepaid_balance(pk1.clone()), U128(30));
+
+    set_promise_results(&get_context(contract_account.clone(), contract_account), vec![near_sdk::PromiseResult::Failed]);
+    assert!(!contract.on_delegated_action_result(request_id));
+    assert_eq!(contract.get_prepaid_balance(pk1), U128(100));
+    assert!(near_sdk::test_utils::get_logs()
+        .iter()
+        .any(|log| log.contains(r#""event":"deposit_refunded""#) && log.contains(r#""amount":"70""#)));
+}
+
+#[test]
+#[should_panic(expected = "ERR_INSUFFICIENT_PREPAID_BALANCE")]
+fn test_execute_delegated_actions_panic_exceeds_prepaid_balance() {
+    let relayer = accounts(1);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = contract_with_prepaid_accounting(relayer.clone(), accounts(0), pk1.clone());
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(pk1, transfer_action(accounts(3), 60));
+}
+
+#[test]
+fn test_auto_top_up_requested_below_threshold_and_credited() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = contract_with_prepaid_accounting(relayer.clone(), owner.clone(), pk1.clone());
+    contract.set_payments_contract(Some(accounts(5)));
+
+    let mut context = get_context(accounts(3), contract_account.clone());
+    context.attached_deposit(NearToken::from_yoctonear(100));
+    testing_env!(context.build());
+    contract.fund_controller_for(pk1.clone());
+
+    testing_env!(get_context(relayer.clone(), contract_account.clone()).build());
+    contract.set_auto_top_up(pk1.clone(), "rev1".to_string(), accounts(3), U128(50), U128(200));
+    let request_id = contract.execute_delegated_actions(pk1.clone(), transfer_action(accounts(4), 60));
+    // The balance is only checked once the action's result is in
+    assert!(!contract.get_auto_top_up(pk1.clone()).unwrap().in_flight);
+    set_promise_results(&get_context(contract_account.clone(), contract_account.clone()), vec![near_sdk::PromiseResult::Successful(vec![])]);
+    contract.on_delegated_action_result(request_id);
+    assert!(contract.get_auto_top_up(pk1.clone()).unwrap().in_flight);
+    // No second request while one is in flight
+    assert!(!contract.request_top_up(pk1.clone()));
+
+    set_promise_results(&get_context(contract_account.clone(), contract_account), vec![near_sdk::PromiseResult::Successful(vec![])]);
+    assert!(contract.on_auto_top_up(pk1.clone(), U128(200)));
+    assert_eq!(contract.get_prepaid_balance(pk1.clone()), U128(240));
+    assert!(!contract.get_auto_top_up(pk1).unwrap().in_flight);
+}
+
+#[test]
+fn test_auto_top_up_not_requested_when_failed_action_is_refunded() {
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    let pk1 = passkey_pk(1);
+    let mut contract = contract_with_prepaid_accounting(relayer.clone(), accounts(0), pk1.clone());
+    contract.set_payments_contract(Some(accounts(5)));
+
+    let mut context = get_context(accounts(3), contract_account.clone());
+    context.attached_deposit(NearToken::from_yoctonear(100));
+    testing_env!(context.build());
+    contract.fund_controller_for(pk1.clone());
+
+    testing_env!(get_context(relayer, contract_account.clone()).build());
+    contract.set_auto_top_up(pk1.clone(), "rev1".to_string(), accounts(3), U128(50), U128(200));
+    let request_id = contract.execute_delegated_actions(pk1.clone(), transfer_action(accounts(4), 60));
+    set_promise_results(&get_context(contract_account.clone(), contract_account), vec![near_sdk::PromiseResult::Failed]);
+    contract.on_delegated_action_result(request_id);
+    assert_eq!(contract.get_prepaid_balance(pk1.clone()), U128(100));
+    assert!(!contract.get_auto_top_up(pk1).unwrap().in_flight);
+}
+
+#[test]
+fn test_auto_top_up_debounced_after_request() {
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    let pk1 = passkey_pk(1);
+    let mut contract = contract_with_prepaid_accounting(relayer.clone(), accounts(0), pk1.clone());
+    contract.set_payments_contract(Some(accounts(5)));
+
+    testing_env!(get_context(relayer, contract_account.clone()).block_height(100).build());
+    contract.set_auto_top_up(pk1.clone(), "rev1".to_string(), accounts(3), U128(50), U128(200));
+    assert!(contract.request_top_up(pk1.clone()));
+    assert_eq!(contract.get_auto_top_up(pk1.clone()).unwrap().requested_at_block, Some(near_sdk::json_types::U64(100)));
+
+    // A failed top-up clears the in-flight flag, but a retry waits for the debounce
+    set_promise_results(&get_context(contract_account.clone(), contract_account.clone()).block_height(100), vec![near_sdk::PromiseResult::Failed]);
+    assert!(!contract.on_auto_top_up(pk1.clone(), U128(200)));
+    assert!(!contract.request_top_up(pk1.clone()));
+
+    testing_env!(get_context(accounts(3), contract_account).block_height(100 + top_up::AUTO_TOP_UP_DEBOUNCE_BLOCKS).build());
+    assert!(contract.request_top_up(pk1));
+}
+
+// Tests for upgrades
+
+#[test]
+fn test_upgrade_with_matching_code_hash() {
+    let owner = accounts(0);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), owner, None);
+    let code = vec![0u8, 97, 115, 109];
+    let code_hash = Base58CryptoHash::from(env::sha256_array(&code));
+    contract.upgrade(Base64VecU8(code.clone()), code_hash, Some("migrate".to_string()));
+
+    // Deploys to the controller's own account, then calls the migration in the same receipt
+    let receipts = near_sdk::test_utils::get_created_receipts();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].receiver_id, accounts(2));
+    assert_eq!(receipts[0].actions.len(), 2);
+    let near_sdk::mock::MockAction::DeployContract { code: deployed, .. } = &receipts[0].actions[0] else {
+        panic!("Expected a DeployContract action");
+    };
+    assert_eq!(deployed, &code);
+    let near_sdk::mock::MockAction::FunctionCallWeight { method_name, args, .. } = &receipts[0].actions[1] else {
+        panic!("Expected a FunctionCall action");
+    };
+    assert_eq!(method_name, b"migrate");
+    assert_eq!(args, b"{}");
+}
+
+#[test]
+#[should_panic(expected = "Code hash mismatch")]
+fn test_upgrade_panic_code_hash_mismatch() {
+    let owner = accounts(0);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), owner, None);
+    let code_hash = Base58CryptoHash::from(env::sha256_array(b"other artifact"));
+    contract.upgrade(Base64VecU8(vec![0u8, 97, 115, 109]), code_hash, None);
+}
+
+// Tests for direct call hardening
+
+fn contract_requiring_direct_call(owner: AccountId, passkey_pk: PublicKey) -> PasskeyController {
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), owner, Some(vec![passkey_pk]));
+    contract.set_require_direct_call(true);
+    contract
+}
+
+#[test]
+#[should_panic(expected = "ERR_DIRECT_ACTIONS_REQUIRE_DIRECT_CALL")]
+fn test_execute_direct_actions_panic_via_contract_when_direct_call_required() {
+    let pk_derp = PublicKey::from_parts(near_sdk::CurveType::ED25519, [7u8; 32].to_vec()).unwrap();
+    let mut contract = contract_requiring_direct_call(accounts(0), pk_derp.clone());
+
+    let mut context = get_context(accounts(5), accounts(2));
+    context.signer_account_id(accounts(3)).signer_account_pk(pk_derp);
+    testing_env!(context.build());
+    contract.execute_direct_actions(transfer_action(accounts(4), 50));
+}
+
+#[test]
+fn test_execute_direct_actions_from_approved_proxy() {
+    let owner = accounts(0);
+    let proxy = accounts(5);
+    let pk_derp = PublicKey::from_parts(near_sdk::CurveType::ED25519, [7u8; 32].to_vec()).unwrap();
+    let mut contract = contract_requiring_direct_call(owner, pk_derp.clone());
+    contract.add_direct_action_proxy(proxy.clone());
+    assert_eq!(contract.get_direct_action_proxies(), vec![proxy.clone()]);
+
+    let mut context = get_context(proxy, accounts(2));
+    context.signer_account_id(accounts(3)).signer_account_pk(pk_derp);
+    testing_env!(context.build());
+    contract.execute_direct_actions_from(accounts(3), transfer_action(accounts(4), 50));
+
+    let receipts = near_sdk::test_utils::get_created_receipts();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].receiver_id, accounts(4));
+    let near_sdk::mock::MockAction::Transfer { deposit, .. } = &receipts[0].actions[0] else {
+        panic!("Expected a Transfer action");
+    };
+    assert_eq!(*deposit, NearToken::from_yoctonear(50));
+}
+
+// Tests for staking pool actions
+
+fn staking_pool_action(action_type: ActionType, pool_id: &str, amount: Option<u128>) -> SerializableAction {
+    SerializableAction {
+        receiver_id: Some(pool_id.parse().unwrap()),
+        amount: amount.map(U128),
+        ..empty_action(action_type)
+    }
+}
+
+#[test]
+fn test_staking_pool_call_builds_pool_methods() {
+    let (method_name, args, deposit) = staking_pool_action(ActionType::StakeWithPool, "pool.near", Some(100)).staking_pool_call();
+    assert_eq!(method_name, "deposit_and_stake");
+    assert_eq!(args, b"{}".to_vec());
+    assert_eq!(deposit, NearToken::from_yoctonear(100));
+
+    let (method_name, args, deposit) = staking_pool_action(ActionType::UnstakeFromPool, "pool.near", Some(40)).staking_pool_call();
+    assert_eq!(method_name, "unstake");
+    let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(&args).unwrap();
+    assert_eq!(args, near_sdk::serde_json::json!({"amount": "40"}));
+    assert_eq!(deposit, NearToken::from_yoctonear(0));
+
+    let (method_name, _, _) = staking_pool_action(ActionType::WithdrawFromPool, "pool.near", None).staking_pool_call();
+    assert_eq!(method_name, "withdraw_all");
+
+    let (method_name, args, _) = staking_pool_action(ActionType::WithdrawFromPool, "pool.near", Some(40)).staking_pool_call();
+    assert_eq!(method_name, "withdraw");
+    let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(&args).unwrap();
+    assert_eq!(args, near_sdk::serde_json::json!({"amount": "40"}));
+}
+
+#[test]
+fn test_execute_delegated_stake_with_whitelisted_pool() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone()]));
+    contract.add_staking_pool("pool.near".parse().unwrap());
+    assert_eq!(contract.get_staking_pools(), vec!["pool.near".parse::<AccountId>().unwrap()]);
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(pk1, staking_pool_action(ActionType::StakeWithPool, "pool.near", Some(100)));
+}
+
+#[test]
+#[should_panic(expected = "ERR_STAKING_POOL_NOT_WHITELISTED")]
+fn test_execute_delegated_stake_panic_pool_not_whitelisted() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer, owner, Some(vec![pk1.clone()]));
+    contract.execute_delegated_actions(pk1, staking_pool_action(ActionType::UnstakeFromPool, "pool.near", Some(40)));
+}
+
+// Resolves a delegated pool action with the given result
+fn resolve_delegated_action(contract: &mut PasskeyController, request_id: Base58CryptoHash, result: near_sdk::PromiseResult) {
+    set_promise_results(&get_context(accounts(2), accounts(2)), vec![result]);
+    contract.on_delegated_action_result(request_id);
+}
+
+#[test]
+fn test_staked_principal_tracked_and_credited_back_to_prepaid_on_withdrawal() {
+    let relayer = accounts(1);
+    let pool: AccountId = "pool.near".parse().unwrap();
+    let pk1 = passkey_pk(1);
+    let mut contract = contract_with_prepaid_accounting(relayer.clone(), accounts(0), pk1.clone());
+    contract.add_staking_pool(pool.clone());
+
+    let mut context = get_context(accounts(3), accounts(2));
+    context.attached_deposit(NearToken::from_yoctonear(100));
+    testing_env!(context.build());
+    contract.fund_controller_for(pk1.clone());
+
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let request_id = contract.execute_delegated_actions(pk1.clone(), staking_pool_action(ActionType::StakeWithPool, "pool.near", Some(100)));
+    assert_eq!(contract.get_staked_principal(pk1.clone(), pool.clone()), staking_pools::StakedPrincipal::default());
+    resolve_delegated_action(&mut contract, request_id, near_sdk::PromiseResult::Successful(vec![]));
+    assert_eq!(contract.get_prepaid_balance(pk1.clone()), U128(0));
+    assert_eq!(
+        contract.get_staked_principal(pk1.clone(), pool.clone()),
+        staking_pools::StakedPrincipal { staked: U128(100), unstaked: U128(0), prepaid_funded: U128(100) }
+    );
+
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let request_id = contract.execute_delegated_actions(pk1.clone(), staking_pool_action(ActionType::UnstakeFromPool, "pool.near", Some(40)));
+    resolve_delegated_action(&mut contract, request_id, near_sdk::PromiseResult::Successful(vec![]));
+    assert_eq!(
+        contract.get_staked_principal(pk1.clone(), pool.clone()),
+        staking_pools::StakedPrincipal { staked: U128(60), unstaked: U128(40), prepaid_funded: U128(100) }
+    );
+
+    // A failed withdrawal puts the principal back
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let request_id = contract.execute_delegated_actions(pk1.clone(), staking_pool_action(ActionType::WithdrawFromPool, "pool.near", Some(40)));
+    assert_eq!(contract.get_staked_principal(pk1.clone(), pool.clone()).unstaked, U128(0));
+    resolve_delegated_action(&mut contract, request_id, near_sdk::PromiseResult::Failed);
+    assert_eq!(contract.get_staked_principal(pk1.clone(), pool.clone()).unstaked, U128(40));
+    assert_eq!(contract.get_prepaid_balance(pk1.clone()), U128(0));
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    let request_id = contract.execute_delegated_actions(pk1.clone(), staking_pool_action(ActionType::WithdrawFromPool, "pool.near", Some(40)));
+    resolve_delegated_action(&mut contract, request_id, near_sdk::PromiseResult::Successful(vec![]));
+    assert_eq!(contract.get_prepaid_balance(pk1.clone()), U128(40));
+    assert_eq!(
+        contract.get_staked_principal(pk1, pool),
+        staking_pools::StakedPrincipal { staked: U128(60), unstaked: U128(0), prepaid_funded: U128(60) }
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_UNSTAKE_EXCEEDS_STAKED_PRINCIPAL")]
+fn test_passkey_cannot_unstake_another_passkeys_principal() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![passkey_pk(1), passkey_pk(2)]));
+    contract.add_staking_pool("pool.near".parse().unwrap());
+
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let request_id = contract.execute_delegated_actions(passkey_pk(1), staking_pool_action(ActionType::StakeWithPool, "pool.near", Some(100)));
+    resolve_delegated_action(&mut contract, request_id, near_sdk::PromiseResult::Successful(vec![]));
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(passkey_pk(2), staking_pool_action(ActionType::UnstakeFromPool, "pool.near", Some(40)));
+}
+
+#[test]
+#[should_panic(expected = "amount is required for WithdrawFromPool")]
+fn test_delegated_withdraw_from_pool_requires_amount() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![passkey_pk(1)]));
+    contract.add_staking_pool("pool.near".parse().unwrap());
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(passkey_pk(1), staking_pool_action(ActionType::WithdrawFromPool, "pool.near", None));
+}
+
+// Tests for allowed FunctionCall receivers
+
+#[test]
+fn test_execute_delegated_function_call_allowed() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone()]));
+    contract.add_allowed_call("app.near".parse().unwrap(), vec!["play".to_string(), "play".to_string()]);
+    assert_eq!(contract.get_allowed_calls("app.near".parse().unwrap()), vec!["play".to_string()]);
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(pk1, function_call_action("app.near", "play"));
+}
+
+#[test]
+#[should_panic(expected = "ERR_CALL_NOT_ALLOWED")]
+fn test_execute_delegated_function_call_panic_method_not_allowed() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone()]));
+    contract.add_allowed_call("app.near".parse().unwrap(), vec!["play".to_string()]);
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(pk1, function_call_action("app.near", "ft_transfer"));
+}
+
+#[test]
+#[should_panic(expected = "ERR_CALL_NOT_ALLOWED")]
+fn test_execute_direct_function_call_panic_method_not_allowed() {
+    let owner = accounts(0);
+    let pk1 = passkey_pk(1);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), owner, Some(vec![pk1.clone()]));
+    contract.add_allowed_call("app.near".parse().unwrap(), vec!["play".to_string()]);
+
+    let mut context = get_context(accounts(3), accounts(2));
+    context.signer_account_pk(pk1);
+    testing_env!(context.build());
+    contract.execute_direct_actions(function_call_action("app.near", "ft_transfer"));
+}
+
+#[test]
+fn test_remove_allowed_call() {
+    let owner = accounts(0);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), owner, None);
+    contract.add_allowed_call("app.near".parse().unwrap(), vec!["play".to_string(), "quit".to_string()]);
+    contract.remove_allowed_call("app.near".parse().unwrap(), Some(vec!["quit".to_string()]));
+    assert!(contract.is_call_allowed("app.near".parse().unwrap(), "play".to_string()));
+    assert!(!contract.is_call_allowed("app.near".parse().unwrap(), "quit".to_string()));
+    contract.remove_allowed_call("app.near".parse().unwrap(), None);
+    assert!(contract.get_allowed_calls("app.near".parse().unwrap()).is_empty());
+}
+
+// Tests for call templates
+
+fn ft_transfer_template() -> templates::CallTemplate {
+    templates::CallTemplate {
+        receiver_id: "token.near".parse().unwrap(),
+        method_name: "ft_transfer".to_string(),
+        args_template: r#"{"receiver_id":"{user}","amount":"{amount}"}"#.to_string(),
+        deposit: Some(U128(1)),
+        gas: None,
+    }
+}
+
+#[test]
+fn test_call_template_renders_params() {
+    let params = templates::TemplateParams { user: Some(accounts(3)), amount: Some(U128(25)) };
+    let action = ft_transfer_template().to_action(&params);
+    let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(&action.args.unwrap().0).unwrap();
+    assert_eq!(args, near_sdk::serde_json::json!({"receiver_id": accounts(3), "amount": "25"}));
+    assert_eq!(action.method_name, Some("ft_transfer".to_string()));
+}
+
+#[test]
+#[should_panic(expected = "ERR_TEMPLATE_MISSING_PARAM: amount")]
+fn test_call_template_panic_missing_param() {
+    let params = templates::TemplateParams { user: Some(accounts(3)), amount: None };
+    ft_transfer_template().render_args(&params);
+}
+
+#[test]
+fn test_execute_template() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone()]));
+    contract.set_call_template("pay".to_string(), ft_transfer_template());
+    contract.add_allowed_call("token.near".parse().unwrap(), vec!["ft_transfer".to_string()]);
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    let params = templates::TemplateParams { user: Some(accounts(3)), amount: Some(U128(25)) };
+    contract.execute_template(pk1.clone(), "pay".to_string(), params);
+    assert_eq!(contract.get_passkey_nonce(pk1).0, 1);
+}
+
+// Tests for self-service passkey registration
+
+fn contract_with_self_registration(owner: AccountId, deposit: u128) -> PasskeyController {
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), owner, None);
+    contract.set_self_registration(Some(self_registration::SelfRegistrationConfig { deposit: U128(deposit) }));
+    contract
+}
+
+fn registration_proof(
+    contract: &mut PasskeyController,
+    signing_key: &ed25519_dalek::SigningKey,
+    account_id: AccountId,
+) -> self_registration::RegistrationProof {
+    use ed25519_dalek::Signer;
+    testing_env!(get_context(account_id.clone(), accounts(2))
+        .attached_deposit(NearToken::from_millinear(10))
+        .build());
+    let challenge = contract.issue_challenge();
+    let payload = contract.get_registration_payload(account_id, passkey_pk_of(signing_key), challenge.clone());
+    self_registration::RegistrationProof {
+        challenge,
+        signature: Base64VecU8(signing_key.sign(&payload.0).to_bytes().to_vec()),
+    }
+}
+
+#[test]
+fn test_register_my_passkey() {
+    let user = accounts(3);
+    let mut contract = contract_with_self_registration(accounts(0), 10);
+    let signing_key = passkey_signing_key(31);
+    let proof = registration_proof(&mut contract, &signing_key, user.clone());
+
+    let mut context = get_context(user, accounts(2));
+    context.attached_deposit(NearToken::from_yoctonear(10));
+    testing_env!(context.build());
+    assert!(contract.register_my_passkey(passkey_pk_of(&signing_key), proof));
+    assert!(near_sdk::test_utils::get_logs().iter().any(|l| l.contains("passkey_self_registered")));
+    // Self-registered passkeys can't execute until approved
+    assert!(!contract.is_passkey_pk_registered(passkey_pk_of(&signing_key)));
+    assert_eq!(contract.get_pending_self_registration(passkey_pk_of(&signing_key)), Some(user));
+
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    assert!(contract.approve_self_registered_passkey(passkey_pk_of(&signing_key)));
+    assert!(contract.is_passkey_pk_registered(passkey_pk_of(&signing_key)));
+    assert!(contract.get_pending_self_registration(passkey_pk_of(&signing_key)).is_none());
+}
+
+#[test]
+#[should_panic(expected = "Only owner or relayer can approve self registered passkeys")]
+fn test_approve_self_registered_passkey_panic_not_owner_or_relayer() {
+    let user = accounts(3);
+    let mut contract = contract_with_self_registration(accounts(0), 0);
+    let signing_key = passkey_signing_key(31);
+    let proof = registration_proof(&mut contract, &signing_key, user.clone());
+    testing_env!(get_context(user.clone(), accounts(2)).build());
+    contract.register_my_passkey(passkey_pk_of(&signing_key), proof);
+    contract.approve_self_registered_passkey(passkey_pk_of(&signing_key));
+}
+
+#[test]
+#[should_panic(expected = "ERR_INVALID_REGISTRATION_SIGNATURE")]
+fn test_register_my_passkey_panic_without_key_possession() {
+    let user = accounts(3);
+    let mut contract = contract_with_self_registration(accounts(0), 0);
+    let signing_key = passkey_signing_key(31);
+    let proof = registration_proof(&mut contract, &signing_key, user.clone());
+
+    testing_env!(get_context(user, accounts(2)).build());
+    contract.register_my_passkey(passkey_pk_of(&passkey_signing_key(32)), proof);
+}
+
+#[test]
+#[should_panic(expected = "ERR_INSUFFICIENT_REGISTRATION_DEPOSIT")]
+fn test_register_my_passkey_panic_without_deposit() {
+    let user = accounts(3);
+    let mut contract = contract_with_self_registration(accounts(0), 10);
+    let signing_key = passkey_signing_key(31);
+    let proof = registration_proof(&mut contract, &signing_key, user.clone());
+
+    testing_env!(get_context(user, accounts(2)).build());
+    contract.register_my_passkey(passkey_pk_of(&signing_key), proof);
+}
+
+#[test]
+#[should_panic(expected = "ERR_SELF_REGISTRATION_DISABLED")]
+fn test_register_my_passkey_panic_when_disabled() {
+    let user = accounts(3);
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), None);
+    let signing_key = passkey_signing_key(31);
+    let proof = registration_proof(&mut contract, &signing_key, user.clone());
+
+    testing_env!(get_context(user, accounts(2)).build());
+    contract.register_my_passkey(passkey_pk_of(&signing_key), proof);
+}
+
+// Tests for expiring passkeys
+
+fn contract_with_session_passkey(relayer: AccountId, passkey_pk: PublicKey, expires_at_ns: u64) -> PasskeyController {
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer, accounts(0), Some(vec![passkey_pk.clone()]));
+    contract.set_passkey_expiry(passkey_pk, Some(near_sdk::json_types::U64(expires_at_ns)));
+    contract
+}
+
+#[test]
+#[should_panic(expected = "ERR_PASSKEY_EXPIRED")]
+fn test_execute_delegated_actions_panic_passkey_expired() {
+    let relayer = accounts(1);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = contract_with_session_passkey(relayer.clone(), pk1.clone(), 1_000);
+
+    testing_env!(get_context(relayer, accounts(2)).block_timestamp(1_001).build());
+    contract.execute_delegated_actions(pk1, transfer_action(accounts(3), 10));
+}
+
+#[test]
+fn test_execute_delegated_actions_before_passkey_expiry() {
+    let relayer = accounts(1);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = contract_with_session_passkey(relayer.clone(), pk1.clone(), 1_000);
+
+    testing_env!(get_context(relayer, accounts(2)).block_timestamp(1_000).build());
+    contract.execute_delegated_actions(pk1, transfer_action(accounts(3), 10));
+}
+
+#[test]
+fn test_prune_expired_passkeys() {
+    let relayer = accounts(1);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let pk2 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [2u8; 32].to_vec()).unwrap();
+    let mut contract = contract_with_session_passkey(relayer.clone(), pk1.clone(), 1_000);
+    contract.add_passkey_pk(pk2.clone());
+    contract.set_passkey_expiry(pk2.clone(), Some(near_sdk::json_types::U64(5_000)));
+
+    testing_env!(get_context(accounts(4), accounts(2)).block_timestamp(2_000).build());
+    assert_eq!(contract.prune_expired_passkeys(10), 1);
+    assert!(!contract.is_passkey_pk_registered(pk1.clone()));
+    assert!(contract.get_passkey_expiry(pk1).is_none());
+    assert!(contract.is_passkey_pk_registered(pk2));
+}
+
+// Tests for action JSON validation
+
+#[test]
+fn test_validate_action_json_reports_field_errors() {
+    let owner = accounts(0);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let contract = PasskeyController::new(accounts(1), owner, None);
+
+    let report = contract.validate_action_json(r#"{"action_type": "FunctionCall", "receiver_id": "app.near"}"#.to_string());
+    assert!(!report.valid);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].field, "method_name");
+
+    let report = contract.validate_action_json(r#"{"action_type": "StakeWithPool", "receiver_id": "pool.near", "amount": "5"}"#.to_string());
+    assert_eq!(report.errors[0].field, "receiver_id");
+    assert_eq!(report.errors[0].message, "ERR_STAKING_POOL_NOT_WHITELISTED");
+
+    let report = contract.validate_action_json("not json".to_string());
+    assert_eq!(report.errors[0].field, "$");
+}
+
+#[test]
+fn test_validate_action_json_accepts_allowed_call() {
+    let owner = accounts(0);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), owner, None);
+    contract.add_allowed_call("app.near".parse().unwrap(), vec!["play".to_string()]);
+
+    let report = contract.validate_action_json(
+        r#"{"action_type": "FunctionCall", "receiver_id": "app.near", "method_name": "play"}"#.to_string(),
+    );
+    assert_eq!(report, validation::ValidationReport { valid: true, errors: vec![] });
+}
+
+// Tests for key management on behalf of managed accounts
+
+fn add_key_action(user_id: Option<AccountId>) -> SerializableAction {
+    SerializableAction {
+        receiver_id: Some("app.near".parse().unwrap()),
+        public_key: Some(PublicKey::from_parts(near_sdk::CurveType::ED25519, [7u8; 32].to_vec()).unwrap()),
+        method_names: Some(vec!["play".to_string()]),
+        user_id,
+        ..empty_action(ActionType::AddKey)
+    }
+}
+
+#[test]
+fn test_key_proxy_call_builds_add_key_args() {
+    let (method_name, args, deposit) = add_key_action(Some("alice.near".parse().unwrap())).key_proxy_call();
+    assert_eq!(method_name, "add_key");
+    let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(&args).unwrap();
+    assert_eq!(args["receiver_id"], "app.near");
+    assert_eq!(args["method_names"], near_sdk::serde_json::json!(["play"]));
+    assert_eq!(args["allowance"], near_sdk::serde_json::Value::Null);
+    assert_eq!(deposit, NearToken::from_yoctonear(0));
+}
+
+#[test]
+fn test_execute_delegated_add_key_for_managed_account() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let controller: AccountId = "controller.near".parse().unwrap();
+    testing_env!(get_context(owner.clone(), controller.clone()).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone()]));
+    contract.add_managed_account("alice.near".parse().unwrap(), pk1.clone());
+    assert!(contract.is_managed_account("alice.near".parse().unwrap()));
+    // Sub-accounts created by the controller are managed without registration
+    assert!(contract.is_managed_account("bob.controller.near".parse().unwrap()));
+    contract.add_managed_account("bob.controller.near".parse().unwrap(), pk1.clone());
+    assert_eq!(contract.get_managed_accounts(), vec!["alice.near".parse::<AccountId>().unwrap()]);
+    assert_eq!(contract.get_managed_account_passkey("alice.near".parse().unwrap()), Some(pk1.clone()));
+
+    testing_env!(get_context(relayer, controller).build());
+    contract.execute_delegated_actions(pk1.clone(), add_key_action(Some("alice.near".parse().unwrap())));
+    contract.execute_delegated_actions(pk1, add_key_action(Some("bob.controller.near".parse().unwrap())));
+}
+
+#[test]
+#[should_panic(expected = "ERR_ACCOUNT_NOT_MANAGED")]
+fn test_execute_delegated_add_key_panic_unmanaged_account() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer, owner, Some(vec![pk1.clone()]));
+    contract.execute_delegated_actions(pk1, add_key_action(Some("mallory.near".parse().unwrap())));
+}
+
+#[test]
+#[should_panic(expected = "ERR_NOT_MANAGED_ACCOUNT_OWNER")]
+fn test_execute_delegated_add_key_panic_other_passkey_on_managed_account() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let pk2 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [2u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone(), pk2.clone()]));
+    contract.add_managed_account("alice.near".parse().unwrap(), pk1);
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(pk2, add_key_action(Some("alice.near".parse().unwrap())));
+}
+
+// Tests for sessions
+
+fn session_policy() -> sessions::SessionPolicy {
+    sessions::SessionPolicy {
+        receivers: vec!["app.near".parse().unwrap()],
+        method_names: vec!["play".to_string()],
+        max_amount_per_action: Some(U128(50)),
+        max_total_amount: Some(U128(80)),
+    }
+}
+
+fn controller_with_session(relayer: AccountId, signing_key: &ed25519_dalek::SigningKey) -> (PasskeyController, near_sdk::json_types::U64) {
+    use ed25519_dalek::Signer;
+    let passkey_pk = passkey_pk_of(signing_key);
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer.clone(), accounts(0), Some(vec![passkey_pk.clone()]));
+    contract.add_allowed_call("app.near".parse().unwrap(), vec!["play".to_string()]);
+    testing_env!(get_context(relayer, accounts(2)).block_timestamp(1_000).build());
+    let ttl = near_sdk::json_types::U64(1_000);
+    let nonce = near_sdk::json_types::U64(1);
+    let payload = contract.get_session_payload(passkey_pk.clone(), session_policy(), ttl, nonce);
+    let signature = Base64VecU8(signing_key.sign(&payload.0).to_bytes().to_vec());
+    let session_id = contract.create_session(passkey_pk, session_policy(), ttl, nonce, signature);
+    (contract, session_id)
+}
+
+fn session_call(method_name: &str, deposit: u128) -> SerializableAction {
+    SerializableAction {
+        deposit: Some(U128(deposit)),
+        ..function_call_action("app.near", method_name)
+    }
+}
+
+#[test]
+fn test_execute_with_session_within_policy() {
+    let relayer = accounts(1);
+    let signing_key = passkey_signing_key(31);
+    let (mut contract, session_id) = controller_with_session(relayer, &signing_key);
+    assert_eq!(contract.get_session(session_id).unwrap().expires_at.0, 2_000);
+
+    contract.execute_with_session(session_id, session_call("play", 40));
+    contract.execute_with_session(session_id, session_call("play", 40));
+    assert_eq!(contract.get_session(session_id).unwrap().spent, U128(80));
+    // Session actions advance the passkey's nonce past the signed session nonce
+    assert_eq!(contract.get_passkey_nonce(passkey_pk_of(&signing_key)).0, 3);
+}
+
+#[test]
+#[should_panic(expected = "ERR_SESSION_METHOD_NOT_ALLOWED")]
+fn test_execute_with_session_panic_method_outside_policy() {
+    let (mut contract, session_id) = controller_with_session(accounts(1), &passkey_signing_key(31));
+    contract.execute_with_session(session_id, session_call("quit", 0));
+}
+
+#[test]
+#[should_panic(expected = "ERR_SESSION_TOTAL_EXCEEDED")]
+fn test_execute_with_session_panic_total_exceeded() {
+    let (mut contract, session_id) = controller_with_session(accounts(1), &passkey_signing_key(31));
+    contract.execute_with_session(session_id, session_call("play", 50));
+    contract.execute_with_session(session_id, session_call("play", 50));
+}
+
+#[test]
+#[should_panic(expected = "ERR_SESSION_EXPIRED")]
+fn test_execute_with_session_panic_expired() {
+    let relayer = accounts(1);
+    let (mut contract, session_id) = controller_with_session(relayer.clone(), &passkey_signing_key(31));
+    testing_env!(get_context(relayer, accounts(2)).block_timestamp(2_001).build());
+    contract.execute_with_session(session_id, session_call("play", 0));
+}
+
+#[test]
+fn test_revoke_and_prune_sessions() {
+    let relayer = accounts(1);
+    let (mut contract, session_id) = controller_with_session(relayer.clone(), &passkey_signing_key(31));
+    assert!(contract.revoke_session(session_id));
+    assert!(contract.get_session(session_id).is_none());
+
+    let (mut contract, session_id) = controller_with_session(relayer.clone(), &passkey_signing_key(31));
+    testing_env!(get_context(relayer, accounts(2)).block_timestamp(2_001).build());
+    assert_eq!(contract.prune_expired_sessions(10), 1);
+    assert!(contract.get_session(session_id).is_none());
+}
+
+#[test]
+#[should_panic(expected = "ERR_INVALID_SESSION_SIGNATURE")]
+fn test_create_session_panic_policy_not_signed() {
+    use ed25519_dalek::Signer;
+    let relayer = accounts(1);
+    let signing_key = passkey_signing_key(31);
+    let passkey_pk = passkey_pk_of(&signing_key);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer, accounts(0), Some(vec![passkey_pk.clone()]));
+    let ttl = near_sdk::json_types::U64(1_000);
+    let nonce = near_sdk::json_types::U64(1);
+    let payload = contract.get_session_payload(passkey_pk.clone(), session_policy(), ttl, nonce);
+    let signature = Base64VecU8(signing_key.sign(&payload.0).to_bytes().to_vec());
+    let widened = sessions::SessionPolicy { max_total_amount: None, ..session_policy() };
+    contract.create_session(passkey_pk, widened, ttl, nonce, signature);
+}
+
+#[test]
+fn test_spendable_controller_balance_excludes_storage_and_reserve() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let context = get_context(owner.clone(), accounts(2))
+        .account_balance(NearToken::from_near(1))
+        .storage_usage(1_000)
+        .build();
+    testing_env!(context);
+    let mut contract = PasskeyController::new(relayer, owner, None);
+    let storage_cost = env::storage_byte_cost().as_yoctonear() * 1_000;
+    contract.set_balance_reserve(U128(NearToken::from_millinear(200).as_yoctonear()));
+    assert_eq!(
+        contract.get_spendable_controller_balance(),
+        U128(NearToken::from_millinear(800).as_yoctonear() - storage_cost)
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_BALANCE_RESERVE_BREACHED")]
+fn test_execute_delegated_actions_panic_when_breaching_reserve() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, vec![1; 32]).unwrap();
+    testing_env!(get_context(relayer.clone(), accounts(2)).account_balance(NearToken::from_near(1)).build());
+    let mut contract = PasskeyController::new(relayer, owner, Some(vec![pk1.clone()]));
+    contract.execute_delegated_actions(pk1, transfer_action(accounts(3), NearToken::from_millinear(950).as_yoctonear()));
+}
+
+fn contract_with_policy_hook(relayer: AccountId, owner: AccountId, pk: PublicKey, post_hook: bool) -> PasskeyController {
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk]));
+    contract.set_policy_hook(Some(policy_hooks::PolicyHookConfig { policy_contract_id: accounts(4), post_hook }));
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract
+}
+
+#[test]
+fn test_policy_hook_approves_action() {
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, vec![1; 32]).unwrap();
+    let mut contract = contract_with_policy_hook(accounts(1), accounts(0), pk1.clone(), true);
+    let request_id = contract.execute_delegated_actions(pk1, transfer_action(accounts(3), 10));
+    assert_eq!(contract.get_execution_receipt(request_id).unwrap().status, receipts::ExecutionStatus::Pending);
+
+    set_promise_results(&get_context(accounts(2), accounts(2)), vec![near_sdk::PromiseResult::Successful(b"true".to_vec())]);
+    assert!(contract.on_policy_checked(request_id, transfer_action(accounts(3), 10)));
+    assert_eq!(contract.get_execution_receipt(request_id).unwrap().status, receipts::ExecutionStatus::Pending);
+
+    set_promise_results(&get_context(accounts(2), accounts(2)), vec![near_sdk::PromiseResult::Successful(vec![])]);
+    assert!(contract.on_delegated_action_result(request_id));
+    assert_eq!(contract.get_execution_receipt(request_id).unwrap().status, receipts::ExecutionStatus::Succeeded);
+}
+
+#[test]
+fn test_policy_hook_rejects_action() {
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, vec![1; 32]).unwrap();
+    let mut contract = contract_with_policy_hook(accounts(1), accounts(0), pk1.clone(), false);
+    let request_id = contract.execute_delegated_actions(pk1, transfer_action(accounts(3), 10));
+
+    set_promise_results(&get_context(accounts(2), accounts(2)), vec![near_sdk::PromiseResult::Successful(b"false".to_vec())]);
+    assert!(!contract.on_policy_checked(request_id, transfer_action(accounts(3), 10)));
+    assert_eq!(contract.get_execution_receipt(request_id).unwrap().status, receipts::ExecutionStatus::Failed);
+    assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""event":"action_rejected_by_policy""#)));
+}
+
+#[test]
+fn test_policy_hook_approval_fails_action_disabled_meanwhile() {
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, vec![1; 32]).unwrap();
+    let mut contract = contract_with_policy_hook(accounts(1), accounts(0), pk1.clone(), false);
+    let request_id = contract.execute_delegated_actions(pk1, transfer_action(accounts(3), 10));
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    contract.set_action_caps(ActionType::Transfer, Some(action_caps::ActionCaps { disabled: true, ..Default::default() }));
+
+    set_promise_results(&get_context(accounts(2), accounts(2)), vec![near_sdk::PromiseResult::Successful(b"true".to_vec())]);
+    assert!(!contract.on_policy_checked(request_id, transfer_action(accounts(3), 10)));
+    assert_eq!(contract.get_execution_receipt(request_id).unwrap().status, receipts::ExecutionStatus::Failed);
+}
+
+#[test]
+#[should_panic(expected = "Only owner can set the policy hook")]
+fn test_set_policy_hook_panic_not_owner() {
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), None);
+    contract.set_policy_hook(None);
+}
+
+#[test]
+fn test_execute_delegated_job_is_idempotent() {
+    let relayer = accounts(1);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, vec![1; 32]).unwrap();
+    testing_env!(get_context(relayer.clone(), accounts(2)).block_timestamp(1_000).build());
+    let mut contract = PasskeyController::new(relayer.clone(), accounts(0), Some(vec![pk1.clone()]));
+
+    let first_id = contract.execute_delegated_job("job-1".to_string(), pk1.clone(), transfer_action(accounts(3), 10));
+    let retry_id = contract.execute_delegated_job("job-1".to_string(), pk1.clone(), transfer_action(accounts(3), 10));
+    assert_eq!(first_id, retry_id);
+    // The retry consumed no nonce, so a new job gets nonce 2
+    let second_id = contract.execute_delegated_job("job-2".to_string(), pk1.clone(), transfer_action(accounts(3), 10));
+    assert_eq!(contract.get_execution_receipt(second_id).unwrap().nonce.0, 2);
+    assert_eq!(contract.get_job("job-1".to_string()).unwrap().request_id, first_id);
+}
+
+#[test]
+fn test_expired_jobs_are_pruned() {
+    let relayer = accounts(1);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, vec![1; 32]).unwrap();
+    testing_env!(get_context(relayer.clone(), accounts(2)).block_timestamp(1_000).build());
+    let mut contract = PasskeyController::new(relayer.clone(), accounts(0), Some(vec![pk1.clone()]));
+    contract.execute_delegated_job("job-1".to_string(), pk1, transfer_action(accounts(3), 10));
+
+    testing_env!(get_context(relayer, accounts(2)).block_timestamp(1_001 + jobs::DEFAULT_JOB_TTL_NS).build());
+    assert!(contract.get_job("job-1".to_string()).is_none());
+    assert_eq!(contract.prune_expired_jobs(10), 1);
+}
+
+#[test]
+fn test_relayer_fee_charged_from_prepaid_balance() {
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = contract_with_prepaid_accounting(relayer.clone(), accounts(0), pk1.clone());
+    contract.set_relayer_fee_config(relayer.clone(), Some(relayer_fees::RelayerFeeConfig { flat_fee: U128(5), fee_bps: 1_000 }));
+
+    let mut context = get_context(accounts(3), contract_account.clone());
+    context.attached_deposit(NearToken::from_yoctonear(100));
+    testing_env!(context.build());
+    contract.fund_controller_for(pk1.clone());
+
+    testing_env!(get_context(relayer.clone(), contract_account.clone()).build());
+    let request_id = contract.execute_delegated_actions(pk1.clone(), transfer_action(accounts(3), 50));
+    // 50 attached + 5 flat + 10% of 50
+    assert_eq!(contract.get_prepaid_balance(pk1.clone()), U128(40));
+    assert_eq!(contract.get_relayer_fee_balance(relayer.clone()), U128(10));
+
+    // The fee is kept when the action fails; only the attached value is refunded
+    set_promise_results(&get_context(contract_account.clone(), contract_account.clone()), vec![near_sdk::PromiseResult::Failed]);
+    contract.on_delegated_action_result(request_id);
+    assert_eq!(contract.get_prepaid_balance(pk1), U128(90));
+
+    testing_env!(get_context(relayer.clone(), contract_account).build());
+    assert_eq!(contract.withdraw_relayer_fees(), U128(10));
+    assert_eq!(contract.get_relayer_fee_balance(relayer), U128(0));
+}
+
+#[test]
+#[should_panic(expected = "ERR_INSUFFICIENT_PREPAID_BALANCE")]
+fn test_relayer_fee_panic_insufficient_prepaid_balance() {
+    let relayer = accounts(1);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = contract_with_prepaid_accounting(relayer.clone(), accounts(0), pk1.clone());
+    contract.set_relayer_fee_config(relayer.clone(), Some(relayer_fees::RelayerFeeConfig { flat_fee: U128(5), fee_bps: 0 }));
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(pk1, transfer_action(accounts(3), 0));
+}
+
+// Tests for estimate_action_cost
+
+#[test]
+fn test_estimate_action_cost_includes_relayer_fee() {
+    let relayer = accounts(1);
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = contract_with_prepaid_accounting(relayer.clone(), accounts(0), pk1);
+    contract.set_relayer_fee_config(relayer, Some(relayer_fees::RelayerFeeConfig { flat_fee: U128(5), fee_bps: 1_000 }));
+
+    let estimate = contract.estimate_action_cost(transfer_action(accounts(3), 50));
+    assert_eq!(estimate.required_deposit, U128(50));
+    assert_eq!(estimate.relayer_fee, U128(10));
+    assert_eq!(
+        estimate.required_gas,
+        cost_estimate::GAS_FOR_DELEGATED_DISPATCH.saturating_add(Gas::from_tgas(5))
+    );
+
+    let estimate = contract.estimate_action_cost(record_spend_action("rev1", accounts(3), 42));
+    assert_eq!(estimate.required_deposit, U128(0));
+    assert_eq!(estimate.relayer_fee, U128(5));
+    assert_eq!(
+        estimate.required_gas,
+        cost_estimate::GAS_FOR_DELEGATED_DISPATCH
+            .saturating_add(GAS_FOR_PAYMENTS_CALL)
+            .saturating_add(Gas::from_tgas(5))
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_CALL_NOT_ALLOWED")]
+fn test_estimate_action_cost_panic_call_not_allowed() {
+    let owner = accounts(0);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let contract = PasskeyController::new(accounts(1), owner, None);
+    contract.estimate_action_cost(function_call_action("app.near", "play"));
+}
+
+// Tests for DeleteAccount beneficiaries
+
+fn delete_account_action(beneficiary_id: AccountId) -> SerializableAction {
+    SerializableAction {
+        beneficiary_id: Some(beneficiary_id),
+        ..empty_action(ActionType::DeleteAccount)
+    }
+}
+
+#[test]
+fn test_delete_account_to_disallowed_beneficiary_is_rejected() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer, owner, Some(vec![pk1.clone()]));
+
+    let request_id = contract.execute_delegated_actions(pk1, delete_account_action(accounts(4)));
+    assert_eq!(contract.get_execution_receipt(request_id).unwrap().status, receipts::ExecutionStatus::Failed);
+    assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""event":"beneficiary_rejected""#)));
+}
+
+#[test]
+fn test_delete_account_to_allowed_beneficiary_or_owner() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner.clone(), Some(vec![pk1.clone()]));
+    contract.add_allowed_beneficiary(accounts(4));
+    assert_eq!(contract.get_allowed_beneficiaries(), vec![accounts(4)]);
+    assert!(contract.is_beneficiary_allowed(owner.clone()));
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    let request_id = contract.execute_delegated_actions(pk1.clone(), delete_account_action(accounts(4)));
+    assert_eq!(contract.get_execution_receipt(request_id).unwrap().status, receipts::ExecutionStatus::Pending);
+    let request_id = contract.execute_delegated_actions(pk1, delete_account_action(owner));
+    assert_eq!(contract.get_execution_receipt(request_id).unwrap().status, receipts::ExecutionStatus::Pending);
+}
+
+// Tests for granted key tracking
+
+#[test]
+fn test_add_key_is_tracked_and_revoked() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer, owner.clone(), Some(vec![pk1.clone()]));
+
+    let request_id = contract.execute_delegated_actions(pk1, add_key_action(None));
+    let granted_pk = PublicKey::from_parts(near_sdk::CurveType::ED25519, [7u8; 32].to_vec()).unwrap();
+    // Only recorded once the AddKey succeeds
+    assert!(contract.get_granted_key(granted_pk.clone()).is_none());
+    resolve_delegated_action(&mut contract, request_id, near_sdk::PromiseResult::Successful(vec![]));
+    let granted = contract.get_granted_keys(0, 10);
+    assert_eq!(granted.len(), 1);
+    assert_eq!(granted[0].public_key, granted_pk);
+    assert_eq!(granted[0].receiver_id, "app.near".parse::<AccountId>().unwrap());
+    assert_eq!(granted[0].method_names, vec!["play".to_string()]);
+    assert_eq!(granted[0].allowance, None);
+
+    testing_env!(get_context(owner, accounts(2)).build());
+    contract.revoke_granted_key(granted_pk.clone());
+    assert!(contract.get_granted_key(granted_pk).is_none());
+}
+
+#[test]
+fn test_failed_add_key_is_not_tracked() {
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer, accounts(0), Some(vec![pk1.clone()]));
+
+    let request_id = contract.execute_delegated_actions(pk1, add_key_action(None));
+    resolve_delegated_action(&mut contract, request_id, near_sdk::PromiseResult::Failed);
+    assert!(contract.get_granted_keys(0, 10).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "Key was not granted by the controller")]
+fn test_revoke_granted_key_panic_unknown_key() {
+    let owner = accounts(0);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), owner, None);
+    contract.revoke_granted_key(PublicKey::from_parts(near_sdk::CurveType::ED25519, [7u8; 32].to_vec()).unwrap());
+}
+
+// Tests for passkey key validation and secp256k1 passkeys
+
+#[test]
+fn test_normalize_passkey_pk_both_curves() {
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let contract = PasskeyController::new(accounts(1), accounts(0), None);
+
+    let ed25519_key = passkey_signing_key(3);
+    let normalized = contract.normalize_passkey_pk(
+        "ED25519".to_string(),
+        Base64VecU8(ed25519_key.verifying_key().to_bytes().to_vec()),
+    );
+    assert_eq!(normalized, passkey_pk_of(&ed25519_key));
+
+    // The SEC1 0x04 tag authenticators export is stripped
+    let secp_key = secp256k1_signing_key(3);
+    let normalized = contract.normalize_passkey_pk(
+        "secp256k1".to_string(),
+        Base64VecU8(secp256k1_uncompressed_pk_bytes(&secp_key)),
+    );
+    assert_eq!(normalized, secp256k1_passkey_pk_of(&secp_key));
+
+    assert_eq!(
+        passkey_keys::normalize_passkey_pk(near_sdk::CurveType::SECP256K1, &[2u8; 33]),
+        Err(passkey_keys::PasskeyKeyError::CompressedSecp256k1)
+    );
+    assert_eq!(
+        passkey_keys::normalize_passkey_pk(near_sdk::CurveType::ED25519, &[0u8; 32]),
+        Err(passkey_keys::PasskeyKeyError::ZeroKey)
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_INVALID_PASSKEY_PK_LENGTH: ed25519 keys are 32 bytes, got 31")]
+fn test_add_passkey_pk_raw_panic_bad_length() {
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer, accounts(0), None);
+    contract.add_passkey_pk_raw("ed25519".to_string(), Base64VecU8(vec![1u8; 31]));
+}
+
+#[test]
+#[should_panic(expected = "ERR_UNSUPPORTED_PASSKEY_CURVE: p256")]
+fn test_add_passkey_pk_raw_panic_unsupported_curve() {
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer, accounts(0), None);
+    contract.add_passkey_pk_raw("p256".to_string(), Base64VecU8(vec![1u8; 64]));
+}
+
+#[test]
+fn test_secp256k1_passkey_signed_envelope() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer, owner, None);
+
+    let signing_key = secp256k1_signing_key(9);
+    assert!(contract.add_passkey_pk_raw("secp256k1".to_string(), Base64VecU8(secp256k1_uncompressed_pk_bytes(&signing_key))));
+    let passkey_pk = secp256k1_passkey_pk_of(&signing_key);
+    assert!(contract.is_passkey_pk_registered(passkey_pk.clone()));
+
+    let action = transfer_action(accounts(3), 100);
+    let payload = contract.get_envelope_payload(passkey_pk.clone(), action.clone(), near_sdk::json_types::U64(1), near_sdk::json_types::U64(u64::MAX));
+    let envelope = envelope::SignedActionEnvelope {
+        action,
+        nonce: near_sdk::json_types::U64(1),
+        valid_until: near_sdk::json_types::U64(u64::MAX),
+        signature: Base64VecU8(secp256k1_sign_prehash(&signing_key, &env::keccak256_array(&payload.0))),
+    };
+    contract.execute_signed_envelope(passkey_pk.clone(), envelope);
+    assert_eq!(contract.get_passkey_nonce(passkey_pk).0, 1);
+}
+
+// Tests for result callbacks
+
+#[test]
+fn test_result_callback_forwarded_once_action_resolves() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    testing_env!(get_context(owner.clone(), contract_account.clone()).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk1.clone()]));
+    contract.add_allowed_call("app.near".parse().unwrap(), vec!["on_result".to_string()]);
+
+    testing_env!(get_context(relayer, contract_account.clone()).build());
+    let callback = result_callbacks::ResultCallback {
+        receiver_id: "app.near".parse().unwrap(),
+        method_name: "on_result".to_string(),
+    };
+    let request_id = contract.execute_delegated_actions_with_callback(pk1, transfer_action(accounts(3), 10), callback.clone());
+    assert_eq!(contract.get_result_callback(request_id), Some(callback));
+
+    set_promise_results(&get_context(contract_account.clone(), contract_account), vec![near_sdk::PromiseResult::Successful(b"42".to_vec())]);
+    assert!(contract.on_delegated_action_result(request_id));
+    assert_eq!(contract.get_result_callback(request_id), None);
+
+    let receipts = near_sdk::test_utils::get_created_receipts();
+    let forwarded = receipts
+        .iter()
+        .find(|receipt| receipt.receiver_id == "app.near".parse::<AccountId>().unwrap())
+        .expect("Result callback should be called");
+    let near_sdk::mock::MockAction::FunctionCallWeight { method_name, args, .. } = &forwarded.actions[0] else {
+        panic!("Expected a FunctionCall action");
+    };
+    assert_eq!(method_name, b"on_result");
+    let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(args).unwrap();
+    assert_eq!(args["succeeded"], true);
+    assert_eq!(args["result"], near_sdk::serde_json::to_value(Base64VecU8(b"42".to_vec())).unwrap());
+}
+
+#[test]
+#[should_panic(expected = "ERR_CALL_NOT_ALLOWED")]
+fn test_result_callback_panic_not_allowed() {
+    let relayer = accounts(1);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let pk1 = PublicKey::from_parts(near_sdk::CurveType::ED25519, [1u8; 32].to_vec()).unwrap();
+    let mut contract = PasskeyController::new(relayer, accounts(0), Some(vec![pk1.clone()]));
+    let callback = result_callbacks::ResultCallback {
+        receiver_id: "app.near".parse().unwrap(),
+        method_name: "on_result".to_string(),
+    };
+    contract.execute_delegated_actions_with_callback(pk1, transfer_action(accounts(3), 10), callback);
+}
+
+#[test]
+fn test_create_account_batches_actions_on_new_account() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let contract_account = accounts(2);
+    let creator = accounts(3);
+    let new_account_id: AccountId = "new.bob".parse().unwrap();
+    let pk_creator = passkey_pk(11);
+
+    testing_env!(get_context(relayer.clone(), contract_account.clone()).build());
+    let mut contract = PasskeyController::new(relayer, owner, Some(vec![pk_creator.clone()]));
+    let mut context = get_context(creator, contract_account);
+    context.signer_account_pk(pk_creator);
+    testing_env!(context.build());
+
+    contract.execute_direct_actions(SerializableAction {
+        receiver_id: Some(new_account_id.clone()),
+        initial_deposit_for_new_account: Some(U128(1_000)),
+        public_key_for_new_account: Some(passkey_pk(12)),
+        code: Some(Base64VecU8(vec![0, 97, 115, 109])),
+        method_name: Some("new".to_string()),
+        args: Some(Base64VecU8(b"{}".to_vec())),
+        ..empty_action(ActionType::CreateAccount)
+    });
+
+    // create_account, transfer, add key, deploy and init call in one receipt on the new account
+    let receipts = near_sdk::test_utils::get_created_receipts();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].receiver_id, new_account_id);
+    assert_eq!(receipts[0].actions.len(), 5);
+}
+
+#[test]
+#[should_panic(expected = "code is required for CreateAccount init call")]
+fn test_create_account_panic_init_call_without_code() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let pk_creator = passkey_pk(11);
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer, owner, Some(vec![pk_creator.clone()]));
+    let mut context = get_context(accounts(3), accounts(2));
+    context.signer_account_pk(pk_creator);
+    testing_env!(context.build());
+
+    contract.execute_direct_actions(SerializableAction {
+        receiver_id: Some("new.bob".parse().unwrap()),
+        method_name: Some("new".to_string()),
+        ..empty_action(ActionType::CreateAccount)
+    });
+}
+
+// Tests for per-ActionType caps
+
+#[test]
+#[should_panic(expected = "ERR_ACTION_DEPOSIT_CAP_EXCEEDED")]
+fn test_action_caps_limit_transfer_deposit() {
+    let owner = accounts(0);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), owner, None);
+    let caps = action_caps::ActionCaps { max_deposit: Some(U128(10)), ..Default::default() };
+    contract.set_action_caps(ActionType::Transfer, Some(caps.clone()));
+    assert_eq!(contract.get_action_caps(ActionType::Transfer), Some(caps.clone()));
+    assert_eq!(contract.get_all_action_caps(), vec![(ActionType::Transfer, caps)]);
+
+    contract.estimate_action_cost(transfer_action(accounts(3), 10));
+    contract.estimate_action_cost(transfer_action(accounts(3), 11));
+}
+
+#[test]
+#[should_panic(expected = "ERR_ACTION_TYPE_DISABLED")]
+fn test_action_caps_disable_deploy_contract() {
+    let owner = accounts(0);
+    let pk = passkey_pk(13);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), owner, Some(vec![pk.clone()]));
+    contract.set_action_caps(ActionType::DeployContract, Some(action_caps::ActionCaps { disabled: true, ..Default::default() }));
+
+    let mut context = get_context(accounts(3), accounts(2));
+    context.signer_account_pk(pk);
+    testing_env!(context.build());
+    contract.execute_direct_actions(SerializableAction {
+        code: Some(Base64VecU8(vec![0, 97, 115, 109])),
+        ..empty_action(ActionType::DeployContract)
+    });
+}
+
+// Tests for relayer heartbeats and failover
+
+fn contract_with_relayer_failover(owner: AccountId, relayer: AccountId, backup: AccountId, pk: PublicKey) -> PasskeyController {
+    testing_env!(get_context(owner.clone(), accounts(2)).block_height(100).build());
+    let mut contract = PasskeyController::new(relayer, owner, Some(vec![pk]));
+    contract.set_relayer_failover(Some(relayer_failover::RelayerFailoverConfig {
+        backup_relayer: backup,
+        heartbeat_interval_blocks: near_sdk::json_types::U64(10),
+        max_missed_heartbeats: 3,
+    }));
+    contract
+}
+
+#[test]
+fn test_backup_relayer_takes_over_after_missed_heartbeats() {
+    let relayer = accounts(1);
+    let backup = accounts(4);
+    let pk = passkey_pk(14);
+    let mut contract = contract_with_relayer_failover(accounts(0), relayer.clone(), backup.clone(), pk.clone());
+
+    testing_env!(get_context(relayer.clone(), accounts(2)).block_height(120).build());
+    contract.relayer_heartbeat();
+    assert_eq!(contract.get_relayer_last_seen(relayer.clone()), Some(near_sdk::json_types::U64(120)));
+    testing_env!(get_context(backup.clone(), accounts(2)).block_height(150).build());
+    assert_eq!(contract.get_active_relayer(), relayer);
+
+    // 31 blocks without a heartbeat: the backup may execute
+    testing_env!(get_context(backup.clone(), accounts(2)).block_height(151).build());
+    assert_eq!(contract.get_active_relayer(), backup);
+    contract.execute_delegated_actions(pk, transfer_action(accounts(3), 1));
+
+    // The primary is back as soon as it sends a heartbeat
+    testing_env!(get_context(relayer.clone(), accounts(2)).block_height(152).build());
+    contract.relayer_heartbeat();
+    assert_eq!(contract.get_active_relayer(), relayer);
+}
+
+#[test]
+#[should_panic(expected = "Only trusted relayer can execute actions")]
+fn test_backup_relayer_panic_while_primary_alive() {
+    let backup = accounts(4);
+    let pk = passkey_pk(14);
+    let mut contract = contract_with_relayer_failover(accounts(0), accounts(1), backup.clone(), pk.clone());
+    testing_env!(get_context(backup, accounts(2)).block_height(130).build());
+    contract.execute_delegated_actions(pk, transfer_action(accounts(3), 1));
+}
+
+// Tests for daily passkey quotas
+
+#[test]
+fn test_passkey_quota_resets_next_day() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let pk = passkey_pk(15);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk.clone()]));
+    contract.set_default_passkey_quota(Some(5));
+    contract.set_passkey_quota(pk.clone(), Some(1));
+    assert_eq!(contract.get_passkey_quota(pk.clone()), Some(1));
+
+    testing_env!(get_context(relayer.clone(), accounts(2)).build());
+    contract.execute_delegated_actions(pk.clone(), transfer_action(accounts(3), 1));
+    assert_eq!(contract.get_passkey_quota_usage(pk.clone()), 1);
+
+    testing_env!(get_context(relayer, accounts(2)).block_timestamp(86_400_000_000_000).build());
+    assert_eq!(contract.get_passkey_quota_usage(pk.clone()), 0);
+    contract.execute_delegated_actions(pk, transfer_action(accounts(3), 1));
+}
+
+#[test]
+#[should_panic(expected = "ERR_DAILY_QUOTA_EXCEEDED")]
+fn test_passkey_quota_panic_exceeded() {
+    let owner = accounts(0);
+    let relayer = accounts(1);
+    let pk = passkey_pk(15);
+    testing_env!(get_context(owner.clone(), accounts(2)).build());
+    let mut contract = PasskeyController::new(relayer.clone(), owner, Some(vec![pk.clone()]));
+    contract.set_default_passkey_quota(Some(1));
+
+    testing_env!(get_context(relayer, accounts(2)).build());
+    contract.execute_delegated_actions(pk.clone(), transfer_action(accounts(3), 1));
+    contract.execute_delegated_actions(pk, transfer_action(accounts(3), 2));
+}
+
+// Tests for config-based initialization
+
+#[test]
+fn test_new_with_config_round_trips_through_get_config() {
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let config: init_config::InitConfig = near_sdk::serde_json::from_value(near_sdk::serde_json::json!({
+        "owner_id": accounts(0),
+        "trusted_relayer_account_id": accounts(1),
+        "passkey_pks": [passkey_pk(16)],
+        "relayer_fees": [[accounts(1), {"flat_fee": "5", "fee_bps": 100}]],
+        "prepaid_accounting_enabled": true,
+        "action_caps": [["DeployContract", {"max_gas": null, "max_deposit": null, "disabled": true}]],
+    }))
+    .unwrap();
+    assert_eq!(config.balance_reserve, U128(balance_reserve::DEFAULT_BALANCE_RESERVE));
+
+    let contract = PasskeyController::new_with_config(config.clone());
+    assert!(contract.is_passkey_pk_registered(passkey_pk(16)));
+    assert_eq!(contract.get_relayer_fee_config(accounts(1)).unwrap().fee_bps, 100);
+    assert_eq!(contract.get_config(), config);
+}
+
+#[test]
+fn test_get_config_lists_a_bounded_page_of_passkeys() {
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let passkey_pks: Vec<_> = (0..=init_config::CONFIG_PASSKEY_LIMIT as u8).map(passkey_pk).collect();
+    let contract = PasskeyController::new(accounts(1), accounts(0), Some(passkey_pks));
+
+    assert_eq!(contract.get_config().passkey_pks.len(), init_config::CONFIG_PASSKEY_LIMIT as usize);
+    assert_eq!(contract.get_limits().passkey_count, init_config::CONFIG_PASSKEY_LIMIT + 1);
+    // The rest is paged through separately
+    let rest = contract.get_passkey_pks(init_config::CONFIG_PASSKEY_LIMIT, 10);
+    assert_eq!(rest.len(), 1);
+    assert!(!contract.get_config().passkey_pks.contains(&rest[0]));
+}
+
+// Tests for auth failure backoff
+
+fn contract_with_auth_backoff(pk: &PublicKey) -> PasskeyController {
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![pk.clone()]));
+    contract.set_auth_backoff(Some(auth_backoff::AuthBackoffConfig {
+        max_failures: 2,
+        window_blocks: near_sdk::json_types::U64(100),
+        base_cooldown_blocks: near_sdk::json_types::U64(10),
+    }));
+    contract
+}
+
+#[test]
+fn test_auth_failures_trigger_doubling_cooldown() {
+    let pk = passkey_pk(17);
+    let mut contract = contract_with_auth_backoff(&pk);
+    let relayer = accounts(1);
+
+    testing_env!(get_context(relayer.clone(), accounts(2)).block_height(50).build());
+    let request_id = contract.execute_delegated_actions(passkey_pk(18), transfer_action(accounts(3), 1));
+    assert!(contract.get_execution_receipt(request_id).is_none());
+    assert_eq!(
+        contract.schedule_action(passkey_pk(18), transfer_action(accounts(3), 1), near_sdk::json_types::U64(0), None),
+        auth_backoff::REJECTED_ID
+    );
+    let failures = contract.get_auth_failures(relayer.clone()).unwrap();
+    assert_eq!((failures.cooldowns, failures.cooldown_until.0), (1, 60));
+
+    testing_env!(get_context(relayer.clone(), accounts(2)).block_height(60).build());
+    contract.execute_delegated_actions(passkey_pk(18), transfer_action(accounts(3), 1));
+    contract.execute_delegated_actions(passkey_pk(18), transfer_action(accounts(3), 1));
+    assert_eq!(contract.get_auth_failures(relayer.clone()).unwrap().cooldown_until.0, 80);
+
+    // An authorized call after the cooldown leaves no failure record behind
+    testing_env!(get_context(relayer.clone(), accounts(2)).block_height(80).build());
+    contract.execute_delegated_actions(pk, transfer_action(accounts(3), 1));
+    assert!(contract.get_auth_failures(relayer).is_none());
+}
+
+#[test]
+#[should_panic(expected = "ERR_AUTH_COOLDOWN: bob can't call until block 60")]
+fn test_auth_cooldown_panic_rejects_calls() {
+    let pk = passkey_pk(17);
+    let mut contract = contract_with_auth_backoff(&pk);
+    testing_env!(get_context(accounts(1), accounts(2)).block_height(50).build());
+    contract.execute_delegated_actions(passkey_pk(18), transfer_action(accounts(3), 1));
+    contract.execute_delegated_actions(passkey_pk(18), transfer_action(accounts(3), 1));
+    contract.execute_delegated_actions(pk, transfer_action(accounts(3), 1));
+}
+
+#[test]
+#[should_panic(expected = "Only trusted relayer can execute actions")]
+fn test_auth_backoff_panic_unknown_caller() {
+    let pk = passkey_pk(17);
+    let mut contract = contract_with_auth_backoff(&pk);
+    testing_env!(get_context(accounts(4), accounts(2)).block_height(50).build());
+    contract.execute_delegated_actions(pk, transfer_action(accounts(3), 1));
+}
+
+// Tests for Borsh-input execution
+
+#[test]
+fn test_execute_delegated_actions_borsh_matches_json() {
+    let pk = passkey_pk(19);
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![pk.clone()]));
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    let action = transfer_action(accounts(3), 1);
+    let payload = near_sdk::borsh::to_vec(&borsh_input::DelegatedActionPayload {
+        passkey_pk_used: pk.clone(),
+        action_to_execute: action.clone(),
+    })
+    .unwrap();
+    let request_id = contract.execute_delegated_actions_borsh(payload);
+    assert_eq!(request_id, Base58CryptoHash::from(receipts::compute_request_id(&pk, 1, &action)));
+    assert!(contract.get_execution_receipt(request_id).is_some());
+}
+
+#[test]
+#[should_panic(expected = "ERR_INVALID_BORSH_PAYLOAD")]
+fn test_execute_delegated_actions_borsh_panic_invalid_payload() {
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![passkey_pk(19)]));
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    contract.execute_delegated_actions_borsh(vec![1, 2, 3]);
+}
+
+// Tests for execution tags
+
+#[test]
+fn test_execution_tag_kept_on_receipt_and_events() {
+    let pk = passkey_pk(20);
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![pk.clone()]));
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    let tag = execution_tags::ExecutionTag { memo: Some("order 42".to_string()), client_id: Some("shop-app".to_string()) };
+    let request_id = contract.execute_delegated_actions_tagged(pk, transfer_action(accounts(3), 1), tag.clone());
+    assert_eq!(contract.get_execution_receipt(request_id).unwrap().tag, Some(tag));
+    assert!(near_sdk::test_utils::get_logs()
+        .iter()
+        .any(|log| log.contains(r#""event":"execution_tagged""#) && log.contains(r#""client_id":"shop-app""#)));
+
+    set_promise_results(&get_context(accounts(2), accounts(2)), vec![near_sdk::PromiseResult::Successful(vec![])]);
+    contract.on_delegated_action_result(request_id);
+    assert!(near_sdk::test_utils::get_logs()
+        .iter()
+        .any(|log| log.contains(r#""event":"execution_resolved""#) && log.contains(r#""memo":"order 42""#)));
+}
+
+#[test]
+#[should_panic(expected = "client_id must be at most 256 characters")]
+fn test_execution_tag_panic_too_long() {
+    let pk = passkey_pk(20);
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![pk.clone()]));
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    let tag = execution_tags::ExecutionTag { memo: None, client_id: Some("x".repeat(257)) };
+    contract.execute_delegated_actions_tagged(pk, transfer_action(accounts(3), 1), tag);
+}
+
+// Tests for state limits
+
+#[test]
+fn test_get_limits_reports_counts_and_caps() {
+    let relayer = accounts(1);
+    let signing_key = passkey_signing_key(32);
+    let (mut contract, _) = controller_with_session(relayer.clone(), &signing_key);
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    contract.set_max_passkeys(Some(1));
+    contract.set_max_active_sessions(Some(5));
+    let limits = contract.get_limits();
+    assert_eq!(
+        limits,
+        limits::ControllerLimits { passkey_count: 1, max_passkeys: Some(1), session_count: 1, max_active_sessions: Some(5) }
+    );
+    // Re-adding a registered passkey doesn't count against the cap
+    testing_env!(get_context(relayer, accounts(2)).build());
+    assert!(!contract.add_passkey_pk(passkey_pk_of(&signing_key)));
+}
+
+#[test]
+#[should_panic(expected = "ERR_MAX_PASSKEYS_REACHED")]
+fn test_add_passkey_pk_panic_max_passkeys_reached() {
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![passkey_pk(21)]));
+    contract.set_max_passkeys(Some(1));
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    contract.add_passkey_pk(passkey_pk(22));
+}
+
+#[test]
+#[should_panic(expected = "ERR_MAX_SESSIONS_REACHED")]
+fn test_create_session_panic_max_sessions_reached() {
+    use ed25519_dalek::Signer;
+    let relayer = accounts(1);
+    let signing_key = passkey_signing_key(33);
+    let (mut contract, _) = controller_with_session(relayer.clone(), &signing_key);
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    contract.set_max_active_sessions(Some(1));
+
+    testing_env!(get_context(relayer, accounts(2)).block_timestamp(1_000).build());
+    let passkey_pk = passkey_pk_of(&signing_key);
+    let ttl = near_sdk::json_types::U64(1_000);
+    let nonce = near_sdk::json_types::U64(2);
+    let payload = contract.get_session_payload(passkey_pk.clone(), session_policy(), ttl, nonce);
+    let signature = Base64VecU8(signing_key.sign(&payload.0).to_bytes().to_vec());
+    contract.create_session(passkey_pk, session_policy(), ttl, nonce, signature);
+}
+
+// Tests for the security config view
+
+#[test]
+fn test_get_security_config_reports_settings_and_queued_actions() {
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![passkey_pk(41)]));
+    let queued = contract.schedule_action(passkey_pk(41), transfer_action(accounts(3), 10), near_sdk::json_types::U64(1_000), None);
+    let cancelled = contract.schedule_action(passkey_pk(41), transfer_action(accounts(3), 20), near_sdk::json_types::U64(1_000), None);
+    testing_env!(get_context(accounts(0), accounts(2)).build());
+    contract.cancel_scheduled(cancelled);
+    contract.set_max_passkeys(Some(3));
+    contract.add_allowed_beneficiary(accounts(3));
+
+    let security = contract.get_security_config();
+    assert_eq!(security.config, contract.get_config());
+    assert_eq!(security.active_relayer, accounts(1));
+    assert_eq!(security.limits.max_passkeys, Some(3));
+    assert_eq!(security.limits.passkey_count, 1);
+    assert!(security.multisig_config.is_none());
+    assert_eq!(security.allowed_beneficiaries, vec![accounts(3)]);
+    assert_eq!(security.scheduled_action_count, 1);
+    assert_eq!(security.scheduled_actions.len(), 1);
+    assert_eq!(security.scheduled_actions[0].0, queued);
+}
+
+#[test]
+fn test_queued_scheduled_actions_paginate_over_the_whole_queue() {
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![passkey_pk(41)]));
+    let ids: Vec<_> = (0..4)
+        .map(|i| contract.schedule_action(passkey_pk(41), transfer_action(accounts(3), 10 + i), near_sdk::json_types::U64(1_000), None))
+        .collect();
+
+    // Executed actions leave the queue
+    testing_env!(get_context(accounts(4), accounts(2)).block_timestamp(1_000).build());
+    contract.execute_scheduled(ids[0]);
+
+    assert_eq!(contract.get_queued_scheduled_count(), 3);
+    let page: Vec<_> = contract.get_queued_scheduled_actions(1, 10).into_iter().map(|(id, _)| id).collect();
+    assert_eq!(page.len(), 2);
+    assert!(!page.contains(&ids[0]));
+    assert_eq!(contract.get_security_config().scheduled_action_count, 3);
+}
+
+#[test]
+fn test_get_security_config_flattens_init_config() {
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    let contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![passkey_pk(42)]));
+    let json = near_sdk::serde_json::to_value(contract.get_security_config()).unwrap();
+    assert_eq!(json["owner_id"], accounts(0).to_string());
+    assert_eq!(json["trusted_relayer_account_id"], accounts(1).to_string());
+    assert_eq!(json["scheduled_actions"], near_sdk::serde_json::json!([]));
+}
+
+// Tests for batch execution plans
+
+#[test]
+fn test_plan_batch_and_execute_returns_request_ids_in_plan_order() {
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![passkey_pk(51)]));
+    let actions = vec![transfer_action(accounts(3), 10), transfer_action(accounts(4), 20)];
+    let plan = contract.plan_batch(actions.clone());
+    assert_eq!(plan.actions.len(), 2);
+    assert_eq!(plan.actions[1].index, 1);
+    assert_eq!(plan.actions[1].target, accounts(4));
+    assert_eq!(plan.actions[1].gas, Gas::from_gas(0));
+
+    let request_ids = contract.execute_delegated_batch(passkey_pk(51), actions.clone(), plan.plan_hash);
+    assert_eq!(
+        request_ids,
+        vec![
+            Base58CryptoHash::from(receipts::compute_request_id(&passkey_pk(51), 1, &actions[0])),
+            Base58CryptoHash::from(receipts::compute_request_id(&passkey_pk(51), 2, &actions[1])),
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "ERR_BATCH_PLAN_MISMATCH")]
+fn test_execute_delegated_batch_panic_plan_mismatch() {
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![passkey_pk(52)]));
+    let plan = contract.plan_batch(vec![transfer_action(accounts(3), 10)]);
+    contract.execute_delegated_batch(passkey_pk(52), vec![transfer_action(accounts(3), 11)], plan.plan_hash);
+}
+
+// Tests for the injectable clock
+
+#[test]
+fn test_test_hooks_advance_time_for_scheduler() {
+    use clock::TestHooks;
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![passkey_pk(61)]));
+    let action = transfer_action(accounts(3), 10);
+    let id = contract.schedule_action(passkey_pk(61), action.clone(), near_sdk::json_types::U64(1_000), None);
+
+    TestHooks::advance_time(1_000);
+    let request_id = contract.execute_scheduled(id);
+    TestHooks::reset();
+    assert_eq!(request_id, Base58CryptoHash::from(receipts::compute_request_id(&passkey_pk(61), 1, &action)));
+}
+
+#[test]
+fn test_test_hooks_pin_challenge_seed_and_expiry() {
+    use clock::TestHooks;
+    testing_env!(get_context(accounts(1), accounts(2)).build());
+    let mut contract = PasskeyController::new(accounts(1), accounts(0), Some(vec![passkey_pk(62)]));
+    TestHooks { block_timestamp: Some(5_000), random_seed: Some([7u8; 32]) }.install();
+    let issued = contract.issue_challenge();
+    let expected_ttl = contract.get_challenge_ttl().0;
+    TestHooks::reset();
+
+    let mut seed = [7u8; 32].to_vec();
+    seed.extend_from_slice(&1u64.to_le_bytes());
+    assert_eq!(issued.0, env::sha256_array(&seed).to_vec());
+    assert_eq!(contract.get_issued_challenge(issued).unwrap().expires_at.0, 5_000 + expected_ttl);
+}
+
+// Tests for state migration
+
+#[test]
+fn test_migrate_from_first_deployed_layout() {
+    #[derive(near_sdk::borsh::BorshSerialize)]
+    #[borsh(crate = "near_sdk::borsh")]
+    struct PasskeyControllerV1 {
+        trusted_relayer_account_id: AccountId,
+        owner_id: AccountId,
+        registered_passkey_pks: near_sdk::store::IterableSet<PublicKey>,
+    }
+    testing_env!(get_context(accounts(2), accounts(2)).build());
+    let mut registered_passkey_pks = near_sdk::store::IterableSet::new(b"p");
+    registered_passkey_pks.insert(passkey_pk(71));
+    registered_passkey_pks.flush();
+    env::state_write(&PasskeyControllerV1 { trusted_relayer_account_id: accounts(1), owner_id: accounts(0), registered_passkey_pks });
+
+    let contract = PasskeyController::migrate();
+    assert!(contract.is_passkey_pk_registered(passkey_pk(71)));
+    assert_eq!(contract.get_config().owner_id, accounts(0));
+    assert_eq!(contract.get_active_relayer(), accounts(1));
+}
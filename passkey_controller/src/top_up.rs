@@ -0,0 +1,128 @@
+use crate::*;
+use near_sdk::json_types::U64;
+use near_sdk::PromiseResult;
+
+const GAS_FOR_ON_AUTO_TOP_UP: Gas = Gas::from_tgas(5);
+
+/// Blocks after a top-up request before the next one can be sent.
+pub const AUTO_TOP_UP_DEBOUNCE_BLOCKS: u64 = 10;
+
+/// Refills a passkey's prepaid balance from the user's balance on a PaymentContract
+/// reverie once it drops below `threshold`. Requires this controller to be a spender
+/// for the reverie (or the payments trusted account).
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoTopUpConfig {
+    pub reverie_id: String,
+    pub user_id: AccountId,
+    pub threshold: U128,
+    pub top_up_amount: U128,
+    pub in_flight: bool, // a top-up request is awaiting its callback
+    #[serde(default)]
+    pub requested_at_block: Option<U64>, // block the last top-up request was sent in
+}
+
+#[near_sdk::near(serializers = [json])]
+struct RecordSpendWithPayoutArgs {
+    reverie_id: String,
+    user_id: AccountId,
+    amount_to_spend: U128,
+}
+
+#[near]
+impl PasskeyController {
+    /// Sets the passkey's auto-top-up. Called by the relayer on the passkey user's behalf.
+    pub fn set_auto_top_up(
+        &mut self,
+        passkey_pk: PublicKey,
+        reverie_id: String,
+        user_id: AccountId,
+        threshold: U128,
+        top_up_amount: U128,
+    ) {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk) {
+            return;
+        }
+        assert!(top_up_amount.0 > 0, "Top-up amount must be greater than 0");
+        self.get_payments_contract_or_panic();
+        self.auto_top_ups.insert(
+            passkey_pk,
+            AutoTopUpConfig { reverie_id, user_id, threshold, top_up_amount, in_flight: false, requested_at_block: None },
+        );
+    }
+
+    pub fn remove_auto_top_up(&mut self, passkey_pk: PublicKey) {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk) {
+            return;
+        }
+        self.auto_top_ups.remove(&passkey_pk);
+    }
+
+    pub fn get_auto_top_up(&self, passkey_pk: PublicKey) -> Option<AutoTopUpConfig> {
+        self.auto_top_ups.get(&passkey_pk).cloned()
+    }
+
+    /// Requests a top-up if the passkey's prepaid balance is below its threshold and no request
+    /// was sent in the last `AUTO_TOP_UP_DEBOUNCE_BLOCKS`. Callable by anyone, e.g. to retry after
+    /// a failed top-up. Returns whether a request was sent.
+    pub fn request_top_up(&mut self, passkey_pk: PublicKey) -> bool {
+        self.maybe_request_top_up(&passkey_pk)
+    }
+
+    #[private]
+    pub fn on_auto_top_up(&mut self, passkey_pk: PublicKey, amount: U128) -> bool {
+        let succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if let Some(config) = self.auto_top_ups.get_mut(&passkey_pk) {
+            config.in_flight = false;
+        }
+        if succeeded {
+            self.credit_prepaid(&passkey_pk, amount.0);
+        } else {
+            log!("Auto top-up of {} failed for passkey {:?}", amount.0, passkey_pk);
+        }
+        succeeded
+    }
+}
+
+impl PasskeyController {
+    // internal method firing `record_spend_with_payout` on the payments contract when the
+    // prepaid balance is below the threshold, no top-up is already in flight and the last
+    // request is older than the debounce. Called once the balance is settled, from the
+    // delegated result callback, rather than when an action is debited.
+    pub(crate) fn maybe_request_top_up(&mut self, passkey_pk: &PublicKey) -> bool {
+        let Some(config) = self.auto_top_ups.get(passkey_pk).cloned() else {
+            return false;
+        };
+        let balance = *self.prepaid_balances.get(passkey_pk).unwrap_or(&0);
+        let debounced = config
+            .requested_at_block
+            .is_some_and(|block| env::block_height() < block.0.saturating_add(AUTO_TOP_UP_DEBOUNCE_BLOCKS));
+        if config.in_flight || debounced || balance >= config.threshold.0 {
+            return false;
+        }
+        let payments_contract_id = self.get_payments_contract_or_panic();
+        let args = near_sdk::serde_json::to_vec(&RecordSpendWithPayoutArgs {
+            reverie_id: config.reverie_id.clone(),
+            user_id: config.user_id.clone(),
+            amount_to_spend: config.top_up_amount,
+        })
+        .unwrap_or_else(|_| panic!("ERR_ARGS_SERIALIZATION"));
+        if let Some(config) = self.auto_top_ups.get_mut(passkey_pk) {
+            config.in_flight = true;
+            config.requested_at_block = Some(U64(env::block_height()));
+        }
+        Promise::new(payments_contract_id)
+            .function_call(
+                "record_spend_with_payout".to_string(),
+                args,
+                NearToken::from_yoctonear(0),
+                GAS_FOR_PAYMENTS_CALL,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_AUTO_TOP_UP)
+                    .on_auto_top_up(passkey_pk.clone(), config.top_up_amount),
+            );
+        true
+    }
+}
@@ -0,0 +1,48 @@
+use crate::*;
+use reveries_types::validation::parse_json;
+
+pub use reveries_types::{FieldError, ValidationReport};
+
+#[near]
+impl PasskeyController {
+    /// Lints a `SerializableAction` JSON before it is submitted: reports parse errors,
+    /// missing fields, and actions this controller would reject (disallowed calls,
+    /// missing payments contract, non-whitelisted staking pools, unmanaged accounts).
+    pub fn validate_action_json(&self, action_json: String) -> ValidationReport {
+        let action: SerializableAction = match parse_json(&action_json) {
+            Ok(action) => action,
+            Err(report) => return report,
+        };
+        let mut report = action.validate();
+        match action.action_type {
+            ActionType::FunctionCall => {
+                if let (Some(receiver_id), Some(method_name)) = (&action.receiver_id, &action.method_name) {
+                    if !self.is_call_allowed(receiver_id.clone(), method_name.clone()) {
+                        report.push("method_name", "ERR_CALL_NOT_ALLOWED");
+                    }
+                }
+            }
+            ActionType::RecordSpend | ActionType::ChargeReverie { .. } | ActionType::ReverieDeposit => {
+                if self.payments_contract_id.is_none() {
+                    report.push("action_type", "Payments contract is not configured");
+                }
+            }
+            ActionType::StakeWithPool | ActionType::UnstakeFromPool | ActionType::WithdrawFromPool => {
+                if let Some(pool_id) = &action.receiver_id {
+                    if !self.staking_pools.contains(pool_id) {
+                        report.push("receiver_id", "ERR_STAKING_POOL_NOT_WHITELISTED");
+                    }
+                }
+            }
+            ActionType::AddKey | ActionType::DeleteKey => {
+                if let Some(account_id) = &action.user_id {
+                    if account_id != &env::current_account_id() && !self.is_managed_account(account_id.clone()) {
+                        report.push("user_id", "ERR_ACCOUNT_NOT_MANAGED");
+                    }
+                }
+            }
+            _ => {}
+        }
+        report
+    }
+}
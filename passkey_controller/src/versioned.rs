@@ -0,0 +1,14 @@
+use crate::*;
+pub use reveries_types::VersionedAction;
+
+#[near]
+impl PasskeyController {
+    /// Same as `execute_delegated_actions`, for relayers sending version-tagged actions.
+    pub fn execute_versioned_action(
+        &mut self,
+        passkey_pk_used: PublicKey,
+        versioned_action: VersionedAction,
+    ) -> Base58CryptoHash {
+        self.execute_delegated_actions(passkey_pk_used, versioned_action.into_latest())
+    }
+}
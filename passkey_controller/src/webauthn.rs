@@ -0,0 +1,170 @@
+use crate::*;
+use crate::envelope::verify_passkey_signature;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use near_sdk::json_types::U64;
+use near_sdk::CryptoHash;
+
+/// Default lifetime of an issued challenge: 5 minutes.
+pub const DEFAULT_CHALLENGE_TTL_NS: u64 = 300_000_000_000;
+
+// WebAuthn authenticator data flag: user present
+const AUTH_DATA_FLAG_UP: u8 = 0x01;
+// rpIdHash (32 bytes) + flags (1 byte) + signCount (4 bytes)
+const MIN_AUTH_DATA_LEN: usize = 37;
+
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone)]
+pub struct IssuedChallenge {
+    pub issued_to: AccountId,
+    pub expires_at: U64,
+}
+
+/// A WebAuthn assertion (navigator.credentials.get) made with an ed25519 passkey.
+#[near_sdk::near(serializers = [json])]
+#[derive(Debug, Clone)]
+pub struct WebAuthnAssertion {
+    pub authenticator_data: Base64VecU8,
+    pub client_data_json: String,
+    pub signature: Base64VecU8,
+}
+
+#[near_sdk::near(serializers = [json])]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+}
+
+/// The WebAuthn challenge the client must sign for `execute_with_webauthn`:
+/// the issued challenge followed by the sha256 of the Borsh-serialized action,
+/// so an assertion is bound to both a single-use challenge and the exact action.
+pub fn webauthn_challenge_bytes(issued_challenge: &CryptoHash, action: &SerializableAction) -> Vec<u8> {
+    let action_bytes = near_sdk::borsh::to_vec(action).unwrap_or_else(|_| panic!("ERR_PAYLOAD_SERIALIZATION"));
+    let mut challenge = issued_challenge.to_vec();
+    challenge.extend_from_slice(&env::sha256_array(&action_bytes));
+    challenge
+}
+
+#[near]
+impl PasskeyController {
+    pub fn set_challenge_ttl(&mut self, challenge_ttl_ns: U64) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Only owner can set challenge ttl"
+        );
+        self.challenge_ttl_ns = challenge_ttl_ns.0;
+    }
+
+    pub fn get_challenge_ttl(&self) -> U64 {
+        U64(self.challenge_ttl_ns)
+    }
+
+    /// Issues a random single-use challenge to the caller, valid for the challenge ttl.
+    /// Callers other than the relayer must attach the storage cost of the challenge, which
+    /// the controller keeps.
+    #[payable]
+    pub fn issue_challenge(&mut self) -> Base64VecU8 {
+        let storage_before = env::storage_usage();
+        self.challenge_counter += 1;
+        let mut seed = clock::random_seed().to_vec();
+        seed.extend_from_slice(&self.challenge_counter.to_le_bytes());
+        let challenge = env::sha256_array(&seed);
+        self.webauthn_challenges.insert(
+            challenge,
+            IssuedChallenge {
+                issued_to: env::predecessor_account_id(),
+                expires_at: U64(clock::block_timestamp() + self.challenge_ttl_ns),
+            },
+        );
+        let caller = env::predecessor_account_id();
+        if caller != self.trusted_relayer_account_id && caller != self.get_active_relayer() {
+            let storage_cost = env::storage_byte_cost().as_yoctonear()
+                * env::storage_usage().saturating_sub(storage_before) as u128;
+            assert!(
+                env::attached_deposit().as_yoctonear() >= storage_cost,
+                "ERR_INSUFFICIENT_CHALLENGE_DEPOSIT: attach {} yoctoNEAR",
+                storage_cost
+            );
+        }
+        Base64VecU8(challenge.to_vec())
+    }
+
+    pub fn get_issued_challenge(&self, challenge: Base64VecU8) -> Option<IssuedChallenge> {
+        let challenge: CryptoHash = challenge.0.try_into().ok()?;
+        self.webauthn_challenges.get(&challenge).cloned()
+    }
+
+    /// Returns the challenge bytes the client must put (base64url encoded) in clientDataJSON.
+    pub fn get_webauthn_challenge(&self, challenge: Base64VecU8, action: SerializableAction) -> Base64VecU8 {
+        let challenge: CryptoHash = challenge.0.try_into().unwrap_or_else(|_| panic!("ERR_INVALID_CHALLENGE_LENGTH"));
+        Base64VecU8(webauthn_challenge_bytes(&challenge, &action))
+    }
+
+    /// Deletes an expired challenge. Callable by anyone to free storage.
+    pub fn prune_expired_challenge(&mut self, challenge: Base64VecU8) -> bool {
+        let Ok(challenge) = CryptoHash::try_from(challenge.0) else {
+            return false;
+        };
+        let expired = self
+            .webauthn_challenges
+            .get(&challenge)
+            .map(|issued| clock::block_timestamp() > issued.expires_at.0)
+            .unwrap_or(false);
+        if expired {
+            self.webauthn_challenges.remove(&challenge);
+        }
+        expired
+    }
+
+    /// Executes an action authorized by a WebAuthn assertion over an issued challenge.
+    /// The challenge is consumed, so the assertion can't be replayed.
+    pub fn execute_with_webauthn(
+        &mut self,
+        passkey_pk: PublicKey,
+        action: SerializableAction,
+        assertion: WebAuthnAssertion,
+    ) -> Base58CryptoHash {
+        if !self.authorize_relayer_with_registered_passkey(&passkey_pk) {
+            return self.rejected_request_id(&passkey_pk, &action);
+        }
+
+        let client_data: ClientData = near_sdk::serde_json::from_str(&assertion.client_data_json)
+            .unwrap_or_else(|_| panic!("ERR_INVALID_CLIENT_DATA_JSON"));
+        assert_eq!(client_data.kind, "webauthn.get", "ERR_INVALID_CLIENT_DATA_TYPE");
+        let challenge_bytes = URL_SAFE_NO_PAD
+            .decode(client_data.challenge.trim_end_matches('='))
+            .unwrap_or_else(|_| panic!("ERR_INVALID_CHALLENGE_ENCODING"));
+        assert_eq!(challenge_bytes.len(), 64, "ERR_INVALID_CHALLENGE_LENGTH");
+        let issued_challenge: CryptoHash = challenge_bytes[..32].try_into().unwrap();
+
+        let issued = self
+            .webauthn_challenges
+            .remove(&issued_challenge)
+            .unwrap_or_else(|| panic!("ERR_CHALLENGE_NOT_FOUND"));
+        assert_eq!(issued.issued_to, env::predecessor_account_id(), "ERR_CHALLENGE_ISSUED_TO_ANOTHER_ACCOUNT");
+        assert!(clock::block_timestamp() <= issued.expires_at.0, "ERR_CHALLENGE_EXPIRED");
+        assert_eq!(
+            challenge_bytes,
+            webauthn_challenge_bytes(&issued_challenge, &action),
+            "ERR_CHALLENGE_ACTION_MISMATCH"
+        );
+
+        let authenticator_data = &assertion.authenticator_data.0;
+        assert!(authenticator_data.len() >= MIN_AUTH_DATA_LEN, "ERR_INVALID_AUTHENTICATOR_DATA");
+        assert!(authenticator_data[32] & AUTH_DATA_FLAG_UP != 0, "ERR_USER_NOT_PRESENT");
+
+        // WebAuthn signs authenticatorData || sha256(clientDataJSON)
+        let mut signed_message = authenticator_data.clone();
+        signed_message.extend_from_slice(&env::sha256_array(assertion.client_data_json.as_bytes()));
+        assert!(
+            verify_passkey_signature(&passkey_pk, &signed_message, &assertion.signature.0),
+            "ERR_INVALID_WEBAUTHN_SIGNATURE"
+        );
+
+        self.assert_single_passkey_can_execute(&action);
+        let nonce = self.next_passkey_nonce(&passkey_pk);
+        self.dispatch_delegated(passkey_pk, nonce, action)
+    }
+}
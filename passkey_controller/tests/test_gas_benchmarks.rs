@@ -0,0 +1,105 @@
+use base64::Engine;
+use serde_json::json;
+use near_workspaces::types::NearToken;
+use near_sdk::{CurveType, PublicKey};
+use reveries_test_utils::gas::assert_within_gas_budget;
+use reveries_test_utils::sandbox::{deploy_wired, WiredContracts};
+
+// Gas budgets (in TGas) for delegated execution of each action type, including the result
+// callback. A change fails the suite when it burns more than the budget plus
+// GAS_REGRESSION_TOLERANCE_PCT percent (default 10), so update these deliberately.
+const TRANSFER_BUDGET_TGAS: u64 = 10;
+const FUNCTION_CALL_BUDGET_TGAS: u64 = 15;
+const CREATE_ACCOUNT_BUDGET_TGAS: u64 = 12;
+const DEPLOY_CONTRACT_BUDGET_TGAS: u64 = 30;
+const ADD_KEY_BUDGET_TGAS: u64 = 10;
+const DELETE_KEY_BUDGET_TGAS: u64 = 10;
+const STAKE_BUDGET_TGAS: u64 = 10;
+const RECORD_SPEND_BUDGET_TGAS: u64 = 20;
+const REVERIE_DEPOSIT_BUDGET_TGAS: u64 = 20;
+const DELETE_ACCOUNT_BUDGET_TGAS: u64 = 10;
+
+fn passkey_pk(seed: u8) -> PublicKey {
+    PublicKey::from_parts(CurveType::ED25519, vec![seed; 32]).unwrap()
+}
+
+#[tokio::test]
+async fn test_gas_budgets_per_action_type() -> Result<(), Box<dyn std::error::Error>> {
+    let controller_wasm = near_workspaces::compile_project("./").await?;
+    let payments_wasm = near_workspaces::compile_project("../payments").await?;
+    let pk = passkey_pk(1);
+    let WiredContracts { worker, payments, controller, owner, relayer } =
+        deploy_wired(&payments_wasm, &controller_wasm, &[pk.clone()]).await?;
+    let user = worker.dev_create_account().await?;
+
+    let create_reverie = controller
+        .as_account()
+        .call(payments.id(), "create_reverie")
+        .args_json(json!({
+            "reverie_id": "rev1",
+            "reverie_type": "type1",
+            "description": "desc1",
+            "access_condition": {"type": "Ed25519", "value": "pubkey1"}
+        }))
+        .transact()
+        .await?;
+    assert!(create_reverie.is_success());
+    let allow_call = owner
+        .call(controller.id(), "add_allowed_call")
+        .args_json(json!({"receiver_id": controller.id(), "method_names": ["get_owner_id"]}))
+        .transact()
+        .await?;
+    assert!(allow_call.is_success());
+
+    let new_account_id = format!("sub.{}", controller.id());
+    let cases = vec![
+        ("Transfer", json!({"action_type": "Transfer", "receiver_id": user.id(), "amount": "1000"}), TRANSFER_BUDGET_TGAS),
+        (
+            "FunctionCall",
+            json!({"action_type": "FunctionCall", "receiver_id": controller.id(), "method_name": "get_owner_id"}),
+            FUNCTION_CALL_BUDGET_TGAS,
+        ),
+        (
+            "CreateAccount",
+            json!({"action_type": "CreateAccount", "receiver_id": new_account_id, "initial_deposit_for_new_account": NearToken::from_millinear(10).as_yoctonear().to_string()}),
+            CREATE_ACCOUNT_BUDGET_TGAS,
+        ),
+        (
+            "DeployContract",
+            json!({"action_type": "DeployContract", "code": base64::engine::general_purpose::STANDARD.encode(&controller_wasm)}),
+            DEPLOY_CONTRACT_BUDGET_TGAS,
+        ),
+        (
+            "AddKey",
+            json!({"action_type": "AddKey", "public_key": passkey_pk(2), "receiver_id": controller.id(), "method_names": []}),
+            ADD_KEY_BUDGET_TGAS,
+        ),
+        ("DeleteKey", json!({"action_type": "DeleteKey", "public_key": passkey_pk(2)}), DELETE_KEY_BUDGET_TGAS),
+        // Fails in the sandbox (stake below the validator minimum), but the cost is still bounded
+        ("Stake", json!({"action_type": "Stake", "stake": "1", "public_key": passkey_pk(3)}), STAKE_BUDGET_TGAS),
+        (
+            "ReverieDeposit",
+            json!({"action_type": "ReverieDeposit", "reverie_id": "rev1", "user_id": user.id(), "amount": "1000"}),
+            REVERIE_DEPOSIT_BUDGET_TGAS,
+        ),
+        (
+            "RecordSpend",
+            json!({"action_type": "RecordSpend", "reverie_id": "rev1", "user_id": user.id(), "amount": "1000"}),
+            RECORD_SPEND_BUDGET_TGAS,
+        ),
+        // Last, as it deletes the controller
+        ("DeleteAccount", json!({"action_type": "DeleteAccount", "beneficiary_id": owner.id()}), DELETE_ACCOUNT_BUDGET_TGAS),
+    ];
+
+    for (name, action, budget_tgas) in cases {
+        let outcome = relayer
+            .call(controller.id(), "execute_delegated_actions")
+            .args_json(json!({"passkey_pk_used": pk, "action_to_execute": action}))
+            .max_gas()
+            .transact()
+            .await?;
+        assert_within_gas_budget(name, &outcome, budget_tgas);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use crate::*;
+
+/// Gas for FunctionCall, payments and staking pool actions that don't set `gas`.
+pub const DEFAULT_CALL_GAS: Gas = Gas::from_tgas(30);
+
+impl PasskeyWallet {
+    // internal method that builds the promise for an action executed by the wallet.
+    // Actions that don't name a receiver operate on the wallet's own account. Payments and
+    // staking pool actions take the payments contract / pool from `receiver_id`, since
+    // the wallet trusts no configured contract.
+    pub(crate) fn build_promise(&self, action: SerializableAction) -> Promise {
+        let receiver_id = || {
+            action
+                .receiver_id
+                .clone()
+                .unwrap_or_else(|| panic!("receiver_id is required for {:?}", action.action_type))
+        };
+        let target_account_id = match action.action_type {
+            ActionType::CreateAccount
+            | ActionType::FunctionCall
+            | ActionType::Transfer
+            | ActionType::RecordSpend
+            | ActionType::ChargeReverie { .. }
+            | ActionType::ReverieDeposit
+            | ActionType::StakeWithPool
+            | ActionType::UnstakeFromPool
+            | ActionType::WithdrawFromPool => receiver_id(),
+            ActionType::DeployContract
+            | ActionType::Stake
+            | ActionType::AddKey
+            | ActionType::DeleteKey
+            | ActionType::DeleteAccount => env::current_account_id(),
+        };
+        let promise = Promise::new(target_account_id);
+        let gas = action.gas.unwrap_or(DEFAULT_CALL_GAS);
+
+        match action.action_type {
+            ActionType::CreateAccount => {
+                let mut promise = promise.create_account();
+                if let Some(deposit) = action.initial_deposit_for_new_account.filter(|d| d.0 > 0) {
+                    promise = promise.transfer(NearToken::from_yoctonear(deposit.0));
+                }
+                match action.public_key_for_new_account.clone() {
+                    Some(pk) => promise.add_full_access_key(pk),
+                    None => promise,
+                }
+            }
+            ActionType::DeployContract => {
+                promise.deploy_contract(action.code.clone().unwrap_or_else(|| panic!("code is required for DeployContract")).0)
+            }
+            ActionType::FunctionCall => promise.function_call(
+                action.method_name.clone().unwrap_or_else(|| panic!("method_name is required for FunctionCall")),
+                action.call_args(),
+                NearToken::from_yoctonear(action.deposit.map(|d| d.0).unwrap_or(0)),
+                gas,
+            ),
+            ActionType::Transfer => promise.transfer(NearToken::from_yoctonear(
+                action.amount.unwrap_or_else(|| panic!("amount is required for Transfer")).0,
+            )),
+            ActionType::Stake => promise.stake(
+                NearToken::from_yoctonear(action.stake.unwrap_or_else(|| panic!("stake amount is required for Stake")).0),
+                action.public_key.clone().unwrap_or_else(|| panic!("public_key is required for Stake")),
+            ),
+            ActionType::AddKey => promise.add_access_key_allowance(
+                action.public_key.clone().unwrap_or_else(|| panic!("public_key is required for AddKey")),
+                action.get_action_allowance(),
+                receiver_id(),
+                action.method_names.clone().unwrap_or_default().join(","),
+            ),
+            ActionType::DeleteKey => {
+                promise.delete_key(action.public_key.clone().unwrap_or_else(|| panic!("public_key is required for DeleteKey")))
+            }
+            ActionType::DeleteAccount => promise.delete_account(
+                action.beneficiary_id.clone().unwrap_or_else(|| panic!("beneficiary_id is required for DeleteAccount")),
+            ),
+            ActionType::RecordSpend | ActionType::ChargeReverie { .. } | ActionType::ReverieDeposit => {
+                let (method_name, args, deposit) = action.payments_call();
+                promise.function_call(method_name, args, deposit, gas)
+            }
+            ActionType::StakeWithPool | ActionType::UnstakeFromPool | ActionType::WithdrawFromPool => {
+                let (method_name, args, deposit) = action.staking_pool_call();
+                promise.function_call(method_name, args, deposit, gas)
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use crate::*;
+
+/// An action for the wallet signed by one of its passkeys. Anyone can submit it;
+/// any change to the action, nonce or expiry invalidates the signature.
+#[near(serializers = [borsh, json])]
+#[derive(Debug, Clone)]
+pub struct SignedWalletRequest {
+    pub action: SerializableAction,
+    pub nonce: U64,
+    pub valid_until: U64, // block timestamp in nanoseconds
+    pub signature: Base64VecU8, // ed25519 signature over the Borsh-serialized WalletActionPayload
+}
+
+/// Canonical payload a passkey signs. Binding the wallet account prevents replaying
+/// a signature against another wallet using the same passkey.
+#[near(serializers = [borsh])]
+#[derive(Debug, Clone)]
+pub struct WalletActionPayload {
+    pub wallet_id: AccountId,
+    pub passkey_pk: PublicKey,
+    pub action: SerializableAction,
+    pub nonce: u64,
+    pub valid_until: u64,
+}
+
+impl WalletActionPayload {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        near_sdk::borsh::to_vec(self).unwrap_or_else(|_| panic!("ERR_PAYLOAD_SERIALIZATION"))
+    }
+}
+
+// Verifies an ed25519 signature made by `passkey_pk` over `message`.
+pub(crate) fn verify_passkey_signature(passkey_pk: &PublicKey, message: &[u8], signature: &[u8]) -> bool {
+    assert!(passkey_pk.curve_type() == CurveType::ED25519, "ERR_PASSKEY_PK_NOT_ED25519");
+    let pk_bytes: [u8; 32] = passkey_pk.as_bytes()[1..]
+        .try_into()
+        .unwrap_or_else(|_| panic!("ERR_INVALID_PASSKEY_PK_LENGTH"));
+    let signature_bytes: [u8; 64] = signature
+        .try_into()
+        .unwrap_or_else(|_| panic!("ERR_INVALID_SIGNATURE_LENGTH"));
+    env::ed25519_verify(&signature_bytes, message, &pk_bytes)
+}
+
+#[near]
+impl PasskeyWallet {
+    /// Returns the exact bytes a passkey must sign for `execute`.
+    pub fn get_action_payload(
+        &self,
+        passkey_pk: PublicKey,
+        action: SerializableAction,
+        nonce: U64,
+        valid_until: U64,
+    ) -> Base64VecU8 {
+        let payload = WalletActionPayload {
+            wallet_id: env::current_account_id(),
+            passkey_pk,
+            action,
+            nonce: nonce.0,
+            valid_until: valid_until.0,
+        };
+        Base64VecU8(payload.to_bytes())
+    }
+
+    /// Executes a signed action from the wallet's own account.
+    pub fn execute(&mut self, passkey_pk: PublicKey, request: SignedWalletRequest) -> Promise {
+        self.consume_nonce(&passkey_pk, request.nonce.0, request.valid_until.0);
+        let payload = WalletActionPayload {
+            wallet_id: env::current_account_id(),
+            passkey_pk: passkey_pk.clone(),
+            action: request.action,
+            nonce: request.nonce.0,
+            valid_until: request.valid_until.0,
+        };
+        assert!(
+            verify_passkey_signature(&passkey_pk, &payload.to_bytes(), &request.signature.0),
+            "ERR_INVALID_SIGNATURE"
+        );
+        self.execute_authorized(payload.action)
+    }
+}
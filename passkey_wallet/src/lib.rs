@@ -0,0 +1,92 @@
+//! A per-user account contract for self-custody: the wallet holds the user's funds,
+//! verifies passkey signatures itself and executes actions from its own account, so
+//! no relayer or shared controller is trusted with the user's assets. Relayers only
+//! pay for gas by submitting signed requests.
+
+pub mod actions;
+pub mod envelope;
+pub mod webauthn;
+#[cfg(test)]
+mod tests_passkey_wallet;
+
+use near_sdk::json_types::{Base64VecU8, U64};
+use near_sdk::store::IterableSet;
+use near_sdk::{env, log, near, AccountId, CurveType, Gas, NearToken, PanicOnDefault, Promise, PublicKey};
+
+pub use reveries_types::{ActionType, SerializableAction};
+
+#[near(contract_state)]
+#[derive(PanicOnDefault)]
+pub struct PasskeyWallet {
+    passkey_pks: IterableSet<PublicKey>,
+    nonce: u64,
+}
+
+#[near]
+impl PasskeyWallet {
+    /// Initializes the wallet with the user's first passkey. Deploy to the user's own
+    /// account (e.g. with CreateAccount + DeployContract from a factory).
+    #[init]
+    pub fn new(passkey_pk: PublicKey) -> Self {
+        assert!(passkey_pk.curve_type() == CurveType::ED25519, "ERR_PASSKEY_PK_NOT_ED25519");
+        let mut passkey_pks = IterableSet::new(b"p");
+        passkey_pks.insert(passkey_pk);
+        Self { passkey_pks, nonce: 0 }
+    }
+
+    /// Adds a passkey. Only callable by the wallet itself, i.e. through a FunctionCall
+    /// action to this account signed by an existing passkey.
+    pub fn add_passkey(&mut self, passkey_pk: PublicKey) -> bool {
+        self.assert_self();
+        assert!(passkey_pk.curve_type() == CurveType::ED25519, "ERR_PASSKEY_PK_NOT_ED25519");
+        self.passkey_pks.insert(passkey_pk)
+    }
+
+    /// Removes a passkey. Only callable by the wallet itself; the last passkey can't be removed.
+    pub fn remove_passkey(&mut self, passkey_pk: PublicKey) -> bool {
+        self.assert_self();
+        assert!(
+            self.passkey_pks.len() > 1 || !self.passkey_pks.contains(&passkey_pk),
+            "ERR_CANNOT_REMOVE_LAST_PASSKEY"
+        );
+        self.passkey_pks.remove(&passkey_pk)
+    }
+
+    pub fn get_passkeys(&self) -> Vec<PublicKey> {
+        self.passkey_pks.iter().cloned().collect()
+    }
+
+    pub fn is_passkey(&self, passkey_pk: PublicKey) -> bool {
+        self.passkey_pks.contains(&passkey_pk)
+    }
+
+    /// Returns the last nonce consumed. The next signed request must use a strictly greater nonce.
+    pub fn get_nonce(&self) -> U64 {
+        U64(self.nonce)
+    }
+}
+
+impl PasskeyWallet {
+    // internal method for methods only the wallet's own actions may call
+    fn assert_self(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "ERR_ONLY_SELF"
+        );
+    }
+
+    // internal method checking the passkey and consuming the request's nonce
+    fn consume_nonce(&mut self, passkey_pk: &PublicKey, nonce: u64, valid_until: u64) {
+        assert!(self.passkey_pks.contains(passkey_pk), "ERR_PASSKEY_NOT_REGISTERED");
+        assert!(env::block_timestamp() <= valid_until, "ERR_REQUEST_EXPIRED");
+        assert!(nonce > self.nonce, "ERR_NONCE_USED");
+        self.nonce = nonce;
+    }
+
+    // internal method executing an authorized action from the wallet's own account
+    fn execute_authorized(&mut self, action: SerializableAction) -> Promise {
+        log!("Wallet executing {:?} with nonce {}", action.action_type, self.nonce);
+        self.build_promise(action)
+    }
+}
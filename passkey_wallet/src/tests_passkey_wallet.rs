@@ -0,0 +1,145 @@
+use super::*;
+use near_sdk::test_utils::{accounts, VMContextBuilder};
+use near_sdk::testing_env;
+use reveries_test_utils::{passkey_pk_of, passkey_signing_key, transfer_action};
+
+fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+    let mut builder = VMContextBuilder::new();
+    builder
+        .current_account_id(accounts(0)) // The user's wallet account
+        .signer_account_id(predecessor_account_id.clone())
+        .predecessor_account_id(predecessor_account_id);
+    builder
+}
+
+fn sign_request(
+    wallet: &PasskeyWallet,
+    signing_key: &ed25519_dalek::SigningKey,
+    action: SerializableAction,
+    nonce: u64,
+) -> envelope::SignedWalletRequest {
+    use ed25519_dalek::Signer;
+    let payload = wallet.get_action_payload(passkey_pk_of(signing_key), action.clone(), U64(nonce), U64(u64::MAX));
+    envelope::SignedWalletRequest {
+        action,
+        nonce: U64(nonce),
+        valid_until: U64(u64::MAX),
+        signature: Base64VecU8(signing_key.sign(&payload.0).to_bytes().to_vec()),
+    }
+}
+
+// Asserts the wallet forwarded a single transfer of `amount` yoctoNEAR to `receiver_id`
+fn assert_forwarded_transfer(receiver_id: AccountId, amount: u128) {
+    let receipts = near_sdk::test_utils::get_created_receipts();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].receiver_id, receiver_id);
+    let near_sdk::mock::MockAction::Transfer { deposit, .. } = &receipts[0].actions[0] else {
+        panic!("Expected a Transfer action");
+    };
+    assert_eq!(deposit.as_yoctonear(), amount);
+}
+
+#[test]
+fn test_execute_signed_request_from_any_submitter() {
+    let signing_key = passkey_signing_key(1);
+    testing_env!(get_context(accounts(1)).build());
+    let mut wallet = PasskeyWallet::new(passkey_pk_of(&signing_key));
+
+    let request = sign_request(&wallet, &signing_key, transfer_action(accounts(2), 100), 1);
+    wallet.execute(passkey_pk_of(&signing_key), request);
+    assert_eq!(wallet.get_nonce(), U64(1));
+    assert_forwarded_transfer(accounts(2), 100);
+}
+
+#[test]
+#[should_panic(expected = "ERR_NONCE_USED")]
+fn test_execute_panic_replayed() {
+    let signing_key = passkey_signing_key(1);
+    testing_env!(get_context(accounts(1)).build());
+    let mut wallet = PasskeyWallet::new(passkey_pk_of(&signing_key));
+    let request = sign_request(&wallet, &signing_key, transfer_action(accounts(2), 100), 1);
+    wallet.execute(passkey_pk_of(&signing_key), request.clone());
+    wallet.execute(passkey_pk_of(&signing_key), request);
+}
+
+#[test]
+#[should_panic(expected = "ERR_INVALID_SIGNATURE")]
+fn test_execute_panic_tampered_action() {
+    let signing_key = passkey_signing_key(1);
+    testing_env!(get_context(accounts(1)).build());
+    let mut wallet = PasskeyWallet::new(passkey_pk_of(&signing_key));
+    let mut request = sign_request(&wallet, &signing_key, transfer_action(accounts(2), 100), 1);
+    request.action = transfer_action(accounts(3), 100);
+    wallet.execute(passkey_pk_of(&signing_key), request);
+}
+
+#[test]
+#[should_panic(expected = "ERR_PASSKEY_NOT_REGISTERED")]
+fn test_execute_panic_unknown_passkey() {
+    let signing_key = passkey_signing_key(1);
+    let other_key = passkey_signing_key(2);
+    testing_env!(get_context(accounts(1)).build());
+    let mut wallet = PasskeyWallet::new(passkey_pk_of(&signing_key));
+    let request = sign_request(&wallet, &other_key, transfer_action(accounts(2), 100), 1);
+    wallet.execute(passkey_pk_of(&other_key), request);
+}
+
+#[test]
+fn test_execute_with_webauthn() {
+    use base64::Engine;
+    use ed25519_dalek::Signer;
+    let signing_key = passkey_signing_key(1);
+    let passkey_pk = passkey_pk_of(&signing_key);
+    testing_env!(get_context(accounts(1)).build());
+    let mut wallet = PasskeyWallet::new(passkey_pk.clone());
+    let action = transfer_action(accounts(2), 100);
+    let challenge = wallet.get_webauthn_challenge(passkey_pk.clone(), action.clone(), U64(1), U64(u64::MAX));
+
+    let mut authenticator_data = vec![0u8; 32];
+    authenticator_data.push(0x01); // user present
+    authenticator_data.extend_from_slice(&1u32.to_be_bytes());
+    let client_data_json = format!(
+        r#"{{"type":"webauthn.get","challenge":"{}","origin":"https://reveries.example"}}"#,
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&challenge.0)
+    );
+    let mut signed_message = authenticator_data.clone();
+    signed_message.extend_from_slice(&env::sha256(client_data_json.as_bytes()));
+    let assertion = webauthn::WebAuthnAssertion {
+        authenticator_data: Base64VecU8(authenticator_data),
+        client_data_json,
+        signature: Base64VecU8(signing_key.sign(&signed_message).to_bytes().to_vec()),
+    };
+
+    wallet.execute_with_webauthn(passkey_pk, action, U64(1), U64(u64::MAX), assertion);
+    assert_eq!(wallet.get_nonce(), U64(1));
+    assert_forwarded_transfer(accounts(2), 100);
+}
+
+#[test]
+fn test_add_and_remove_passkey_by_wallet_itself() {
+    let signing_key = passkey_signing_key(1);
+    let second_pk = passkey_pk_of(&passkey_signing_key(2));
+    testing_env!(get_context(accounts(0)).build());
+    let mut wallet = PasskeyWallet::new(passkey_pk_of(&signing_key));
+    assert!(wallet.add_passkey(second_pk.clone()));
+    assert!(wallet.is_passkey(second_pk.clone()));
+    assert!(wallet.remove_passkey(passkey_pk_of(&signing_key)));
+    assert_eq!(wallet.get_passkeys(), vec![second_pk]);
+}
+
+#[test]
+#[should_panic(expected = "ERR_ONLY_SELF")]
+fn test_add_passkey_panic_not_self() {
+    testing_env!(get_context(accounts(1)).build());
+    let mut wallet = PasskeyWallet::new(passkey_pk_of(&passkey_signing_key(1)));
+    wallet.add_passkey(passkey_pk_of(&passkey_signing_key(2)));
+}
+
+#[test]
+#[should_panic(expected = "ERR_CANNOT_REMOVE_LAST_PASSKEY")]
+fn test_remove_last_passkey_panics() {
+    let passkey_pk = passkey_pk_of(&passkey_signing_key(1));
+    testing_env!(get_context(accounts(0)).build());
+    let mut wallet = PasskeyWallet::new(passkey_pk.clone());
+    wallet.remove_passkey(passkey_pk);
+}
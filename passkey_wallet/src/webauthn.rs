@@ -0,0 +1,88 @@
+use crate::*;
+use crate::envelope::{verify_passkey_signature, WalletActionPayload};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+// WebAuthn authenticator data flag: user present
+const AUTH_DATA_FLAG_UP: u8 = 0x01;
+// rpIdHash (32 bytes) + flags (1 byte) + signCount (4 bytes)
+const MIN_AUTH_DATA_LEN: usize = 37;
+
+/// A WebAuthn assertion (navigator.credentials.get) made with an ed25519 passkey.
+#[near(serializers = [json])]
+#[derive(Debug, Clone)]
+pub struct WebAuthnAssertion {
+    pub authenticator_data: Base64VecU8,
+    pub client_data_json: String,
+    pub signature: Base64VecU8,
+}
+
+#[near(serializers = [json])]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+}
+
+#[near]
+impl PasskeyWallet {
+    /// Returns the challenge the client must put (base64url encoded) in clientDataJSON:
+    /// the sha256 of the `WalletActionPayload`. The nonce makes each challenge single-use,
+    /// so the wallet doesn't need to issue and store challenges.
+    pub fn get_webauthn_challenge(
+        &self,
+        passkey_pk: PublicKey,
+        action: SerializableAction,
+        nonce: U64,
+        valid_until: U64,
+    ) -> Base64VecU8 {
+        let payload = WalletActionPayload {
+            wallet_id: env::current_account_id(),
+            passkey_pk,
+            action,
+            nonce: nonce.0,
+            valid_until: valid_until.0,
+        };
+        Base64VecU8(env::sha256(&payload.to_bytes()))
+    }
+
+    /// Executes an action authorized by a WebAuthn assertion over the action's challenge.
+    pub fn execute_with_webauthn(
+        &mut self,
+        passkey_pk: PublicKey,
+        action: SerializableAction,
+        nonce: U64,
+        valid_until: U64,
+        assertion: WebAuthnAssertion,
+    ) -> Promise {
+        self.consume_nonce(&passkey_pk, nonce.0, valid_until.0);
+        let payload = WalletActionPayload {
+            wallet_id: env::current_account_id(),
+            passkey_pk: passkey_pk.clone(),
+            action,
+            nonce: nonce.0,
+            valid_until: valid_until.0,
+        };
+
+        let client_data: ClientData = near_sdk::serde_json::from_str(&assertion.client_data_json)
+            .unwrap_or_else(|_| panic!("ERR_INVALID_CLIENT_DATA_JSON"));
+        assert_eq!(client_data.kind, "webauthn.get", "ERR_INVALID_CLIENT_DATA_TYPE");
+        let challenge = URL_SAFE_NO_PAD
+            .decode(client_data.challenge.trim_end_matches('='))
+            .unwrap_or_else(|_| panic!("ERR_INVALID_CHALLENGE_ENCODING"));
+        assert_eq!(challenge, env::sha256(&payload.to_bytes()), "ERR_CHALLENGE_ACTION_MISMATCH");
+
+        let authenticator_data = &assertion.authenticator_data.0;
+        assert!(authenticator_data.len() >= MIN_AUTH_DATA_LEN, "ERR_INVALID_AUTHENTICATOR_DATA");
+        assert!(authenticator_data[32] & AUTH_DATA_FLAG_UP != 0, "ERR_USER_NOT_PRESENT");
+
+        // WebAuthn signs authenticatorData || sha256(clientDataJSON)
+        let mut signed_message = authenticator_data.clone();
+        signed_message.extend_from_slice(&env::sha256_array(assertion.client_data_json.as_bytes()));
+        assert!(
+            verify_passkey_signature(&passkey_pk, &signed_message, &assertion.signature.0),
+            "ERR_INVALID_WEBAUTHN_SIGNATURE"
+        );
+        self.execute_authorized(payload.action)
+    }
+}
@@ -0,0 +1,177 @@
+use crate::*;
+use near_sdk::json_types::{Base58CryptoHash, U64};
+use near_sdk::{Gas, PromiseResult};
+use crate::storage::StorageCostEstimate;
+
+/// Cache lifetime of an access check result when the reverie doesn't set one: 10 minutes.
+pub const DEFAULT_ACCESS_CACHE_TTL_NS: u64 = 600_000_000_000;
+const GAS_FOR_ACCESS_CHECK: Gas = Gas::from_tgas(10);
+const GAS_FOR_ON_ACCESS_CHECKED: Gas = Gas::from_tgas(5);
+const GAS_FOR_ON_DAO_POLICY: Gas = Gas::from_tgas(10);
+
+/// Cached result of a reverie's on-chain access check for one user.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrantRecord {
+    pub granted: bool,
+    pub expires_at: U64, // block timestamp in nanoseconds
+    pub condition_hash: Base58CryptoHash, // access condition the result was computed for
+}
+
+#[near]
+impl PaymentContract {
+    /// Sets how long access check results are cached for a reverie. `None` restores the default.
+    pub fn set_access_cache_ttl(&mut self, reverie_id: ReverieId, ttl_ns: Option<U64>) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can set access cache ttls");
+        self.require_reverie_exists(&reverie_id);
+        match ttl_ns {
+            Some(ttl_ns) => self.access_cache_ttls.insert(reverie_id, ttl_ns.0),
+            None => self.access_cache_ttls.remove(&reverie_id),
+        };
+    }
+
+    pub fn get_access_cache_ttl(&self, reverie_id: ReverieId) -> U64 {
+        U64(self.access_cache_ttl(&reverie_id))
+    }
+
+    /// Evaluates a `Contract` access condition by calling
+    /// `address.access_function_name(access_function_args)` and caches the boolean result.
+    /// When the args are a JSON object, `account_id` is set to `user_id`. A `DaoMember` condition
+    /// is checked against the DAO's `get_policy`. Revoked users are refused.
+    /// Callers other than the trusted account attach the storage cost of the cached result
+    /// (`estimate_storage_cost_check_access`), kept even if the check fails; the excess is refunded.
+    #[payable]
+    pub fn check_access(&mut self, reverie_id: ReverieId, user_id: AccountId) -> Promise {
+        self.assert_access_not_revoked(&reverie_id, &user_id);
+        let storage_bytes = self.access_grant_storage_bytes(&reverie_id, &user_id);
+        if env::predecessor_account_id() != self.trusted_account {
+            let cost = StorageCostEstimate::from_bytes(storage_bytes).cost.0;
+            assert!(
+                env::attached_deposit().as_yoctonear() >= cost,
+                "Attach {} yoctoNEAR to cover the storage of the access check result",
+                cost
+            );
+        }
+        self.refund_excess_storage_deposit(storage_bytes);
+        let metadata = self
+            .reverie_metadata
+            .get(&reverie_id)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str(&format!("ReverieId {} not found in registry", reverie_id)));
+        let (address, access_function_name, access_function_args) = match metadata.access_condition {
+            AccessCondition::Contract { address, access_function_name, access_function_args } => {
+                (address, access_function_name, access_function_args)
+            }
+            AccessCondition::DaoMember { dao_contract, role } => {
+                return Promise::new(dao_contract)
+                    .function_call("get_policy".to_string(), b"{}".to_vec(), NearToken::from_yoctonear(0), GAS_FOR_ACCESS_CHECK)
+                    .then(
+                        Self::ext(env::current_account_id())
+                            .with_static_gas(GAS_FOR_ON_DAO_POLICY)
+                            .on_dao_policy_checked(reverie_id, user_id, role),
+                    );
+            }
+            _ => env::panic_str(&format!("Reverie {} has no on-chain access check", reverie_id)),
+        };
+        let contract_id: AccountId = address
+            .parse()
+            .unwrap_or_else(|_| env::panic_str(&format!("Invalid access contract address {}", address)));
+        let mut args: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(&access_function_args)
+            .unwrap_or_else(|_| env::panic_str("Invalid access_function_args. Expected a JSON string"));
+        if let Some(object) = args.as_object_mut() {
+            object.insert("account_id".to_string(), near_sdk::serde_json::json!(user_id));
+        }
+        Promise::new(contract_id)
+            .function_call(access_function_name, args.to_string().into_bytes(), NearToken::from_yoctonear(0), GAS_FOR_ACCESS_CHECK)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_ACCESS_CHECKED)
+                    .on_access_checked(reverie_id, user_id),
+            )
+    }
+
+    /// Caches the access check result. Failed checks aren't cached, so they can be retried.
+    #[private]
+    pub fn on_access_checked(&mut self, reverie_id: ReverieId, user_id: AccountId) -> bool {
+        let granted = match env::promise_result(0) {
+            PromiseResult::Successful(value) => near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(false),
+            PromiseResult::Failed => {
+                log!("Access check for user {} on reverie {} failed", user_id, reverie_id);
+                return false;
+            }
+        };
+        self.cache_access_result(reverie_id, user_id, granted)
+    }
+
+    /// Grants access if the user is in one of the DAO's group roles (`role` only, if set)
+    /// and caches the result like `on_access_checked`.
+    #[private]
+    pub fn on_dao_policy_checked(&mut self, reverie_id: ReverieId, user_id: AccountId, role: Option<String>) -> bool {
+        let granted = match env::promise_result(0) {
+            PromiseResult::Successful(value) => near_sdk::serde_json::from_slice::<near_sdk::serde_json::Value>(&value)
+                .map_or(false, |policy| dao_policy_grants(&policy, &user_id, role.as_deref())),
+            PromiseResult::Failed => {
+                log!("DAO policy check for user {} on reverie {} failed", user_id, reverie_id);
+                return false;
+            }
+        };
+        self.cache_access_result(reverie_id, user_id, granted)
+    }
+
+    /// Fast check against the cache: true only for an unexpired grant computed
+    /// for the reverie's current access condition, to a user whose access isn't revoked.
+    pub fn has_cached_access(&self, reverie_id: ReverieId, user_id: AccountId) -> bool {
+        self.get_access_grant(reverie_id, user_id).map_or(false, |grant| grant.granted)
+    }
+
+    /// Returns the cached result, if it is unexpired and still matches the access condition.
+    pub fn get_access_grant(&self, reverie_id: ReverieId, user_id: AccountId) -> Option<GrantRecord> {
+        if self.access_revoked(&reverie_id, &user_id) {
+            return None;
+        }
+        let condition_hash = self.access_condition_hash(&reverie_id)?;
+        self.access_grants
+            .get(&self.user_key(&reverie_id, &user_id))
+            .filter(|grant| grant.condition_hash == condition_hash && clock::block_timestamp() <= grant.expires_at.0)
+            .cloned()
+    }
+}
+
+impl PaymentContract {
+    // internal method caching an access check result, unless the user was revoked while it was in flight
+    fn cache_access_result(&mut self, reverie_id: ReverieId, user_id: AccountId, granted: bool) -> bool {
+        if self.access_revoked(&reverie_id, &user_id) {
+            return false;
+        }
+        let Some(condition_hash) = self.access_condition_hash(&reverie_id) else {
+            return granted;
+        };
+        let expires_at = U64(clock::block_timestamp().saturating_add(self.access_cache_ttl(&reverie_id)));
+        self.access_grants.insert(self.user_key(&reverie_id, &user_id), GrantRecord { granted, expires_at, condition_hash });
+        granted
+    }
+
+    fn access_cache_ttl(&self, reverie_id: &str) -> u64 {
+        self.access_cache_ttls.get(reverie_id).copied().unwrap_or(DEFAULT_ACCESS_CACHE_TTL_NS)
+    }
+
+    // Grants are tied to the condition they were computed for, so updating a reverie's
+    // access condition (or recreating the reverie with another) invalidates them
+    fn access_condition_hash(&self, reverie_id: &str) -> Option<Base58CryptoHash> {
+        let metadata = self.reverie_metadata.get(reverie_id)?;
+        let bytes = near_sdk::borsh::to_vec(&metadata.access_condition)
+            .unwrap_or_else(|_| env::panic_str("Failed to serialize access condition"));
+        Some(Base58CryptoHash::from(env::sha256_array(&bytes)))
+    }
+}
+
+// Whether a Sputnik DAO policy lists `user_id` in a `Group` role, named `role` if set
+fn dao_policy_grants(policy: &near_sdk::serde_json::Value, user_id: &AccountId, role: Option<&str>) -> bool {
+    policy["roles"].as_array().map_or(false, |roles| {
+        roles
+            .iter()
+            .filter(|entry| role.map_or(true, |role| entry["name"] == role))
+            .filter_map(|entry| entry["kind"]["Group"].as_array())
+            .any(|members| members.iter().any(|member| member == user_id.as_str()))
+    })
+}
@@ -0,0 +1,68 @@
+use crate::*;
+
+#[near]
+impl PaymentContract {
+    /// The account operating a reverie: it can freeze it, manage its spenders, resolve its
+    /// disputes and collect its revenue. Defaults to the trusted account, which keeps these
+    /// rights on every reverie.
+    pub fn get_reverie_admin(&self, reverie_id: ReverieId) -> AccountId {
+        self.require_reverie_exists(&reverie_id);
+        self.reverie_admin(&reverie_id)
+    }
+
+    pub fn get_pending_reverie_transfer(&self, reverie_id: ReverieId) -> Option<AccountId> {
+        self.pending_reverie_transfers.get(&reverie_id).cloned()
+    }
+
+    /// First step of handing a reverie to another operator: `new_admin` takes over once it
+    /// calls `accept_reverie_transfer`. A new proposal replaces a pending one. User balances
+    /// are untouched.
+    pub fn propose_reverie_transfer(&mut self, reverie_id: ReverieId, new_admin: AccountId) {
+        self.require_reverie_exists(&reverie_id);
+        let admin = self.reverie_admin(&reverie_id);
+        assert_eq!(env::predecessor_account_id(), admin, "Only the reverie admin can transfer reverie {}", reverie_id);
+        assert_ne!(new_admin, admin, "{} is already the admin of reverie {}", new_admin, reverie_id);
+        self.pending_reverie_transfers.insert(reverie_id.clone(), new_admin.clone());
+        self.emit_event(events::PaymentEvent::ReverieTransferProposed {
+            reverie_id,
+            admin,
+            new_admin,
+        });
+    }
+
+    pub fn accept_reverie_transfer(&mut self, reverie_id: ReverieId) {
+        let new_admin = env::predecessor_account_id();
+        assert_eq!(
+            self.pending_reverie_transfers.get(&reverie_id),
+            Some(&new_admin),
+            "No transfer of reverie {} pending for {}",
+            reverie_id,
+            new_admin
+        );
+        self.require_reverie_exists(&reverie_id);
+        self.pending_reverie_transfers.remove(&reverie_id);
+        let previous_admin = self.reverie_admin(&reverie_id);
+        if new_admin == self.trusted_account {
+            self.reverie_admins.remove(&reverie_id);
+        } else {
+            self.reverie_admins.insert(reverie_id.clone(), new_admin.clone());
+        }
+        self.emit_event(events::PaymentEvent::ReverieTransferred {
+            reverie_id,
+            previous_admin,
+            new_admin,
+        });
+    }
+}
+
+impl PaymentContract {
+    pub(crate) fn reverie_admin(&self, reverie_id: &str) -> AccountId {
+        self.reverie_admins.get(reverie_id).cloned().unwrap_or_else(|| self.trusted_account.clone())
+    }
+
+    // internal check for methods operating a single reverie
+    pub(crate) fn assert_reverie_admin(&self, reverie_id: &str, message: &str) {
+        let caller = env::predecessor_account_id();
+        assert!(caller == self.trusted_account || caller == self.reverie_admin(reverie_id), "{}", message);
+    }
+}
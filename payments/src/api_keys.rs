@@ -0,0 +1,29 @@
+use crate::*;
+use near_sdk::json_types::Base58CryptoHash;
+
+#[near]
+impl PaymentContract {
+    /// Anchors the sha256 of the API credential of the service backing a reverie, so clients
+    /// can check they're talking to the legitimate backend. Setting a new hash rotates the
+    /// credential; `None` removes it.
+    pub fn set_reverie_api_key_hash(&mut self, reverie_id: ReverieId, sha256: Option<Base58CryptoHash>) {
+        self.assert_reverie_admin(&reverie_id, "Only the reverie admin can set API key hashes");
+        self.require_reverie_exists(&reverie_id);
+        match sha256 {
+            Some(sha256) => self.api_key_hashes.insert(reverie_id.clone(), sha256.into()),
+            None => self.api_key_hashes.remove(&reverie_id),
+        };
+        self.emit_event(events::PaymentEvent::ApiKeyRotated { reverie_id, sha256 });
+    }
+
+    pub fn get_reverie_api_key_hash(&self, reverie_id: ReverieId) -> Option<Base58CryptoHash> {
+        self.api_key_hashes.get(&reverie_id).map(|hash| Base58CryptoHash::from(*hash))
+    }
+
+    /// True if `preimage` hashes to the reverie's anchored API key hash.
+    pub fn verify_api_key(&self, reverie_id: ReverieId, preimage: String) -> bool {
+        self.api_key_hashes
+            .get(&reverie_id)
+            .is_some_and(|hash| *hash == env::sha256_array(preimage.as_bytes()))
+    }
+}
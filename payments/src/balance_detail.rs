@@ -0,0 +1,45 @@
+use crate::*;
+
+/// Lifetime totals and in-flight amounts of one user on one reverie.
+#[near(serializers = [borsh])]
+#[derive(Clone, Debug, Default)]
+pub struct UserTotals {
+    pub deposited: u128,
+    pub spent: u128,
+    pub locked: u128, // withdrawals awaiting their transfer callback
+}
+
+/// A user's balance on a reverie, returned by `get_balance_detail` for dashboards.
+/// `locked` has already left `available` but is returned to it if the withdrawal fails.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BalanceDetail {
+    pub available: U128,
+    pub locked: U128,
+    pub lifetime_deposited: U128,
+    pub lifetime_spent: U128,
+}
+
+#[near]
+impl PaymentContract {
+    pub fn get_balance_detail(&self, reverie_id: ReverieId, user_id: AccountId) -> BalanceDetail {
+        let available = self.get_balance(reverie_id.clone(), user_id.clone());
+        let totals = self.user_totals.get(&self.user_key(&reverie_id, &user_id)).cloned().unwrap_or_default();
+        BalanceDetail {
+            available,
+            locked: U128(totals.locked),
+            lifetime_deposited: U128(totals.deposited),
+            lifetime_spent: U128(totals.spent),
+        }
+    }
+}
+
+impl PaymentContract {
+    // internal method applying `update` to a user's totals on a reverie
+    pub(crate) fn update_user_totals(&mut self, reverie_id: &str, user_id: &AccountId, update: impl FnOnce(&mut UserTotals)) {
+        let key = self.user_key(reverie_id, user_id);
+        let mut totals = self.user_totals.get(&key).cloned().unwrap_or_default();
+        update(&mut totals);
+        self.user_totals.insert(key, totals);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// Max distinct spend categories tracked per reverie, to keep the per-reverie entry bounded.
+pub const MAX_SPEND_CATEGORIES: usize = 32;
+pub const MAX_SPEND_CATEGORY_LEN: usize = 32;
+
+/// Running spend totals for one category (e.g. "inference", "storage") of a reverie.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CategorySpend {
+    pub category: String,
+    pub total_spent: U128,
+    pub spend_count: U64,
+}
+
+#[near]
+impl PaymentContract {
+    /// Spend totals per category for a reverie. Spends recorded without a category aren't included.
+    pub fn get_spend_by_category(&self, reverie_id: ReverieId) -> Vec<CategorySpend> {
+        self.spend_by_category.get(&reverie_id).cloned().unwrap_or_default()
+    }
+}
+
+impl PaymentContract {
+    // internal method validating a spend category before any balance changes
+    pub(crate) fn assert_valid_spend_category(&self, reverie_id: &str, category: &str) {
+        assert!(
+            !category.is_empty() && category.len() <= MAX_SPEND_CATEGORY_LEN,
+            "Spend category must be between 1 and {} characters",
+            MAX_SPEND_CATEGORY_LEN
+        );
+        let categories = self.spend_by_category.get(reverie_id);
+        let is_known = categories.map_or(false, |c| c.iter().any(|c| c.category == category));
+        assert!(
+            is_known || categories.map_or(0, |c| c.len()) < MAX_SPEND_CATEGORIES,
+            "Too many spend categories for reverie {}. Max {}",
+            reverie_id,
+            MAX_SPEND_CATEGORIES
+        );
+    }
+
+    // internal method adding a spend to its category's totals
+    pub(crate) fn record_spend_category(&mut self, reverie_id: &str, category: &str, amount: u128) {
+        let mut categories = self.spend_by_category.get(reverie_id).cloned().unwrap_or_default();
+        match categories.iter_mut().find(|c| c.category == category) {
+            Some(entry) => {
+                entry.total_spent = U128(entry.total_spent.0 + amount);
+                entry.spend_count = U64(entry.spend_count.0 + 1);
+            }
+            None => categories.push(CategorySpend {
+                category: category.to_string(),
+                total_spent: U128(amount),
+                spend_count: U64(1),
+            }),
+        }
+        self.spend_by_category.insert(reverie_id.to_string(), categories);
+    }
+}
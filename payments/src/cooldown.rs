@@ -0,0 +1,101 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// A withdrawal requested with `request_withdrawal` on a reverie with a cooldown.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingWithdrawal {
+    pub amount: U128,
+    pub requested_at: U64, // block timestamp in nanoseconds
+}
+
+#[near]
+impl PaymentContract {
+    /// Sets how long users must wait after requesting a withdrawal before claiming it, so a
+    /// balance can't be drained right after a session. `None` allows immediate withdrawals again.
+    pub fn set_withdrawal_cooldown(&mut self, reverie_id: ReverieId, withdrawal_cooldown_ns: Option<U64>) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can set withdrawal cooldowns");
+        self.require_reverie_exists(&reverie_id);
+        match withdrawal_cooldown_ns {
+            Some(cooldown) if cooldown.0 > 0 => self.withdrawal_cooldowns.insert(reverie_id, cooldown.0),
+            _ => self.withdrawal_cooldowns.remove(&reverie_id),
+        };
+    }
+
+    pub fn get_withdrawal_cooldown(&self, reverie_id: ReverieId) -> Option<U64> {
+        self.withdrawal_cooldowns.get(&reverie_id).map(|cooldown| U64(*cooldown))
+    }
+
+    /// Starts a withdrawal on a reverie with a cooldown, replacing any earlier request.
+    pub fn request_withdrawal(&mut self, reverie_id: ReverieId, amount: U128) -> U64 {
+        self.require_reverie_exists(&reverie_id);
+        assert!(
+            self.withdrawal_cooldowns.get(&reverie_id).is_some(),
+            "Reverie {} has no withdrawal cooldown. Use withdraw",
+            reverie_id
+        );
+        assert!(amount.0 > 0, "Withdrawal amount must be greater than 0");
+        let user_id = env::predecessor_account_id();
+        let balance = self.get_balance(reverie_id.clone(), user_id.clone()).0;
+        assert!(
+            balance >= amount.0,
+            "Insufficient balance to withdraw. User {} has {}, requested {} for reverie {}",
+            user_id, balance, amount.0, reverie_id
+        );
+        let pending = PendingWithdrawal { amount, requested_at: U64(clock::block_timestamp()) };
+        self.pending_withdrawals.insert(self.user_key(&reverie_id, &user_id), pending);
+        let claimable_at = self.withdrawal_claimable_at(&reverie_id, &user_id);
+        log!("Requested withdrawal of {} for user {} on reverie {}, claimable at {}", amount.0, user_id, reverie_id, claimable_at);
+        U64(claimable_at)
+    }
+
+    /// Pays out the caller's pending withdrawal once its cooldown has passed.
+    pub fn claim_withdrawal(&mut self, reverie_id: ReverieId) {
+        let user_id = env::predecessor_account_id();
+        let key = self.user_key(&reverie_id, &user_id);
+        let pending = self
+            .pending_withdrawals
+            .get(&key)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str(&format!("No pending withdrawal for user {} on reverie {}", user_id, reverie_id)));
+        let claimable_at = self.withdrawal_claimable_at(&reverie_id, &user_id);
+        assert!(
+            clock::block_timestamp() >= claimable_at,
+            "Withdrawal is not claimable until {}",
+            claimable_at
+        );
+        self.pending_withdrawals.remove(&key);
+        self.internal_withdraw(reverie_id, user_id.clone(), pending.amount, user_id);
+    }
+
+    pub fn cancel_withdrawal(&mut self, reverie_id: ReverieId) {
+        self.pending_withdrawals.remove(&self.user_key(&reverie_id, &env::predecessor_account_id()));
+    }
+
+    pub fn get_pending_withdrawal(&self, reverie_id: ReverieId, user_id: AccountId) -> Option<PendingWithdrawal> {
+        self.pending_withdrawals.get(&self.user_key(&reverie_id, &user_id)).cloned()
+    }
+}
+
+impl PaymentContract {
+    // internal method for withdrawals that bypass `request_withdrawal`/`claim_withdrawal`
+    pub(crate) fn assert_no_withdrawal_cooldown(&self, reverie_id: &str) {
+        assert!(
+            self.withdrawal_cooldowns.get(reverie_id).is_none(),
+            "Reverie {} has a withdrawal cooldown. Use request_withdrawal and claim_withdrawal",
+            reverie_id
+        );
+    }
+
+    // A pending withdrawal is claimable a cooldown after it was requested. Spends made in the
+    // meantime don't push the claim back, so a spender can't hold a withdrawal off forever.
+    fn withdrawal_claimable_at(&self, reverie_id: &str, user_id: &AccountId) -> u64 {
+        let cooldown = self.withdrawal_cooldowns.get(reverie_id).copied().unwrap_or(0);
+        let requested_at = self
+            .pending_withdrawals
+            .get(&self.user_key(reverie_id, user_id))
+            .map(|pending| pending.requested_at.0)
+            .unwrap_or(0);
+        requested_at.saturating_add(cooldown)
+    }
+}
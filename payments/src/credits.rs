@@ -0,0 +1,90 @@
+use crate::*;
+
+const YOCTO_PER_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+
+/// Upper bound on `credits_per_near`, so conversions can't overflow.
+pub const MAX_CREDITS_PER_NEAR: u128 = 1_000_000_000_000;
+
+/// A balance in both units. `credits` is None when the reverie has no credit rate.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreditBalance {
+    pub balance: U128, // yoctoNEAR
+    pub credits: Option<U128>,
+}
+
+#[near]
+impl PaymentContract {
+    /// Sets how many credits one NEAR buys on a NEAR reverie, or removes the rate with `None`.
+    /// Balances are held in yoctoNEAR, so a new rate reprices existing credits.
+    pub fn set_credits_per_near(&mut self, reverie_id: ReverieId, credits_per_near: Option<U128>) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can set credit rates");
+        self.assert_near_denominated(&reverie_id);
+        if let Some(rate) = credits_per_near {
+            assert!(
+                rate.0 > 0 && rate.0 <= MAX_CREDITS_PER_NEAR,
+                "Credits per NEAR must be between 1 and {}",
+                MAX_CREDITS_PER_NEAR
+            );
+        }
+        if let Some(metadata) = self.reverie_metadata.get_mut(&reverie_id) {
+            metadata.credits_per_near = credits_per_near;
+        }
+    }
+
+    pub fn get_credits_per_near(&self, reverie_id: ReverieId) -> Option<U128> {
+        self.reverie_metadata.get(&reverie_id).and_then(|metadata| metadata.credits_per_near)
+    }
+
+    /// Spends `credits` from a user's balance. The yoctoNEAR charged is rounded up, so a spend
+    /// never costs less than its credits. Returns the yoctoNEAR spent.
+    pub fn record_spend_credits(&mut self, reverie_id: ReverieId, user_id: AccountId, credits: U128) -> U128 {
+        self.assert_can_record_spend(&reverie_id);
+        assert!(credits.0 > 0, "Credits must be greater than 0");
+        let amount = credits_to_yocto(credits.0, self.assert_credit_rate(&reverie_id), true);
+        U128(self.internal_record_spend(&reverie_id, &user_id, amount, None, None, true))
+    }
+
+    /// Withdraws `credits` of the caller's balance. The yoctoNEAR paid out is rounded down,
+    /// so a withdrawal never pays more than its credits.
+    pub fn withdraw_credits(&mut self, reverie_id: ReverieId, credits: U128) {
+        self.assert_no_withdrawal_cooldown(&reverie_id);
+        let amount = credits_to_yocto(credits.0, self.assert_credit_rate(&reverie_id), false);
+        let user_id = env::predecessor_account_id();
+        self.internal_withdraw(reverie_id, user_id.clone(), U128(amount), user_id);
+    }
+
+    /// A user's balance in yoctoNEAR and, on reveries with a credit rate, in whole credits (rounded down).
+    pub fn get_credit_balance(&self, reverie_id: ReverieId, user_id: AccountId) -> CreditBalance {
+        let balance = self.get_balance(reverie_id.clone(), user_id).0;
+        CreditBalance {
+            balance: U128(balance),
+            credits: self
+                .get_credits_per_near(reverie_id)
+                .map(|rate| U128(yocto_to_credits(balance, rate.0))),
+        }
+    }
+}
+
+impl PaymentContract {
+    // internal method returning the reverie's credit rate, panicking if it has none
+    fn assert_credit_rate(&self, reverie_id: &str) -> u128 {
+        self.get_credits_per_near(reverie_id.to_string())
+            .unwrap_or_else(|| env::panic_str(&format!("Reverie {} has no credit rate", reverie_id)))
+            .0
+    }
+}
+
+// Splits the division so intermediate products stay below u128::MAX for rates up to MAX_CREDITS_PER_NEAR
+fn credits_to_yocto(credits: u128, rate: u128, round_up: bool) -> u128 {
+    let whole = (credits / rate)
+        .checked_mul(YOCTO_PER_NEAR)
+        .unwrap_or_else(|| env::panic_str("Credit amount too large"));
+    let remainder = (credits % rate) * YOCTO_PER_NEAR;
+    let rounding = u128::from(round_up && remainder % rate != 0);
+    whole + remainder / rate + rounding
+}
+
+fn yocto_to_credits(yocto: u128, rate: u128) -> u128 {
+    (yocto / YOCTO_PER_NEAR).saturating_mul(rate) + (yocto % YOCTO_PER_NEAR) * rate / YOCTO_PER_NEAR
+}
@@ -0,0 +1,67 @@
+use crate::*;
+use near_sdk::{ext_contract, Gas, PromiseOrValue};
+
+pub use reveries_types::Denomination;
+
+const GAS_FOR_FT_TRANSFER: Gas = Gas::from_tgas(10);
+
+#[ext_contract(ext_ft_core)]
+pub trait FungibleTokenCore {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+/// `msg` of an `ft_transfer_call` into this contract. `user_id` defaults to the token sender.
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct FtDepositMsg {
+    pub reverie_id: ReverieId,
+    pub user_id: Option<AccountId>,
+}
+
+#[near]
+impl PaymentContract {
+    /// NEP-141 receiver: credits tokens sent with `ft_transfer_call` to an FT-denominated
+    /// reverie. Panics (so the token contract refunds the sender) if the token or msg is wrong.
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let deposit: FtDepositMsg = near_sdk::serde_json::from_str(&msg)
+            .unwrap_or_else(|_| env::panic_str("Invalid ft_on_transfer msg. Expected {\"reverie_id\": ..., \"user_id\": ...}"));
+        let token_id = env::predecessor_account_id();
+        match self.get_denomination(&deposit.reverie_id) {
+            Denomination::Ft { contract } if contract == token_id => {}
+            _ => env::panic_str(&format!("Reverie {} does not accept deposits of token {}", deposit.reverie_id, token_id)),
+        }
+        assert!(amount.0 > 0, "Deposit amount must be greater than 0");
+        let user_id = deposit.user_id.unwrap_or(sender_id);
+        let new_balance = self.internal_deposit(deposit.reverie_id.clone(), user_id.clone(), amount.0, true);
+        self.notify_deposit_hook(&deposit.reverie_id, &user_id, amount.0, new_balance);
+        // Nothing is returned to the sender
+        PromiseOrValue::Value(U128(0))
+    }
+}
+
+impl PaymentContract {
+    pub(crate) fn get_denomination(&self, reverie_id: &str) -> Denomination {
+        self.reverie_metadata
+            .get(reverie_id)
+            .map(|metadata| metadata.denomination.clone())
+            .unwrap_or_else(|| env::panic_str(&format!("ReverieId {} not found in registry", reverie_id)))
+    }
+
+    // internal method for methods that move NEAR in or out of a reverie directly
+    pub(crate) fn assert_near_denominated(&self, reverie_id: &str) {
+        if let Denomination::Ft { contract } = self.get_denomination(reverie_id) {
+            env::panic_str(&format!("Reverie {} is denominated in token {}", reverie_id, contract));
+        }
+    }
+
+    // internal method paying `amount` of the reverie's denomination out to `receiver_id`
+    pub(crate) fn payout(&self, reverie_id: &str, receiver_id: AccountId, amount: u128) -> Promise {
+        match self.get_denomination(reverie_id) {
+            Denomination::Near => Promise::new(receiver_id).transfer(NearToken::from_yoctonear(amount)),
+            Denomination::Ft { contract } => ext_ft_core::ext(contract)
+                .with_attached_deposit(NearToken::from_yoctonear(1))
+                .with_static_gas(GAS_FOR_FT_TRANSFER)
+                .ft_transfer(receiver_id, U128(amount), Some(format!("reverie {} payout", reverie_id))),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::*;
+
+#[near]
+impl PaymentContract {
+    pub fn get_reveries_by_type(&self, reverie_type: String, from_index: u32, limit: u32) -> Vec<ReverieId> {
+        paginate(self.reveries_by_type.get(&reverie_type), from_index, limit)
+    }
+
+    pub fn get_reveries_by_tag(&self, tag: String, from_index: u32, limit: u32) -> Vec<ReverieId> {
+        paginate(self.reveries_by_tag.get(&tag), from_index, limit)
+    }
+
+    /// `access_kind` is the access condition's `type` tag: "Umbral", "Ecdsa", "Ed25519", "Contract" or "DaoMember".
+    pub fn get_reveries_by_access_kind(&self, access_kind: String, from_index: u32, limit: u32) -> Vec<ReverieId> {
+        paginate(self.reveries_by_access_kind.get(&access_kind), from_index, limit)
+    }
+}
+
+impl PaymentContract {
+    // internal method adding a reverie to the discovery indexes
+    pub(crate) fn index_reverie(&mut self, reverie_id: &str, metadata: &ReverieMetadata) {
+        add_to_index(&mut self.reveries_by_type, metadata.reverie_type.clone(), reverie_id);
+        add_to_index(&mut self.reveries_by_access_kind, metadata.access_condition.kind().to_string(), reverie_id);
+        for tag in metadata.listing.tags.iter() {
+            add_to_index(&mut self.reveries_by_tag, tag.clone(), reverie_id);
+        }
+    }
+
+    // internal method removing a reverie from the discovery indexes
+    pub(crate) fn unindex_reverie(&mut self, reverie_id: &str, metadata: &ReverieMetadata) {
+        remove_from_index(&mut self.reveries_by_type, metadata.reverie_type.clone(), reverie_id);
+        remove_from_index(&mut self.reveries_by_access_kind, metadata.access_condition.kind().to_string(), reverie_id);
+        for tag in metadata.listing.tags.iter() {
+            remove_from_index(&mut self.reveries_by_tag, tag.clone(), reverie_id);
+        }
+    }
+}
+
+fn paginate(ids: Option<&Vec<ReverieId>>, from_index: u32, limit: u32) -> Vec<ReverieId> {
+    ids.map(|ids| ids.iter().skip(from_index as usize).take(limit as usize).cloned().collect())
+        .unwrap_or_default()
+}
+
+fn add_to_index(index: &mut LookupMap<String, Vec<ReverieId>>, key: String, reverie_id: &str) {
+    let mut ids = index.get(&key).cloned().unwrap_or_default();
+    if !ids.iter().any(|id| id == reverie_id) {
+        ids.push(reverie_id.to_string());
+        index.insert(key, ids);
+    }
+}
+
+fn remove_from_index(index: &mut LookupMap<String, Vec<ReverieId>>, key: String, reverie_id: &str) {
+    let Some(mut ids) = index.get(&key).cloned() else {
+        return;
+    };
+    ids.retain(|id| id != reverie_id);
+    if ids.is_empty() {
+        index.remove(&key);
+    } else {
+        index.insert(key, ids);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::*;
+use near_sdk::json_types::{Base58CryptoHash, U64};
+
+/// Disputes the trusted account hasn't resolved within this period can be refunded by anyone.
+pub const DISPUTE_RESOLUTION_PERIOD_NS: u64 = 7 * 24 * 60 * 60 * 1_000_000_000; // 7 days
+
+/// A spend recorded on a reverie with a dispute window, keyed by the `seq` of its Spend event.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpendRecord {
+    pub reverie_id: ReverieId,
+    pub user_id: AccountId,
+    pub amount: U128,
+    pub recorded_at: U64, // block timestamp in nanoseconds
+    pub evidence_hash: Option<Base58CryptoHash>, // hash of the off-chain usage log behind the spend
+    pub reverie_nonce: u64, // creation nonce of the reverie charged, so a re-created one isn't refunded
+}
+
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dispute {
+    pub spend: SpendRecord,
+    pub disputed_at: U64,
+    pub resolve_by: U64, // refundable by anyone after this
+}
+
+#[near]
+impl PaymentContract {
+    /// Sets how long users can dispute spends on a reverie after they are recorded.
+    /// Spends are only disputable if recorded while a window is set. `None` disables disputes.
+    pub fn set_dispute_window(&mut self, reverie_id: ReverieId, dispute_window_ns: Option<U64>) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can set dispute windows");
+        self.require_reverie_exists(&reverie_id);
+        match dispute_window_ns {
+            Some(window) if window.0 > 0 => self.dispute_windows.insert(reverie_id, window.0),
+            _ => self.dispute_windows.remove(&reverie_id),
+        };
+    }
+
+    pub fn get_dispute_window(&self, reverie_id: ReverieId) -> Option<U64> {
+        self.dispute_windows.get(&reverie_id).map(|window| U64(*window))
+    }
+
+    pub fn get_spend_record(&self, spend_id: U64) -> Option<SpendRecord> {
+        self.spend_records.get(&spend_id.0).cloned()
+    }
+
+    /// Disputes one of the caller's spends within the reverie's dispute window, holding
+    /// its amount until `resolve_dispute` or, after the resolution period, `refund_expired_dispute`.
+    pub fn dispute_spend(&mut self, spend_id: U64) -> Dispute {
+        let spend = self
+            .spend_records
+            .get(&spend_id.0)
+            .cloned()
+            .filter(|spend| self.is_current_reverie(spend))
+            .unwrap_or_else(|| env::panic_str(&format!("Spend {} is not disputable", spend_id.0)));
+        assert_eq!(env::predecessor_account_id(), spend.user_id, "Only the user charged can dispute a spend");
+        let window = *self.dispute_windows.get(&spend.reverie_id).unwrap_or(&0);
+        let now = clock::block_timestamp();
+        assert!(
+            now <= spend.recorded_at.0.saturating_add(window),
+            "Dispute window for spend {} has closed",
+            spend_id.0
+        );
+        self.spend_records.remove(&spend_id.0);
+        let dispute = Dispute {
+            spend: spend.clone(),
+            disputed_at: U64(now),
+            resolve_by: U64(now.saturating_add(DISPUTE_RESOLUTION_PERIOD_NS)),
+        };
+        self.disputes.insert(spend_id.0, dispute.clone());
+        self.emit_event(events::PaymentEvent::SpendDisputed {
+            spend_id,
+            reverie_id: spend.reverie_id,
+            user_id: spend.user_id,
+            amount: spend.amount,
+        });
+        dispute
+    }
+
+    pub fn get_dispute(&self, spend_id: U64) -> Option<Dispute> {
+        self.disputes.get(&spend_id.0).cloned()
+    }
+
+    /// Settles a dispute, crediting the spend back to the user if `refund` is set.
+    pub fn resolve_dispute(&mut self, spend_id: U64, refund: bool) {
+        let reverie_id = self
+            .disputes
+            .get(&spend_id.0)
+            .map(|dispute| dispute.spend.reverie_id.clone())
+            .unwrap_or_else(|| env::panic_str(&format!("No dispute for spend {}", spend_id.0)));
+        self.assert_reverie_admin(&reverie_id, "Only the reverie admin can resolve disputes");
+        self.internal_resolve_dispute(spend_id, refund);
+    }
+
+    /// Refunds a dispute left unresolved past its `resolve_by`. Callable by anyone.
+    pub fn refund_expired_dispute(&mut self, spend_id: U64) {
+        let dispute = self
+            .disputes
+            .get(&spend_id.0)
+            .unwrap_or_else(|| env::panic_str(&format!("No dispute for spend {}", spend_id.0)));
+        assert!(
+            clock::block_timestamp() > dispute.resolve_by.0,
+            "Dispute for spend {} can be resolved until {}",
+            spend_id.0,
+            dispute.resolve_by.0
+        );
+        self.internal_resolve_dispute(spend_id, true);
+    }
+
+    /// Removes a spend record whose dispute window has closed. Callable by anyone to free storage.
+    pub fn prune_spend_record(&mut self, spend_id: U64) -> bool {
+        let Some(spend) = self.spend_records.get(&spend_id.0) else {
+            return false;
+        };
+        let window = *self.dispute_windows.get(&spend.reverie_id).unwrap_or(&0);
+        if self.is_current_reverie(spend) && clock::block_timestamp() <= spend.recorded_at.0.saturating_add(window) {
+            return false;
+        }
+        self.spend_records.remove(&spend_id.0);
+        true
+    }
+}
+
+impl PaymentContract {
+    // internal method keeping a spend's details while it can still be disputed
+    pub(crate) fn record_disputable_spend(
+        &mut self,
+        reverie_id: &str,
+        user_id: &AccountId,
+        amount: u128,
+        evidence_hash: Option<Base58CryptoHash>,
+        spend_id: u64,
+    ) {
+        if amount == 0 || self.dispute_windows.get(reverie_id).is_none() {
+            return;
+        }
+        self.spend_records.insert(
+            spend_id,
+            SpendRecord {
+                reverie_id: reverie_id.to_string(),
+                user_id: user_id.clone(),
+                amount: U128(amount),
+                recorded_at: U64(clock::block_timestamp()),
+                evidence_hash,
+                reverie_nonce: self.reverie_nonce(reverie_id),
+            },
+        );
+    }
+
+    // Whether a spend was charged by the reverie currently registered under its id, rather
+    // than by one since deleted
+    fn is_current_reverie(&self, spend: &SpendRecord) -> bool {
+        self.reverie_metadata.get(&spend.reverie_id).is_some() && self.reverie_nonce(&spend.reverie_id) == spend.reverie_nonce
+    }
+
+    fn internal_resolve_dispute(&mut self, spend_id: U64, refund: bool) {
+        let dispute = self
+            .disputes
+            .remove(&spend_id.0)
+            .unwrap_or_else(|| env::panic_str(&format!("No dispute for spend {}", spend_id.0)));
+        // A reverie deleted while the dispute was open has no balance left to refund into, and
+        // one re-created under its id doesn't owe the old reverie's refunds
+        let refunded = refund && self.is_current_reverie(&dispute.spend);
+        let SpendRecord { reverie_id, user_id, amount, .. } = dispute.spend;
+        let seq = self.emit_event(events::PaymentEvent::DisputeResolved {
+            spend_id,
+            reverie_id: reverie_id.clone(),
+            user_id: user_id.clone(),
+            refunded,
+        });
+        if !refunded {
+            return;
+        }
+        let mut user_balances = self.get_balances_for_reverie(&reverie_id);
+        let new_balance = *user_balances.get(&user_id).unwrap_or(&0) + amount.0;
+        user_balances.insert(user_id.clone(), new_balance);
+        self.reverie_balances.insert(reverie_id.clone(), user_balances);
+        self.track_depositor(&reverie_id, &user_id);
+        self.record_ledger_entry(&reverie_id, &user_id, ledger::LedgerEntry::SpendRefund(amount.0), seq);
+        self.record_daily_stats(&reverie_id, &user_id, &ledger::LedgerEntry::SpendRefund(amount.0));
+        events::CreditEvent::mint(&reverie_id, &user_id, amount.0).emit();
+        log!("Refunded disputed spend {} of {} to user {} on reverie {}", spend_id.0, amount.0, user_id, reverie_id);
+    }
+}
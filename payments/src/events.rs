@@ -0,0 +1,263 @@
+use crate::*;
+use near_sdk::json_types::{Base58CryptoHash, U64};
+
+pub const EVENT_STANDARD: &str = "reveries";
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+pub const CREDIT_EVENT_STANDARD: &str = "reveries_credit";
+pub const CREDIT_EVENT_STANDARD_VERSION: &str = "1.0.0";
+/// How many recent `PaymentEvent`s are kept on-chain for `get_events_since`.
+pub const EVENT_BUFFER_SIZE: u64 = 256;
+
+/// NEP-297 events emitted by the payments contract.
+/// Each log also carries a contract-wide `seq` so consumers can detect gaps.
+#[near(serializers = [borsh, json])]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum PaymentEvent {
+    Deposit {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        amount: U128,
+        new_balance: U128,
+        // Set when the reverie has a deposit split: `credited` went to the user's balance
+        // and `revenue` to the reverie's revenue
+        #[serde(skip_serializing_if = "Option::is_none")]
+        credited: Option<U128>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        revenue: Option<U128>,
+    },
+    Spend {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        amount: U128,
+        new_balance: U128,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        category: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        evidence_hash: Option<Base58CryptoHash>,
+    },
+    Withdraw {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        amount: U128,
+        new_balance: U128,
+    },
+    WithdrawRefunded {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        amount: U128,
+        new_balance: U128,
+    },
+    DustConsolidated {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        amount: U128,
+    },
+    ReencryptionGranted {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        kfrag_hash: String,
+    },
+    ReencryptionRevoked {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+    },
+    ReverieCreated {
+        reverie_id: ReverieId,
+    },
+    ReverieCreatedWithDeposit {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        amount: U128,
+        new_balance: U128,
+    },
+    ReverieDeleted {
+        reverie_id: ReverieId,
+    },
+    ReverieFrozen {
+        reverie_id: ReverieId,
+        frozen: bool,
+    },
+    ReverieTransferProposed {
+        reverie_id: ReverieId,
+        admin: AccountId,
+        new_admin: AccountId,
+    },
+    ReverieTransferred {
+        reverie_id: ReverieId,
+        previous_admin: AccountId,
+        new_admin: AccountId,
+    },
+    AccessRevoked {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        reason: String,
+    },
+    AccessReinstated {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+    },
+    ReverieShutDown {
+        reverie_id: ReverieId,
+    },
+    DepositorRefunded {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        amount: U128,
+    },
+    ApiKeyRotated {
+        reverie_id: ReverieId,
+        sha256: Option<Base58CryptoHash>,
+    },
+    SpendDisputed {
+        spend_id: U64,
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        amount: U128,
+    },
+    DisputeResolved {
+        spend_id: U64,
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        refunded: bool,
+    },
+    VestingStarted {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        amount: U128,
+        end: U64,
+    },
+    VestedClaimed {
+        reverie_id: ReverieId,
+        amount: U128,
+    },
+    VestingCancelled {
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        refunded: U128,
+    },
+}
+
+/// A `PaymentEvent` kept in the on-chain buffer, with where it happened.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedEvent {
+    pub seq: U64,
+    pub block_height: U64,
+    pub block_timestamp: U64,
+    pub event: PaymentEvent,
+}
+
+/// How a `RecordedEvent` is stored. The event is kept as its JSON log rather than Borsh, so
+/// new variants and optional fields don't break decoding of entries written before them.
+#[near(serializers = [borsh])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredEvent {
+    pub seq: u64,
+    pub block_height: u64,
+    pub block_timestamp: u64,
+    pub event_json: String,
+}
+
+impl StoredEvent {
+    fn to_recorded(&self) -> Option<RecordedEvent> {
+        Some(RecordedEvent {
+            seq: U64(self.seq),
+            block_height: U64(self.block_height),
+            block_timestamp: U64(self.block_timestamp),
+            event: near_sdk::serde_json::from_str(&self.event_json).ok()?,
+        })
+    }
+}
+
+#[near]
+impl PaymentContract {
+    /// Up to `limit` buffered events after `seq`, oldest first, so light clients can tail the
+    /// contract with view calls. Only the last `EVENT_BUFFER_SIZE` events are kept: if the
+    /// first returned `seq` isn't `seq + 1`, the client fell behind and missed events.
+    pub fn get_events_since(&self, seq: U64, limit: u32) -> Vec<RecordedEvent> {
+        let oldest = self.event_seq.saturating_sub(EVENT_BUFFER_SIZE) + 1;
+        let start = (seq.0 + 1).max(oldest);
+        let end = self.event_seq.min(start.saturating_add(limit.min(EVENT_BUFFER_SIZE as u32) as u64).saturating_sub(1));
+        (start..=end)
+            .filter_map(|seq| self.recent_events.get(&(seq % EVENT_BUFFER_SIZE)).and_then(StoredEvent::to_recorded))
+            .collect()
+    }
+}
+
+impl PaymentContract {
+    /// Logs `event` as `EVENT_JSON` with the next sequence number and returns that number.
+    /// The event also replaces the oldest entry of the on-chain buffer.
+    pub(crate) fn emit_event(&mut self, event: PaymentEvent) -> u64 {
+        self.event_seq += 1;
+        let mut log = near_sdk::serde_json::to_value(&event)
+            .unwrap_or_else(|_| env::panic_str("Failed to serialize event"));
+        self.recent_events.insert(
+            self.event_seq % EVENT_BUFFER_SIZE,
+            StoredEvent {
+                seq: self.event_seq,
+                block_height: env::block_height(),
+                block_timestamp: clock::block_timestamp(),
+                event_json: log.to_string(),
+            },
+        );
+        let fields = log.as_object_mut().unwrap_or_else(|| env::panic_str("Event must serialize to an object"));
+        fields.insert("standard".to_string(), EVENT_STANDARD.into());
+        fields.insert("version".to_string(), EVENT_STANDARD_VERSION.into());
+        fields.insert("seq".to_string(), self.event_seq.into());
+        env::log_str(&format!("EVENT_JSON:{}", log));
+        self.event_seq
+    }
+}
+
+/// Credit supply changes in the shape of NEP-141 `ft_mint`/`ft_burn` events (a list of
+/// `{ owner_id, amount, memo }`, plus `reverie_id`), so generic FT indexers can chart
+/// credit supply per reverie without parsing `PaymentEvent`.
+#[near(serializers = [json])]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+#[derive(Clone, Debug, PartialEq)]
+pub enum CreditEvent {
+    ReverieCreditMint(Vec<CreditEventData>),
+    ReverieCreditBurn(Vec<CreditEventData>),
+    ReverieCreditSpend(Vec<CreditEventData>),
+}
+
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreditEventData {
+    pub owner_id: AccountId,
+    pub amount: U128,
+    pub reverie_id: ReverieId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+impl CreditEvent {
+    pub fn mint(reverie_id: &str, owner_id: &AccountId, amount: u128) -> Self {
+        CreditEvent::ReverieCreditMint(vec![CreditEventData::new(reverie_id, owner_id, amount)])
+    }
+
+    pub fn burn(reverie_id: &str, owner_id: &AccountId, amount: u128) -> Self {
+        CreditEvent::ReverieCreditBurn(vec![CreditEventData::new(reverie_id, owner_id, amount)])
+    }
+
+    pub fn spend(reverie_id: &str, owner_id: &AccountId, amount: u128) -> Self {
+        CreditEvent::ReverieCreditSpend(vec![CreditEventData::new(reverie_id, owner_id, amount)])
+    }
+
+    /// Logs the event as `EVENT_JSON`.
+    pub fn emit(&self) {
+        let mut log = near_sdk::serde_json::to_value(self)
+            .unwrap_or_else(|_| env::panic_str("Failed to serialize event"));
+        let fields = log.as_object_mut().unwrap_or_else(|| env::panic_str("Event must serialize to an object"));
+        fields.insert("standard".to_string(), CREDIT_EVENT_STANDARD.into());
+        fields.insert("version".to_string(), CREDIT_EVENT_STANDARD_VERSION.into());
+        env::log_str(&format!("EVENT_JSON:{}", log));
+    }
+}
+
+impl CreditEventData {
+    fn new(reverie_id: &str, owner_id: &AccountId, amount: u128) -> Self {
+        Self { owner_id: owner_id.clone(), amount: U128(amount), reverie_id: reverie_id.to_string(), memo: None }
+    }
+}
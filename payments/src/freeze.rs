@@ -0,0 +1,47 @@
+use crate::*;
+
+#[near]
+impl PaymentContract {
+    /// Blocks deposits, spends and credit transfers on a reverie, e.g. while an incident is
+    /// investigated. Withdrawals stay open so users can always exit.
+    pub fn freeze_reverie(&mut self, reverie_id: ReverieId) {
+        self.set_reverie_frozen(reverie_id, true);
+    }
+
+    pub fn unfreeze_reverie(&mut self, reverie_id: ReverieId) {
+        self.set_reverie_frozen(reverie_id, false);
+    }
+
+    pub fn is_reverie_frozen(&self, reverie_id: ReverieId) -> bool {
+        self.reverie_metadata.get(&reverie_id).map_or(false, |metadata| metadata.frozen)
+    }
+}
+
+impl PaymentContract {
+    // internal method flipping a reverie's frozen flag, callable by the reverie's admin
+    fn set_reverie_frozen(&mut self, reverie_id: ReverieId, frozen: bool) {
+        self.assert_reverie_admin(&reverie_id, "Only the reverie admin can freeze reveries");
+        if !frozen {
+            self.assert_not_shut_down(&reverie_id);
+        }
+        let metadata = self
+            .reverie_metadata
+            .get_mut(&reverie_id)
+            .unwrap_or_else(|| env::panic_str(&format!("ReverieId {} not found in registry", reverie_id)));
+        if metadata.frozen == frozen {
+            return;
+        }
+        metadata.frozen = frozen;
+        log!("Reverie {} {}", reverie_id, if frozen { "frozen" } else { "unfrozen" });
+        self.emit_event(events::PaymentEvent::ReverieFrozen { reverie_id, frozen });
+    }
+
+    // internal method rejecting deposits and spends on a frozen reverie
+    pub(crate) fn assert_not_frozen(&self, reverie_id: &str) {
+        assert!(
+            !self.reverie_metadata.get(reverie_id).map_or(false, |metadata| metadata.frozen),
+            "Reverie {} is frozen",
+            reverie_id
+        );
+    }
+}
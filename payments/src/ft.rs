@@ -0,0 +1,118 @@
+use crate::*;
+
+pub const FT_METADATA_SPEC: &str = "ft-1.0.0";
+
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FungibleTokenMetadata {
+    pub spec: String,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+/// NEP-141 view of the balances of one chosen reverie. Credits are backed 1:1 by
+/// the reverie's internal balances, so only NEAR-denominated reveries can be exposed
+/// and the token always has 24 decimals.
+#[near]
+impl PaymentContract {
+    /// Chooses which reverie's credits are exposed as the contract's fungible token.
+    pub fn set_ft_reverie(&mut self, reverie_id: Option<ReverieId>) {
+        assert_eq!(env::predecessor_account_id(), env::current_account_id(), "Only the contract account can set the FT reverie");
+        if let Some(reverie_id) = reverie_id.as_ref() {
+            self.require_reverie_exists(reverie_id);
+            self.assert_near_denominated(reverie_id);
+        }
+        self.ft_reverie_id = reverie_id;
+    }
+
+    pub fn get_ft_reverie(&self) -> Option<ReverieId> {
+        self.ft_reverie_id.clone()
+    }
+
+    #[payable]
+    pub fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        near_sdk::assert_one_yocto();
+        let reverie_id = self.get_ft_reverie_or_panic();
+        self.assert_not_frozen(&reverie_id);
+        let sender_id = env::predecessor_account_id();
+        assert_ne!(sender_id, receiver_id, "Sender and receiver should be different");
+        assert!(amount.0 > 0, "The amount should be a positive number");
+
+        let mut user_balances = self.get_balances_for_reverie(&reverie_id);
+        let sender_balance = *user_balances.get(&sender_id).unwrap_or(&0);
+        assert!(
+            sender_balance >= amount.0,
+            "Insufficient balance to transfer. User {} has {}, requested {} for reverie {}",
+            sender_id, sender_balance, amount.0, reverie_id
+        );
+        if sender_balance == amount.0 {
+            user_balances.remove(&sender_id);
+        } else {
+            user_balances.insert(sender_id.clone(), sender_balance - amount.0);
+        }
+        let receiver_balance = *user_balances.get(&receiver_id).unwrap_or(&0);
+        user_balances.insert(receiver_id.clone(), receiver_balance + amount.0);
+        self.reverie_balances.insert(reverie_id.clone(), user_balances);
+        self.track_depositor(&reverie_id, &receiver_id);
+
+        emit_ft_transfer(&sender_id, &receiver_id, amount, memo);
+    }
+
+    pub fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        self.get_balance(self.get_ft_reverie_or_panic(), account_id)
+    }
+
+    /// Outstanding credits of the FT reverie: deposits minus spends and withdrawals, less the
+    /// vesting deposits that haven't vested yet, which sit in no balance.
+    pub fn ft_total_supply(&self) -> U128 {
+        let reverie_id = self.get_ft_reverie_or_panic();
+        let checkpoint = self.get_ledger_checkpoint(reverie_id.clone());
+        U128(
+            checkpoint
+                .total_deposits
+                .0
+                .saturating_sub(checkpoint.total_spends.0)
+                .saturating_sub(checkpoint.total_withdrawals.0)
+                .saturating_sub(self.unvested_principal(&reverie_id)),
+        )
+    }
+
+    pub fn ft_metadata(&self) -> FungibleTokenMetadata {
+        let reverie_id = self.get_ft_reverie_or_panic();
+        self.assert_near_denominated(&reverie_id);
+        FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: format!("{} credits", reverie_id),
+            symbol: reverie_id.to_uppercase(),
+            decimals: 24,
+        }
+    }
+}
+
+impl PaymentContract {
+    fn get_ft_reverie_or_panic(&self) -> ReverieId {
+        self.ft_reverie_id
+            .clone()
+            .unwrap_or_else(|| env::panic_str("FT reverie is not configured"))
+    }
+}
+
+// Logs a NEP-141 `ft_transfer` event, so wallets and indexers pick up credit transfers.
+fn emit_ft_transfer(old_owner_id: &AccountId, new_owner_id: &AccountId, amount: U128, memo: Option<String>) {
+    let mut data = near_sdk::serde_json::json!({
+        "old_owner_id": old_owner_id,
+        "new_owner_id": new_owner_id,
+        "amount": amount,
+    });
+    if let Some(memo) = memo {
+        data["memo"] = memo.into();
+    }
+    let log = near_sdk::serde_json::json!({
+        "standard": "nep141",
+        "version": "1.0.0",
+        "event": "ft_transfer",
+        "data": [data],
+    });
+    env::log_str(&format!("EVENT_JSON:{}", log));
+}
@@ -0,0 +1,88 @@
+use crate::*;
+use near_sdk::{Gas, PromiseResult};
+
+/// Default gas for a deposit hook call when neither the hook nor `set_default_hook_gas` sets it.
+pub const GAS_FOR_DEPOSIT_HOOK: Gas = Gas::from_tgas(10);
+const GAS_FOR_ON_DEPOSIT_HOOK_RESULT: Gas = Gas::from_tgas(5);
+
+/// A service contract method called on every `deposit`/`deposit_for` into a reverie,
+/// with JSON args `{ reverie_id, user_id, amount, new_balance }`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DepositHook {
+    pub contract_id: AccountId,
+    pub method_name: String,
+    pub gas: Option<Gas>,
+}
+
+#[near(serializers = [json])]
+struct DepositHookArgs {
+    reverie_id: ReverieId,
+    user_id: AccountId,
+    amount: U128,
+    new_balance: U128,
+}
+
+#[near]
+impl PaymentContract {
+    pub fn set_deposit_hook(&mut self, reverie_id: ReverieId, hook: Option<DepositHook>) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can set deposit hooks");
+        self.require_reverie_exists(&reverie_id);
+        match hook {
+            Some(hook) => self.deposit_hooks.insert(reverie_id, hook),
+            None => self.deposit_hooks.remove(&reverie_id),
+        };
+    }
+
+    pub fn get_deposit_hook(&self, reverie_id: ReverieId) -> Option<DepositHook> {
+        self.deposit_hooks.get(&reverie_id).cloned()
+    }
+
+    /// Gas for deposit hooks that don't set their own. `None` falls back to `GAS_FOR_DEPOSIT_HOOK`.
+    pub fn set_default_hook_gas(&mut self, gas: Option<Gas>) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can set deposit hooks");
+        self.default_hook_gas = gas;
+    }
+
+    pub fn get_default_hook_gas(&self) -> Gas {
+        self.default_hook_gas.unwrap_or(GAS_FOR_DEPOSIT_HOOK)
+    }
+
+    /// The deposit is already credited, so a failing hook is only logged.
+    #[private]
+    pub fn on_deposit_hook_result(&mut self, reverie_id: ReverieId, user_id: AccountId) -> bool {
+        let succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if !succeeded {
+            log!("Deposit hook failed for user {} on reverie {}", user_id, reverie_id);
+        }
+        succeeded
+    }
+}
+
+impl PaymentContract {
+    // internal method to push a deposit notification to the reverie's hook, if one is set
+    pub(crate) fn notify_deposit_hook(&self, reverie_id: &str, user_id: &AccountId, amount: u128, new_balance: u128) {
+        let Some(hook) = self.deposit_hooks.get(reverie_id) else {
+            return;
+        };
+        let args = near_sdk::serde_json::to_vec(&DepositHookArgs {
+            reverie_id: reverie_id.to_string(),
+            user_id: user_id.clone(),
+            amount: U128(amount),
+            new_balance: U128(new_balance),
+        })
+        .unwrap_or_else(|_| env::panic_str("Failed to serialize deposit hook args"));
+        Promise::new(hook.contract_id.clone())
+            .function_call(
+                hook.method_name.clone(),
+                args,
+                NearToken::from_yoctonear(0),
+                hook.gas.unwrap_or_else(|| self.get_default_hook_gas()),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_DEPOSIT_HOOK_RESULT)
+                    .on_deposit_hook_result(reverie_id.to_string(), user_id.clone()),
+            );
+    }
+}
@@ -0,0 +1,91 @@
+use crate::*;
+use crate::open_registry::OpenRegistryConfig;
+use crate::oracle::PriceOracleConfig;
+use crate::splits::MAX_SPLIT_BPS;
+use near_sdk::Gas;
+
+/// A reverie created at initialization.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReverieSeed {
+    pub reverie_id: ReverieId,
+    #[serde(flatten)]
+    pub metadata: ReverieMetadata,
+    /// Defaults to the trusted account
+    #[serde(default)]
+    pub admin: Option<AccountId>,
+    /// Upfront fee taken from deposits, see `set_deposit_split`
+    #[serde(default)]
+    pub deposit_split_bps: Option<u16>,
+}
+
+/// Everything a deployment is initialized with, so it can be reproduced from one JSON
+/// document. `get_config` returns the current values in the same shape.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitConfig {
+    pub trusted_account: AccountId,
+    #[serde(default)]
+    pub price_oracle: Option<PriceOracleConfig>,
+    #[serde(default)]
+    pub reveries: Vec<ReverieSeed>,
+    #[serde(default)]
+    pub authorized_spenders: Vec<AccountId>,
+    #[serde(default)]
+    pub open_registry: Option<OpenRegistryConfig>,
+    #[serde(default)]
+    pub max_reveries: Option<u32>,
+    /// Gas for deposit hooks that don't set their own
+    #[serde(default)]
+    pub default_hook_gas: Option<Gas>,
+}
+
+#[near]
+impl PaymentContract {
+    #[init]
+    pub fn new_with_config(config: InitConfig) -> Self {
+        let mut contract = Self::new(config.trusted_account);
+        contract.price_oracle = config.price_oracle;
+        contract.authorized_spenders = config.authorized_spenders;
+        contract.open_registry = config.open_registry;
+        contract.default_hook_gas = config.default_hook_gas;
+        for seed in config.reveries {
+            let reverie_id = normalize_reverie_id(&seed.reverie_id).unwrap_or_else(|err| env::panic_str(&err));
+            contract.internal_create_reverie(reverie_id.clone(), seed.metadata);
+            if let Some(split_bps) = seed.deposit_split_bps {
+                assert!(split_bps <= MAX_SPLIT_BPS, "split_bps must be at most {}", MAX_SPLIT_BPS);
+                contract.deposit_splits.insert(reverie_id.clone(), split_bps);
+            }
+            if let Some(admin) = seed.admin.filter(|admin| admin != &contract.trusted_account) {
+                contract.reverie_admins.insert(reverie_id, admin);
+            }
+        }
+        // Seeded reveries aren't capped, so the deployment document can't lock itself out
+        contract.max_reveries = config.max_reveries;
+        contract
+    }
+
+    pub fn get_config(&self) -> InitConfig {
+        InitConfig {
+            trusted_account: self.trusted_account.clone(),
+            price_oracle: self.price_oracle.clone(),
+            reveries: self
+                .reverie_ids
+                .iter()
+                .filter_map(|reverie_id| {
+                    let metadata = self.reverie_metadata.get(reverie_id)?.clone();
+                    Some(ReverieSeed {
+                        reverie_id: reverie_id.clone(),
+                        metadata,
+                        admin: self.reverie_admins.get(reverie_id).cloned(),
+                        deposit_split_bps: self.deposit_splits.get(reverie_id).copied(),
+                    })
+                })
+                .collect(),
+            authorized_spenders: self.authorized_spenders.clone(),
+            open_registry: self.open_registry.clone(),
+            max_reveries: self.max_reveries,
+            default_hook_gas: self.default_hook_gas,
+        }
+    }
+}
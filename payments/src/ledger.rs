@@ -0,0 +1,70 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// Running totals for a reverie, updated on every balance change so off-chain
+/// accounting can reconcile against `last_event_seq` without replaying all events.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LedgerCheckpoint {
+    pub total_deposits: U128,
+    pub total_spends: U128,
+    pub total_withdrawals: U128,
+    pub last_event_seq: U64,
+}
+
+pub(crate) enum LedgerEntry {
+    Deposit(u128),
+    Spend(u128),
+    Withdrawal(u128),
+    WithdrawalRefund(u128),
+    SpendRefund(u128),
+    VestingRefund(u128), // unvested remainder returned straight to the user on cancel
+}
+
+#[near]
+impl PaymentContract {
+    pub fn get_ledger_checkpoint(&self, reverie_id: ReverieId) -> LedgerCheckpoint {
+        self.require_reverie_exists(&reverie_id);
+        self.ledger_checkpoints.get(&reverie_id).cloned().unwrap_or_default()
+    }
+
+    /// Returns the sequence number of the most recent event emitted by the contract.
+    pub fn get_last_event_seq(&self) -> U64 {
+        U64(self.event_seq)
+    }
+}
+
+impl PaymentContract {
+    // internal method to fold a balance change into the reverie's checkpoint
+    fn update_ledger(&mut self, reverie_id: &str, entry: LedgerEntry, event_seq: u64) {
+        let mut checkpoint = self.ledger_checkpoints.get(reverie_id).cloned().unwrap_or_default();
+        match entry {
+            LedgerEntry::Deposit(amount) => checkpoint.total_deposits = U128(checkpoint.total_deposits.0 + amount),
+            LedgerEntry::Spend(amount) => checkpoint.total_spends = U128(checkpoint.total_spends.0 + amount),
+            LedgerEntry::Withdrawal(amount) => checkpoint.total_withdrawals = U128(checkpoint.total_withdrawals.0 + amount),
+            LedgerEntry::WithdrawalRefund(amount) => {
+                checkpoint.total_withdrawals = U128(checkpoint.total_withdrawals.0.saturating_sub(amount))
+            }
+            LedgerEntry::SpendRefund(amount) => checkpoint.total_spends = U128(checkpoint.total_spends.0.saturating_sub(amount)),
+            LedgerEntry::VestingRefund(amount) => checkpoint.total_withdrawals = U128(checkpoint.total_withdrawals.0 + amount),
+        }
+        checkpoint.last_event_seq = U64(event_seq);
+        self.ledger_checkpoints.insert(reverie_id.to_string(), checkpoint);
+    }
+
+    // internal method recording a change of a user's balance in both the reverie's checkpoint
+    // and the user's totals, so lifetime totals reconcile with the ledger. A withdrawal stays
+    // locked until its transfer resolves, so its refund leaves the totals alone. A vesting
+    // refund is transferred without a callback, so it never locks.
+    pub(crate) fn record_ledger_entry(&mut self, reverie_id: &str, user_id: &AccountId, entry: LedgerEntry, event_seq: u64) {
+        self.update_user_totals(reverie_id, user_id, |totals| match entry {
+            LedgerEntry::Deposit(amount) => totals.deposited += amount,
+            LedgerEntry::Spend(amount) => totals.spent += amount,
+            LedgerEntry::Withdrawal(amount) => totals.locked += amount,
+            LedgerEntry::WithdrawalRefund(_) => {}
+            LedgerEntry::SpendRefund(amount) => totals.spent = totals.spent.saturating_sub(amount),
+            LedgerEntry::VestingRefund(_) => {}
+        });
+        self.update_ledger(reverie_id, entry, event_seq);
+    }
+}
@@ -1,36 +1,55 @@
+pub mod access_cache;
+pub mod admins;
+pub mod api_keys;
+pub mod balance_detail;
+pub mod categories;
+pub mod cooldown;
+pub mod credits;
+pub mod denomination;
+pub mod discovery;
+pub mod disputes;
+pub mod events;
+pub mod freeze;
+pub mod ft;
+pub mod hooks;
+pub mod init_config;
+pub mod ledger;
+pub mod limits;
+pub mod locks;
+pub mod membership;
+pub mod migrate;
+pub mod open_registry;
+pub mod oracle;
+pub mod passkey_withdraw;
+pub mod permits;
+pub mod revocations;
+pub mod rounding;
+pub mod schema;
+pub mod security_config;
+pub mod shutdown;
+#[cfg(feature = "test-utils")]
+pub mod snapshot;
+pub mod spenders;
+pub mod splits;
+pub mod state_dump;
+pub mod stats;
+pub mod storage;
+pub mod umbral;
+pub mod upgrade;
+pub mod vesting;
+pub mod views;
 #[cfg(test)]
 mod tests_payments;
 
-use near_sdk::{log, near, PanicOnDefault, NearToken};
-use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::store::LookupMap;
-use near_sdk::{env, AccountId};
-use near_sdk::json_types::U128;
-use near_sdk::serde::{Deserialize, Serialize};
-use schemars::JsonSchema;
-
-pub type ReverieId = String;
-
-#[derive(JsonSchema, BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
-#[serde(crate = "near_sdk::serde")]
-pub struct ReverieMetadata {
-    pub reverie_type: String,
-    pub description: String,
-    pub access_condition: AccessCondition,
-}
+use near_sdk::{log, near, PanicOnDefault, NearToken, Promise};
+use near_sdk::store::{IterableSet, LookupMap, LookupSet};
+use near_sdk::{env, AccountId, PublicKey};
+use near_sdk::json_types::{Base58CryptoHash, Base64VecU8, U128};
 
-#[derive(JsonSchema, BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
-#[serde(tag = "type", content = "value", crate = "near_sdk::serde")]
-pub enum AccessCondition {
-    Umbral(String), // Use String for public key serialization
-    Ecdsa(String), // Use String for address serialization
-    Ed25519(String),
-    Contract {
-        address: String,
-        access_function_name: String,
-        access_function_args: String, // Store as JSON string
-    },
-}
+pub use reveries_types::{clock, derive_reverie_id, normalize_reverie_id, AccessCondition, Denomination, ReverieId, ReverieListing, ReverieMetadata, ValidationReport, MAX_REVERIE_ID_LEN};
+
+/// Max recipients per `distribute` call, to stay well within the gas limit.
+pub const MAX_DISTRIBUTION_RECIPIENTS: usize = 100;
 
 #[near(contract_state)]
 #[derive(PanicOnDefault)]
@@ -40,6 +59,59 @@ pub struct PaymentContract {
     reverie_balances: LookupMap<ReverieId, LookupMap<AccountId, u128>>,
     reverie_ids: Vec<ReverieId>,
     reverie_metadata: LookupMap<ReverieId, ReverieMetadata>,
+    price_oracle: Option<oracle::PriceOracleConfig>,
+    event_seq: u64,
+    ledger_checkpoints: LookupMap<ReverieId, ledger::LedgerCheckpoint>,
+    withdraw_passkeys: LookupMap<PublicKey, AccountId>,
+    withdraw_nonces: LookupMap<AccountId, u64>,
+    reverie_spenders: LookupMap<ReverieId, Vec<AccountId>>,
+    fee_pool: u128,
+    ft_reverie_id: Option<ReverieId>,
+    umbral_public_keys: LookupMap<ReverieId, umbral::UmbralPublicKeys>,
+    reencryption_grants: LookupMap<storage::UserKey, umbral::ReencryptionGrant>,
+    deposit_hooks: LookupMap<ReverieId, hooks::DepositHook>,
+    reveries_by_type: LookupMap<String, Vec<ReverieId>>,
+    reveries_by_access_kind: LookupMap<String, Vec<ReverieId>>,
+    reveries_by_tag: LookupMap<String, Vec<ReverieId>>,
+    daily_stats: LookupMap<(Option<u64>, u32), stats::DailyStats>,
+    daily_active_users: LookupSet<(Option<u64>, u32, AccountId)>,
+    next_reverie_index: u64,
+    delegation_keys: LookupMap<AccountId, PublicKey>,
+    permit_nonces: LookupMap<AccountId, u64>,
+    deletion_cursor: u64,
+    user_locks: LookupMap<AccountId, u64>,
+    spend_by_category: LookupMap<ReverieId, Vec<categories::CategorySpend>>,
+    user_totals: LookupMap<storage::UserKey, balance_detail::UserTotals>,
+    withdrawal_cooldowns: LookupMap<ReverieId, u64>,
+    pending_withdrawals: LookupMap<storage::UserKey, cooldown::PendingWithdrawal>,
+    access_grants: LookupMap<storage::UserKey, access_cache::GrantRecord>,
+    access_cache_ttls: LookupMap<ReverieId, u64>,
+    deposit_splits: LookupMap<ReverieId, u16>,
+    reverie_revenue: LookupMap<ReverieId, u128>,
+    membership_nft_reveries: LookupSet<ReverieId>,
+    memberships: LookupMap<storage::UserKey, u64>,
+    memberships_by_owner: LookupMap<AccountId, IterableSet<(ReverieId, u64)>>,
+    dispute_windows: LookupMap<ReverieId, u64>,
+    spend_records: LookupMap<u64, disputes::SpendRecord>,
+    disputes: LookupMap<u64, disputes::Dispute>,
+    vesting_periods: LookupMap<ReverieId, u64>,
+    vesting_schedules: LookupMap<storage::UserKey, vesting::VestingSchedule>,
+    vesting_users: LookupMap<ReverieId, Vec<AccountId>>,
+    recent_events: LookupMap<u64, events::StoredEvent>,
+    reverie_admins: LookupMap<ReverieId, AccountId>,
+    pending_reverie_transfers: LookupMap<ReverieId, AccountId>,
+    access_revocations: LookupMap<storage::UserKey, revocations::Revocation>,
+    api_key_hashes: LookupMap<ReverieId, near_sdk::CryptoHash>,
+    reverie_depositors: LookupMap<ReverieId, IterableSet<AccountId>>,
+    reverie_shutdowns: LookupMap<ReverieId, u32>,
+    authorized_spenders: Vec<AccountId>,
+    max_reveries: Option<u32>,
+    open_registry: Option<open_registry::OpenRegistryConfig>,
+    untracked_depositor_reveries: LookupSet<ReverieId>,
+    reverie_nonces: LookupMap<ReverieId, u64>,
+    reencryption_grantees: LookupMap<ReverieId, IterableSet<AccountId>>,
+    default_hook_gas: Option<near_sdk::Gas>,
+    vesting_principal: LookupMap<ReverieId, u128>,
 }
 
 #[near]
@@ -52,6 +124,59 @@ impl PaymentContract {
             reverie_balances: LookupMap::new(b"b"),
             reverie_ids: Vec::new(),
             reverie_metadata: LookupMap::new(b"r"),
+            price_oracle: None,
+            event_seq: 0,
+            ledger_checkpoints: LookupMap::new(b"l"),
+            withdraw_passkeys: LookupMap::new(b"k"),
+            withdraw_nonces: LookupMap::new(b"w"),
+            reverie_spenders: LookupMap::new(b"s"),
+            fee_pool: 0,
+            ft_reverie_id: None,
+            umbral_public_keys: LookupMap::new(b"u"),
+            reencryption_grants: LookupMap::new(b"J"),
+            deposit_hooks: LookupMap::new(b"h"),
+            reveries_by_type: LookupMap::new(b"y"),
+            reveries_by_access_kind: LookupMap::new(b"x"),
+            next_reverie_index: 0,
+            delegation_keys: LookupMap::new(b"d"),
+            permit_nonces: LookupMap::new(b"p"),
+            deletion_cursor: 0,
+            user_locks: LookupMap::new(b"o"),
+            spend_by_category: LookupMap::new(b"c"),
+            user_totals: LookupMap::new(b"t"),
+            withdrawal_cooldowns: LookupMap::new(b"q"),
+            pending_withdrawals: LookupMap::new(b"v"),
+            access_grants: LookupMap::new(b"a"),
+            access_cache_ttls: LookupMap::new(b"e"),
+            deposit_splits: LookupMap::new(b"f"),
+            reverie_revenue: LookupMap::new(b"i"),
+            membership_nft_reveries: LookupSet::new(b"j"),
+            memberships: LookupMap::new(b"m"),
+            memberships_by_owner: LookupMap::new(b"n"),
+            dispute_windows: LookupMap::new(b"W"),
+            spend_records: LookupMap::new(b"S"),
+            disputes: LookupMap::new(b"D"),
+            reveries_by_tag: LookupMap::new(b"T"),
+            daily_stats: LookupMap::new(b"U"),
+            daily_active_users: LookupSet::new(b"A"),
+            vesting_periods: LookupMap::new(b"V"),
+            vesting_schedules: LookupMap::new(b"X"),
+            vesting_users: LookupMap::new(b"Y"),
+            recent_events: LookupMap::new(b"E"),
+            reverie_admins: LookupMap::new(b"B"),
+            pending_reverie_transfers: LookupMap::new(b"P"),
+            access_revocations: LookupMap::new(b"R"),
+            api_key_hashes: LookupMap::new(b"H"),
+            reverie_depositors: LookupMap::new(b"F"),
+            reverie_shutdowns: LookupMap::new(b"C"),
+            authorized_spenders: Vec::new(),
+            max_reveries: None,
+            open_registry: None,
+            untracked_depositor_reveries: LookupSet::new(b"G"),
+            reverie_nonces: LookupMap::new(b"I"),
+            reencryption_grantees: LookupMap::new(b"K"),
+            default_hook_gas: None,
+            vesting_principal: LookupMap::new(b"L"),
         }
     }
 
@@ -66,24 +191,114 @@ impl PaymentContract {
     // Allows users to pay for usage tokens with NEAR for a specific ReverieId
     #[payable]
     pub fn deposit(&mut self, reverie_id: String) {
+        let user_id = env::predecessor_account_id();
+        let attached = env::attached_deposit().as_yoctonear();
+        // Unknown ids under the open registry prefix are created, paying storage from the deposit
+        let storage_cost = self.auto_register_reverie(&reverie_id, &user_id, attached);
+        self.assert_near_denominated(&reverie_id);
+        let amount = attached - storage_cost.unwrap_or(0);
+        let new_balance = self.internal_deposit_with_event(reverie_id.clone(), user_id.clone(), amount, true, storage_cost.is_some());
+        self.notify_deposit_hook(&reverie_id, &user_id, amount, new_balance);
+    }
+
+    // Pays for usage tokens on behalf of another user, e.g. from a PasskeyController.
+    #[payable]
+    pub fn deposit_for(&mut self, reverie_id: String, user_id: AccountId) {
+        self.assert_near_denominated(&reverie_id);
+        let amount = env::attached_deposit().as_yoctonear();
+        let new_balance = self.internal_deposit(reverie_id.clone(), user_id.clone(), amount, true);
+        self.notify_deposit_hook(&reverie_id, &user_id, amount, new_balance);
+    }
+
+    // Splits the attached deposit across many users' balances, e.g. to airdrop prepaid credits.
+    // The amounts must add up to exactly the attached deposit. Deposit hooks are not called;
+    // services can follow the emitted deposit events instead.
+    #[payable]
+    pub fn distribute(&mut self, reverie_id: String, recipients: Vec<(AccountId, U128)>) {
+        self.require_reverie_exists(&reverie_id);
+        self.assert_near_denominated(&reverie_id);
+        assert!(!recipients.is_empty(), "Recipients must not be empty");
+        assert!(
+            recipients.len() <= MAX_DISTRIBUTION_RECIPIENTS,
+            "Too many recipients. Max {} per call",
+            MAX_DISTRIBUTION_RECIPIENTS
+        );
+        let total = recipients.iter().fold(0u128, |total, (_, amount)| {
+            assert!(amount.0 > 0, "Distribution amounts must be greater than 0");
+            total.checked_add(amount.0).unwrap_or_else(|| env::panic_str("Distribution total overflow"))
+        });
+        let attached = env::attached_deposit().as_yoctonear();
+        assert_eq!(
+            total, attached,
+            "Distribution total {} does not match attached deposit {}",
+            total, attached
+        );
+
+        for (user_id, amount) in recipients {
+            self.internal_deposit(reverie_id.clone(), user_id, amount.0, false);
+        }
+    }
+
+    // internal method to credit a deposit to a user's balance, less the reverie's
+    // deposit split when `apply_split` is set
+    fn internal_deposit(&mut self, reverie_id: String, user_id: AccountId, amount_deposited: u128, apply_split: bool) -> u128 {
+        self.internal_deposit_with_event(reverie_id, user_id, amount_deposited, apply_split, false)
+    }
+
+    // `internal_deposit`, logging a `ReverieCreatedWithDeposit` event instead of `Deposit`
+    // when the deposit seeds a reverie created in the same call
+    fn internal_deposit_with_event(
+        &mut self,
+        reverie_id: String,
+        user_id: AccountId,
+        amount_deposited: u128,
+        apply_split: bool,
+        reverie_created: bool,
+    ) -> u128 {
         if self.reverie_metadata.get(&reverie_id).is_none() {
             env::panic_str(&format!("ReverieId {} not found in registry", reverie_id));
         }
-
-        let user_id = env::predecessor_account_id();
-        let amount_deposited = env::attached_deposit().as_yoctonear();
+        self.assert_not_frozen(&reverie_id);
+        let revenue = if apply_split { self.take_deposit_split(&reverie_id, amount_deposited) } else { 0 };
+        let amount_credited = amount_deposited - revenue;
 
         let mut user_balances = self.reverie_balances
             .remove(&reverie_id)
-            .unwrap_or_else(|| {
-                LookupMap::new(format!("b:{}", reverie_id).as_bytes())
-            });
+            .unwrap_or_else(|| self.new_reverie_balances());
 
         let current_balance = user_balances.get(&user_id).unwrap_or(&0);
-        let new_balance = current_balance + amount_deposited;
+        let new_balance = current_balance + amount_credited;
         user_balances.insert(user_id.clone(), new_balance);
         self.reverie_balances.insert(reverie_id.clone(), user_balances);
+        self.track_depositor(&reverie_id, &user_id);
         log!("Deposited {} for user {} on reverie {}", amount_deposited, user_id, reverie_id);
+        let event = if reverie_created {
+            events::PaymentEvent::ReverieCreatedWithDeposit {
+                reverie_id: reverie_id.clone(),
+                user_id: user_id.clone(),
+                amount: U128(amount_deposited),
+                new_balance: U128(new_balance),
+            }
+        } else {
+            events::PaymentEvent::Deposit {
+                reverie_id: reverie_id.clone(),
+                user_id: user_id.clone(),
+                amount: U128(amount_deposited),
+                new_balance: U128(new_balance),
+                credited: (revenue > 0).then_some(U128(amount_credited)),
+                revenue: (revenue > 0).then_some(U128(revenue)),
+            }
+        };
+        let seq = self.emit_event(event);
+        self.record_ledger_entry(&reverie_id, &user_id, ledger::LedgerEntry::Deposit(amount_deposited), seq);
+        self.record_daily_stats(&reverie_id, &user_id, &ledger::LedgerEntry::Deposit(amount_deposited));
+        if revenue > 0 {
+            // The split leaves user balances like a spend, so the checkpoint still reconciles
+            self.record_ledger_entry(&reverie_id, &user_id, ledger::LedgerEntry::Spend(revenue), seq);
+        }
+        events::CreditEvent::mint(&reverie_id, &user_id, amount_credited).emit();
+        self.maybe_mint_membership(&reverie_id, &user_id);
+        new_balance
     }
 
 
@@ -108,35 +323,92 @@ impl PaymentContract {
     fn get_balances_for_reverie(&mut self, reverie_id: &str) -> LookupMap<AccountId, u128> {
         self.require_reverie_exists(reverie_id);
         self.reverie_balances.remove(reverie_id)
-            .unwrap_or_else(|| LookupMap::new(format!("b:{}", reverie_id).as_bytes()))
+            .unwrap_or_else(|| self.new_reverie_balances())
     }
 
     // Records Usage Spend for a user for a specific ReverieId.
-    pub fn record_spend(&mut self, reverie_id: String, user_id: AccountId, amount_to_spend: U128) {
-        // Only callable by the trusted account.
-        assert_eq!(
-            env::predecessor_account_id(),
-            self.trusted_account,
-            "Only the trusted account can call this method"
+    // `category` (e.g. "inference", "storage") adds the spend to `get_spend_by_category` totals.
+    // `evidence_hash` is the hash of the service's off-chain usage log backing the charge,
+    // kept with the Spend event and spend record so the bill can be audited against the log.
+    pub fn record_spend(
+        &mut self,
+        reverie_id: String,
+        user_id: AccountId,
+        amount_to_spend: U128,
+        category: Option<String>,
+        evidence_hash: Option<Base58CryptoHash>,
+    ) {
+        // Only callable by the trusted account or one of the reverie's spenders.
+        self.assert_can_record_spend(&reverie_id);
+
+        self.internal_record_spend(&reverie_id, &user_id, amount_to_spend.0, category, evidence_hash, true);
+    }
+
+    // Records a spend and pays the spent amount out to the caller, e.g. a PasskeyController
+    // topping up a user's prepaid execution balance from their reverie credits.
+    pub fn record_spend_with_payout(&mut self, reverie_id: String, user_id: AccountId, amount_to_spend: U128) {
+        self.assert_can_record_spend(&reverie_id);
+        let spender_id = env::predecessor_account_id();
+        self.acquire_user_lock(&user_id);
+        let spent = self.internal_record_spend(&reverie_id, &user_id, amount_to_spend.0, None, None, false);
+        self.payout(&reverie_id, spender_id, spent).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(locks::GAS_FOR_ON_PAYOUT_RESULT)
+                .on_spend_payout_transfer(reverie_id, user_id, U128(spent)),
         );
+    }
 
-        let mut user_balances = self.get_balances_for_reverie(&reverie_id);
-        let current_balance = *user_balances.get(&user_id).unwrap_or(&0);
+    // internal method to deduct a spend from a user's balance. Returns the amount spent after rounding.
+    // Spends whose amount left the contract (payouts) aren't `disputable`, as a refund would be unbacked.
+    fn internal_record_spend(
+        &mut self,
+        reverie_id: &str,
+        user_id: &AccountId,
+        amount_to_spend: u128,
+        category: Option<String>,
+        evidence_hash: Option<Base58CryptoHash>,
+        disputable: bool,
+    ) -> u128 {
+        self.assert_not_frozen(reverie_id);
+        if let Some(category) = category.as_deref() {
+            self.assert_valid_spend_category(reverie_id, category);
+        }
+        let amount_to_spend = self.get_rounding_policy(reverie_id).round_spend(amount_to_spend);
+        let mut user_balances = self.get_balances_for_reverie(reverie_id);
+        let current_balance = *user_balances.get(user_id).unwrap_or(&0);
         assert!(
-            current_balance >= amount_to_spend.0,
+            current_balance >= amount_to_spend,
             "Insufficient balance to record spend. User {} has {}, needed {} for reverie {}",
-            user_id, current_balance, amount_to_spend.0, reverie_id
+            user_id, current_balance, amount_to_spend, reverie_id
         );
 
-        let new_balance = current_balance - amount_to_spend.0;
+        let new_balance = current_balance - amount_to_spend;
         if new_balance == 0 {
-            user_balances.remove(&user_id);
+            user_balances.remove(user_id);
         } else {
             user_balances.insert(user_id.clone(), new_balance);
         }
 
-        self.reverie_balances.insert(reverie_id.clone(), user_balances);
-        log!("Recorded spend of {} for user {} on reverie {}", amount_to_spend.0, user_id, reverie_id);
+        self.reverie_balances.insert(reverie_id.to_string(), user_balances);
+        log!("Recorded spend of {} for user {} on reverie {}", amount_to_spend, user_id, reverie_id);
+        let seq = self.emit_event(events::PaymentEvent::Spend {
+            reverie_id: reverie_id.to_string(),
+            user_id: user_id.clone(),
+            amount: U128(amount_to_spend),
+            new_balance: U128(new_balance),
+            category: category.clone(),
+            evidence_hash,
+        });
+        self.record_ledger_entry(reverie_id, user_id, ledger::LedgerEntry::Spend(amount_to_spend), seq);
+        self.record_daily_stats(reverie_id, user_id, &ledger::LedgerEntry::Spend(amount_to_spend));
+        if disputable {
+            self.record_disputable_spend(reverie_id, user_id, amount_to_spend, evidence_hash, seq);
+        }
+        if let Some(category) = category.as_deref() {
+            self.record_spend_category(reverie_id, category, amount_to_spend);
+        }
+        events::CreditEvent::spend(reverie_id, user_id, amount_to_spend).emit();
+        amount_to_spend
     }
 
     pub fn get_trusted_account(&self) -> AccountId {
@@ -150,9 +422,17 @@ impl PaymentContract {
     }
 
     pub fn withdraw(&mut self, reverie_id: String, amount: U128) {
+        self.assert_no_withdrawal_cooldown(&reverie_id);
+        let user_id = env::predecessor_account_id();
+        self.internal_withdraw(reverie_id, user_id.clone(), amount, user_id);
+    }
+
+    // internal method to debit a user's balance and pay it out to the receiver.
+    // The user stays locked until `on_withdraw_transfer` resolves the transfer.
+    fn internal_withdraw(&mut self, reverie_id: String, user_id: AccountId, amount: U128, receiver_id: AccountId) {
         self.require_reverie_exists(&reverie_id);
+        self.acquire_user_lock(&user_id);
 
-        let user_id = env::predecessor_account_id();
         let mut user_balances = self.get_balances_for_reverie(&reverie_id);
         let current_balance = *user_balances.get(&user_id).unwrap_or(&0);
 
@@ -172,53 +452,310 @@ impl PaymentContract {
 
         self.reverie_balances.insert(reverie_id.clone(), user_balances);
 
-        near_sdk::Promise::new(user_id.clone()).transfer(near_sdk::NearToken::from_yoctonear(amount.0));
+        self.payout(&reverie_id, receiver_id.clone(), amount.0)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(locks::GAS_FOR_ON_PAYOUT_RESULT)
+                    .on_withdraw_transfer(reverie_id.clone(), user_id.clone(), amount),
+            );
         log!(
-            "Withdrew {} yoctoNEAR for user {} on reverie {}. New balance: {}",
+            "Withdrew {} for user {} on reverie {} to {}. New balance: {}",
             amount.0,
             user_id,
             reverie_id,
+            receiver_id,
             new_balance
         );
+        let seq = self.emit_event(events::PaymentEvent::Withdraw {
+            reverie_id: reverie_id.clone(),
+            user_id: user_id.clone(),
+            amount,
+            new_balance: U128(new_balance),
+        });
+        self.record_ledger_entry(&reverie_id, &user_id, ledger::LedgerEntry::Withdrawal(amount.0), seq);
+        self.record_daily_stats(&reverie_id, &user_id, &ledger::LedgerEntry::Withdrawal(amount.0));
+        events::CreditEvent::burn(&reverie_id, &user_id, amount.0).emit();
     }
 
     /// Create a new reverie entry. Only the contract account can call this.
+    /// `denomination` defaults to NEAR and can't be changed once balances exist.
+    /// `listing` adds an icon, info URL and tags for marketplaces.
+    /// An attached deposit pays for the reverie's storage and the excess is refunded.
+    #[payable]
     pub fn create_reverie(
         &mut self,
         reverie_id: ReverieId,
         reverie_type: String,
         description: String,
         access_condition: AccessCondition,
+        denomination: Option<Denomination>,
+        listing: Option<ReverieListing>,
     ) {
         assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can create reveries");
-        assert!(self.reverie_metadata.get(&reverie_id).is_none(), "ReverieId '{}' already exists on reverie_metadata", reverie_id);
-        assert!(self.reverie_balances.get(&reverie_id).is_none(), "ReverieId '{}' already exists on reverie_balances", reverie_id);
+        // Ids are stored lowercased, so clients derive the same id regardless of casing
+        let reverie_id = normalize_reverie_id(&reverie_id).unwrap_or_else(|err| env::panic_str(&err));
         let metadata = ReverieMetadata {
             reverie_type,
             description,
             access_condition,
+            rounding_policy: rounding::RoundingPolicy::default(),
+            denomination: denomination.unwrap_or_default(),
+            listing: listing.unwrap_or_default(),
+            frozen: false,
+            credits_per_near: None,
         };
-        self.reverie_ids.push(reverie_id.clone());
-        self.reverie_metadata.insert(reverie_id.clone(), metadata);
-        self.reverie_balances.insert(reverie_id.clone(), LookupMap::new(format!("b:{}", reverie_id).as_bytes()));
+        self.refund_excess_storage_deposit(self.create_reverie_storage_bytes(&reverie_id, &metadata));
+        self.internal_create_reverie(reverie_id, metadata);
+    }
+
+    /// Creates a NEAR reverie and credits the attached deposit to the caller in one call,
+    /// logging a single `ReverieCreatedWithDeposit` event. The reverie's storage is paid out of
+    /// the deposit. Callers other than the trusted account can only create reveries under the
+    /// open registry's prefix, type and access condition, and become their admin.
+    /// Returns the caller's balance.
+    #[payable]
+    pub fn create_reverie_with_deposit(
+        &mut self,
+        reverie_id: ReverieId,
+        reverie_type: String,
+        description: String,
+        access_condition: AccessCondition,
+        listing: Option<ReverieListing>,
+    ) -> U128 {
+        let caller = env::predecessor_account_id();
+        let attached = env::attached_deposit().as_yoctonear();
+        let reverie_id = normalize_reverie_id(&reverie_id).unwrap_or_else(|err| env::panic_str(&err));
+        if caller != self.trusted_account {
+            let config = self
+                .open_registry
+                .as_ref()
+                .unwrap_or_else(|| env::panic_str("Only the trusted account can create reveries"));
+            assert!(
+                reverie_id.starts_with(&config.id_prefix),
+                "Reverie id must start with the open registry prefix {}",
+                config.id_prefix
+            );
+            assert!(
+                reverie_type == config.reverie_type && access_condition == config.access_condition,
+                "Open registry reveries must use its reverie type and access condition"
+            );
+        }
+        let metadata = ReverieMetadata {
+            reverie_type,
+            description,
+            access_condition,
+            rounding_policy: rounding::RoundingPolicy::default(),
+            denomination: Denomination::Near,
+            listing: listing.unwrap_or_default(),
+            frozen: false,
+            credits_per_near: None,
+        };
+        let storage_cost = storage::StorageCostEstimate::from_bytes(self.create_reverie_storage_bytes(&reverie_id, &metadata)).cost.0;
+        assert!(
+            attached > storage_cost,
+            "Deposit {} doesn't cover the storage cost {} of reverie {}",
+            attached, storage_cost, reverie_id
+        );
+        self.insert_reverie(reverie_id.clone(), metadata);
+        if caller != self.trusted_account {
+            self.reverie_admins.insert(reverie_id.clone(), caller.clone());
+        }
+        U128(self.internal_deposit_with_event(reverie_id, caller, attached - storage_cost, false, true))
+    }
+
+    /// Creates a reverie under `derive_reverie_id` of its metadata, so ids can't be squatted
+    /// and clients can predict them with `get_derived_reverie_id`. Returns the id.
+    #[payable]
+    pub fn create_reverie_derived(
+        &mut self,
+        reverie_type: String,
+        description: String,
+        access_condition: AccessCondition,
+        denomination: Option<Denomination>,
+        listing: Option<ReverieListing>,
+    ) -> ReverieId {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can create reveries");
+        let metadata = ReverieMetadata {
+            reverie_type,
+            description,
+            access_condition,
+            rounding_policy: rounding::RoundingPolicy::default(),
+            denomination: denomination.unwrap_or_default(),
+            listing: listing.unwrap_or_default(),
+            frozen: false,
+            credits_per_near: None,
+        };
+        let reverie_id = derive_reverie_id(&metadata);
+        self.refund_excess_storage_deposit(self.create_reverie_storage_bytes(&reverie_id, &metadata));
+        self.internal_create_reverie(reverie_id.clone(), metadata);
+        reverie_id
+    }
+
+    pub fn get_derived_reverie_id(
+        &self,
+        reverie_type: String,
+        description: String,
+        access_condition: AccessCondition,
+        denomination: Option<Denomination>,
+        listing: Option<ReverieListing>,
+    ) -> ReverieId {
+        derive_reverie_id(&ReverieMetadata {
+            reverie_type,
+            description,
+            access_condition,
+            rounding_policy: rounding::RoundingPolicy::default(),
+            denomination: denomination.unwrap_or_default(),
+            listing: listing.unwrap_or_default(),
+            frozen: false,
+            credits_per_near: None,
+        })
     }
 
-    /// For testing only
+    /// For testing only. Large registries should use `delete_reveries_range` instead.
     pub fn delete_all_reveries(&mut self) {
         assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can delete all reveries");
-        let reverie_ids = self.reverie_ids.clone();
+        let reverie_ids: Vec<ReverieId> = self.reverie_ids.drain(..).collect();
         for reverie_id in reverie_ids {
-            self.delete_reverie_admin(reverie_id);
+            self.internal_delete_reverie(reverie_id);
         }
+        self.deletion_cursor = 0;
     }
 
     pub fn delete_reverie_admin(&mut self, reverie_id: ReverieId) {
         assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can delete reveries");
-        self.reverie_metadata.remove(&reverie_id);
-        self.reverie_balances.remove(&reverie_id);
         if let Some(index) = self.reverie_ids.iter().position(|id| id == &reverie_id) {
             self.reverie_ids.remove(index);
         }
+        self.internal_delete_reverie(reverie_id);
+    }
+
+    /// Deletes up to `limit` reveries starting at `from_index` in `get_reverie_ids`, so a large
+    /// registry can be cleared across several transactions. Later ids shift down, so the next
+    /// batch starts at the same index, which is stored as the deletion cursor. Returns the number deleted.
+    pub fn delete_reveries_range(&mut self, from_index: u64, limit: u64) -> u64 {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can delete reveries");
+        let start = (from_index as usize).min(self.reverie_ids.len());
+        let end = start.saturating_add(limit as usize).min(self.reverie_ids.len());
+        let deleted: Vec<ReverieId> = self.reverie_ids.drain(start..end).collect();
+        for reverie_id in deleted.iter() {
+            self.internal_delete_reverie(reverie_id.clone());
+        }
+        self.deletion_cursor = start as u64;
+        deleted.len() as u64
+    }
+
+    /// Index the next `delete_reveries_range` batch should start from.
+    pub fn get_deletion_cursor(&self) -> u64 {
+        self.deletion_cursor
+    }
+
+    /// Purges a user's history on a reverie once their balance is empty: lifetime totals,
+    /// pending withdrawal, cached access and membership token.
+    pub fn purge_user(&mut self, reverie_id: ReverieId, user_id: AccountId) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can purge users");
+        let balance = self.get_balance(reverie_id.clone(), user_id.clone()).0;
+        assert!(balance == 0, "User {} still has a balance of {} on reverie {}", user_id, balance, reverie_id);
+        assert!(!self.user_lock_held(&user_id), "Another operation is in progress for user {}", user_id);
+        let key = self.user_key(&reverie_id, &user_id);
+        self.user_totals.remove(&key);
+        self.pending_withdrawals.remove(&key);
+        self.access_grants.remove(&key);
+        self.burn_membership(&reverie_id, &user_id);
+        log!("Purged user {} from reverie {}", user_id, reverie_id);
+    }
+
+    // internal method registering a reverie under an already normalized id
+    fn internal_create_reverie(&mut self, reverie_id: ReverieId, metadata: ReverieMetadata) {
+        self.insert_reverie(reverie_id.clone(), metadata);
+        self.emit_event(events::PaymentEvent::ReverieCreated { reverie_id });
+    }
+
+    // internal method registering a reverie without logging its creation
+    fn insert_reverie(&mut self, reverie_id: ReverieId, metadata: ReverieMetadata) {
+        metadata.listing.check().unwrap_or_else(|err| env::panic_str(&err));
+        assert!(self.reverie_metadata.get(&reverie_id).is_none(), "ReverieId '{}' already exists on reverie_metadata", reverie_id);
+        assert!(self.reverie_balances.get(&reverie_id).is_none(), "ReverieId '{}' already exists on reverie_balances", reverie_id);
+        self.assert_can_add_reverie();
+        // Plans of a deleted reverie stay cancellable under its old nonce until this replaces it
+        assert!(
+            !self.vesting_users.contains_key(&reverie_id),
+            "ReverieId '{}' still has vesting plans from before it was deleted",
+            reverie_id
+        );
+        self.reverie_ids.push(reverie_id.clone());
+        self.assign_reverie_nonce(&reverie_id);
+        self.index_reverie(&reverie_id, &metadata);
+        self.reverie_metadata.insert(reverie_id.clone(), metadata);
+        let user_balances = self.new_reverie_balances();
+        self.reverie_balances.insert(reverie_id.clone(), user_balances);
+    }
+
+    // internal method removing all of a reverie's state except its entry in `reverie_ids`.
+    // Per-user records are keyed by the reverie's nonce, which a re-created reverie replaces.
+    fn internal_delete_reverie(&mut self, reverie_id: ReverieId) {
+        self.release_reverie_revenue(&reverie_id);
+        if let Some(metadata) = self.reverie_metadata.remove(&reverie_id) {
+            self.unindex_reverie(&reverie_id, &metadata);
+        }
+        self.reverie_balances.remove(&reverie_id);
+        self.ledger_checkpoints.remove(&reverie_id);
+        self.vesting_principal.remove(&reverie_id);
+        self.reverie_spenders.remove(&reverie_id);
+        self.umbral_public_keys.remove(&reverie_id);
+        if let Some(mut grantees) = self.reencryption_grantees.remove(&reverie_id) {
+            grantees.clear();
+        }
+        self.deposit_hooks.remove(&reverie_id);
+        self.spend_by_category.remove(&reverie_id);
+        self.withdrawal_cooldowns.remove(&reverie_id);
+        self.access_cache_ttls.remove(&reverie_id);
+        self.membership_nft_reveries.remove(&reverie_id);
+        self.dispute_windows.remove(&reverie_id);
+        self.vesting_periods.remove(&reverie_id);
+        self.reverie_admins.remove(&reverie_id);
+        self.pending_reverie_transfers.remove(&reverie_id);
+        self.api_key_hashes.remove(&reverie_id);
+        if let Some(mut depositors) = self.reverie_depositors.remove(&reverie_id) {
+            depositors.clear();
+        }
+        self.reverie_shutdowns.remove(&reverie_id);
+        self.untracked_depositor_reveries.remove(&reverie_id);
+        if self.ft_reverie_id.as_ref() == Some(&reverie_id) {
+            self.ft_reverie_id = None;
+        }
+        self.emit_event(events::PaymentEvent::ReverieDeleted { reverie_id });
+    }
+
+    /// Updates a reverie's type, description and access condition, keeping its rounding policy.
+    /// `listing` replaces its listing; `None` keeps the current one.
+    pub fn update_reverie(
+        &mut self,
+        reverie_id: ReverieId,
+        reverie_type: String,
+        description: String,
+        access_condition: AccessCondition,
+        listing: Option<ReverieListing>,
+    ) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can update reveries");
+        let old_metadata = self
+            .reverie_metadata
+            .get(&reverie_id)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str(&format!("ReverieId {} not found in registry", reverie_id)));
+        let metadata = ReverieMetadata {
+            reverie_type,
+            description,
+            access_condition,
+            rounding_policy: old_metadata.rounding_policy.clone(),
+            denomination: old_metadata.denomination.clone(),
+            listing: listing.unwrap_or_else(|| old_metadata.listing.clone()),
+            frozen: old_metadata.frozen,
+            credits_per_near: old_metadata.credits_per_near,
+        };
+        metadata.listing.check().unwrap_or_else(|err| env::panic_str(&err));
+        self.unindex_reverie(&reverie_id, &old_metadata);
+        self.index_reverie(&reverie_id, &metadata);
+        self.reverie_metadata.insert(reverie_id, metadata);
     }
 
     pub fn get_reverie_metadata(&self, reverie_id: ReverieId) -> Option<ReverieMetadata> {
@@ -228,4 +765,17 @@ impl PaymentContract {
     pub fn get_reverie_ids(&self) -> Vec<ReverieId> {
         self.reverie_ids.clone()
     }
+
+    /// Whether `reverie_id` would be accepted by `create_reverie` (after lowercasing).
+    pub fn is_valid_reverie_id(&self, reverie_id: String) -> bool {
+        normalize_reverie_id(&reverie_id).is_ok()
+    }
+
+    /// Lints an `AccessCondition` JSON before it is passed to `create_reverie` or `update_reverie`.
+    pub fn validate_access_condition(&self, json: String) -> ValidationReport {
+        match reveries_types::validation::parse_json::<AccessCondition>(&json) {
+            Ok(condition) => condition.validate(),
+            Err(report) => report,
+        }
+    }
 }
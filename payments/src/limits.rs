@@ -0,0 +1,33 @@
+use crate::*;
+
+/// Number of reveries against the cap. `None` is unlimited.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReverieLimits {
+    pub reverie_count: u32,
+    pub max_reveries: Option<u32>,
+}
+
+#[near]
+impl PaymentContract {
+    /// Caps how many reveries can exist at once, protecting shared deployments from unbounded growth.
+    pub fn set_max_reveries(&mut self, max_reveries: Option<u32>) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can set limits");
+        self.max_reveries = max_reveries;
+    }
+
+    pub fn get_reverie_limits(&self) -> ReverieLimits {
+        ReverieLimits {
+            reverie_count: self.reverie_ids.len() as u32,
+            max_reveries: self.max_reveries,
+        }
+    }
+}
+
+impl PaymentContract {
+    pub(crate) fn assert_can_add_reverie(&self) {
+        if let Some(max_reveries) = self.max_reveries {
+            assert!((self.reverie_ids.len() as u32) < max_reveries, "ERR_MAX_REVERIES_REACHED");
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::*;
+use near_sdk::{Gas, PromiseResult};
+
+pub(crate) const GAS_FOR_ON_PAYOUT_RESULT: Gas = Gas::from_tgas(5);
+
+/// How long a lock holds if its callback never releases it, e.g. because the callback
+/// failed: 1 hour, far longer than a callback can be delayed.
+pub const USER_LOCK_TIMEOUT_NS: u64 = 3_600_000_000_000;
+
+#[near]
+impl PaymentContract {
+    /// Whether a payout for this user is awaiting its callback. Withdrawals and spend
+    /// payouts for the user are rejected until it resolves or `USER_LOCK_TIMEOUT_NS` passes.
+    pub fn is_user_locked(&self, user_id: AccountId) -> bool {
+        self.user_lock_held(&user_id)
+    }
+
+    /// Releases a user's lock left behind by a callback that failed before releasing it.
+    pub fn force_release_user_lock(&mut self, user_id: AccountId) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can release user locks");
+        if self.user_locks.remove(&user_id).is_none() {
+            env::panic_str(&format!("User {} is not locked", user_id));
+        }
+        log!("Released lock of user {}", user_id);
+    }
+
+    /// Releases the user's lock, re-crediting the withdrawal if the transfer failed.
+    #[private]
+    pub fn on_withdraw_transfer(&mut self, reverie_id: ReverieId, user_id: AccountId, amount: U128) -> bool {
+        self.release_user_lock(&user_id);
+        self.update_user_totals(&reverie_id, &user_id, |totals| totals.locked = totals.locked.saturating_sub(amount.0));
+        let succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if !succeeded {
+            self.refund_failed_withdraw(&reverie_id, &user_id, amount.0);
+        }
+        succeeded
+    }
+
+    /// Releases the user's lock after a `record_spend_with_payout` transfer. The spend
+    /// stays recorded even if the transfer to the spender failed.
+    #[private]
+    pub fn on_spend_payout_transfer(&mut self, reverie_id: ReverieId, user_id: AccountId, amount: U128) -> bool {
+        self.release_user_lock(&user_id);
+        let succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if !succeeded {
+            log!("Payout of {} for user {} on reverie {} failed", amount.0, user_id, reverie_id);
+        }
+        succeeded
+    }
+}
+
+impl PaymentContract {
+    // internal method locking a user for the duration of a payout and its callback,
+    // so a second payout can't run against a balance whose transfer may still be refunded
+    pub(crate) fn acquire_user_lock(&mut self, user_id: &AccountId) {
+        assert!(!self.user_lock_held(user_id), "Another operation is in progress for user {}", user_id);
+        self.user_locks.insert(user_id.clone(), clock::block_timestamp());
+    }
+
+    pub(crate) fn user_lock_held(&self, user_id: &AccountId) -> bool {
+        self.user_locks
+            .get(user_id)
+            .is_some_and(|locked_at| clock::block_timestamp() <= locked_at.saturating_add(USER_LOCK_TIMEOUT_NS))
+    }
+
+    pub(crate) fn release_user_lock(&mut self, user_id: &AccountId) {
+        self.user_locks.remove(user_id);
+    }
+
+    // internal method reversing a withdrawal whose transfer failed
+    pub(crate) fn refund_failed_withdraw(&mut self, reverie_id: &str, user_id: &AccountId, amount: u128) {
+        // The reverie may have been deleted while the transfer was in flight
+        let Some(mut user_balances) = self.reverie_balances.remove(reverie_id) else {
+            log!("Withdraw of {} for user {} failed and reverie {} no longer exists", amount, user_id, reverie_id);
+            return;
+        };
+        let new_balance = *user_balances.get(user_id).unwrap_or(&0) + amount;
+        user_balances.insert(user_id.clone(), new_balance);
+        self.reverie_balances.insert(reverie_id.to_string(), user_balances);
+        self.track_depositor(reverie_id, user_id);
+        log!("Withdraw of {} for user {} on reverie {} failed and was refunded", amount, user_id, reverie_id);
+        let seq = self.emit_event(events::PaymentEvent::WithdrawRefunded {
+            reverie_id: reverie_id.to_string(),
+            user_id: user_id.clone(),
+            amount: U128(amount),
+            new_balance: U128(new_balance),
+        });
+        self.record_ledger_entry(reverie_id, user_id, ledger::LedgerEntry::WithdrawalRefund(amount), seq);
+        self.record_daily_stats(reverie_id, user_id, &ledger::LedgerEntry::WithdrawalRefund(amount));
+        events::CreditEvent::mint(reverie_id, user_id, amount).emit();
+    }
+}
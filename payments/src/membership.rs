@@ -0,0 +1,165 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+pub const NFT_METADATA_SPEC: &str = "nft-1.0.0";
+
+/// NEP-171 token proving a user has deposited into a reverie. Ids are `{reverie_id}:{user_id}`.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MembershipToken {
+    pub token_id: String,
+    pub owner_id: AccountId,
+    pub metadata: MembershipTokenMetadata,
+}
+
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MembershipTokenMetadata {
+    pub title: String,
+    pub issued_at: U64, // block timestamp in milliseconds, as in NEP-177
+}
+
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct NFTContractMetadata {
+    pub spec: String,
+    pub name: String,
+    pub symbol: String,
+}
+
+/// Non-transferable NEP-171 membership tokens, minted on a user's first deposit into a
+/// reverie with membership NFTs enabled and burned by `purge_user`, so other contracts
+/// can gate features on `nft_token` ownership.
+#[near]
+impl PaymentContract {
+    pub fn set_membership_nft(&mut self, reverie_id: ReverieId, enabled: bool) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can set membership NFTs");
+        self.require_reverie_exists(&reverie_id);
+        if enabled {
+            self.membership_nft_reveries.insert(reverie_id);
+        } else {
+            self.membership_nft_reveries.remove(&reverie_id);
+        }
+    }
+
+    pub fn is_membership_nft_enabled(&self, reverie_id: ReverieId) -> bool {
+        self.membership_nft_reveries.contains(&reverie_id)
+    }
+
+    pub fn nft_token(&self, token_id: String) -> Option<MembershipToken> {
+        let (reverie_id, user_id) = token_id.split_once(':')?;
+        let user_id: AccountId = user_id.parse().ok()?;
+        self.membership_token(reverie_id, &user_id)
+    }
+
+    pub fn nft_tokens_for_owner(&self, account_id: AccountId, from_index: Option<U128>, limit: Option<u64>) -> Vec<MembershipToken> {
+        self.owner_memberships(&account_id)
+            .skip(from_index.map_or(0, |index| index.0 as usize))
+            .take(limit.map_or(usize::MAX, |limit| limit as usize))
+            .filter_map(|reverie_id| self.membership_token(reverie_id, &account_id))
+            .collect()
+    }
+
+    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        U128(self.owner_memberships(&account_id).count() as u128)
+    }
+
+    pub fn nft_metadata(&self) -> NFTContractMetadata {
+        NFTContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: "Reverie memberships".to_string(),
+            symbol: "REVERIE".to_string(),
+        }
+    }
+
+    #[payable]
+    pub fn nft_transfer(&mut self, receiver_id: AccountId, token_id: String, approval_id: Option<u64>, memo: Option<String>) {
+        let _ = (receiver_id, token_id, approval_id, memo);
+        env::panic_str("Membership tokens are non-transferable");
+    }
+
+    #[payable]
+    pub fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> bool {
+        let _ = (receiver_id, token_id, approval_id, memo, msg);
+        env::panic_str("Membership tokens are non-transferable");
+    }
+}
+
+impl PaymentContract {
+    // Ids of the reveries an owner holds a token of. Entries minted by a reverie that has since
+    // been deleted and re-created carry the old nonce and are skipped.
+    fn owner_memberships<'a>(&'a self, owner_id: &AccountId) -> impl Iterator<Item = &'a ReverieId> + 'a {
+        self.memberships_by_owner
+            .get(owner_id)
+            .into_iter()
+            .flat_map(|memberships| memberships.iter())
+            .filter(|(reverie_id, nonce)| self.reverie_nonce(reverie_id) == *nonce)
+            .map(|(reverie_id, _)| reverie_id)
+    }
+
+    fn membership_token(&self, reverie_id: &str, user_id: &AccountId) -> Option<MembershipToken> {
+        let issued_at_ms = *self.memberships.get(&self.user_key(reverie_id, user_id))?;
+        Some(MembershipToken {
+            token_id: format!("{}:{}", reverie_id, user_id),
+            owner_id: user_id.clone(),
+            metadata: MembershipTokenMetadata {
+                title: format!("{} member", reverie_id),
+                issued_at: U64(issued_at_ms),
+            },
+        })
+    }
+
+    // internal method minting the user's membership token on their first deposit
+    pub(crate) fn maybe_mint_membership(&mut self, reverie_id: &str, user_id: &AccountId) {
+        let key = self.user_key(reverie_id, user_id);
+        if !self.membership_nft_reveries.contains(reverie_id) || self.memberships.contains_key(&key) {
+            return;
+        }
+        let nonce = key.0;
+        self.memberships.insert(key, env::block_timestamp_ms());
+        if !self.memberships_by_owner.contains_key(user_id) {
+            let memberships = self.new_owner_memberships();
+            self.memberships_by_owner.insert(user_id.clone(), memberships);
+        }
+        if let Some(memberships) = self.memberships_by_owner.get_mut(user_id) {
+            memberships.insert((reverie_id.to_string(), nonce));
+        }
+        emit_nft_event("nft_mint", user_id, reverie_id);
+    }
+
+    pub(crate) fn burn_membership(&mut self, reverie_id: &str, user_id: &AccountId) {
+        let key = self.user_key(reverie_id, user_id);
+        let nonce = key.0;
+        if self.memberships.remove(&key).is_none() {
+            return;
+        }
+        if let Some(memberships) = self.memberships_by_owner.get_mut(user_id) {
+            memberships.remove(&(reverie_id.to_string(), nonce));
+        }
+        if self.memberships_by_owner.get(user_id).is_some_and(|memberships| memberships.is_empty()) {
+            self.memberships_by_owner.remove(user_id);
+        }
+        emit_nft_event("nft_burn", user_id, reverie_id);
+    }
+}
+
+// Logs a NEP-171 `nft_mint`/`nft_burn` event, so wallets and indexers pick up memberships.
+fn emit_nft_event(event: &str, owner_id: &AccountId, reverie_id: &str) {
+    let log = near_sdk::serde_json::json!({
+        "standard": "nep171",
+        "version": "1.0.0",
+        "event": event,
+        "data": [{
+            "owner_id": owner_id,
+            "token_ids": [format!("{}:{}", reverie_id, owner_id)],
+        }],
+    });
+    env::log_str(&format!("EVENT_JSON:{}", log));
+}
@@ -0,0 +1,45 @@
+use crate::*;
+use reveries_types::ReverieMetadataV1;
+
+// State layout of the first deployed payments contract.
+#[near(serializers = [borsh])]
+struct PaymentContractV1 {
+    greeting: String,
+    trusted_account: AccountId,
+    reverie_balances: LookupMap<ReverieId, LookupMap<AccountId, u128>>,
+    reverie_ids: Vec<ReverieId>,
+    reverie_metadata: LookupMap<ReverieId, ReverieMetadata>,
+}
+
+#[near]
+impl PaymentContract {
+    /// Migrates state written by the first deployed contract, giving every later setting its
+    /// default. Stored reverie metadata is rewritten in the current layout and indexed for
+    /// discovery. Existing balance maps keep their `b:{reverie_id}` prefixes (each map stores its
+    /// own prefix), only new reveries use `StorageKey::ReverieBalances`.
+    /// Call through `upgrade(.., Some("migrate"))`.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old: PaymentContractV1 = env::state_read().unwrap_or_else(|| env::panic_str("Failed to read old state"));
+        let mut contract = Self::new(old.trusted_account);
+        contract.greeting = old.greeting;
+        contract.reverie_balances = old.reverie_balances;
+        contract.reverie_ids = old.reverie_ids;
+        contract.reverie_metadata = old.reverie_metadata;
+
+        let legacy_metadata: LookupMap<ReverieId, ReverieMetadataV1> = LookupMap::new(b"r");
+        for reverie_id in contract.reverie_ids.clone() {
+            contract.assign_reverie_nonce(&reverie_id);
+            let Some(old_metadata) = legacy_metadata.get(&reverie_id).cloned() else {
+                continue;
+            };
+            // Their depositors predate `track_depositor`, so a shutdown can't know it refunded everyone
+            contract.untracked_depositor_reveries.insert(reverie_id.clone());
+            let metadata = ReverieMetadata::from(old_metadata);
+            contract.index_reverie(&reverie_id, &metadata);
+            contract.reverie_metadata.insert(reverie_id, metadata);
+        }
+        contract
+    }
+}
@@ -0,0 +1,69 @@
+use crate::*;
+use crate::storage::StorageCostEstimate;
+
+/// Lets anyone create a reverie by depositing to an unknown id starting with `id_prefix`.
+/// The reverie gets `reverie_type` and `access_condition`, and the depositor as its admin.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpenRegistryConfig {
+    pub id_prefix: String,
+    pub reverie_type: String,
+    pub access_condition: AccessCondition,
+}
+
+#[near]
+impl PaymentContract {
+    /// Enables the open registry, or disables it with `None`. Reveries it created are kept.
+    pub fn set_open_registry(&mut self, config: Option<OpenRegistryConfig>) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can configure the open registry");
+        if let Some(config) = &config {
+            assert!(
+                !config.id_prefix.is_empty() && normalize_reverie_id(&config.id_prefix).as_ref() == Ok(&config.id_prefix),
+                "Open registry prefix must be a valid lowercase reverie id"
+            );
+            if let Some(error) = config.access_condition.validate().errors.first() {
+                env::panic_str(&format!("Invalid open registry access condition {}: {}", error.field, error.message));
+            }
+        }
+        self.open_registry = config;
+    }
+
+    pub fn get_open_registry(&self) -> Option<OpenRegistryConfig> {
+        self.open_registry.clone()
+    }
+}
+
+impl PaymentContract {
+    // internal method creating an unknown reverie under the open registry prefix, owned by
+    // `payer`. Returns the storage cost taken from `deposit`, or None if nothing was created.
+    pub(crate) fn auto_register_reverie(&mut self, reverie_id: &str, payer: &AccountId, deposit: u128) -> Option<u128> {
+        let config = self.open_registry.clone()?;
+        if !reverie_id.starts_with(&config.id_prefix) || self.reverie_metadata.contains_key(reverie_id) {
+            return None;
+        }
+        let normalized = normalize_reverie_id(reverie_id).unwrap_or_else(|err| env::panic_str(&err));
+        assert_eq!(normalized, reverie_id, "Open registry ids must be lowercase");
+        let metadata = ReverieMetadata {
+            reverie_type: config.reverie_type,
+            description: String::new(),
+            access_condition: config.access_condition,
+            rounding_policy: rounding::RoundingPolicy::default(),
+            denomination: Denomination::Near,
+            listing: ReverieListing::default(),
+            frozen: false,
+            credits_per_near: None,
+        };
+        let storage_cost = StorageCostEstimate::from_bytes(self.create_reverie_storage_bytes(reverie_id, &metadata)).cost.0;
+        assert!(
+            deposit > storage_cost,
+            "Deposit {} doesn't cover the storage cost {} of reverie {}",
+            deposit, storage_cost, reverie_id
+        );
+        self.insert_reverie(normalized, metadata);
+        if payer != &self.trusted_account {
+            self.reverie_admins.insert(reverie_id.to_string(), payer.clone());
+        }
+        log!("Reverie {} registered by {}", reverie_id, payer);
+        Some(storage_cost)
+    }
+}
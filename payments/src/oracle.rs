@@ -0,0 +1,139 @@
+use crate::*;
+use near_sdk::json_types::U64;
+use near_sdk::{ext_contract, Gas, PromiseError};
+
+const GAS_FOR_GET_PRICE_DATA: Gas = Gas::from_tgas(10);
+const GAS_FOR_ON_PRICE_FOR_USAGE: Gas = Gas::from_tgas(15);
+
+/// Oracle settings used to convert USD-denominated usage into yoctoNEAR.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriceOracleConfig {
+    pub oracle_id: AccountId, // e.g. priceoracle.near
+    pub asset_id: String, // e.g. wrap.near
+    pub max_staleness_ns: U64,
+}
+
+// Price format returned by priceoracle.near
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct Price {
+    pub multiplier: U128,
+    pub decimals: u8,
+}
+
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct AssetOptionalPrice {
+    pub asset_id: String,
+    pub price: Option<Price>,
+}
+
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct PriceData {
+    pub timestamp: U64,
+    pub recency_duration_sec: u32,
+    pub prices: Vec<AssetOptionalPrice>,
+}
+
+impl PriceData {
+    /// Earliest time the reports behind `prices` may date from. priceoracle.near stamps
+    /// `timestamp` with the block it answered in and aggregates the reports of the
+    /// `recency_duration_sec` before it, so `timestamp` alone says nothing about their age.
+    pub fn reported_since(&self) -> u64 {
+        self.timestamp.0.saturating_sub(self.recency_duration_sec as u64 * 1_000_000_000)
+    }
+}
+
+#[ext_contract(ext_price_oracle)]
+pub trait PriceOracle {
+    fn get_price_data(&self, asset_ids: Option<Vec<String>>) -> PriceData;
+}
+
+/// Converts USD cents to yoctoNEAR at the given oracle price, rounding up so the
+/// service is never under-charged.
+/// For wrap.near the oracle reports `decimals = 24 + 4`, so one NEAR is worth
+/// `multiplier / 10^4` USD and one cent is `10^(decimals - 2) / multiplier` yoctoNEAR.
+pub fn usd_cents_to_yocto(cents: u64, price: &Price) -> u128 {
+    assert!(price.multiplier.0 > 0, "Oracle price multiplier must be greater than 0");
+    assert!(price.decimals >= 2, "Oracle price decimals must be at least 2");
+    let scale = 10u128
+        .checked_pow((price.decimals - 2) as u32)
+        .unwrap_or_else(|| env::panic_str("Oracle price decimals overflow"));
+    let numerator = (cents as u128)
+        .checked_mul(scale)
+        .unwrap_or_else(|| env::panic_str("USD amount overflow"));
+    numerator.div_ceil(price.multiplier.0)
+}
+
+#[near]
+impl PaymentContract {
+    /// Configures the price oracle. Only the contract account can call this.
+    pub fn set_price_oracle(&mut self, oracle_id: AccountId, asset_id: String, max_staleness_ns: U64) {
+        assert_eq!(env::predecessor_account_id(), env::current_account_id(), "Only the contract account can set the price oracle");
+        self.price_oracle = Some(PriceOracleConfig {
+            oracle_id,
+            asset_id,
+            max_staleness_ns,
+        });
+    }
+
+    pub fn get_price_oracle(&self) -> Option<PriceOracleConfig> {
+        self.price_oracle.clone()
+    }
+
+    /// Records a spend denominated in USD cents. The NEAR/USD price is fetched from the
+    /// configured oracle and the equivalent yoctoNEAR is deducted in the callback.
+    pub fn record_usage_usd(&mut self, reverie_id: ReverieId, user_id: AccountId, cents: U64, category: Option<String>) -> Promise {
+        self.assert_can_record_spend(&reverie_id);
+        self.require_reverie_exists(&reverie_id);
+        // Oracle prices convert USD to yoctoNEAR
+        self.assert_near_denominated(&reverie_id);
+        assert!(cents.0 > 0, "Usage amount must be greater than 0");
+        if let Some(category) = category.as_deref() {
+            self.assert_valid_spend_category(&reverie_id, category);
+        }
+        let oracle = self.price_oracle.clone().unwrap_or_else(|| env::panic_str("Price oracle is not configured"));
+
+        ext_price_oracle::ext(oracle.oracle_id)
+            .with_static_gas(GAS_FOR_GET_PRICE_DATA)
+            .get_price_data(Some(vec![oracle.asset_id]))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_PRICE_FOR_USAGE)
+                    .on_price_for_usage(reverie_id, user_id, cents, category),
+            )
+    }
+
+    #[private]
+    pub fn on_price_for_usage(
+        &mut self,
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        cents: U64,
+        category: Option<String>,
+        #[callback_result] price_data: Result<PriceData, PromiseError>,
+    ) -> U128 {
+        let oracle = self.price_oracle.clone().unwrap_or_else(|| env::panic_str("Price oracle is not configured"));
+        let price_data = price_data.unwrap_or_else(|_| env::panic_str("Failed to fetch price from oracle"));
+
+        let now = clock::block_timestamp();
+        let reported_since = price_data.reported_since();
+        assert!(
+            now.saturating_sub(reported_since) <= oracle.max_staleness_ns.0,
+            "Oracle price is stale. Reported since {}, block timestamp {}",
+            reported_since, now
+        );
+        let price = price_data
+            .prices
+            .into_iter()
+            .find(|p| p.asset_id == oracle.asset_id)
+            .and_then(|p| p.price)
+            .unwrap_or_else(|| env::panic_str(&format!("Oracle has no price for {}", oracle.asset_id)));
+
+        let amount = self.internal_record_spend(&reverie_id, &user_id, usd_cents_to_yocto(cents.0, &price), category, None, true);
+        log!("Recorded usage of {} USD cents as {} yoctoNEAR for user {} on reverie {}", cents.0, amount, user_id, reverie_id);
+        U128(amount)
+    }
+}
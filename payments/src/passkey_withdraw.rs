@@ -0,0 +1,200 @@
+use crate::*;
+use near_sdk::json_types::U64;
+use near_sdk::CurveType;
+
+/// Withdrawal, or new passkey binding, authorized by a passkey bound to `user_id`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug)]
+pub struct SignedWithdrawRequest {
+    pub user_id: AccountId,
+    pub passkey_pk: PublicKey,
+    pub nonce: U64,
+    pub valid_until: U64, // block timestamp in nanoseconds
+    pub signature: Base64VecU8, // ed25519 signature over the Borsh-serialized WithdrawPayload or BindPasskeyPayload
+}
+
+/// Canonical payload the passkey signs for `withdraw_with_passkey`.
+#[near(serializers = [borsh])]
+#[derive(Clone, Debug)]
+pub struct WithdrawPayload {
+    pub contract_id: AccountId,
+    pub reverie_id: ReverieId,
+    pub user_id: AccountId,
+    pub amount: u128,
+    pub receiver_id: AccountId,
+    pub nonce: u64,
+    pub valid_until: u64,
+}
+
+impl WithdrawPayload {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        near_sdk::borsh::to_vec(self).unwrap_or_else(|_| env::panic_str("Failed to serialize withdraw payload"))
+    }
+}
+
+/// Canonical payload a passkey already bound to `user_id` signs to bind `new_passkey_pk`
+/// with `bind_withdraw_passkey_with_signature`. Shares the user's withdraw nonces.
+#[near(serializers = [borsh])]
+#[derive(Clone, Debug)]
+pub struct BindPasskeyPayload {
+    pub contract_id: AccountId,
+    pub user_id: AccountId,
+    pub new_passkey_pk: PublicKey,
+    pub nonce: u64,
+    pub valid_until: u64,
+}
+
+impl BindPasskeyPayload {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        near_sdk::borsh::to_vec(self).unwrap_or_else(|_| env::panic_str("Failed to serialize bind payload"))
+    }
+}
+
+// Verifies an ed25519 signature made by `passkey_pk` over `message`.
+pub(crate) fn verify_ed25519(passkey_pk: &PublicKey, message: &[u8], signature: &[u8]) -> bool {
+    assert!(passkey_pk.curve_type() == CurveType::ED25519, "Passkey must be an ED25519 key");
+    let pk_bytes: [u8; 32] = passkey_pk.as_bytes()[1..]
+        .try_into()
+        .unwrap_or_else(|_| env::panic_str("Invalid passkey length"));
+    let signature_bytes: [u8; 64] = signature
+        .try_into()
+        .unwrap_or_else(|_| env::panic_str("Invalid signature length"));
+    env::ed25519_verify(&signature_bytes, message, &pk_bytes)
+}
+
+#[near]
+impl PaymentContract {
+    /// Binds a passkey to a user so it can authorize withdrawals for that user.
+    /// Only callable by the user; passkey-only users add passkeys with
+    /// `bind_withdraw_passkey_with_signature`.
+    pub fn bind_withdraw_passkey(&mut self, user_id: AccountId, passkey_pk: PublicKey) {
+        assert_eq!(env::predecessor_account_id(), user_id, "Only the user can bind a withdraw passkey");
+        self.internal_bind_withdraw_passkey(user_id, passkey_pk);
+    }
+
+    /// Binds `new_passkey_pk` to the user of `signed_request`, authorized by a signature over
+    /// the `BindPasskeyPayload` from a passkey already bound to them. Anyone can submit it.
+    pub fn bind_withdraw_passkey_with_signature(&mut self, new_passkey_pk: PublicKey, signed_request: SignedWithdrawRequest) {
+        let payload = BindPasskeyPayload {
+            contract_id: env::current_account_id(),
+            user_id: signed_request.user_id.clone(),
+            new_passkey_pk: new_passkey_pk.clone(),
+            nonce: signed_request.nonce.0,
+            valid_until: signed_request.valid_until.0,
+        };
+        self.assert_signed_by_bound_passkey(&signed_request, &payload.to_bytes());
+        self.internal_bind_withdraw_passkey(signed_request.user_id, new_passkey_pk);
+    }
+
+    pub fn unbind_withdraw_passkey(&mut self, passkey_pk: PublicKey) {
+        let user_id = self
+            .withdraw_passkeys
+            .get(&passkey_pk)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str("Passkey is not bound"));
+        let caller = env::predecessor_account_id();
+        assert!(
+            caller == user_id || caller == self.trusted_account,
+            "Only the user or the trusted account can unbind a withdraw passkey"
+        );
+        self.withdraw_passkeys.remove(&passkey_pk);
+    }
+
+    pub fn get_withdraw_passkey_owner(&self, passkey_pk: PublicKey) -> Option<AccountId> {
+        self.withdraw_passkeys.get(&passkey_pk).cloned()
+    }
+
+    /// Returns the last nonce used by a passkey withdrawal for this user.
+    pub fn get_withdraw_nonce(&self, user_id: AccountId) -> U64 {
+        U64(*self.withdraw_nonces.get(&user_id).unwrap_or(&0))
+    }
+
+    /// Returns the exact bytes a bound passkey must sign for `bind_withdraw_passkey_with_signature`.
+    pub fn get_bind_passkey_payload(&self, user_id: AccountId, new_passkey_pk: PublicKey, nonce: U64, valid_until: U64) -> Base64VecU8 {
+        let payload = BindPasskeyPayload {
+            contract_id: env::current_account_id(),
+            user_id,
+            new_passkey_pk,
+            nonce: nonce.0,
+            valid_until: valid_until.0,
+        };
+        Base64VecU8(payload.to_bytes())
+    }
+
+    /// Returns the exact bytes a passkey must sign for `withdraw_with_passkey`.
+    pub fn get_withdraw_payload(
+        &self,
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        amount: U128,
+        receiver_id: AccountId,
+        nonce: U64,
+        valid_until: U64,
+    ) -> Base64VecU8 {
+        let payload = WithdrawPayload {
+            contract_id: env::current_account_id(),
+            reverie_id,
+            user_id,
+            amount: amount.0,
+            receiver_id,
+            nonce: nonce.0,
+            valid_until: valid_until.0,
+        };
+        Base64VecU8(payload.to_bytes())
+    }
+
+    /// Withdraws a user's balance to `receiver_id`, authorized by a signature from a passkey
+    /// bound to that user. Anyone (e.g. a relayer) can submit the signed request.
+    pub fn withdraw_with_passkey(
+        &mut self,
+        reverie_id: ReverieId,
+        amount: U128,
+        receiver_id: AccountId,
+        signed_request: SignedWithdrawRequest,
+    ) {
+        self.assert_no_withdrawal_cooldown(&reverie_id);
+        let payload = WithdrawPayload {
+            contract_id: env::current_account_id(),
+            reverie_id: reverie_id.clone(),
+            user_id: signed_request.user_id.clone(),
+            amount: amount.0,
+            receiver_id: receiver_id.clone(),
+            nonce: signed_request.nonce.0,
+            valid_until: signed_request.valid_until.0,
+        };
+        self.assert_signed_by_bound_passkey(&signed_request, &payload.to_bytes());
+        self.internal_withdraw(reverie_id, signed_request.user_id, amount, receiver_id);
+    }
+}
+
+impl PaymentContract {
+    // internal method binding a passkey to a user, unless it is bound to someone else
+    fn internal_bind_withdraw_passkey(&mut self, user_id: AccountId, passkey_pk: PublicKey) {
+        assert!(passkey_pk.curve_type() == CurveType::ED25519, "Passkey must be an ED25519 key");
+        if let Some(existing) = self.withdraw_passkeys.get(&passkey_pk) {
+            assert_eq!(existing, &user_id, "Passkey is already bound to another user");
+        }
+        self.withdraw_passkeys.insert(passkey_pk, user_id);
+    }
+
+    // internal method checking a request was signed over `message` by a passkey bound to its
+    // user, and consuming its nonce
+    fn assert_signed_by_bound_passkey(&mut self, signed_request: &SignedWithdrawRequest, message: &[u8]) {
+        let bound_user = self
+            .withdraw_passkeys
+            .get(&signed_request.passkey_pk)
+            .unwrap_or_else(|| env::panic_str("Passkey is not bound to any user"));
+        assert_eq!(bound_user, &signed_request.user_id, "Passkey is not bound to this user");
+        assert!(
+            clock::block_timestamp() <= signed_request.valid_until.0,
+            "Withdraw request has expired"
+        );
+        let last_nonce = *self.withdraw_nonces.get(&signed_request.user_id).unwrap_or(&0);
+        assert!(signed_request.nonce.0 > last_nonce, "Withdraw request nonce already used");
+        assert!(
+            verify_ed25519(&signed_request.passkey_pk, message, &signed_request.signature.0),
+            "Invalid passkey signature for withdraw request"
+        );
+        self.withdraw_nonces.insert(signed_request.user_id.clone(), signed_request.nonce.0);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::*;
+use crate::passkey_withdraw::verify_ed25519;
+use near_sdk::json_types::U64;
+use near_sdk::CurveType;
+
+/// Spend authorization signed by a user's delegation key, capping what can be spent
+/// from their balance in a single `record_spend_with_permit` call by the spender it names.
+/// Permits are used in nonce order, each nonce following the last one used.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug)]
+pub struct SpendPermit {
+    pub max_amount: U128,
+    pub nonce: U64,
+    pub deadline: U64, // block timestamp in nanoseconds
+    pub signature: Base64VecU8, // ed25519 signature over the Borsh-serialized SpendPermitPayload
+}
+
+/// Canonical payload the delegation key signs for `record_spend_with_permit`.
+#[near(serializers = [borsh])]
+#[derive(Clone, Debug)]
+pub struct SpendPermitPayload {
+    pub contract_id: AccountId,
+    pub reverie_id: ReverieId,
+    pub user_id: AccountId,
+    pub spender_id: AccountId, // only account that can submit the permit
+    pub max_amount: u128,
+    pub nonce: u64,
+    pub deadline: u64,
+}
+
+impl SpendPermitPayload {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        near_sdk::borsh::to_vec(self).unwrap_or_else(|_| env::panic_str("Failed to serialize spend permit payload"))
+    }
+}
+
+#[near]
+impl PaymentContract {
+    /// Registers the ED25519 key the caller signs spend permits with, replacing any previous key.
+    pub fn set_delegation_key(&mut self, public_key: PublicKey) {
+        assert!(public_key.curve_type() == CurveType::ED25519, "Delegation key must be an ED25519 key");
+        self.delegation_keys.insert(env::predecessor_account_id(), public_key);
+    }
+
+    /// Removes the caller's delegation key, invalidating any outstanding permits.
+    pub fn remove_delegation_key(&mut self) {
+        self.delegation_keys.remove(&env::predecessor_account_id());
+    }
+
+    pub fn get_delegation_key(&self, user_id: AccountId) -> Option<PublicKey> {
+        self.delegation_keys.get(&user_id).cloned()
+    }
+
+    /// Returns the last nonce used by a spend permit for this user. The next permit must use this + 1.
+    pub fn get_permit_nonce(&self, user_id: AccountId) -> U64 {
+        U64(*self.permit_nonces.get(&user_id).unwrap_or(&0))
+    }
+
+    /// Returns the exact bytes a delegation key must sign for `record_spend_with_permit`.
+    pub fn get_spend_permit_payload(
+        &self,
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        spender_id: AccountId,
+        max_amount: U128,
+        nonce: U64,
+        deadline: U64,
+    ) -> Base64VecU8 {
+        let payload = SpendPermitPayload {
+            contract_id: env::current_account_id(),
+            reverie_id,
+            user_id,
+            spender_id,
+            max_amount: max_amount.0,
+            nonce: nonce.0,
+            deadline: deadline.0,
+        };
+        Base64VecU8(payload.to_bytes())
+    }
+
+    /// Records a spend authorized by the user's own signature rather than the trusted account,
+    /// so services can charge while the trusted account is unavailable. Only the permit's
+    /// spender can submit it, `amount` may not exceed its signed `max_amount`, and each permit
+    /// can be used once.
+    pub fn record_spend_with_permit(
+        &mut self,
+        reverie_id: ReverieId,
+        user_id: AccountId,
+        amount: U128,
+        permit: SpendPermit,
+    ) -> U128 {
+        self.require_reverie_exists(&reverie_id);
+        let delegation_key = self
+            .delegation_keys
+            .get(&user_id)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str("User has no delegation key"));
+        assert!(clock::block_timestamp() <= permit.deadline.0, "Spend permit has expired");
+        assert!(
+            amount.0 <= permit.max_amount.0,
+            "Spend amount {} exceeds permit cap {}",
+            amount.0, permit.max_amount.0
+        );
+        let last_nonce = *self.permit_nonces.get(&user_id).unwrap_or(&0);
+        assert!(permit.nonce.0 > last_nonce, "Spend permit nonce already used");
+        assert_eq!(permit.nonce.0, last_nonce + 1, "Spend permit nonce must be {}", last_nonce + 1);
+
+        let payload = SpendPermitPayload {
+            contract_id: env::current_account_id(),
+            reverie_id: reverie_id.clone(),
+            user_id: user_id.clone(),
+            spender_id: env::predecessor_account_id(),
+            max_amount: permit.max_amount.0,
+            nonce: permit.nonce.0,
+            deadline: permit.deadline.0,
+        };
+        assert!(
+            verify_ed25519(&delegation_key, &payload.to_bytes(), &permit.signature.0),
+            "Invalid delegation key signature for spend permit"
+        );
+
+        self.permit_nonces.insert(user_id.clone(), permit.nonce.0);
+        U128(self.internal_record_spend(&reverie_id, &user_id, amount.0, None, None, true))
+    }
+}
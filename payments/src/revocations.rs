@@ -0,0 +1,63 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// Why and when a user was denied access to a reverie regardless of its access condition.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Revocation {
+    pub reason: String,
+    pub revoked_by: AccountId,
+    pub revoked_at: U64,
+}
+
+#[near]
+impl PaymentContract {
+    /// Denies `user_id` access to a reverie even if they meet its access condition, e.g. for
+    /// abuse. `check_access` refuses them and cached grants stop counting until reinstated.
+    pub fn revoke_access(&mut self, reverie_id: ReverieId, user_id: AccountId, reason: String) {
+        self.assert_reverie_admin(&reverie_id, "Only the reverie admin can revoke access");
+        self.require_reverie_exists(&reverie_id);
+        let key = self.user_key(&reverie_id, &user_id);
+        self.access_grants.remove(&key);
+        self.access_revocations.insert(
+            key,
+            Revocation {
+                reason: reason.clone(),
+                revoked_by: env::predecessor_account_id(),
+                revoked_at: U64(clock::block_timestamp()),
+            },
+        );
+        self.emit_event(events::PaymentEvent::AccessRevoked { reverie_id, user_id, reason });
+    }
+
+    pub fn reinstate_access(&mut self, reverie_id: ReverieId, user_id: AccountId) {
+        self.assert_reverie_admin(&reverie_id, "Only the reverie admin can reinstate access");
+        if self.access_revocations.remove(&self.user_key(&reverie_id, &user_id)).is_none() {
+            env::panic_str(&format!("Access of user {} to reverie {} is not revoked", user_id, reverie_id));
+        }
+        self.emit_event(events::PaymentEvent::AccessReinstated { reverie_id, user_id });
+    }
+
+    pub fn get_access_revocation(&self, reverie_id: ReverieId, user_id: AccountId) -> Option<Revocation> {
+        self.access_revocations.get(&self.user_key(&reverie_id, &user_id)).cloned()
+    }
+
+    pub fn is_access_revoked(&self, reverie_id: ReverieId, user_id: AccountId) -> bool {
+        self.access_revoked(&reverie_id, &user_id)
+    }
+}
+
+impl PaymentContract {
+    pub(crate) fn access_revoked(&self, reverie_id: &str, user_id: &AccountId) -> bool {
+        self.access_revocations.contains_key(&self.user_key(reverie_id, user_id))
+    }
+
+    pub(crate) fn assert_access_not_revoked(&self, reverie_id: &str, user_id: &AccountId) {
+        if let Some(revocation) = self.access_revocations.get(&self.user_key(reverie_id, user_id)) {
+            env::panic_str(&format!(
+                "Access of user {} to reverie {} was revoked: {}",
+                user_id, reverie_id, revocation.reason
+            ));
+        }
+    }
+}
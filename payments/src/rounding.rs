@@ -0,0 +1,77 @@
+use crate::*;
+
+pub use reveries_types::RoundingPolicy;
+
+#[near]
+impl PaymentContract {
+    pub fn set_reverie_rounding_policy(&mut self, reverie_id: ReverieId, rounding_policy: RoundingPolicy) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can set rounding policies");
+        let mut metadata = self
+            .reverie_metadata
+            .get(&reverie_id)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str(&format!("ReverieId {} not found in registry", reverie_id)));
+        metadata.rounding_policy = rounding_policy;
+        self.reverie_metadata.insert(reverie_id, metadata);
+    }
+
+    /// Moves a user's balance into the fee pool if it is below the reverie's dust threshold.
+    /// Callable by the user, the trusted account or a reverie spender. Returns the amount moved.
+    pub fn consolidate_dust(&mut self, reverie_id: ReverieId, user_id: AccountId) -> U128 {
+        let caller = env::predecessor_account_id();
+        assert!(
+            caller == user_id || self.can_record_spend(&reverie_id, &caller),
+            "Only the user, the trusted account or a reverie spender can consolidate dust"
+        );
+        // The fee pool is held in NEAR
+        self.assert_near_denominated(&reverie_id);
+        self.assert_not_frozen(&reverie_id);
+        let dust_threshold = self.get_rounding_policy(&reverie_id).dust_threshold.0;
+        let mut user_balances = self.get_balances_for_reverie(&reverie_id);
+        let balance = *user_balances.get(&user_id).unwrap_or(&0);
+        assert!(
+            balance < dust_threshold,
+            "Balance {} is not below the dust threshold {}",
+            balance, dust_threshold
+        );
+        if balance == 0 {
+            self.reverie_balances.insert(reverie_id, user_balances);
+            return U128(0);
+        }
+
+        user_balances.remove(&user_id);
+        self.reverie_balances.insert(reverie_id.clone(), user_balances);
+        self.fee_pool += balance;
+        log!("Consolidated {} dust for user {} on reverie {}", balance, user_id, reverie_id);
+        let seq = self.emit_event(events::PaymentEvent::DustConsolidated {
+            reverie_id: reverie_id.clone(),
+            user_id: user_id.clone(),
+            amount: U128(balance),
+        });
+        // Dust is billed like a spend so the checkpoint still reconciles against balances
+        self.record_ledger_entry(&reverie_id, &user_id, ledger::LedgerEntry::Spend(balance), seq);
+        events::CreditEvent::spend(&reverie_id, &user_id, balance).emit();
+        U128(balance)
+    }
+
+    pub fn get_fee_pool(&self) -> U128 {
+        U128(self.fee_pool)
+    }
+
+    pub fn withdraw_fee_pool(&mut self, amount: U128, receiver_id: AccountId) {
+        assert_eq!(env::predecessor_account_id(), env::current_account_id(), "Only the contract account can withdraw the fee pool");
+        assert!(amount.0 > 0, "Withdrawal amount must be greater than 0");
+        assert!(amount.0 <= self.fee_pool, "Insufficient fee pool. Has {}, requested {}", self.fee_pool, amount.0);
+        self.fee_pool -= amount.0;
+        Promise::new(receiver_id).transfer(NearToken::from_yoctonear(amount.0));
+    }
+}
+
+impl PaymentContract {
+    pub(crate) fn get_rounding_policy(&self, reverie_id: &str) -> RoundingPolicy {
+        self.reverie_metadata
+            .get(reverie_id)
+            .map(|metadata| metadata.rounding_policy.clone())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,25 @@
+use crate::*;
+use crate::events::PaymentEvent;
+use crate::ledger::LedgerCheckpoint;
+use schemars::schema_for;
+
+#[near]
+impl PaymentContract {
+    /// JSON Schema of `AccessCondition`, for building and validating reverie forms client-side.
+    pub fn get_access_condition_schema(&self) -> String {
+        near_sdk::serde_json::to_string(&schema_for!(AccessCondition))
+            .unwrap_or_else(|_| env::panic_str("Failed to serialize schema"))
+    }
+
+    /// JSON Schemas of the contract's public types, keyed by type name.
+    /// The full contract ABI is generated by `cargo near build`.
+    pub fn get_abi(&self) -> String {
+        let schemas = near_sdk::serde_json::json!({
+            "ReverieMetadata": schema_for!(ReverieMetadata),
+            "AccessCondition": schema_for!(AccessCondition),
+            "LedgerCheckpoint": schema_for!(LedgerCheckpoint),
+            "PaymentEvent": schema_for!(PaymentEvent),
+        });
+        schemas.to_string()
+    }
+}
@@ -0,0 +1,33 @@
+use crate::*;
+use crate::limits::ReverieLimits;
+use crate::open_registry::OpenRegistryConfig;
+use crate::oracle::PriceOracleConfig;
+
+/// Every contract-level security setting in one response, for reviews and monitoring.
+/// Per-reverie settings are left out, as there is no bound on the number of reveries.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SecurityConfig {
+    pub trusted_account: AccountId,
+    pub authorized_spenders: Vec<AccountId>,
+    pub open_registry: Option<OpenRegistryConfig>,
+    pub price_oracle: Option<PriceOracleConfig>,
+    pub limits: ReverieLimits,
+    pub ft_reverie_id: Option<ReverieId>,
+    pub fee_pool: U128,
+}
+
+#[near]
+impl PaymentContract {
+    pub fn get_security_config(&self) -> SecurityConfig {
+        SecurityConfig {
+            trusted_account: self.trusted_account.clone(),
+            authorized_spenders: self.authorized_spenders.clone(),
+            open_registry: self.open_registry.clone(),
+            price_oracle: self.price_oracle.clone(),
+            limits: self.get_reverie_limits(),
+            ft_reverie_id: self.ft_reverie_id.clone(),
+            fee_pool: U128(self.fee_pool),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::*;
+use near_sdk::PromiseResult;
+
+#[near]
+impl PaymentContract {
+    /// Permanently stops a reverie: it is frozen for good and its depositors can be paid back
+    /// with `refund_depositors`, after which the reverie is deleted. Reveries created before the
+    /// upgrade that started tracking depositors are kept, so users it missed can still withdraw.
+    pub fn shutdown_reverie(&mut self, reverie_id: ReverieId) {
+        self.assert_reverie_admin(&reverie_id, "Only the reverie admin can shut down reveries");
+        self.require_reverie_exists(&reverie_id);
+        assert!(!self.is_reverie_shut_down(reverie_id.clone()), "Reverie {} is already shut down", reverie_id);
+        if let Some(metadata) = self.reverie_metadata.get_mut(&reverie_id) {
+            metadata.frozen = true;
+        }
+        self.reverie_shutdowns.insert(reverie_id.clone(), 0);
+        log!("Reverie {} shut down", reverie_id);
+        self.emit_event(events::PaymentEvent::ReverieShutDown { reverie_id });
+    }
+
+    /// Pays back the remaining balances of up to `limit` depositors of a shut down reverie.
+    /// Anyone can call it; the reverie is deleted once every refund has settled.
+    /// Users with a payout in flight are skipped until a later call. Returns the number refunded.
+    pub fn refund_depositors(&mut self, reverie_id: ReverieId, limit: u32) -> u32 {
+        assert!(self.is_reverie_shut_down(reverie_id.clone()), "Reverie {} is not shut down", reverie_id);
+        let selected: Vec<AccountId> = match self.reverie_depositors.get(&reverie_id) {
+            Some(depositors) => depositors
+                .iter()
+                .filter(|user_id| !self.user_lock_held(user_id))
+                .take(limit as usize)
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
+        let mut user_balances = self.get_balances_for_reverie(&reverie_id);
+        let mut refunds = Vec::new();
+        if let Some(depositors) = self.reverie_depositors.get_mut(&reverie_id) {
+            for user_id in selected {
+                depositors.remove(&user_id);
+                if let Some(balance) = user_balances.remove(&user_id) {
+                    refunds.push((user_id, balance));
+                }
+            }
+        }
+        if self.reverie_depositors.get(&reverie_id).is_some_and(|depositors| depositors.is_empty()) {
+            self.reverie_depositors.remove(&reverie_id);
+        }
+        self.reverie_balances.insert(reverie_id.clone(), user_balances);
+
+        let refunded = refunds.len() as u32;
+        for (user_id, amount) in refunds {
+            self.acquire_user_lock(&user_id);
+            self.payout(&reverie_id, user_id.clone(), amount).then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(locks::GAS_FOR_ON_PAYOUT_RESULT)
+                    .on_depositor_refund(reverie_id.clone(), user_id.clone(), U128(amount)),
+            );
+            let seq = self.emit_event(events::PaymentEvent::DepositorRefunded {
+                reverie_id: reverie_id.clone(),
+                user_id: user_id.clone(),
+                amount: U128(amount),
+            });
+            self.record_daily_stats(&reverie_id, &user_id, &ledger::LedgerEntry::Withdrawal(amount));
+            self.record_ledger_entry(&reverie_id, &user_id, ledger::LedgerEntry::Withdrawal(amount), seq);
+            events::CreditEvent::burn(&reverie_id, &user_id, amount).emit();
+        }
+        if let Some(in_flight) = self.reverie_shutdowns.get_mut(&reverie_id) {
+            *in_flight += refunded;
+        }
+        self.try_finalize_shutdown(&reverie_id);
+        refunded
+    }
+
+    /// Settles a shutdown refund, re-crediting it if the transfer failed so a later
+    /// `refund_depositors` call can retry it.
+    #[private]
+    pub fn on_depositor_refund(&mut self, reverie_id: ReverieId, user_id: AccountId, amount: U128) -> bool {
+        self.release_user_lock(&user_id);
+        self.update_user_totals(&reverie_id, &user_id, |totals| totals.locked = totals.locked.saturating_sub(amount.0));
+        if let Some(in_flight) = self.reverie_shutdowns.get_mut(&reverie_id) {
+            *in_flight = in_flight.saturating_sub(1);
+        }
+        let succeeded = matches!(env::promise_result(0), PromiseResult::Successful(_));
+        if !succeeded {
+            self.refund_failed_withdraw(&reverie_id, &user_id, amount.0);
+        }
+        self.try_finalize_shutdown(&reverie_id);
+        succeeded
+    }
+
+    pub fn is_reverie_shut_down(&self, reverie_id: ReverieId) -> bool {
+        self.reverie_shutdowns.contains_key(&reverie_id)
+    }
+
+    /// Accounts that have held a balance on the reverie and haven't been refunded by a shutdown.
+    pub fn get_depositor_count(&self, reverie_id: ReverieId) -> u32 {
+        self.reverie_depositors.get(&reverie_id).map_or(0, |depositors| depositors.len() as u32)
+    }
+}
+
+impl PaymentContract {
+    // internal method indexing a user credited on a reverie, so a shutdown can find them
+    pub(crate) fn track_depositor(&mut self, reverie_id: &str, user_id: &AccountId) {
+        if !self.reverie_depositors.contains_key(reverie_id) {
+            let depositors = self.new_reverie_depositors();
+            self.reverie_depositors.insert(reverie_id.to_string(), depositors);
+        }
+        if let Some(depositors) = self.reverie_depositors.get_mut(reverie_id) {
+            depositors.insert(user_id.clone());
+        }
+    }
+
+    // internal method rejecting an unfreeze of a shut down reverie
+    pub(crate) fn assert_not_shut_down(&self, reverie_id: &str) {
+        assert!(!self.reverie_shutdowns.contains_key(reverie_id), "Reverie {} is shut down", reverie_id);
+    }
+
+    // internal method hard deleting a shut down reverie once no depositor or refund is left.
+    // Reveries created before depositors were tracked may hold balances of users no set
+    // knows about, so they are never deleted and those users withdraw on their own.
+    fn try_finalize_shutdown(&mut self, reverie_id: &str) {
+        if self.untracked_depositor_reveries.contains(reverie_id)
+            || self.reverie_depositors.contains_key(reverie_id)
+            || self.reverie_shutdowns.get(reverie_id) != Some(&0)
+        {
+            return;
+        }
+        if let Some(index) = self.reverie_ids.iter().position(|id| id == reverie_id) {
+            self.reverie_ids.remove(index);
+        }
+        self.internal_delete_reverie(reverie_id.to_string());
+    }
+}
@@ -0,0 +1,110 @@
+use crate::*;
+
+/// Collections that can be exported and imported in chunks.
+/// User balances live in non-iterable maps, so balance chunks page over caller-provided user ids.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub enum SnapshotCollection {
+    Reveries,
+    Balances { reverie_id: ReverieId, user_ids: Vec<AccountId> },
+}
+
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReverieSnapshot {
+    pub reverie_id: ReverieId,
+    pub metadata: ReverieMetadata,
+    pub ledger: ledger::LedgerCheckpoint,
+}
+
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BalanceSnapshot {
+    pub reverie_id: ReverieId,
+    pub user_id: AccountId,
+    pub balance: U128,
+}
+
+#[near]
+impl PaymentContract {
+    /// Exports up to `limit` entries of a collection starting at `from`, as Borsh-encoded
+    /// `Vec<ReverieSnapshot>` or `Vec<BalanceSnapshot>`.
+    pub fn export_state_chunk(&self, collection: SnapshotCollection, from: u32, limit: u32) -> Base64VecU8 {
+        let bytes = match collection {
+            SnapshotCollection::Reveries => {
+                let chunk: Vec<ReverieSnapshot> = self
+                    .reverie_ids
+                    .iter()
+                    .skip(from as usize)
+                    .take(limit as usize)
+                    .filter_map(|reverie_id| {
+                        self.reverie_metadata.get(reverie_id).map(|metadata| ReverieSnapshot {
+                            reverie_id: reverie_id.clone(),
+                            metadata: metadata.clone(),
+                            ledger: self.ledger_checkpoints.get(reverie_id).cloned().unwrap_or_default(),
+                        })
+                    })
+                    .collect();
+                near_sdk::borsh::to_vec(&chunk)
+            }
+            SnapshotCollection::Balances { reverie_id, user_ids } => {
+                let user_balances = self
+                    .reverie_balances
+                    .get(&reverie_id)
+                    .unwrap_or_else(|| env::panic_str(&format!("ReverieId {} not found in balances", reverie_id)));
+                let chunk: Vec<BalanceSnapshot> = user_ids
+                    .into_iter()
+                    .skip(from as usize)
+                    .take(limit as usize)
+                    .filter_map(|user_id| {
+                        user_balances.get(&user_id).map(|balance| BalanceSnapshot {
+                            reverie_id: reverie_id.clone(),
+                            user_id,
+                            balance: U128(*balance),
+                        })
+                    })
+                    .collect();
+                near_sdk::borsh::to_vec(&chunk)
+            }
+        };
+        Base64VecU8(bytes.unwrap_or_else(|_| env::panic_str("Failed to serialize state chunk")))
+    }
+
+    /// Imports a chunk produced by `export_state_chunk`. Only the contract account can call this.
+    /// Existing entries are overwritten.
+    pub fn import_state_chunk(&mut self, collection: SnapshotCollection, chunk: Base64VecU8) {
+        assert_eq!(env::predecessor_account_id(), env::current_account_id(), "Only the contract account can import state");
+        match collection {
+            SnapshotCollection::Reveries => {
+                let entries: Vec<ReverieSnapshot> = near_sdk::borsh::from_slice(&chunk.0)
+                    .unwrap_or_else(|_| env::panic_str("Invalid reveries chunk"));
+                for entry in entries {
+                    if !self.reverie_ids.contains(&entry.reverie_id) {
+                        self.reverie_ids.push(entry.reverie_id.clone());
+                    }
+                    if self.reverie_balances.get(&entry.reverie_id).is_none() {
+                        let user_balances = self.new_reverie_balances();
+                        self.reverie_balances.insert(entry.reverie_id.clone(), user_balances);
+                        self.assign_reverie_nonce(&entry.reverie_id);
+                    }
+                    if let Some(old_metadata) = self.reverie_metadata.get(&entry.reverie_id).cloned() {
+                        self.unindex_reverie(&entry.reverie_id, &old_metadata);
+                    }
+                    self.index_reverie(&entry.reverie_id, &entry.metadata);
+                    self.reverie_metadata.insert(entry.reverie_id.clone(), entry.metadata);
+                    self.ledger_checkpoints.insert(entry.reverie_id, entry.ledger);
+                }
+            }
+            SnapshotCollection::Balances { .. } => {
+                let entries: Vec<BalanceSnapshot> = near_sdk::borsh::from_slice(&chunk.0)
+                    .unwrap_or_else(|_| env::panic_str("Invalid balances chunk"));
+                for entry in entries {
+                    let mut user_balances = self.get_balances_for_reverie(&entry.reverie_id);
+                    user_balances.insert(entry.user_id.clone(), entry.balance.0);
+                    self.reverie_balances.insert(entry.reverie_id.clone(), user_balances);
+                    self.track_depositor(&entry.reverie_id, &entry.user_id);
+                }
+            }
+        }
+    }
+}
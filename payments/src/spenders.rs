@@ -0,0 +1,89 @@
+use crate::*;
+
+#[near]
+impl PaymentContract {
+    /// Authorizes `spender_id` to record spends for this reverie only, so independent
+    /// services can share one deployment. Only the reverie admin can manage spenders.
+    pub fn add_reverie_spender(&mut self, reverie_id: ReverieId, spender_id: AccountId) {
+        self.assert_reverie_admin(&reverie_id, "Only the reverie admin can manage reverie spenders");
+        self.require_reverie_exists(&reverie_id);
+        let mut spenders = self.reverie_spenders.get(&reverie_id).cloned().unwrap_or_default();
+        assert!(!spenders.contains(&spender_id), "{} is already a spender for reverie {}", spender_id, reverie_id);
+        spenders.push(spender_id.clone());
+        self.reverie_spenders.insert(reverie_id.clone(), spenders);
+        log!("Added spender {} for reverie {}", spender_id, reverie_id);
+    }
+
+    pub fn remove_reverie_spender(&mut self, reverie_id: ReverieId, spender_id: AccountId) {
+        self.assert_reverie_admin(&reverie_id, "Only the reverie admin can manage reverie spenders");
+        let mut spenders = self.reverie_spenders.get(&reverie_id).cloned().unwrap_or_default();
+        let index = spenders
+            .iter()
+            .position(|id| id == &spender_id)
+            .unwrap_or_else(|| env::panic_str(&format!("{} is not a spender for reverie {}", spender_id, reverie_id)));
+        spenders.remove(index);
+        if spenders.is_empty() {
+            self.reverie_spenders.remove(&reverie_id);
+        } else {
+            self.reverie_spenders.insert(reverie_id.clone(), spenders);
+        }
+        log!("Removed spender {} for reverie {}", spender_id, reverie_id);
+    }
+
+    pub fn get_reverie_spenders(&self, reverie_id: ReverieId) -> Vec<AccountId> {
+        self.reverie_spenders.get(&reverie_id).cloned().unwrap_or_default()
+    }
+
+    /// Authorizes `spender_id` to record spends on every reverie, like the trusted account,
+    /// so several backend workers can bill users. Only the trusted account can manage them.
+    pub fn add_authorized_spender(&mut self, spender_id: AccountId) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can manage authorized spenders");
+        assert!(!self.authorized_spenders.contains(&spender_id), "{} is already an authorized spender", spender_id);
+        self.authorized_spenders.push(spender_id.clone());
+        log!("Added authorized spender {}", spender_id);
+    }
+
+    pub fn remove_authorized_spender(&mut self, spender_id: AccountId) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can manage authorized spenders");
+        let index = self
+            .authorized_spenders
+            .iter()
+            .position(|id| id == &spender_id)
+            .unwrap_or_else(|| env::panic_str(&format!("{} is not an authorized spender", spender_id)));
+        self.authorized_spenders.remove(index);
+        log!("Removed authorized spender {}", spender_id);
+    }
+
+    pub fn get_authorized_spenders(&self) -> Vec<AccountId> {
+        self.authorized_spenders.clone()
+    }
+
+    pub fn is_authorized_spender(&self, account_id: AccountId) -> bool {
+        self.authorized_spenders.contains(&account_id)
+    }
+
+    pub fn is_reverie_spender(&self, reverie_id: ReverieId, account_id: AccountId) -> bool {
+        self.can_record_spend(&reverie_id, &account_id)
+    }
+}
+
+impl PaymentContract {
+    // internal check: the trusted account and authorized spenders can spend on any reverie,
+    // reverie spenders only on their own
+    pub(crate) fn can_record_spend(&self, reverie_id: &str, account_id: &AccountId) -> bool {
+        account_id == &self.trusted_account
+            || self.authorized_spenders.contains(account_id)
+            || self
+                .reverie_spenders
+                .get(reverie_id)
+                .map(|spenders| spenders.contains(account_id))
+                .unwrap_or(false)
+    }
+
+    pub(crate) fn assert_can_record_spend(&self, reverie_id: &str) {
+        assert!(
+            self.can_record_spend(reverie_id, &env::predecessor_account_id()),
+            "Only the trusted account can call this method"
+        );
+    }
+}
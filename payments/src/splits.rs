@@ -0,0 +1,79 @@
+use crate::*;
+
+pub const MAX_SPLIT_BPS: u16 = 10_000;
+
+#[near]
+impl PaymentContract {
+    /// Routes `split_bps` (basis points) of every `deposit`, `deposit_for` and `ft_on_transfer`
+    /// into the reverie's revenue instead of the user's balance, e.g. for upfront fees.
+    /// `None` disables the split. `distribute` is never split.
+    pub fn set_deposit_split(&mut self, reverie_id: ReverieId, split_bps: Option<u16>) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can set deposit splits");
+        self.require_reverie_exists(&reverie_id);
+        match split_bps {
+            Some(split_bps) => {
+                assert!(split_bps <= MAX_SPLIT_BPS, "split_bps must be at most {}", MAX_SPLIT_BPS);
+                self.deposit_splits.insert(reverie_id, split_bps);
+            }
+            None => {
+                self.deposit_splits.remove(&reverie_id);
+            }
+        }
+    }
+
+    pub fn get_deposit_split(&self, reverie_id: ReverieId) -> Option<u16> {
+        self.deposit_splits.get(&reverie_id).copied()
+    }
+
+    pub fn get_reverie_revenue(&self, reverie_id: ReverieId) -> U128 {
+        U128(*self.reverie_revenue.get(&reverie_id).unwrap_or(&0))
+    }
+
+    /// Pays out revenue collected by deposit splits, in the reverie's denomination.
+    pub fn withdraw_reverie_revenue(&mut self, reverie_id: ReverieId, amount: U128, receiver_id: AccountId) -> Promise {
+        self.assert_reverie_admin(&reverie_id, "Only the reverie admin can withdraw reverie revenue");
+        assert!(amount.0 > 0, "Withdrawal amount must be greater than 0");
+        let revenue = *self.reverie_revenue.get(&reverie_id).unwrap_or(&0);
+        assert!(amount.0 <= revenue, "Insufficient reverie revenue. Has {}, requested {}", revenue, amount.0);
+        if revenue == amount.0 {
+            self.reverie_revenue.remove(&reverie_id);
+        } else {
+            self.reverie_revenue.insert(reverie_id.clone(), revenue - amount.0);
+        }
+        log!("Withdrew {} revenue of reverie {} to {}", amount.0, reverie_id, receiver_id);
+        self.payout(&reverie_id, receiver_id, amount.0)
+    }
+}
+
+impl PaymentContract {
+    // internal method taking the reverie's split out of a deposit. Returns the revenue share.
+    pub(crate) fn take_deposit_split(&mut self, reverie_id: &str, amount: u128) -> u128 {
+        let Some(split_bps) = self.deposit_splits.get(reverie_id).copied() else {
+            return 0;
+        };
+        let revenue = amount * split_bps as u128 / MAX_SPLIT_BPS as u128;
+        if revenue > 0 {
+            let total = self.reverie_revenue.get(reverie_id).unwrap_or(&0) + revenue;
+            self.reverie_revenue.insert(reverie_id.to_string(), total);
+        }
+        revenue
+    }
+
+    // internal method for reverie deletion: undrawn NEAR revenue moves to the fee pool and
+    // undrawn token revenue is paid out to the reverie admin, so it isn't stranded or
+    // inherited by a reverie recreated under the same id
+    pub(crate) fn release_reverie_revenue(&mut self, reverie_id: &str) {
+        self.deposit_splits.remove(reverie_id);
+        let Some(revenue) = self.reverie_revenue.remove(reverie_id) else {
+            return;
+        };
+        match self.reverie_metadata.get(reverie_id).map(|metadata| metadata.denomination.clone()) {
+            Some(Denomination::Ft { contract }) => {
+                let admin = self.reverie_admin(reverie_id);
+                log!("Reverie {} deleted with {} undrawn revenue in token {}, paid out to {}", reverie_id, revenue, contract, admin);
+                self.payout(reverie_id, admin, revenue);
+            }
+            _ => self.fee_pool += revenue,
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::*;
+
+/// Version leading every `dump_reverie_state` page, bumped when the dump layout changes.
+pub const STATE_DUMP_VERSION: u32 = 1;
+
+/// One page of `dump_reverie_state`. Balances page over the reverie's depositors; the page is
+/// the last one once `from + limit >= depositor_count`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReverieStateDump {
+    pub version: u32, // STATE_DUMP_VERSION
+    pub reverie_id: ReverieId,
+    pub metadata: ReverieMetadata,
+    pub ledger: ledger::LedgerCheckpoint,
+    pub admin: AccountId,
+    pub depositor_count: u32,
+    pub from: u32,
+    pub balances: Vec<(AccountId, U128)>,
+}
+
+#[near]
+impl PaymentContract {
+    /// Borsh-encoded `ReverieStateDump` of a reverie with the balances of up to `limit` of its
+    /// depositors starting at `from`, so its state can be rebuilt off-chain without raw trie access.
+    pub fn dump_reverie_state(&self, reverie_id: ReverieId, from: u32, limit: u32) -> Base64VecU8 {
+        let metadata = self
+            .reverie_metadata
+            .get(&reverie_id)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str(&format!("ReverieId {} not found in registry", reverie_id)));
+        let user_balances = self
+            .reverie_balances
+            .get(&reverie_id)
+            .unwrap_or_else(|| env::panic_str(&format!("ReverieId {} not found in balances", reverie_id)));
+        let (depositor_count, balances) = match self.reverie_depositors.get(&reverie_id) {
+            Some(depositors) => (
+                depositors.len(),
+                depositors
+                    .iter()
+                    .skip(from as usize)
+                    .take(limit as usize)
+                    .filter_map(|user_id| user_balances.get(user_id).map(|balance| (user_id.clone(), U128(*balance))))
+                    .collect(),
+            ),
+            None => (0, Vec::new()),
+        };
+        let dump = ReverieStateDump {
+            version: STATE_DUMP_VERSION,
+            reverie_id: reverie_id.clone(),
+            metadata,
+            ledger: self.ledger_checkpoints.get(&reverie_id).cloned().unwrap_or_default(),
+            admin: self.reverie_admin(&reverie_id),
+            depositor_count,
+            from,
+            balances,
+        };
+        Base64VecU8(near_sdk::borsh::to_vec(&dump).unwrap_or_else(|_| env::panic_str("Failed to serialize state dump")))
+    }
+}
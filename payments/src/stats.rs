@@ -0,0 +1,67 @@
+use crate::*;
+use crate::ledger::LedgerEntry;
+
+pub const NANOS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Totals for one UTC day, for a single reverie or all reveries.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DailyStats {
+    pub deposits: U128,
+    pub spends: U128,
+    pub withdrawals: U128,
+    pub active_users: u32, // distinct users that deposited, spent or withdrew
+}
+
+// `None` keys the rollup across all reveries, `Some` a reverie's creation nonce
+type StatsKey = (Option<u64>, u32);
+
+#[near]
+impl PaymentContract {
+    /// `day` counts UTC days since the Unix epoch, see `get_current_day`.
+    pub fn get_daily_stats(&self, reverie_id: ReverieId, day: u32) -> DailyStats {
+        self.daily_stats.get(&(Some(self.reverie_nonce(&reverie_id)), day)).cloned().unwrap_or_default()
+    }
+
+    pub fn get_global_daily_stats(&self, day: u32) -> DailyStats {
+        self.daily_stats.get(&(None, day)).cloned().unwrap_or_default()
+    }
+
+    pub fn get_current_day(&self) -> u32 {
+        current_day()
+    }
+}
+
+impl PaymentContract {
+    // internal method adding a balance change to today's rollups. Refunds are taken off
+    // the day they happen on, which may differ from the day of the original operation.
+    pub(crate) fn record_daily_stats(&mut self, reverie_id: &str, user_id: &AccountId, entry: &LedgerEntry) {
+        let day = current_day();
+        let counts_as_activity = matches!(
+            entry,
+            LedgerEntry::Deposit(_) | LedgerEntry::Spend(_) | LedgerEntry::Withdrawal(_) | LedgerEntry::VestingRefund(_)
+        );
+        for scope in [Some(self.reverie_nonce(reverie_id)), None] {
+            let first_activity = counts_as_activity
+                && self.daily_active_users.insert((scope, day, user_id.clone()));
+            let key: StatsKey = (scope, day);
+            let mut stats = self.daily_stats.get(&key).cloned().unwrap_or_default();
+            match entry {
+                LedgerEntry::Deposit(amount) => stats.deposits = U128(stats.deposits.0 + amount),
+                LedgerEntry::Spend(amount) => stats.spends = U128(stats.spends.0 + amount),
+                LedgerEntry::Withdrawal(amount) => stats.withdrawals = U128(stats.withdrawals.0 + amount),
+                LedgerEntry::WithdrawalRefund(amount) => stats.withdrawals = U128(stats.withdrawals.0.saturating_sub(*amount)),
+                LedgerEntry::SpendRefund(amount) => stats.spends = U128(stats.spends.0.saturating_sub(*amount)),
+                LedgerEntry::VestingRefund(amount) => stats.withdrawals = U128(stats.withdrawals.0 + amount),
+            }
+            if first_activity {
+                stats.active_users += 1;
+            }
+            self.daily_stats.insert(key, stats);
+        }
+    }
+}
+
+fn current_day() -> u32 {
+    (clock::block_timestamp() / NANOS_PER_DAY) as u32
+}
@@ -0,0 +1,206 @@
+use crate::*;
+use near_sdk::json_types::U64;
+use near_sdk::BorshStorageKey;
+
+/// Prefixes of collections created at runtime. Borsh encodes the variant as a leading
+/// 0x00.. byte, so these can't collide with the single-letter prefixes of the top-level
+/// collections, and a reverie index keeps keys short and unique however reverie ids look.
+#[near(serializers = [borsh])]
+#[derive(BorshStorageKey)]
+pub enum StorageKey {
+    ReverieBalances { reverie_index: u64 },
+    ReverieDepositors { reverie_index: u64 },
+    OwnerMemberships { reverie_index: u64 },
+    ReencryptionGrantees { reverie_index: u64 },
+}
+
+/// Key of a user's records on a reverie: the reverie's creation nonce rather than its id, so
+/// a reverie re-created under a deleted one's id doesn't inherit its users' totals,
+/// pending withdrawals, grants, revocations, vesting plans, memberships or re-encryption grants.
+pub type UserKey = (u64, AccountId);
+
+impl PaymentContract {
+    // internal method taking an index that is never handed out again
+    fn next_index(&mut self) -> u64 {
+        let reverie_index = self.next_reverie_index;
+        self.next_reverie_index += 1;
+        reverie_index
+    }
+
+    // internal method creating an empty balances map under a prefix that is never reused,
+    // so a re-created reverie id can't see balances left behind by a deleted one
+    pub(crate) fn new_reverie_balances(&mut self) -> LookupMap<AccountId, u128> {
+        LookupMap::new(StorageKey::ReverieBalances { reverie_index: self.next_index() })
+    }
+
+    // internal method creating an empty depositor set under a prefix that is never reused
+    pub(crate) fn new_reverie_depositors(&mut self) -> IterableSet<AccountId> {
+        IterableSet::new(StorageKey::ReverieDepositors { reverie_index: self.next_index() })
+    }
+
+    // internal method creating an empty set of an owner's memberships under a prefix that is never reused
+    pub(crate) fn new_owner_memberships(&mut self) -> IterableSet<(ReverieId, u64)> {
+        IterableSet::new(StorageKey::OwnerMemberships { reverie_index: self.next_index() })
+    }
+
+    // internal method creating an empty set of a reverie's re-encryption grantees under a prefix that is never reused
+    pub(crate) fn new_reencryption_grantees(&mut self) -> IterableSet<AccountId> {
+        IterableSet::new(StorageKey::ReencryptionGrantees { reverie_index: self.next_index() })
+    }
+
+    // internal method giving a reverie being created a fresh creation nonce
+    pub(crate) fn assign_reverie_nonce(&mut self, reverie_id: &str) {
+        let nonce = self.next_index();
+        self.reverie_nonces.insert(reverie_id.to_string(), nonce);
+    }
+
+    // internal method returning the creation nonce of a reverie. A deleted reverie keeps its
+    // nonce until its id is re-created; ids that never existed get one no reverie has.
+    pub(crate) fn reverie_nonce(&self, reverie_id: &str) -> u64 {
+        self.reverie_nonces.get(reverie_id).copied().unwrap_or(u64::MAX)
+    }
+
+    pub(crate) fn user_key(&self, reverie_id: &str, user_id: &AccountId) -> UserKey {
+        (self.reverie_nonce(reverie_id), user_id.clone())
+    }
+}
+
+/// Bytes the protocol charges per stored record on top of its key and value.
+pub const STORAGE_RECORD_OVERHEAD: u64 = 40;
+
+/// Storage an operation adds to the contract, and what it costs at the current byte price.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct StorageCostEstimate {
+    pub bytes: U64,
+    pub cost: U128,
+}
+
+impl StorageCostEstimate {
+    pub(crate) fn from_bytes(bytes: u64) -> Self {
+        Self {
+            bytes: U64(bytes),
+            cost: U128(bytes as u128 * env::storage_byte_cost().as_yoctonear()),
+        }
+    }
+}
+
+#[near]
+impl PaymentContract {
+    /// Storage a `deposit` by `user_id` would add: their balance and totals entries on the
+    /// first deposit, and the membership token if the reverie mints one. 0 for repeat deposits.
+    pub fn estimate_storage_cost_deposit(&self, reverie_id: ReverieId, user_id: AccountId) -> StorageCostEstimate {
+        self.require_reverie_exists(&reverie_id);
+        StorageCostEstimate::from_bytes(self.deposit_storage_bytes(&reverie_id, &user_id))
+    }
+
+    /// Storage `check_access` would add by caching a result for `user_id`. 0 if one is cached already.
+    pub fn estimate_storage_cost_check_access(&self, reverie_id: ReverieId, user_id: AccountId) -> StorageCostEstimate {
+        StorageCostEstimate::from_bytes(self.access_grant_storage_bytes(&reverie_id, &user_id))
+    }
+
+    /// Storage `create_reverie_derived` would add for `metadata`. Attach at least `cost`
+    /// to have the caller pay for it; the excess is refunded.
+    pub fn estimate_storage_cost_create_reverie(&self, metadata: ReverieMetadata) -> StorageCostEstimate {
+        let reverie_id = derive_reverie_id(&metadata);
+        StorageCostEstimate::from_bytes(self.create_reverie_storage_bytes(&reverie_id, &metadata))
+    }
+}
+
+impl PaymentContract {
+    // internal method sizing the records a first deposit writes
+    pub(crate) fn deposit_storage_bytes(&self, reverie_id: &str, user_id: &AccountId) -> u64 {
+        let mut bytes = 0;
+        let has_balance = self
+            .reverie_balances
+            .get(reverie_id)
+            .is_some_and(|balances| balances.contains_key(user_id));
+        if !has_balance {
+            // ReverieBalances prefix: variant byte + u64 index
+            bytes += record_bytes(9 + borsh_len(user_id), borsh_len(&0u128));
+        }
+        let key = self.user_key(reverie_id, user_id);
+        if !self.user_totals.contains_key(&key) {
+            bytes += record_bytes(1 + borsh_len(&key), borsh_len(&balance_detail::UserTotals::default()));
+        }
+        if self.membership_nft_reveries.contains(reverie_id) && !self.memberships.contains_key(&key) {
+            bytes += record_bytes(1 + borsh_len(&key), borsh_len(&0u64));
+            let prefix = StorageKey::OwnerMemberships { reverie_index: 0 };
+            // The set stores each entry under its position, and its position under the entry,
+            // with one byte added to the prefix for each
+            let prefix_len = borsh_len(&prefix) + 1;
+            let entry_len = borsh_len(&(reverie_id.to_string(), key.0));
+            bytes += record_bytes(prefix_len + 4, entry_len) + record_bytes(prefix_len + entry_len, 4);
+            if !self.memberships_by_owner.contains_key(user_id) {
+                let owner_memberships = IterableSet::<(ReverieId, u64)>::new(prefix);
+                bytes += record_bytes(1 + borsh_len(user_id), borsh_len(&owner_memberships));
+            }
+        }
+        bytes
+    }
+
+    // internal method sizing the grant record an access check result is cached in
+    pub(crate) fn access_grant_storage_bytes(&self, reverie_id: &str, user_id: &AccountId) -> u64 {
+        let key = self.user_key(reverie_id, user_id);
+        if self.access_grants.contains_key(&key) {
+            return 0;
+        }
+        let grant = access_cache::GrantRecord {
+            granted: false,
+            expires_at: U64(0),
+            condition_hash: Base58CryptoHash::from([0u8; 32]),
+        };
+        record_bytes(1 + borsh_len(&key), borsh_len(&grant))
+    }
+
+    // internal method sizing the records `internal_create_reverie` writes
+    pub(crate) fn create_reverie_storage_bytes(&self, reverie_id: &str, metadata: &ReverieMetadata) -> u64 {
+        let key_len = 1 + borsh_len(&reverie_id.to_string());
+        let balances_len = borsh_len(&StorageKey::ReverieBalances { reverie_index: 0 }) + 4;
+        let mut bytes = record_bytes(key_len, borsh_len(metadata)) + record_bytes(key_len, balances_len);
+        // `reverie_ids` lives in the contract state record, which already exists
+        bytes += borsh_len(&reverie_id.to_string());
+        let index_bytes = |index: &LookupMap<String, Vec<ReverieId>>, key: &String| {
+            vec_entry_bytes(index.contains_key(key), 1 + borsh_len(key), reverie_id)
+        };
+        bytes += index_bytes(&self.reveries_by_type, &metadata.reverie_type);
+        bytes += index_bytes(&self.reveries_by_access_kind, &metadata.access_condition.kind().to_string());
+        for tag in metadata.listing.tags.iter() {
+            bytes += index_bytes(&self.reveries_by_tag, tag);
+        }
+        bytes
+    }
+
+    // internal method keeping the storage cost of `bytes` out of the attached deposit and
+    // refunding the rest. Calls without a deposit leave storage to the contract, as before.
+    pub(crate) fn refund_excess_storage_deposit(&self, bytes: u64) {
+        let attached = env::attached_deposit().as_yoctonear();
+        if attached == 0 {
+            return;
+        }
+        let cost = StorageCostEstimate::from_bytes(bytes).cost.0;
+        assert!(attached >= cost, "Attached deposit {} doesn't cover the storage cost {}", attached, cost);
+        let refund = attached - cost;
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(NearToken::from_yoctonear(refund));
+        }
+    }
+}
+
+fn borsh_len<T: near_sdk::borsh::BorshSerialize>(value: &T) -> u64 {
+    near_sdk::borsh::to_vec(value).expect("borsh serialization").len() as u64
+}
+
+fn record_bytes(key_len: u64, value_len: u64) -> u64 {
+    key_len + value_len + STORAGE_RECORD_OVERHEAD
+}
+
+// Bytes a reverie id adds to a `Vec<ReverieId>` value, creating the record if it doesn't exist
+fn vec_entry_bytes(exists: bool, key_len: u64, reverie_id: &str) -> u64 {
+    let entry_len = borsh_len(&reverie_id.to_string());
+    if exists {
+        entry_len
+    } else {
+        record_bytes(key_len, 4 + entry_len)
+    }
+}
@@ -1,16 +1,11 @@
 use super::*;
 use near_sdk::test_utils::{accounts, VMContextBuilder};
 use near_sdk::testing_env;
+use reveries_test_utils::{deposit_context, passkey_pk_of, passkey_signing_key};
 use std::convert::TryFrom;
 
 fn get_context(predecessor_account_id: AccountId, attached_deposit_yocto: u128) -> VMContextBuilder {
-    let mut builder = VMContextBuilder::new();
-    builder
-        .current_account_id(accounts(0)) // Contract account
-        .signer_account_id(predecessor_account_id.clone())
-        .predecessor_account_id(predecessor_account_id)
-        .attached_deposit(NearToken::from_yoctonear(attached_deposit_yocto));
-    builder
+    deposit_context(predecessor_account_id, accounts(0), attached_deposit_yocto) // accounts(0) is the contract
 }
 
 fn new_contract(trusted_account: AccountId) -> PaymentContract {
@@ -27,6 +22,8 @@ fn contract_with_reverie(trusted_account: AccountId) -> PaymentContract {
         "type1".to_string(),
         "desc1".to_string(),
         AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+        None,
     );
     contract
 }
@@ -95,7 +92,7 @@ fn record_spend_success() {
     assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user.clone()), U128(100));
 
     testing_env!(get_context(trusted_account.clone(), 0).build());
-    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(30));
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(30), None, None);
     assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user.clone()), U128(70));
 }
 
@@ -110,7 +107,7 @@ fn record_spend_unauthorized() {
     contract.deposit(TEST_REVERIE_ID.to_string());
 
     testing_env!(get_context(unauthorized_caller.clone(), 0).build());
-    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(30));
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(30), None, None);
 }
 
 #[test]
@@ -123,7 +120,7 @@ fn record_spend_insufficient_balance() {
     contract.deposit(TEST_REVERIE_ID.to_string());
 
     testing_env!(get_context(trusted_account.clone(), 0).build());
-    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(30));
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(30), None, None);
 }
 
 #[test]
@@ -252,6 +249,8 @@ fn test_create_reverie_success() {
         "type1".to_string(),
         "desc1".to_string(),
         AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+        None,
     );
     let meta = contract.get_reverie_metadata(TEST_REVERIE_ID.to_string()).expect("Reverie should exist");
     assert_eq!(meta.reverie_type, "type1");
@@ -274,6 +273,8 @@ fn test_create_reverie_unauthorized() {
         "type1".to_string(),
         "desc1".to_string(),
         AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+        None,
     );
 }
 
@@ -288,12 +289,16 @@ fn test_create_reverie_duplicate() {
         "type1".to_string(),
         "desc1".to_string(),
         AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+        None,
     );
     contract.create_reverie(
         "dup".to_string(),
         "type2".to_string(),
         "desc2".to_string(),
         AccessCondition::Ed25519("pubkey2".to_string()),
+        None,
+        None,
     );
 }
 
@@ -308,12 +313,16 @@ fn test_delete_all_reveries() {
         "type1".to_string(),
         "desc1".to_string(),
         AccessCondition::Ed25519("pk1".to_string()),
+        None,
+        None,
     );
     contract.create_reverie(
         "r2".to_string(),
         "type2".to_string(),
         "desc2".to_string(),
         AccessCondition::Ecdsa("pk2".to_string()),
+        None,
+        None,
     );
     assert!(contract.get_reverie_metadata("r1".to_string()).is_some());
     assert!(contract.get_reverie_metadata("r2".to_string()).is_some());
@@ -336,6 +345,8 @@ fn test_delete_all_reveries_unauthorized() {
         "type1".to_string(),
         "desc1".to_string(),
         AccessCondition::Ed25519("pk1".to_string()),
+        None,
+        None,
     );
     testing_env!(get_context(not_trusted.clone(), 0).build());
     contract.delete_all_reveries();
@@ -356,12 +367,16 @@ fn test_get_reverie_metadata_and_ids() {
         "type1".to_string(),
         "desc1".to_string(),
         AccessCondition::Ed25519("pk1".to_string()),
+        None,
+        None,
     );
     contract.create_reverie(
         "r2".to_string(),
         "type2".to_string(),
         "desc2".to_string(),
         AccessCondition::Ecdsa("pk2".to_string()),
+        None,
+        None,
     );
 
     // Test get_reverie_metadata
@@ -399,6 +414,8 @@ fn test_create_reverie_consistency_success() {
         "type_cons".to_string(),
         "desc_cons".to_string(),
         AccessCondition::Ed25519("pk_cons".to_string()),
+        None,
+        None,
     );
 
     assert!(contract.reverie_metadata.get(&reverie_id).is_some(), "Metadata should exist after creation");
@@ -420,6 +437,8 @@ fn test_create_reverie_duplicate_id_panics() {
         "type_dup1".to_string(),
         "desc_dup1".to_string(),
         AccessCondition::Ed25519("pk_dup1".to_string()),
+        None,
+        None,
     );
 
     // Attempt second creation with same ID (should panic)
@@ -428,6 +447,8 @@ fn test_create_reverie_duplicate_id_panics() {
         "type_dup2".to_string(),
         "desc_dup2".to_string(),
         AccessCondition::Ed25519("pk_dup2".to_string()),
+        None,
+        None,
     );
 }
 
@@ -439,8 +460,8 @@ fn test_delete_all_reveries_consistency() {
     let reverie_id2 = "del_rev2".to_string();
 
     testing_env!(get_context(trusted.clone(), 0).build());
-    contract.create_reverie(reverie_id1.clone(), "t1".to_string(), "d1".to_string(), AccessCondition::Ed25519("pk1".to_string()));
-    contract.create_reverie(reverie_id2.clone(), "t2".to_string(), "d2".to_string(), AccessCondition::Ed25519("pk2".to_string()));
+    contract.create_reverie(reverie_id1.clone(), "t1".to_string(), "d1".to_string(), AccessCondition::Ed25519("pk1".to_string()), None, None);
+    contract.create_reverie(reverie_id2.clone(), "t2".to_string(), "d2".to_string(), AccessCondition::Ed25519("pk2".to_string()), None, None);
 
     assert_eq!(contract.reverie_ids.len(), 2);
     contract.delete_all_reveries();
@@ -450,4 +471,2446 @@ fn test_delete_all_reveries_consistency() {
     assert!(contract.reverie_metadata.get(&reverie_id2).is_none(), "Metadata for rev2 should be gone");
     assert!(contract.reverie_balances.get(&reverie_id2).is_none(), "Balances for rev2 should be gone");
     assert!(contract.reverie_ids.is_empty(), "Reverie IDs list should be empty");
-}
\ No newline at end of file
+}
+#[test]
+fn test_usd_cents_to_yocto_rounds_up() {
+    // $3.0000 per NEAR reported with wrap.near decimals (24 + 4)
+    let price = oracle::Price { multiplier: U128(30000), decimals: 28 };
+    // 3 cents is exactly 0.01 NEAR
+    assert_eq!(oracle::usd_cents_to_yocto(3, &price), NearToken::from_millinear(10).as_yoctonear());
+    // 1 cent is a third of that, rounded up
+    assert_eq!(oracle::usd_cents_to_yocto(1, &price), 3_333_333_333_333_333_333_334);
+}
+
+fn contract_with_oracle(trusted_account: AccountId) -> PaymentContract {
+    let mut contract = contract_with_reverie(trusted_account);
+    testing_env!(get_context(accounts(0), 0).build());
+    contract.set_price_oracle(
+        "priceoracle.testnet".parse().unwrap(),
+        "wrap.testnet".to_string(),
+        near_sdk::json_types::U64(60_000_000_000),
+    );
+    contract
+}
+
+fn price_data(timestamp: u64, multiplier: u128) -> oracle::PriceData {
+    oracle::PriceData {
+        timestamp: near_sdk::json_types::U64(timestamp),
+        recency_duration_sec: 30,
+        prices: vec![oracle::AssetOptionalPrice {
+            asset_id: "wrap.testnet".to_string(),
+            price: Some(oracle::Price { multiplier: U128(multiplier), decimals: 28 }),
+        }],
+    }
+}
+
+#[test]
+fn test_on_price_for_usage_deducts_equivalent_yocto() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_oracle(trusted.clone());
+    testing_env!(get_context(user.clone(), NearToken::from_near(1).as_yoctonear()).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    testing_env!(get_context(accounts(0), 0).block_timestamp(1_000).build());
+    let charged = contract.on_price_for_usage(
+        TEST_REVERIE_ID.to_string(),
+        user.clone(),
+        near_sdk::json_types::U64(300),
+        None,
+        Ok(price_data(1_000, 30000)),
+    );
+    // $3.00 at $3.00 per NEAR
+    assert_eq!(charged, U128(NearToken::from_near(1).as_yoctonear()));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(0));
+}
+
+#[test]
+#[should_panic(expected = "Oracle price is stale")]
+fn test_on_price_for_usage_rejects_stale_price() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_oracle(trusted.clone());
+    testing_env!(get_context(user.clone(), NearToken::from_near(1).as_yoctonear()).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    testing_env!(get_context(accounts(0), 0).block_timestamp(120_000_000_000).build());
+    contract.on_price_for_usage(
+        TEST_REVERIE_ID.to_string(),
+        user,
+        near_sdk::json_types::U64(1),
+        None,
+        Ok(price_data(1_000, 30000)),
+    );
+}
+
+#[test]
+#[should_panic(expected = "Oracle price is stale")]
+fn test_on_price_for_usage_rejects_reports_older_than_staleness() {
+    let user = accounts(1);
+    let mut contract = contract_with_oracle(accounts(2));
+    testing_env!(get_context(user.clone(), NearToken::from_near(1).as_yoctonear()).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    // Answered in the same block, but from reports up to 90s old with a 60s limit
+    let now = 200_000_000_000;
+    let mut data = price_data(now, 30000);
+    data.recency_duration_sec = 90;
+    testing_env!(get_context(accounts(0), 0).block_timestamp(now).build());
+    contract.on_price_for_usage(TEST_REVERIE_ID.to_string(), user, near_sdk::json_types::U64(1), None, Ok(data));
+}
+
+#[test]
+fn test_ledger_checkpoint_tracks_totals_and_event_seq() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    // create_reverie emitted seq 1
+    assert_eq!(contract.get_last_event_seq().0, 1);
+
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(30), None, None);
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+
+    let checkpoint = contract.get_ledger_checkpoint(TEST_REVERIE_ID.to_string());
+    assert_eq!(checkpoint, ledger::LedgerCheckpoint {
+        total_deposits: U128(100),
+        total_spends: U128(30),
+        total_withdrawals: U128(20),
+        last_event_seq: near_sdk::json_types::U64(4),
+    });
+}
+
+#[test]
+fn test_deposit_emits_sequenced_event() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    let logs = near_sdk::test_utils::get_logs();
+    let event_log = logs.iter().find(|l| l.starts_with("EVENT_JSON:")).expect("Deposit event should be logged");
+    let event: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(&event_log["EVENT_JSON:".len()..]).unwrap();
+    assert_eq!(event["standard"], "reveries");
+    assert_eq!(event["event"], "deposit");
+    assert_eq!(event["seq"], 2);
+    assert_eq!(event["data"]["amount"], "100");
+    assert_eq!(event["data"]["new_balance"], "100");
+}
+
+#[test]
+fn test_get_access_condition_schema() {
+    let contract = new_contract(accounts(1));
+    let schema: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(&contract.get_access_condition_schema()).unwrap();
+    assert_eq!(schema["title"], "AccessCondition");
+
+    let abi: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(&contract.get_abi()).unwrap();
+    assert!(abi["ReverieMetadata"].is_object());
+    assert!(abi["LedgerCheckpoint"].is_object());
+}
+
+fn signed_withdraw_request(
+    contract: &PaymentContract,
+    signing_key: &ed25519_dalek::SigningKey,
+    user_id: AccountId,
+    amount: u128,
+    receiver_id: AccountId,
+    nonce: u64,
+) -> passkey_withdraw::SignedWithdrawRequest {
+    use ed25519_dalek::Signer;
+    let passkey_pk = PublicKey::from_parts(near_sdk::CurveType::ED25519, signing_key.verifying_key().to_bytes().to_vec()).unwrap();
+    let payload = contract.get_withdraw_payload(
+        TEST_REVERIE_ID.to_string(),
+        user_id.clone(),
+        U128(amount),
+        receiver_id,
+        near_sdk::json_types::U64(nonce),
+        near_sdk::json_types::U64(u64::MAX),
+    );
+    passkey_withdraw::SignedWithdrawRequest {
+        user_id,
+        passkey_pk,
+        nonce: near_sdk::json_types::U64(nonce),
+        valid_until: near_sdk::json_types::U64(u64::MAX),
+        signature: Base64VecU8(signing_key.sign(&payload.0).to_bytes().to_vec()),
+    }
+}
+
+fn contract_with_withdraw_passkey(user: AccountId, trusted: AccountId, signing_key: &ed25519_dalek::SigningKey) -> PaymentContract {
+    let mut contract = contract_with_reverie(trusted);
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    let passkey_pk = PublicKey::from_parts(near_sdk::CurveType::ED25519, signing_key.verifying_key().to_bytes().to_vec()).unwrap();
+    contract.bind_withdraw_passkey(user, passkey_pk);
+    contract
+}
+
+#[test]
+#[should_panic(expected = "Only the user can bind a withdraw passkey")]
+fn test_bind_withdraw_passkey_panic_trusted_account() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    contract.bind_withdraw_passkey(accounts(1), passkey_pk_of(&passkey_signing_key(5)));
+}
+
+#[test]
+fn test_bind_withdraw_passkey_with_signature_of_bound_passkey() {
+    use ed25519_dalek::Signer;
+    let user = accounts(1);
+    let signing_key = passkey_signing_key(5);
+    let mut contract = contract_with_withdraw_passkey(user.clone(), accounts(2), &signing_key);
+    let new_passkey_pk = passkey_pk_of(&passkey_signing_key(6));
+    let nonce = near_sdk::json_types::U64(1);
+    let valid_until = near_sdk::json_types::U64(u64::MAX);
+    let payload = contract.get_bind_passkey_payload(user.clone(), new_passkey_pk.clone(), nonce, valid_until);
+    let request = passkey_withdraw::SignedWithdrawRequest {
+        user_id: user.clone(),
+        passkey_pk: passkey_pk_of(&signing_key),
+        nonce,
+        valid_until,
+        signature: Base64VecU8(signing_key.sign(&payload.0).to_bytes().to_vec()),
+    };
+    testing_env!(get_context(accounts(3), 0).build());
+    contract.bind_withdraw_passkey_with_signature(new_passkey_pk.clone(), request);
+    assert_eq!(contract.get_withdraw_passkey_owner(new_passkey_pk), Some(user.clone()));
+    assert_eq!(contract.get_withdraw_nonce(user).0, 1);
+}
+
+#[test]
+fn test_withdraw_with_passkey() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let relayer = accounts(3);
+    let signing_key = passkey_signing_key(5);
+    let mut contract = contract_with_withdraw_passkey(user.clone(), trusted, &signing_key);
+
+    let request = signed_withdraw_request(&contract, &signing_key, user.clone(), 40, accounts(4), 1);
+    testing_env!(get_context(relayer, 0).build());
+    contract.withdraw_with_passkey(TEST_REVERIE_ID.to_string(), U128(40), accounts(4), request);
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user.clone()), U128(60));
+    assert_eq!(contract.get_withdraw_nonce(user).0, 1);
+}
+
+#[test]
+#[should_panic(expected = "Invalid passkey signature for withdraw request")]
+fn test_withdraw_with_passkey_rejects_changed_receiver() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let relayer = accounts(3);
+    let signing_key = passkey_signing_key(5);
+    let mut contract = contract_with_withdraw_passkey(user.clone(), trusted, &signing_key);
+
+    let request = signed_withdraw_request(&contract, &signing_key, user, 40, accounts(4), 1);
+    testing_env!(get_context(relayer.clone(), 0).build());
+    contract.withdraw_with_passkey(TEST_REVERIE_ID.to_string(), U128(40), relayer, request);
+}
+
+#[test]
+#[should_panic(expected = "Withdraw request nonce already used")]
+fn test_withdraw_with_passkey_rejects_replay() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let relayer = accounts(3);
+    let signing_key = passkey_signing_key(5);
+    let mut contract = contract_with_withdraw_passkey(user.clone(), trusted, &signing_key);
+
+    let request = signed_withdraw_request(&contract, &signing_key, user, 10, accounts(4), 1);
+    testing_env!(get_context(relayer, 0).build());
+    contract.withdraw_with_passkey(TEST_REVERIE_ID.to_string(), U128(10), accounts(4), request.clone());
+    contract.withdraw_with_passkey(TEST_REVERIE_ID.to_string(), U128(10), accounts(4), request);
+}
+
+#[test]
+fn test_deposit_for_credits_named_user() {
+    let payer = accounts(3);
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted);
+    testing_env!(get_context(payer.clone(), 70).build());
+    contract.deposit_for(TEST_REVERIE_ID.to_string(), user.clone());
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(70));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), payer), U128(0));
+}
+
+#[test]
+fn test_distribute_credits_each_recipient() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(trusted, 60).build());
+    contract.distribute(TEST_REVERIE_ID.to_string(), vec![(accounts(3), U128(10)), (accounts(4), U128(50))]);
+
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), accounts(3)), U128(10));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), accounts(4)), U128(50));
+    let deposit_events = near_sdk::test_utils::get_logs().iter().filter(|l| l.contains(r#""event":"deposit""#)).count();
+    assert_eq!(deposit_events, 2);
+}
+
+#[test]
+#[should_panic(expected = "Distribution total 60 does not match attached deposit 61")]
+fn test_distribute_rejects_mismatched_total() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(trusted, 61).build());
+    contract.distribute(TEST_REVERIE_ID.to_string(), vec![(accounts(3), U128(10)), (accounts(4), U128(50))]);
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn test_export_and_import_state_chunks() {
+    use snapshot::SnapshotCollection;
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut source = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    source.deposit(TEST_REVERIE_ID.to_string());
+
+    let reveries = source.export_state_chunk(SnapshotCollection::Reveries, 0, 10);
+    let balances_collection = SnapshotCollection::Balances {
+        reverie_id: TEST_REVERIE_ID.to_string(),
+        user_ids: vec![user.clone(), accounts(3)],
+    };
+    let balances = source.export_state_chunk(balances_collection.clone(), 0, 10);
+
+    // Restore into a fresh contract under a different storage context
+    testing_env!(get_context(accounts(0), 0).build());
+    let mut target = new_contract(trusted);
+    target.import_state_chunk(SnapshotCollection::Reveries, reveries);
+    target.import_state_chunk(balances_collection, balances);
+
+    assert_eq!(target.get_reverie_ids(), vec![TEST_REVERIE_ID.to_string()]);
+    assert_eq!(target.get_balance(TEST_REVERIE_ID.to_string(), user), U128(100));
+    assert_eq!(target.get_ledger_checkpoint(TEST_REVERIE_ID.to_string()).total_deposits, U128(100));
+}
+
+#[test]
+fn test_reverie_spender_can_only_spend_on_its_reverie() {
+    let trusted = accounts(2);
+    let spender = accounts(3);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.create_reverie("rev2".to_string(), "Type".to_string(), "Other".to_string(), AccessCondition::Ed25519("pk".to_string()), None, None);
+    contract.add_reverie_spender(TEST_REVERIE_ID.to_string(), spender.clone());
+    assert_eq!(contract.get_reverie_spenders(TEST_REVERIE_ID.to_string()), vec![spender.clone()]);
+    assert!(contract.is_reverie_spender(TEST_REVERIE_ID.to_string(), spender.clone()));
+    assert!(!contract.is_reverie_spender("rev2".to_string(), spender.clone()));
+
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    testing_env!(get_context(spender.clone(), 0).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(40), None, None);
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(60));
+
+    testing_env!(get_context(trusted, 0).build());
+    contract.remove_reverie_spender(TEST_REVERIE_ID.to_string(), spender);
+    assert!(contract.get_reverie_spenders(TEST_REVERIE_ID.to_string()).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "Only the trusted account can call this method")]
+fn test_reverie_spender_cannot_spend_on_other_reverie() {
+    let trusted = accounts(2);
+    let spender = accounts(3);
+    let mut contract = contract_with_reverie(trusted);
+    contract.create_reverie("rev2".to_string(), "Type".to_string(), "Other".to_string(), AccessCondition::Ed25519("pk".to_string()), None, None);
+    contract.add_reverie_spender(TEST_REVERIE_ID.to_string(), spender.clone());
+
+    testing_env!(get_context(spender, 0).build());
+    contract.record_spend("rev2".to_string(), accounts(1), U128(1), None, None);
+}
+
+#[test]
+fn test_rounding_policy_rounds_spends_up() {
+    let trusted = accounts(2);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted.clone());
+    let policy = rounding::RoundingPolicy { spend_increment: U128(10), dust_threshold: U128(0) };
+    contract.set_reverie_rounding_policy(TEST_REVERIE_ID.to_string(), policy.clone());
+    assert_eq!(contract.get_reverie_metadata(TEST_REVERIE_ID.to_string()).unwrap().rounding_policy, policy);
+
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(21), None, None);
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(70));
+}
+
+#[test]
+fn test_consolidate_dust_moves_balance_to_fee_pool() {
+    let trusted = accounts(2);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.set_reverie_rounding_policy(
+        TEST_REVERIE_ID.to_string(),
+        rounding::RoundingPolicy { spend_increment: U128(0), dust_threshold: U128(10) },
+    );
+    testing_env!(get_context(user.clone(), 5).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    testing_env!(get_context(trusted, 0).build());
+    assert_eq!(contract.consolidate_dust(TEST_REVERIE_ID.to_string(), user.clone()), U128(5));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(0));
+    assert_eq!(contract.get_fee_pool(), U128(5));
+    assert_eq!(contract.get_ledger_checkpoint(TEST_REVERIE_ID.to_string()).total_spends, U128(5));
+}
+
+#[test]
+#[should_panic(expected = "Balance 50 is not below the dust threshold 10")]
+fn test_consolidate_dust_rejects_balance_above_threshold() {
+    let trusted = accounts(2);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted);
+    contract.set_reverie_rounding_policy(
+        TEST_REVERIE_ID.to_string(),
+        rounding::RoundingPolicy { spend_increment: U128(0), dust_threshold: U128(10) },
+    );
+    testing_env!(get_context(user.clone(), 50).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    contract.consolidate_dust(TEST_REVERIE_ID.to_string(), user);
+}
+
+#[test]
+fn test_ft_transfer_moves_reverie_credits() {
+    let trusted = accounts(2);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted);
+    testing_env!(get_context(accounts(0), 0).build());
+    contract.set_ft_reverie(Some(TEST_REVERIE_ID.to_string()));
+
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    testing_env!(get_context(user.clone(), 1).build());
+    contract.ft_transfer(accounts(3), U128(30), Some("gift".to_string()));
+    assert_eq!(contract.ft_balance_of(user), U128(70));
+    assert_eq!(contract.ft_balance_of(accounts(3)), U128(30));
+    assert_eq!(contract.ft_total_supply(), U128(100));
+    assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains("\"standard\":\"nep141\"")));
+}
+
+#[test]
+fn test_ft_total_supply_excludes_unvested_deposits() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.set_vesting_period(TEST_REVERIE_ID.to_string(), Some(near_sdk::json_types::U64(1_000)));
+    testing_env!(get_context(accounts(0), 0).build());
+    contract.set_ft_reverie(Some(TEST_REVERIE_ID.to_string()));
+
+    testing_env!(get_context(user.clone(), 100).block_timestamp(0).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user.clone(), 1_000).block_timestamp(0).build());
+    contract.deposit_vesting(TEST_REVERIE_ID.to_string());
+    assert_eq!(contract.ft_total_supply(), contract.ft_balance_of(user.clone()));
+
+    testing_env!(get_context(trusted, 0).block_timestamp(250).build());
+    contract.claim_vested(TEST_REVERIE_ID.to_string());
+    assert_eq!(contract.ft_total_supply(), U128(100));
+
+    testing_env!(get_context(user.clone(), 0).block_timestamp(400).build());
+    contract.cancel_vesting(TEST_REVERIE_ID.to_string());
+    assert_eq!(contract.ft_total_supply(), contract.ft_balance_of(user));
+}
+
+#[test]
+#[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+fn test_ft_transfer_requires_one_yocto() {
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(accounts(0), 0).build());
+    contract.set_ft_reverie(Some(TEST_REVERIE_ID.to_string()));
+    testing_env!(get_context(accounts(1), 0).build());
+    contract.ft_transfer(accounts(3), U128(30), None);
+}
+
+fn contract_with_umbral_reverie(trusted_account: AccountId) -> PaymentContract {
+    let mut contract = new_contract(trusted_account.clone());
+    testing_env!(get_context(trusted_account, 0).build());
+    contract.create_reverie(
+        TEST_REVERIE_ID.to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        AccessCondition::Umbral("umbral_pk".to_string()),
+        None,
+        None,
+    );
+    contract.set_umbral_public_keys(
+        TEST_REVERIE_ID.to_string(),
+        umbral::UmbralPublicKeys { delegating_pk: "delegating".to_string(), verifying_pk: "verifying".to_string() },
+    );
+    contract
+}
+
+#[test]
+fn test_grant_and_revoke_reencryption() {
+    let user = accounts(1);
+    let mut contract = contract_with_umbral_reverie(accounts(2));
+    contract.grant_reencryption(TEST_REVERIE_ID.to_string(), user.clone(), "kfrag1".to_string());
+    contract.grant_reencryption(TEST_REVERIE_ID.to_string(), user.clone(), "kfrag2".to_string());
+
+    let grants = contract.get_reencryption_grants(TEST_REVERIE_ID.to_string(), 0, 10);
+    assert_eq!(grants.len(), 1);
+    assert_eq!(grants[0].kfrag_hash, "kfrag2");
+
+    contract.grant_reencryption(TEST_REVERIE_ID.to_string(), accounts(3), "kfrag3".to_string());
+    let page = contract.get_reencryption_grants(TEST_REVERIE_ID.to_string(), 1, 10);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].user_id, accounts(3));
+
+    contract.revoke_reencryption(TEST_REVERIE_ID.to_string(), user.clone());
+    assert!(contract.get_reencryption_grant(TEST_REVERIE_ID.to_string(), user).is_none());
+    let grants = contract.get_reencryption_grants(TEST_REVERIE_ID.to_string(), 0, 10);
+    assert_eq!(grants.len(), 1);
+    assert_eq!(grants[0].kfrag_hash, "kfrag3");
+}
+
+#[test]
+#[should_panic(expected = "No re-encryption grant for user bob on reverie rev1")]
+fn test_revoke_reencryption_panic_without_grant() {
+    let mut contract = contract_with_umbral_reverie(accounts(2));
+    contract.revoke_reencryption(TEST_REVERIE_ID.to_string(), accounts(1));
+}
+
+#[test]
+#[should_panic(expected = "Reverie rev1 does not use Umbral access")]
+fn test_grant_reencryption_panic_non_umbral_reverie() {
+    let mut contract = contract_with_reverie(accounts(2));
+    contract.grant_reencryption(TEST_REVERIE_ID.to_string(), accounts(1), "kfrag1".to_string());
+}
+
+#[test]
+fn test_deposit_hook_failure_is_tolerated() {
+    let trusted = accounts(2);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted);
+    let hook = hooks::DepositHook { contract_id: accounts(4), method_name: "on_reverie_deposit".to_string(), gas: None };
+    contract.set_deposit_hook(TEST_REVERIE_ID.to_string(), Some(hook.clone()));
+    assert_eq!(contract.get_deposit_hook(TEST_REVERIE_ID.to_string()), Some(hook));
+
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    testing_env!(
+        get_context(accounts(0), 0).build(),
+        near_sdk::test_vm_config(),
+        near_sdk::RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![near_sdk::PromiseResult::Failed]
+    );
+    assert!(!contract.on_deposit_hook_result(TEST_REVERIE_ID.to_string(), user.clone()));
+    // The deposit stays credited
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(100));
+}
+
+#[test]
+fn test_record_spend_with_payout_debits_user() {
+    let trusted = accounts(2);
+    let spender = accounts(3);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted);
+    contract.add_reverie_spender(TEST_REVERIE_ID.to_string(), spender.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    testing_env!(get_context(spender, 0).build());
+    contract.record_spend_with_payout(TEST_REVERIE_ID.to_string(), user.clone(), U128(30));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(70));
+}
+
+#[test]
+fn test_discovery_indexes_follow_create_update_delete() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted);
+    contract.create_reverie("rev2".to_string(), "type1".to_string(), "desc2".to_string(), AccessCondition::Umbral("pk".to_string()), None, None);
+    assert_eq!(contract.get_reveries_by_type("type1".to_string(), 0, 10), vec![TEST_REVERIE_ID.to_string(), "rev2".to_string()]);
+    assert_eq!(contract.get_reveries_by_type("type1".to_string(), 1, 10), vec!["rev2".to_string()]);
+    assert_eq!(contract.get_reveries_by_access_kind("Umbral".to_string(), 0, 10), vec!["rev2".to_string()]);
+
+    contract.update_reverie(TEST_REVERIE_ID.to_string(), "type2".to_string(), "desc1".to_string(), AccessCondition::Umbral("pk".to_string()), None);
+    assert_eq!(contract.get_reveries_by_type("type2".to_string(), 0, 10), vec![TEST_REVERIE_ID.to_string()]);
+    assert_eq!(contract.get_reveries_by_access_kind("Ed25519".to_string(), 0, 10), Vec::<ReverieId>::new());
+    assert_eq!(contract.get_reveries_by_access_kind("Umbral".to_string(), 0, 10).len(), 2);
+
+    contract.delete_reverie_admin("rev2".to_string());
+    assert_eq!(contract.get_reveries_by_type("type1".to_string(), 0, 10), Vec::<ReverieId>::new());
+    assert_eq!(contract.get_reveries_by_access_kind("Umbral".to_string(), 0, 10), vec![TEST_REVERIE_ID.to_string()]);
+}
+
+fn listing_with_tags(tags: &[&str]) -> ReverieListing {
+    ReverieListing {
+        icon_url: Some("https://example.com/icon.png".to_string()),
+        info_url: None,
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+    }
+}
+
+#[test]
+fn test_get_reveries_by_tag() {
+    let mut contract = contract_with_reverie(accounts(2));
+    contract.create_reverie(
+        "rev2".to_string(),
+        "type1".to_string(),
+        "desc2".to_string(),
+        AccessCondition::Ed25519("pk".to_string()),
+        None,
+        Some(listing_with_tags(&["ai", "music"])),
+    );
+    assert_eq!(contract.get_reveries_by_tag("music".to_string(), 0, 10), vec!["rev2".to_string()]);
+    let metadata = contract.get_reverie_metadata("rev2".to_string()).unwrap();
+    assert_eq!(metadata.listing.icon_url.as_deref(), Some("https://example.com/icon.png"));
+
+    // Replacing the listing re-indexes the tags; omitting it keeps them
+    contract.update_reverie(
+        "rev2".to_string(),
+        "type1".to_string(),
+        "desc2".to_string(),
+        AccessCondition::Ed25519("pk".to_string()),
+        Some(listing_with_tags(&["ai"])),
+    );
+    assert_eq!(contract.get_reveries_by_tag("music".to_string(), 0, 10), Vec::<ReverieId>::new());
+    contract.update_reverie("rev2".to_string(), "type1".to_string(), "desc3".to_string(), AccessCondition::Ed25519("pk".to_string()), None);
+    assert_eq!(contract.get_reveries_by_tag("ai".to_string(), 0, 10), vec!["rev2".to_string()]);
+
+    contract.delete_reverie_admin("rev2".to_string());
+    assert_eq!(contract.get_reveries_by_tag("ai".to_string(), 0, 10), Vec::<ReverieId>::new());
+}
+
+#[test]
+#[should_panic(expected = "Duplicate tag 'ai'")]
+fn test_create_reverie_panic_duplicate_tags() {
+    let mut contract = contract_with_reverie(accounts(2));
+    contract.create_reverie(
+        "rev2".to_string(),
+        "type1".to_string(),
+        "desc2".to_string(),
+        AccessCondition::Ed25519("pk".to_string()),
+        None,
+        Some(listing_with_tags(&["ai", "ai"])),
+    );
+}
+
+#[test]
+#[should_panic(expected = "Only the contract account can upgrade the contract")]
+fn test_upgrade_unauthorized() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted);
+    let code = vec![0u8, 97, 115, 109];
+    let code_hash = near_sdk::json_types::Base58CryptoHash::from(env::sha256_array(&code));
+    contract.upgrade(Base64VecU8(code), code_hash, None);
+}
+
+#[test]
+fn test_upgrade_deploys_code_and_calls_migration() {
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(accounts(0), 0).build());
+    let code = vec![0u8, 97, 115, 109];
+    let code_hash = near_sdk::json_types::Base58CryptoHash::from(env::sha256_array(&code));
+    contract.upgrade(Base64VecU8(code.clone()), code_hash, Some("migrate".to_string()));
+
+    let receipts = near_sdk::test_utils::get_created_receipts();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].receiver_id, accounts(0));
+    let near_sdk::mock::MockAction::DeployContract { code: deployed, .. } = &receipts[0].actions[0] else {
+        panic!("Expected a DeployContract action");
+    };
+    assert_eq!(deployed, &code);
+    let near_sdk::mock::MockAction::FunctionCallWeight { method_name, args, .. } = &receipts[0].actions[1] else {
+        panic!("Expected a FunctionCall action");
+    };
+    assert_eq!(method_name, b"migrate");
+    assert_eq!(args, b"{}");
+}
+
+#[test]
+fn test_balance_changes_emit_credit_events() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(30), None, None);
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+
+    let credit_event = near_sdk::test_utils::get_logs()
+        .iter()
+        .filter_map(|l| l.strip_prefix("EVENT_JSON:"))
+        .map(|l| near_sdk::serde_json::from_str::<near_sdk::serde_json::Value>(l).unwrap())
+        .find(|event| event["standard"] == "reveries_credit")
+        .expect("Credit event should be logged");
+    assert_eq!(credit_event["event"], "reverie_credit_burn");
+    assert_eq!(credit_event["data"][0]["owner_id"], user.to_string());
+    assert_eq!(credit_event["data"][0]["amount"], "20");
+    assert_eq!(credit_event["data"][0]["reverie_id"], TEST_REVERIE_ID);
+}
+
+#[test]
+fn test_recreated_reverie_does_not_inherit_old_balances() {
+    let trusted = accounts(2);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    testing_env!(get_context(trusted, 0).build());
+    contract.delete_reverie_admin(TEST_REVERIE_ID.to_string());
+    contract.create_reverie(
+        TEST_REVERIE_ID.to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+        None,
+    );
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(0));
+}
+
+#[test]
+fn test_recreated_reverie_does_not_inherit_user_records() {
+    let trusted = accounts(2);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.set_withdrawal_cooldown(TEST_REVERIE_ID.to_string(), Some(near_sdk::json_types::U64(1_000)));
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.request_withdrawal(TEST_REVERIE_ID.to_string(), U128(20));
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.revoke_access(TEST_REVERIE_ID.to_string(), user.clone(), "abuse".to_string());
+
+    contract.delete_reverie_admin(TEST_REVERIE_ID.to_string());
+    contract.create_reverie(
+        TEST_REVERIE_ID.to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+        None,
+    );
+    let detail = contract.get_balance_detail(TEST_REVERIE_ID.to_string(), user.clone());
+    assert_eq!(detail.lifetime_deposited, U128(0));
+    assert!(contract.get_pending_withdrawal(TEST_REVERIE_ID.to_string(), user.clone()).is_none());
+    assert!(!contract.is_access_revoked(TEST_REVERIE_ID.to_string(), user));
+}
+
+#[test]
+fn test_create_reverie_normalizes_id() {
+    let trusted = accounts(2);
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    contract.create_reverie("My-Reverie".to_string(), "type1".to_string(), "desc1".to_string(), AccessCondition::Ed25519("pk".to_string()), None, None);
+    assert_eq!(contract.get_reverie_ids(), vec!["my-reverie".to_string()]);
+    assert!(contract.is_valid_reverie_id("My-Reverie".to_string()));
+    assert!(!contract.is_valid_reverie_id("my reverie".to_string()));
+}
+
+#[test]
+#[should_panic(expected = "ReverieId contains invalid character ' '")]
+fn test_create_reverie_rejects_invalid_id() {
+    let trusted = accounts(2);
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    contract.create_reverie("my reverie".to_string(), "type1".to_string(), "desc1".to_string(), AccessCondition::Ed25519("pk".to_string()), None, None);
+}
+
+fn signed_spend_permit(
+    contract: &PaymentContract,
+    signing_key: &ed25519_dalek::SigningKey,
+    user_id: AccountId,
+    spender_id: AccountId,
+    max_amount: u128,
+    nonce: u64,
+) -> permits::SpendPermit {
+    use ed25519_dalek::Signer;
+    let payload = contract.get_spend_permit_payload(
+        TEST_REVERIE_ID.to_string(),
+        user_id,
+        spender_id,
+        U128(max_amount),
+        near_sdk::json_types::U64(nonce),
+        near_sdk::json_types::U64(u64::MAX),
+    );
+    permits::SpendPermit {
+        max_amount: U128(max_amount),
+        nonce: near_sdk::json_types::U64(nonce),
+        deadline: near_sdk::json_types::U64(u64::MAX),
+        signature: Base64VecU8(signing_key.sign(&payload.0).to_bytes().to_vec()),
+    }
+}
+
+fn contract_with_delegation_key(user: AccountId, trusted: AccountId, signing_key: &ed25519_dalek::SigningKey) -> PaymentContract {
+    let mut contract = contract_with_reverie(trusted);
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    let delegation_key = PublicKey::from_parts(near_sdk::CurveType::ED25519, signing_key.verifying_key().to_bytes().to_vec()).unwrap();
+    testing_env!(get_context(user, 0).build());
+    contract.set_delegation_key(delegation_key);
+    contract
+}
+
+#[test]
+fn test_record_spend_with_permit() {
+    let user = accounts(1);
+    let signing_key = passkey_signing_key(7);
+    let mut contract = contract_with_delegation_key(user.clone(), accounts(2), &signing_key);
+
+    let permit = signed_spend_permit(&contract, &signing_key, user.clone(), accounts(3), 30, 1);
+    testing_env!(get_context(accounts(3), 0).build());
+    let spent = contract.record_spend_with_permit(TEST_REVERIE_ID.to_string(), user.clone(), U128(25), permit);
+    assert_eq!(spent, U128(25));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user.clone()), U128(75));
+    assert_eq!(contract.get_permit_nonce(user).0, 1);
+}
+
+#[test]
+#[should_panic(expected = "Spend amount 31 exceeds permit cap 30")]
+fn test_record_spend_with_permit_rejects_amount_over_cap() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let signing_key = passkey_signing_key(7);
+    let mut contract = contract_with_delegation_key(user.clone(), trusted.clone(), &signing_key);
+
+    let permit = signed_spend_permit(&contract, &signing_key, user.clone(), trusted.clone(), 30, 1);
+    testing_env!(get_context(trusted, 0).build());
+    contract.record_spend_with_permit(TEST_REVERIE_ID.to_string(), user, U128(31), permit);
+}
+
+#[test]
+#[should_panic(expected = "Invalid delegation key signature for spend permit")]
+fn test_record_spend_with_permit_rejects_raised_cap() {
+    let user = accounts(1);
+    let signing_key = passkey_signing_key(7);
+    let mut contract = contract_with_delegation_key(user.clone(), accounts(2), &signing_key);
+
+    let mut permit = signed_spend_permit(&contract, &signing_key, user.clone(), accounts(3), 30, 1);
+    permit.max_amount = U128(90);
+    testing_env!(get_context(accounts(3), 0).build());
+    contract.record_spend_with_permit(TEST_REVERIE_ID.to_string(), user, U128(90), permit);
+}
+
+#[test]
+#[should_panic(expected = "Spend permit nonce already used")]
+fn test_record_spend_with_permit_rejects_replay() {
+    let user = accounts(1);
+    let signing_key = passkey_signing_key(7);
+    let mut contract = contract_with_delegation_key(user.clone(), accounts(2), &signing_key);
+
+    let permit = signed_spend_permit(&contract, &signing_key, user.clone(), accounts(3), 10, 1);
+    testing_env!(get_context(accounts(3), 0).build());
+    contract.record_spend_with_permit(TEST_REVERIE_ID.to_string(), user.clone(), U128(10), permit.clone());
+    contract.record_spend_with_permit(TEST_REVERIE_ID.to_string(), user, U128(10), permit);
+}
+
+#[test]
+#[should_panic(expected = "Invalid delegation key signature for spend permit")]
+fn test_record_spend_with_permit_rejects_other_spender() {
+    let user = accounts(1);
+    let signing_key = passkey_signing_key(7);
+    let mut contract = contract_with_delegation_key(user.clone(), accounts(2), &signing_key);
+
+    let permit = signed_spend_permit(&contract, &signing_key, user.clone(), accounts(3), 10, 1);
+    testing_env!(get_context(accounts(4), 0).build());
+    contract.record_spend_with_permit(TEST_REVERIE_ID.to_string(), user, U128(10), permit);
+}
+
+#[test]
+#[should_panic(expected = "Spend permit nonce must be 1")]
+fn test_record_spend_with_permit_rejects_skipped_nonce() {
+    let user = accounts(1);
+    let signing_key = passkey_signing_key(7);
+    let mut contract = contract_with_delegation_key(user.clone(), accounts(2), &signing_key);
+
+    let permit = signed_spend_permit(&contract, &signing_key, user.clone(), accounts(3), 10, 2);
+    testing_env!(get_context(accounts(3), 0).build());
+    contract.record_spend_with_permit(TEST_REVERIE_ID.to_string(), user, U128(10), permit);
+}
+
+#[test]
+fn test_get_reverie_view() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user, 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).build());
+    contract.add_reverie_spender(TEST_REVERIE_ID.to_string(), accounts(3));
+
+    let view = contract.get_reverie(TEST_REVERIE_ID.to_string()).unwrap();
+    assert_eq!(view.reverie_id, TEST_REVERIE_ID);
+    assert_eq!(view.metadata.reverie_type, "type1");
+    assert_eq!(view.ledger.total_deposits, U128(100));
+    assert_eq!(view.spenders, vec![accounts(3)]);
+    assert!(view.price_oracle.is_none());
+    assert!(contract.get_reverie("missing".to_string()).is_none());
+}
+
+#[test]
+fn test_delete_reveries_range() {
+    let trusted = accounts(1);
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    for reverie_id in ["r1", "r2", "r3", "r4", "r5"] {
+        contract.create_reverie(
+            reverie_id.to_string(),
+            "type1".to_string(),
+            "desc1".to_string(),
+            AccessCondition::Ed25519("pk1".to_string()),
+            None,
+            None,
+        );
+    }
+
+    assert_eq!(contract.delete_reveries_range(1, 2), 2);
+    assert_eq!(contract.get_deletion_cursor(), 1);
+    assert_eq!(contract.get_reverie_ids(), vec!["r1".to_string(), "r4".to_string(), "r5".to_string()]);
+    assert!(contract.get_reverie_metadata("r2".to_string()).is_none());
+
+    let cursor = contract.get_deletion_cursor();
+    assert_eq!(contract.delete_reveries_range(cursor, 10), 2);
+    assert_eq!(contract.delete_reveries_range(contract.get_deletion_cursor(), 10), 0);
+    assert_eq!(contract.get_reverie_ids(), vec!["r1".to_string()]);
+    assert_eq!(contract.get_reveries_by_type("type1".to_string(), 0, 10), vec!["r1".to_string()]);
+}
+
+fn resolve_payout(result: near_sdk::PromiseResult) {
+    testing_env!(
+        get_context(accounts(0), 0).build(),
+        near_sdk::test_vm_config(),
+        near_sdk::RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![result]
+    );
+}
+
+#[test]
+#[should_panic(expected = "Another operation is in progress for user")]
+fn test_withdraw_rejected_while_previous_withdraw_in_flight() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+    assert!(contract.is_user_locked(user));
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+}
+
+#[test]
+fn test_withdraw_lock_released_after_callback() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+
+    resolve_payout(near_sdk::PromiseResult::Successful(vec![]));
+    assert!(contract.on_withdraw_transfer(TEST_REVERIE_ID.to_string(), user.clone(), U128(20)));
+    assert!(!contract.is_user_locked(user.clone()));
+
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(60));
+}
+
+#[test]
+fn test_user_lock_expires_without_callback() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user.clone(), 0).block_timestamp(1_000).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+
+    testing_env!(get_context(user.clone(), 0).block_timestamp(1_000 + locks::USER_LOCK_TIMEOUT_NS + 1).build());
+    assert!(!contract.is_user_locked(user.clone()));
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(60));
+}
+
+#[test]
+fn test_force_release_user_lock() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+
+    testing_env!(get_context(trusted, 0).build());
+    contract.force_release_user_lock(user.clone());
+    assert!(!contract.is_user_locked(user));
+}
+
+#[test]
+#[should_panic(expected = "Only the trusted account can release user locks")]
+fn test_force_release_user_lock_panic_not_trusted() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.force_release_user_lock(user);
+}
+
+#[test]
+fn test_failed_withdraw_transfer_is_refunded() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+
+    resolve_payout(near_sdk::PromiseResult::Failed);
+    assert!(!contract.on_withdraw_transfer(TEST_REVERIE_ID.to_string(), user.clone(), U128(20)));
+    assert!(!contract.is_user_locked(user.clone()));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(100));
+    assert_eq!(contract.get_ledger_checkpoint(TEST_REVERIE_ID.to_string()).total_withdrawals, U128(0));
+}
+
+#[test]
+#[should_panic(expected = "Another operation is in progress for user")]
+fn test_spend_payout_rejected_while_withdraw_in_flight() {
+    let user = accounts(1);
+    let spender = accounts(3);
+    let mut contract = contract_with_reverie(accounts(2));
+    contract.add_reverie_spender(TEST_REVERIE_ID.to_string(), spender.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+
+    testing_env!(get_context(spender, 0).build());
+    contract.record_spend_with_payout(TEST_REVERIE_ID.to_string(), user, U128(30));
+}
+
+#[test]
+fn test_spend_by_category_totals() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    testing_env!(get_context(trusted, 0).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(10), Some("inference".to_string()), None);
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(15), Some("inference".to_string()), None);
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(5), Some("storage".to_string()), None);
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user, U128(1), None, None);
+
+    let totals = contract.get_spend_by_category(TEST_REVERIE_ID.to_string());
+    assert_eq!(totals, vec![
+        categories::CategorySpend { category: "inference".to_string(), total_spent: U128(25), spend_count: near_sdk::json_types::U64(2) },
+        categories::CategorySpend { category: "storage".to_string(), total_spent: U128(5), spend_count: near_sdk::json_types::U64(1) },
+    ]);
+    assert!(near_sdk::test_utils::get_logs().iter().any(|l| l.contains(r#""category":"storage""#)));
+}
+
+#[test]
+#[should_panic(expected = "Spend category must be between 1 and 32 characters")]
+fn test_record_spend_rejects_empty_category() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user, U128(10), Some(String::new()), None);
+}
+
+fn contract_with_ft_reverie(trusted: AccountId, token_id: AccountId) -> PaymentContract {
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    contract.create_reverie(
+        TEST_REVERIE_ID.to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        AccessCondition::Ed25519("pubkey1".to_string()),
+        Some(Denomination::Ft { contract: token_id }),
+        None,
+    );
+    contract
+}
+
+#[test]
+#[should_panic(expected = "Reverie rev1 is denominated in token")]
+fn test_set_ft_reverie_panic_token_denominated() {
+    let mut contract = contract_with_ft_reverie(accounts(2), accounts(4));
+    testing_env!(get_context(accounts(0), 0).build());
+    contract.set_ft_reverie(Some(TEST_REVERIE_ID.to_string()));
+}
+
+#[test]
+fn test_ft_on_transfer_credits_ft_reverie() {
+    let token = accounts(4);
+    let user = accounts(1);
+    let mut contract = contract_with_ft_reverie(accounts(2), token.clone());
+
+    testing_env!(get_context(token, 0).build());
+    let msg = near_sdk::serde_json::json!({"reverie_id": TEST_REVERIE_ID}).to_string();
+    let refund = contract.ft_on_transfer(user.clone(), U128(500), msg);
+    assert!(matches!(refund, near_sdk::PromiseOrValue::Value(U128(0))));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user.clone()), U128(500));
+
+    // Withdrawals pay the token back and keep the user locked until the callback
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(200));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user.clone()), U128(300));
+    assert!(contract.is_user_locked(user));
+}
+
+#[test]
+#[should_panic(expected = "Reverie rev1 does not accept deposits of token")]
+fn test_ft_on_transfer_rejects_other_token() {
+    let mut contract = contract_with_ft_reverie(accounts(2), accounts(4));
+    testing_env!(get_context(accounts(3), 0).build());
+    let msg = near_sdk::serde_json::json!({"reverie_id": TEST_REVERIE_ID}).to_string();
+    contract.ft_on_transfer(accounts(1), U128(500), msg);
+}
+
+#[test]
+#[should_panic(expected = "Reverie rev1 is denominated in token")]
+fn test_near_deposit_rejected_for_ft_reverie() {
+    let mut contract = contract_with_ft_reverie(accounts(2), accounts(4));
+    testing_env!(get_context(accounts(1), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+}
+
+#[test]
+fn test_balance_detail_tracks_lifetime_totals() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user.clone(), 50).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(30), None, None);
+
+    assert_eq!(contract.get_balance_detail(TEST_REVERIE_ID.to_string(), user), balance_detail::BalanceDetail {
+        available: U128(120),
+        locked: U128(0),
+        lifetime_deposited: U128(150),
+        lifetime_spent: U128(30),
+    });
+}
+
+#[test]
+fn test_balance_detail_reports_in_flight_withdraw_as_locked() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+
+    let detail = contract.get_balance_detail(TEST_REVERIE_ID.to_string(), user.clone());
+    assert_eq!((detail.available, detail.locked), (U128(80), U128(20)));
+
+    resolve_payout(near_sdk::PromiseResult::Failed);
+    contract.on_withdraw_transfer(TEST_REVERIE_ID.to_string(), user.clone(), U128(20));
+    let detail = contract.get_balance_detail(TEST_REVERIE_ID.to_string(), user);
+    assert_eq!((detail.available, detail.locked), (U128(100), U128(0)));
+}
+
+fn contract_with_cooldown(user: AccountId, trusted: AccountId, cooldown_ns: u64) -> PaymentContract {
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user, 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).build());
+    contract.set_withdrawal_cooldown(TEST_REVERIE_ID.to_string(), Some(near_sdk::json_types::U64(cooldown_ns)));
+    contract
+}
+
+#[test]
+#[should_panic(expected = "has a withdrawal cooldown. Use request_withdrawal and claim_withdrawal")]
+fn test_withdraw_rejected_when_cooldown_active() {
+    let user = accounts(1);
+    let mut contract = contract_with_cooldown(user.clone(), accounts(2), 1_000);
+    testing_env!(get_context(user, 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(20));
+}
+
+#[test]
+fn test_claim_withdrawal_after_cooldown() {
+    let user = accounts(1);
+    let mut contract = contract_with_cooldown(user.clone(), accounts(2), 1_000);
+    testing_env!(get_context(user.clone(), 0).block_timestamp(5_000).build());
+    let claimable_at = contract.request_withdrawal(TEST_REVERIE_ID.to_string(), U128(20));
+    assert_eq!(claimable_at.0, 6_000);
+
+    testing_env!(get_context(user.clone(), 0).block_timestamp(6_000).build());
+    contract.claim_withdrawal(TEST_REVERIE_ID.to_string());
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user.clone()), U128(80));
+    assert_eq!(contract.get_pending_withdrawal(TEST_REVERIE_ID.to_string(), user), None);
+}
+
+#[test]
+fn test_spend_after_request_does_not_push_back_claim() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_cooldown(user.clone(), trusted.clone(), 1_000);
+    testing_env!(get_context(user.clone(), 0).block_timestamp(5_000).build());
+    contract.request_withdrawal(TEST_REVERIE_ID.to_string(), U128(20));
+
+    testing_env!(get_context(trusted, 0).block_timestamp(5_900).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(10), None, None);
+
+    testing_env!(get_context(user.clone(), 0).block_timestamp(6_000).build());
+    contract.claim_withdrawal(TEST_REVERIE_ID.to_string());
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(70));
+}
+
+#[test]
+#[should_panic(expected = "Withdrawal is not claimable until 7000")]
+fn test_new_request_restarts_cooldown() {
+    let user = accounts(1);
+    let mut contract = contract_with_cooldown(user.clone(), accounts(2), 1_000);
+    testing_env!(get_context(user.clone(), 0).block_timestamp(5_000).build());
+    contract.request_withdrawal(TEST_REVERIE_ID.to_string(), U128(20));
+    testing_env!(get_context(user.clone(), 0).block_timestamp(6_000).build());
+    contract.request_withdrawal(TEST_REVERIE_ID.to_string(), U128(30));
+    contract.claim_withdrawal(TEST_REVERIE_ID.to_string());
+}
+
+#[test]
+fn test_test_hooks_advance_time_for_withdrawal_cooldown() {
+    use clock::TestHooks;
+    let user = accounts(1);
+    let mut contract = contract_with_cooldown(user.clone(), accounts(2), 1_000);
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.request_withdrawal(TEST_REVERIE_ID.to_string(), U128(20));
+
+    TestHooks::advance_time(1_000);
+    contract.claim_withdrawal(TEST_REVERIE_ID.to_string());
+    TestHooks::reset();
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(80));
+}
+
+#[test]
+fn test_validate_access_condition() {
+    let contract = new_contract(accounts(0));
+    let report = contract.validate_access_condition(
+        r#"{"type": "Contract", "value": {"address": "gate.near", "access_function_name": "", "access_function_args": "{}"}}"#.to_string(),
+    );
+    assert!(!report.valid);
+    assert_eq!(report.errors[0].field, "access_function_name");
+
+    assert!(contract.validate_access_condition(r#"{"type": "Ed25519", "value": "pk"}"#.to_string()).valid);
+    assert_eq!(contract.validate_access_condition("{}".to_string()).errors[0].field, "$");
+}
+
+fn gated_access_condition(method: &str) -> AccessCondition {
+    AccessCondition::Contract {
+        address: "gate.near".to_string(),
+        access_function_name: method.to_string(),
+        access_function_args: "{}".to_string(),
+    }
+}
+
+fn contract_with_gated_reverie(trusted: AccountId) -> PaymentContract {
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    contract.create_reverie(
+        TEST_REVERIE_ID.to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        gated_access_condition("has_access"),
+        None,
+        None,
+    );
+    contract
+}
+
+fn resolve_access_check(timestamp: u64, result: near_sdk::PromiseResult) {
+    testing_env!(
+        get_context(accounts(0), 0).block_timestamp(timestamp).build(),
+        near_sdk::test_vm_config(),
+        near_sdk::RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![result]
+    );
+}
+
+#[test]
+fn test_access_check_result_is_cached_until_ttl() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_gated_reverie(trusted.clone());
+    contract.set_access_cache_ttl(TEST_REVERIE_ID.to_string(), Some(near_sdk::json_types::U64(1_000)));
+    contract.check_access(TEST_REVERIE_ID.to_string(), user.clone());
+    assert!(!contract.has_cached_access(TEST_REVERIE_ID.to_string(), user.clone()));
+
+    resolve_access_check(5_000, near_sdk::PromiseResult::Successful(b"true".to_vec()));
+    assert!(contract.on_access_checked(TEST_REVERIE_ID.to_string(), user.clone()));
+    assert!(contract.has_cached_access(TEST_REVERIE_ID.to_string(), user.clone()));
+    assert_eq!(contract.get_access_grant(TEST_REVERIE_ID.to_string(), user.clone()).unwrap().expires_at.0, 6_000);
+
+    testing_env!(get_context(user.clone(), 0).block_timestamp(6_001).build());
+    assert!(!contract.has_cached_access(TEST_REVERIE_ID.to_string(), user));
+}
+
+#[test]
+fn test_access_grant_invalidated_by_condition_update() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_gated_reverie(trusted.clone());
+    resolve_access_check(0, near_sdk::PromiseResult::Successful(b"true".to_vec()));
+    contract.on_access_checked(TEST_REVERIE_ID.to_string(), user.clone());
+    assert!(contract.has_cached_access(TEST_REVERIE_ID.to_string(), user.clone()));
+
+    testing_env!(get_context(trusted, 0).build());
+    contract.update_reverie(
+        TEST_REVERIE_ID.to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        gated_access_condition("is_member"),
+        None,
+    );
+    assert!(!contract.has_cached_access(TEST_REVERIE_ID.to_string(), user));
+}
+
+#[test]
+fn test_failed_access_check_is_not_cached() {
+    let user = accounts(1);
+    let mut contract = contract_with_gated_reverie(accounts(2));
+    resolve_access_check(0, near_sdk::PromiseResult::Failed);
+    assert!(!contract.on_access_checked(TEST_REVERIE_ID.to_string(), user.clone()));
+    assert_eq!(contract.get_access_grant(TEST_REVERIE_ID.to_string(), user), None);
+}
+
+#[test]
+#[should_panic(expected = "to cover the storage of the access check result")]
+fn test_check_access_panic_without_storage_deposit() {
+    let user = accounts(1);
+    let mut contract = contract_with_gated_reverie(accounts(2));
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.check_access(TEST_REVERIE_ID.to_string(), user);
+}
+
+#[test]
+fn test_check_access_by_user_with_storage_deposit() {
+    let user = accounts(1);
+    let mut contract = contract_with_gated_reverie(accounts(2));
+    let cost = contract.estimate_storage_cost_check_access(TEST_REVERIE_ID.to_string(), user.clone()).cost.0;
+    assert!(cost > 0);
+    testing_env!(get_context(user.clone(), cost).build());
+    contract.check_access(TEST_REVERIE_ID.to_string(), user);
+    assert_eq!(near_sdk::test_utils::get_created_receipts().len(), 2);
+}
+
+#[test]
+#[should_panic(expected = "has no on-chain access check")]
+fn test_check_access_panic_off_chain_condition() {
+    let mut contract = contract_with_reverie(accounts(2));
+    contract.check_access(TEST_REVERIE_ID.to_string(), accounts(1));
+}
+
+#[test]
+fn test_deposit_split_routes_share_to_revenue() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.set_deposit_split(TEST_REVERIE_ID.to_string(), Some(2_000));
+
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user.clone()), U128(80));
+    assert_eq!(contract.get_reverie_revenue(TEST_REVERIE_ID.to_string()), U128(20));
+    assert!(near_sdk::test_utils::get_logs()
+        .iter()
+        .any(|log| log.contains(r#""credited":"80""#) && log.contains(r#""revenue":"20""#)));
+    let checkpoint = contract.get_ledger_checkpoint(TEST_REVERIE_ID.to_string());
+    assert_eq!(checkpoint.total_deposits.0 - checkpoint.total_spends.0, 80);
+    let detail = contract.get_balance_detail(TEST_REVERIE_ID.to_string(), user.clone());
+    assert_eq!((detail.lifetime_deposited, detail.lifetime_spent), (checkpoint.total_deposits, checkpoint.total_spends));
+
+    testing_env!(get_context(trusted, 0).build());
+    contract.withdraw_reverie_revenue(TEST_REVERIE_ID.to_string(), U128(20), accounts(3));
+    assert_eq!(contract.get_reverie_revenue(TEST_REVERIE_ID.to_string()), U128(0));
+}
+
+#[test]
+fn test_delete_ft_reverie_pays_out_revenue_to_admin() {
+    let token = accounts(4);
+    let trusted = accounts(2);
+    let mut contract = contract_with_ft_reverie(trusted.clone(), token.clone());
+    contract.set_deposit_split(TEST_REVERIE_ID.to_string(), Some(2_000));
+    testing_env!(get_context(token.clone(), 0).build());
+    let msg = near_sdk::serde_json::json!({"reverie_id": TEST_REVERIE_ID}).to_string();
+    let _ = contract.ft_on_transfer(accounts(1), U128(500), msg);
+
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.delete_reverie_admin(TEST_REVERIE_ID.to_string());
+    let receipts = near_sdk::test_utils::get_created_receipts();
+    assert_eq!(receipts.len(), 1);
+    assert_eq!(receipts[0].receiver_id, token);
+    let near_sdk::mock::MockAction::FunctionCallWeight { method_name, args, .. } = &receipts[0].actions[0] else {
+        panic!("Expected an ft_transfer call");
+    };
+    assert_eq!(method_name, b"ft_transfer");
+    let args: near_sdk::serde_json::Value = near_sdk::serde_json::from_slice(args).unwrap();
+    assert_eq!(args["receiver_id"], trusted.to_string());
+    assert_eq!(args["amount"], "100");
+}
+
+#[test]
+fn test_distribute_is_not_split() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.set_deposit_split(TEST_REVERIE_ID.to_string(), Some(2_000));
+    testing_env!(get_context(trusted, 100).build());
+    contract.distribute(TEST_REVERIE_ID.to_string(), vec![(accounts(1), U128(100))]);
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), accounts(1)), U128(100));
+    assert_eq!(contract.get_reverie_revenue(TEST_REVERIE_ID.to_string()), U128(0));
+}
+
+#[test]
+#[should_panic(expected = "split_bps must be at most 10000")]
+fn test_set_deposit_split_rejects_over_100_percent() {
+    let mut contract = contract_with_reverie(accounts(2));
+    contract.set_deposit_split(TEST_REVERIE_ID.to_string(), Some(10_001));
+}
+
+#[test]
+fn test_get_balances_and_can_spend_many() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(accounts(1), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(accounts(3), 40).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    let users = vec![accounts(1), accounts(3), accounts(4)];
+    assert_eq!(contract.get_balances(TEST_REVERIE_ID.to_string(), users), vec![U128(100), U128(40), U128(0)]);
+    assert_eq!(
+        contract.can_spend_many(
+            TEST_REVERIE_ID.to_string(),
+            vec![(accounts(1), U128(100)), (accounts(3), U128(41)), (accounts(4), U128(0))]
+        ),
+        vec![true, false, true]
+    );
+}
+
+#[test]
+fn test_membership_minted_on_first_deposit_and_burned_on_purge() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.set_membership_nft(TEST_REVERIE_ID.to_string(), true);
+    let token_id = format!("{}:{}", TEST_REVERIE_ID, user);
+
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""event":"nft_mint""#)));
+    testing_env!(get_context(user.clone(), 50).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    assert!(!near_sdk::test_utils::get_logs().iter().any(|log| log.contains("nft_mint")));
+
+    let token = contract.nft_token(token_id.clone()).unwrap();
+    assert_eq!(token.owner_id, user);
+    assert_eq!(contract.nft_supply_for_owner(user.clone()), U128(1));
+    assert_eq!(contract.nft_tokens_for_owner(user.clone(), None, None), vec![token]);
+
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(150), None, None);
+    contract.purge_user(TEST_REVERIE_ID.to_string(), user.clone());
+    assert!(contract.nft_token(token_id).is_none());
+    assert_eq!(contract.nft_supply_for_owner(user), U128(0));
+    assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains(r#""event":"nft_burn""#)));
+}
+
+#[test]
+fn test_no_membership_when_disabled() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    assert!(contract.nft_token(format!("{}:{}", TEST_REVERIE_ID, user)).is_none());
+}
+
+#[test]
+#[should_panic(expected = "Membership tokens are non-transferable")]
+fn test_membership_nft_transfer_panics() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    contract.set_membership_nft(TEST_REVERIE_ID.to_string(), true);
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user.clone(), 1).build());
+    contract.nft_transfer(accounts(3), format!("{}:{}", TEST_REVERIE_ID, user), None, None);
+}
+
+#[test]
+#[should_panic(expected = "still has a balance")]
+fn test_purge_user_requires_empty_balance() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).build());
+    contract.purge_user(TEST_REVERIE_ID.to_string(), user);
+}
+
+#[test]
+fn test_create_reverie_derived_matches_predicted_id() {
+    let trusted = accounts(2);
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    let condition = AccessCondition::Ed25519("pubkey1".to_string());
+    let predicted = contract.get_derived_reverie_id("type1".to_string(), "desc1".to_string(), condition.clone(), None, None);
+    let reverie_id = contract.create_reverie_derived("type1".to_string(), "desc1".to_string(), condition, None, None);
+    assert_eq!(reverie_id, predicted);
+    assert!(contract.get_reverie_metadata(reverie_id).is_some());
+}
+
+#[test]
+#[should_panic(expected = "already exists")]
+fn test_create_reverie_derived_panic_duplicate_metadata() {
+    let trusted = accounts(2);
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    let condition = AccessCondition::Ed25519("pubkey1".to_string());
+    contract.create_reverie_derived("type1".to_string(), "desc1".to_string(), condition.clone(), None, None);
+    contract.create_reverie_derived("type1".to_string(), "desc1".to_string(), condition, None, None);
+}
+
+fn contract_with_disputed_spend(user: AccountId, trusted: AccountId) -> (PaymentContract, near_sdk::json_types::U64) {
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.set_dispute_window(TEST_REVERIE_ID.to_string(), Some(near_sdk::json_types::U64(1_000)));
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).block_timestamp(10_000).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(60), None, None);
+    let spend_log = near_sdk::test_utils::get_logs()
+        .into_iter()
+        .find(|log| log.contains(r#""event":"spend""#))
+        .unwrap();
+    let spend_event: near_sdk::serde_json::Value =
+        near_sdk::serde_json::from_str(spend_log.trim_start_matches("EVENT_JSON:")).unwrap();
+    let spend_id = near_sdk::json_types::U64(spend_event["seq"].as_u64().unwrap());
+
+    testing_env!(get_context(user, 0).block_timestamp(10_500).build());
+    contract.dispute_spend(spend_id);
+    (contract, spend_id)
+}
+
+#[test]
+fn test_resolve_dispute_with_refund() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let (mut contract, spend_id) = contract_with_disputed_spend(user.clone(), trusted.clone());
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user.clone()), U128(40));
+    assert_eq!(contract.get_dispute(spend_id).unwrap().spend.amount, U128(60));
+
+    testing_env!(get_context(trusted, 0).build());
+    contract.resolve_dispute(spend_id, true);
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(100));
+    assert!(contract.get_dispute(spend_id).is_none());
+    assert_eq!(contract.get_ledger_checkpoint(TEST_REVERIE_ID.to_string()).total_spends, U128(0));
+}
+
+#[test]
+fn test_unresolved_dispute_refunds_after_deadline() {
+    let user = accounts(1);
+    let (mut contract, spend_id) = contract_with_disputed_spend(user.clone(), accounts(2));
+    let resolve_by = contract.get_dispute(spend_id).unwrap().resolve_by.0;
+    testing_env!(get_context(accounts(3), 0).block_timestamp(resolve_by + 1).build());
+    contract.refund_expired_dispute(spend_id);
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(100));
+}
+
+#[test]
+fn test_expired_dispute_not_refunded_into_recreated_reverie() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let (mut contract, spend_id) = contract_with_disputed_spend(user.clone(), trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    contract.delete_reverie_admin(TEST_REVERIE_ID.to_string());
+    contract.create_reverie(
+        TEST_REVERIE_ID.to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+        None,
+    );
+    let resolve_by = contract.get_dispute(spend_id).unwrap().resolve_by.0;
+    testing_env!(get_context(accounts(3), 0).block_timestamp(resolve_by + 1).build());
+    contract.refund_expired_dispute(spend_id);
+    assert!(contract.get_dispute(spend_id).is_none());
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(0));
+    assert_eq!(contract.get_ledger_checkpoint(TEST_REVERIE_ID.to_string()).total_spends, U128(0));
+}
+
+#[test]
+#[should_panic(expected = "Dispute window for spend")]
+fn test_dispute_spend_panic_after_window() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.set_dispute_window(TEST_REVERIE_ID.to_string(), Some(near_sdk::json_types::U64(1_000)));
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).block_timestamp(10_000).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(60), None, None);
+    testing_env!(get_context(user, 0).block_timestamp(11_001).build());
+    // The Spend event follows ReverieCreated and Deposit
+    contract.dispute_spend(near_sdk::json_types::U64(3));
+}
+
+#[test]
+fn test_daily_stats_rollup() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.create_reverie("rev2".to_string(), "type1".to_string(), "desc2".to_string(), AccessCondition::Ed25519("pk".to_string()), None, None);
+    let day_ns = stats::NANOS_PER_DAY;
+
+    testing_env!(get_context(accounts(1), 100).block_timestamp(day_ns * 3).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(accounts(1), 50).block_timestamp(day_ns * 3 + 1).build());
+    contract.deposit("rev2".to_string());
+    testing_env!(get_context(accounts(3), 20).block_timestamp(day_ns * 3 + 2).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).block_timestamp(day_ns * 3 + 3).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), accounts(1), U128(30), None, None);
+    assert_eq!(contract.get_current_day(), 3);
+
+    let stats = contract.get_daily_stats(TEST_REVERIE_ID.to_string(), 3);
+    assert_eq!(stats.deposits, U128(120));
+    assert_eq!(stats.spends, U128(30));
+    assert_eq!(stats.active_users, 2);
+    let global = contract.get_global_daily_stats(3);
+    assert_eq!(global.deposits, U128(170));
+    assert_eq!(global.active_users, 2);
+
+    testing_env!(get_context(accounts(1), 0).block_timestamp(day_ns * 4).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(10));
+    let next_day = contract.get_daily_stats(TEST_REVERIE_ID.to_string(), 4);
+    assert_eq!(next_day.withdrawals, U128(10));
+    assert_eq!(next_day.active_users, 1);
+}
+
+#[test]
+fn test_frozen_reverie_still_allows_withdrawals() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), NearToken::from_near(10).as_yoctonear()).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.freeze_reverie(TEST_REVERIE_ID.to_string());
+    assert!(contract.is_reverie_frozen(TEST_REVERIE_ID.to_string()));
+    assert!(contract.get_reverie_metadata(TEST_REVERIE_ID.to_string()).unwrap().frozen);
+
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw(TEST_REVERIE_ID.to_string(), U128(NearToken::from_near(3).as_yoctonear()));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user.clone()), U128(NearToken::from_near(7).as_yoctonear()));
+
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.unfreeze_reverie(TEST_REVERIE_ID.to_string());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(1), None, None);
+    assert!(!contract.is_reverie_frozen(TEST_REVERIE_ID.to_string()));
+}
+
+#[test]
+#[should_panic(expected = "Reverie rev1 is frozen")]
+fn test_deposit_panic_reverie_frozen() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.freeze_reverie(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(user, NearToken::from_near(1).as_yoctonear()).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+}
+
+#[test]
+#[should_panic(expected = "Reverie rev1 is frozen")]
+fn test_record_spend_panic_reverie_frozen() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), NearToken::from_near(1).as_yoctonear()).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).build());
+    contract.freeze_reverie(TEST_REVERIE_ID.to_string());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user, U128(1), None, None);
+}
+
+#[test]
+fn test_vesting_deposit_claim_and_cancel() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.set_vesting_period(TEST_REVERIE_ID.to_string(), Some(near_sdk::json_types::U64(1_000)));
+
+    testing_env!(get_context(user.clone(), 1_000).block_timestamp(0).build());
+    contract.deposit_vesting(TEST_REVERIE_ID.to_string());
+
+    testing_env!(get_context(trusted.clone(), 0).block_timestamp(250).build());
+    assert_eq!(contract.get_claimable_vested(TEST_REVERIE_ID.to_string()), U128(250));
+    assert_eq!(contract.claim_vested(TEST_REVERIE_ID.to_string()), U128(250));
+    assert_eq!(contract.get_reverie_revenue(TEST_REVERIE_ID.to_string()), U128(250));
+
+    // The user keeps only the unvested remainder; the vested part since the claim goes to the reverie
+    testing_env!(get_context(user.clone(), 0).block_timestamp(400).build());
+    assert_eq!(contract.cancel_vesting(TEST_REVERIE_ID.to_string()), U128(600));
+    assert_eq!(contract.get_reverie_revenue(TEST_REVERIE_ID.to_string()), U128(400));
+    assert!(contract.get_vesting_schedule(TEST_REVERIE_ID.to_string(), user.clone()).is_none());
+
+    // Vesting goes through the same bookkeeping as balance deposits
+    let checkpoint = contract.get_ledger_checkpoint(TEST_REVERIE_ID.to_string());
+    assert_eq!(
+        (checkpoint.total_deposits, checkpoint.total_spends, checkpoint.total_withdrawals),
+        (U128(1_000), U128(400), U128(600))
+    );
+    let detail = contract.get_balance_detail(TEST_REVERIE_ID.to_string(), user);
+    assert_eq!((detail.lifetime_deposited, detail.lifetime_spent, detail.locked), (U128(1_000), U128(400), U128(0)));
+    let stats = contract.get_daily_stats(TEST_REVERIE_ID.to_string(), 0);
+    assert_eq!((stats.deposits, stats.spends, stats.withdrawals), (U128(1_000), U128(400), U128(600)));
+}
+
+#[test]
+fn test_vesting_large_amount_over_long_period() {
+    const DAY_NS: u64 = 24 * 60 * 60 * 1_000_000_000;
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.set_vesting_period(TEST_REVERIE_ID.to_string(), Some(near_sdk::json_types::U64(30 * DAY_NS)));
+
+    let amount = NearToken::from_near(1_000).as_yoctonear();
+    testing_env!(get_context(user.clone(), amount).block_timestamp(0).build());
+    let schedule = contract.deposit_vesting(TEST_REVERIE_ID.to_string());
+    assert_eq!(schedule.vested_at(15 * DAY_NS), amount / 2);
+    assert_eq!(schedule.vested_at(30 * DAY_NS), amount);
+
+    testing_env!(get_context(trusted, 0).block_timestamp(15 * DAY_NS).build());
+    assert_eq!(contract.get_claimable_vested(TEST_REVERIE_ID.to_string()), U128(amount / 2));
+    assert_eq!(contract.claim_vested(TEST_REVERIE_ID.to_string()), U128(amount / 2));
+
+    testing_env!(get_context(user, 0).block_timestamp(30 * DAY_NS).build());
+    assert_eq!(contract.cancel_vesting(TEST_REVERIE_ID.to_string()), U128(0));
+    assert_eq!(contract.get_reverie_revenue(TEST_REVERIE_ID.to_string()), U128(amount));
+}
+
+#[test]
+#[should_panic(expected = "Reverie rev1 has no vesting plan")]
+fn test_deposit_vesting_panic_no_plan() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(user, 1_000).build());
+    contract.deposit_vesting(TEST_REVERIE_ID.to_string());
+}
+
+#[test]
+fn test_admit_returns_ticket() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).block_height(42).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    let ticket = contract.admit(TEST_REVERIE_ID.to_string(), user.clone(), U128(60));
+    assert!(ticket.admitted);
+    assert_eq!(ticket.balance, U128(100));
+    assert_eq!(ticket.block_height.0, 42);
+    assert!(ticket.event_seq.0 > 0);
+
+    assert!(!contract.admit(TEST_REVERIE_ID.to_string(), user.clone(), U128(101)).admitted);
+    testing_env!(get_context(trusted, 0).build());
+    contract.freeze_reverie(TEST_REVERIE_ID.to_string());
+    assert!(!contract.admit(TEST_REVERIE_ID.to_string(), user, U128(60)).admitted);
+}
+
+#[test]
+fn test_estimate_storage_cost_deposit_only_first_deposit() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    let estimate = contract.estimate_storage_cost_deposit(TEST_REVERIE_ID.to_string(), user.clone());
+    assert!(estimate.bytes.0 > 0);
+    assert_eq!(estimate.cost.0, estimate.bytes.0 as u128 * env::storage_byte_cost().as_yoctonear());
+
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    let estimate = contract.estimate_storage_cost_deposit(TEST_REVERIE_ID.to_string(), user);
+    assert_eq!(estimate.bytes.0, 0);
+}
+
+#[test]
+#[should_panic(expected = "doesn't cover the storage cost")]
+fn test_create_reverie_panic_deposit_below_storage_cost() {
+    let trusted = accounts(2);
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 1).build());
+    contract.create_reverie(
+        TEST_REVERIE_ID.to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+        None,
+    );
+}
+
+#[test]
+fn test_get_events_since_tails_buffer() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(user.clone(), 100).block_height(7).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    let last_seq = contract.get_last_event_seq().0;
+    let events = contract.get_events_since(near_sdk::json_types::U64(last_seq - 2), 10);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].seq.0, last_seq - 1);
+    assert_eq!(events[1].block_height.0, 7);
+    assert!(matches!(events[1].event, events::PaymentEvent::Deposit { new_balance: U128(200), .. }));
+    assert!(contract.get_events_since(near_sdk::json_types::U64(last_seq), 10).is_empty());
+    assert_eq!(contract.get_events_since(near_sdk::json_types::U64(0), 1).len(), 1);
+}
+
+#[test]
+fn test_get_events_since_skips_overwritten_events() {
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(user, 1).build());
+    for _ in 0..events::EVENT_BUFFER_SIZE {
+        contract.deposit(TEST_REVERIE_ID.to_string());
+    }
+    // The reverie's creation event was overwritten, so the first event returned is later
+    let events = contract.get_events_since(near_sdk::json_types::U64(0), 10);
+    assert_eq!(events[0].seq.0, contract.get_last_event_seq().0 - events::EVENT_BUFFER_SIZE + 1);
+    assert!(events[0].seq.0 > 1);
+}
+
+#[test]
+fn test_get_events_since_reads_events_buffered_before_new_fields() {
+    let mut contract = contract_with_reverie(accounts(2));
+    let seq = contract.get_last_event_seq().0;
+    // A deposit logged before `credited` and `revenue` existed
+    contract.recent_events.insert(
+        seq % events::EVENT_BUFFER_SIZE,
+        events::StoredEvent {
+            seq,
+            block_height: 0,
+            block_timestamp: 0,
+            event_json: r#"{"event":"deposit","data":{"reverie_id":"rev1","user_id":"bob","amount":"5","new_balance":"5"}}"#.to_string(),
+        },
+    );
+    let events = contract.get_events_since(near_sdk::json_types::U64(seq - 1), 1);
+    assert!(matches!(&events[0].event, events::PaymentEvent::Deposit { credited: None, .. }));
+}
+
+#[test]
+fn test_reverie_transfer_two_step() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let operator = accounts(3);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    assert_eq!(contract.get_reverie_admin(TEST_REVERIE_ID.to_string()), trusted);
+
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.propose_reverie_transfer(TEST_REVERIE_ID.to_string(), operator.clone());
+    // Nothing changes until the new admin accepts
+    assert_eq!(contract.get_reverie_admin(TEST_REVERIE_ID.to_string()), trusted);
+    assert_eq!(contract.get_pending_reverie_transfer(TEST_REVERIE_ID.to_string()), Some(operator.clone()));
+
+    testing_env!(get_context(operator.clone(), 0).build());
+    contract.accept_reverie_transfer(TEST_REVERIE_ID.to_string());
+    assert_eq!(contract.get_reverie_admin(TEST_REVERIE_ID.to_string()), operator);
+    assert!(contract.get_pending_reverie_transfer(TEST_REVERIE_ID.to_string()).is_none());
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(100));
+
+    // The new admin operates the reverie
+    contract.freeze_reverie(TEST_REVERIE_ID.to_string());
+    assert!(contract.is_reverie_frozen(TEST_REVERIE_ID.to_string()));
+}
+
+#[test]
+#[should_panic(expected = "No transfer of reverie rev1 pending for danny")]
+fn test_accept_reverie_transfer_panic_not_proposed() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    contract.propose_reverie_transfer(TEST_REVERIE_ID.to_string(), accounts(4));
+    testing_env!(get_context(accounts(3), 0).build());
+    contract.accept_reverie_transfer(TEST_REVERIE_ID.to_string());
+}
+
+#[test]
+fn test_revoke_access_overrides_cached_grant() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_gated_reverie(trusted.clone());
+    contract.check_access(TEST_REVERIE_ID.to_string(), user.clone());
+    resolve_access_check(5_000, near_sdk::PromiseResult::Successful(b"true".to_vec()));
+    contract.on_access_checked(TEST_REVERIE_ID.to_string(), user.clone());
+    assert!(contract.has_cached_access(TEST_REVERIE_ID.to_string(), user.clone()));
+
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.revoke_access(TEST_REVERIE_ID.to_string(), user.clone(), "abuse".to_string());
+    assert!(!contract.has_cached_access(TEST_REVERIE_ID.to_string(), user.clone()));
+    assert_eq!(contract.get_access_revocation(TEST_REVERIE_ID.to_string(), user.clone()).unwrap().reason, "abuse");
+
+    contract.reinstate_access(TEST_REVERIE_ID.to_string(), user.clone());
+    assert!(!contract.is_access_revoked(TEST_REVERIE_ID.to_string(), user.clone()));
+    // The grant was dropped on revocation, so access has to be checked again
+    assert!(!contract.has_cached_access(TEST_REVERIE_ID.to_string(), user));
+}
+
+#[test]
+#[should_panic(expected = "Access of user bob to reverie rev1 was revoked: abuse")]
+fn test_check_access_panic_revoked_user() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_gated_reverie(trusted.clone());
+    contract.revoke_access(TEST_REVERIE_ID.to_string(), user.clone(), "abuse".to_string());
+    contract.check_access(TEST_REVERIE_ID.to_string(), user);
+}
+
+#[test]
+fn test_verify_api_key_against_anchored_hash() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted);
+    assert!(!contract.verify_api_key(TEST_REVERIE_ID.to_string(), "secret".to_string()));
+
+    let hash = near_sdk::json_types::Base58CryptoHash::from(env::sha256_array(b"secret"));
+    contract.set_reverie_api_key_hash(TEST_REVERIE_ID.to_string(), Some(hash));
+    assert_eq!(contract.get_reverie_api_key_hash(TEST_REVERIE_ID.to_string()), Some(hash));
+    assert!(contract.verify_api_key(TEST_REVERIE_ID.to_string(), "secret".to_string()));
+    assert!(!contract.verify_api_key(TEST_REVERIE_ID.to_string(), "secret2".to_string()));
+
+    // Rotating replaces the old credential
+    let rotated = near_sdk::json_types::Base58CryptoHash::from(env::sha256_array(b"secret2"));
+    contract.set_reverie_api_key_hash(TEST_REVERIE_ID.to_string(), Some(rotated));
+    assert!(!contract.verify_api_key(TEST_REVERIE_ID.to_string(), "secret".to_string()));
+}
+
+#[test]
+fn test_new_with_config_seeds_reveries() {
+    let trusted = accounts(2);
+    testing_env!(get_context(trusted.clone(), 0).build());
+    let config: init_config::InitConfig = near_sdk::serde_json::from_value(near_sdk::serde_json::json!({
+        "trusted_account": trusted,
+        "reveries": [{
+            "reverie_id": "Rev1",
+            "reverie_type": "type1",
+            "description": "desc1",
+            "access_condition": {"type": "Ed25519", "value": "pubkey1"},
+            "tags": ["ai"],
+            "admin": accounts(3),
+            "deposit_split_bps": 250,
+        }],
+        "max_reveries": 1,
+        "default_hook_gas": "20000000000000",
+    }))
+    .unwrap();
+    let contract = PaymentContract::new_with_config(config);
+    assert_eq!(contract.get_reverie_ids(), vec![TEST_REVERIE_ID.to_string()]);
+    assert_eq!(contract.get_reverie_admin(TEST_REVERIE_ID.to_string()), accounts(3));
+    assert_eq!(contract.get_deposit_split(TEST_REVERIE_ID.to_string()), Some(250));
+    assert_eq!(contract.get_reverie_limits().max_reveries, Some(1));
+    assert_eq!(contract.get_default_hook_gas(), near_sdk::Gas::from_tgas(20));
+
+    let exported = contract.get_config();
+    assert_eq!(exported.reveries[0].reverie_id, TEST_REVERIE_ID);
+    assert_eq!(exported.reveries[0].metadata.listing.tags, vec!["ai".to_string()]);
+    assert_eq!(PaymentContract::new_with_config(exported.clone()).get_config(), exported);
+}
+
+#[test]
+fn test_get_security_config_reports_contract_settings() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.add_authorized_spender(accounts(4));
+    contract.set_max_reveries(Some(5));
+
+    let security = contract.get_security_config();
+    assert_eq!(security.trusted_account, trusted);
+    assert_eq!(security.authorized_spenders, vec![accounts(4)]);
+    assert_eq!(security.limits, limits::ReverieLimits { reverie_count: 1, max_reveries: Some(5) });
+    assert!(security.open_registry.is_none());
+    assert_eq!(security.fee_pool, U128(0));
+}
+
+#[test]
+fn test_create_reverie_with_deposit_credits_caller() {
+    let trusted = accounts(2);
+    let deposit = NearToken::from_near(1).as_yoctonear();
+    testing_env!(get_context(trusted.clone(), deposit).build());
+    let mut contract = PaymentContract::new(trusted.clone());
+    let balance = contract.create_reverie_with_deposit(
+        "Rev1".to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+    );
+    // The reverie's storage is paid out of the deposit
+    assert!(balance.0 > 0 && balance.0 < deposit);
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), trusted.clone()), balance);
+    assert_eq!(contract.get_reverie_admin(TEST_REVERIE_ID.to_string()), trusted);
+
+    // A single composite event covers both the creation and the deposit
+    let events = contract.get_events_since(near_sdk::json_types::U64(0), 10);
+    assert_eq!(events.len(), 1);
+    assert!(matches!(
+        events[0].event,
+        events::PaymentEvent::ReverieCreatedWithDeposit { amount, new_balance, .. } if amount == balance && new_balance == balance
+    ));
+}
+
+#[test]
+#[should_panic(expected = "ReverieId 'rev1' already exists on reverie_metadata")]
+fn test_create_reverie_with_deposit_panic_existing_reverie() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(trusted, NearToken::from_near(1).as_yoctonear()).build());
+    contract.create_reverie_with_deposit(
+        TEST_REVERIE_ID.to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+    );
+}
+
+#[test]
+#[should_panic(expected = "doesn't cover the storage cost")]
+fn test_create_reverie_with_deposit_panic_deposit_below_storage_cost() {
+    let trusted = accounts(2);
+    testing_env!(get_context(trusted.clone(), 500).build());
+    let mut contract = PaymentContract::new(trusted);
+    contract.create_reverie_with_deposit(
+        TEST_REVERIE_ID.to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+    );
+}
+
+#[test]
+fn test_create_reverie_with_deposit_by_open_registry_user() {
+    let trusted = accounts(2);
+    let user = accounts(1);
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    contract.set_open_registry(Some(open_registry_config()));
+
+    let metadata = ReverieMetadata {
+        reverie_type: "open".to_string(),
+        description: "desc1".to_string(),
+        access_condition: AccessCondition::Ed25519("pubkey1".to_string()),
+        rounding_policy: rounding::RoundingPolicy::default(),
+        denomination: Denomination::Near,
+        listing: ReverieListing::default(),
+        frozen: false,
+        credits_per_near: None,
+    };
+    let storage_cost = storage::StorageCostEstimate::from_bytes(
+        contract.create_reverie_storage_bytes("user-alice", &metadata),
+    ).cost.0;
+
+    let deposit = NearToken::from_near(1).as_yoctonear();
+    testing_env!(get_context(user.clone(), deposit).build());
+    let balance = contract.create_reverie_with_deposit(
+        "user-alice".to_string(),
+        metadata.reverie_type,
+        metadata.description,
+        metadata.access_condition,
+        None,
+    );
+    assert_eq!(balance, U128(deposit - storage_cost));
+    assert_eq!(contract.get_reverie_admin("user-alice".to_string()), user.clone());
+    assert_eq!(contract.get_balance("user-alice".to_string(), user), balance);
+}
+
+#[test]
+#[should_panic(expected = "Only the trusted account can create reveries")]
+fn test_create_reverie_with_deposit_panic_user_without_open_registry() {
+    let trusted = accounts(2);
+    let mut contract = new_contract(trusted);
+    testing_env!(get_context(accounts(1), NearToken::from_near(1).as_yoctonear()).build());
+    contract.create_reverie_with_deposit(
+        "user-alice".to_string(),
+        "open".to_string(),
+        "desc1".to_string(),
+        AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+    );
+}
+
+#[test]
+#[should_panic(expected = "Reverie id must start with the open registry prefix user-")]
+fn test_create_reverie_with_deposit_panic_outside_open_registry_prefix() {
+    let trusted = accounts(2);
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    contract.set_open_registry(Some(open_registry_config()));
+    testing_env!(get_context(accounts(1), NearToken::from_near(1).as_yoctonear()).build());
+    contract.create_reverie_with_deposit(
+        "team-alice".to_string(),
+        "open".to_string(),
+        "desc1".to_string(),
+        AccessCondition::Ed25519("pubkey1".to_string()),
+        None,
+    );
+}
+
+#[test]
+fn test_refund_depositors_in_chunks_then_deletes_reverie() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    for user in [accounts(1), accounts(3)] {
+        testing_env!(get_context(user, 100).build());
+        contract.deposit(TEST_REVERIE_ID.to_string());
+    }
+    testing_env!(get_context(trusted, 0).build());
+    contract.shutdown_reverie(TEST_REVERIE_ID.to_string());
+    assert!(contract.is_reverie_frozen(TEST_REVERIE_ID.to_string()));
+
+    assert_eq!(contract.refund_depositors(TEST_REVERIE_ID.to_string(), 1), 1);
+    assert_eq!(contract.get_depositor_count(TEST_REVERIE_ID.to_string()), 1);
+    assert_eq!(contract.refund_depositors(TEST_REVERIE_ID.to_string(), 10), 1);
+    assert_eq!(contract.get_depositor_count(TEST_REVERIE_ID.to_string()), 0);
+    let stats = contract.get_daily_stats(TEST_REVERIE_ID.to_string(), 0);
+    assert_eq!((stats.deposits, stats.withdrawals), (U128(200), U128(200)));
+    // Deleted only once both transfers have settled
+    resolve_payout(near_sdk::PromiseResult::Successful(vec![]));
+    assert!(contract.on_depositor_refund(TEST_REVERIE_ID.to_string(), accounts(3), U128(100)));
+    assert!(contract.get_reverie_metadata(TEST_REVERIE_ID.to_string()).is_some());
+    assert!(contract.on_depositor_refund(TEST_REVERIE_ID.to_string(), accounts(1), U128(100)));
+    assert!(contract.get_reverie_metadata(TEST_REVERIE_ID.to_string()).is_none());
+    assert!(contract.get_reverie_ids().is_empty());
+}
+
+#[test]
+fn test_failed_depositor_refund_is_retried() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.shutdown_reverie(TEST_REVERIE_ID.to_string());
+    contract.refund_depositors(TEST_REVERIE_ID.to_string(), 10);
+
+    resolve_payout(near_sdk::PromiseResult::Failed);
+    assert!(!contract.on_depositor_refund(TEST_REVERIE_ID.to_string(), user.clone(), U128(100)));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user.clone()), U128(100));
+    assert_eq!(contract.get_depositor_count(TEST_REVERIE_ID.to_string()), 1);
+    testing_env!(get_context(trusted, 0).build());
+    assert_eq!(contract.refund_depositors(TEST_REVERIE_ID.to_string(), 10), 1);
+}
+
+#[test]
+#[should_panic(expected = "Reverie rev1 is shut down")]
+fn test_unfreeze_panic_shut_down_reverie() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted);
+    contract.shutdown_reverie(TEST_REVERIE_ID.to_string());
+    contract.unfreeze_reverie(TEST_REVERIE_ID.to_string());
+}
+
+#[test]
+fn test_record_spend_keeps_evidence_hash() {
+    let user = accounts(1);
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(trusted, 0).build());
+    contract.set_dispute_window(TEST_REVERIE_ID.to_string(), Some(near_sdk::json_types::U64(1_000)));
+
+    let evidence = near_sdk::json_types::Base58CryptoHash::from(env::sha256_array(b"usage log"));
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user, U128(30), None, Some(evidence));
+    let spend_id = contract.get_last_event_seq();
+    assert_eq!(contract.get_spend_record(spend_id).unwrap().evidence_hash, Some(evidence));
+    let events = contract.get_events_since(near_sdk::json_types::U64(spend_id.0 - 1), 1);
+    assert!(matches!(
+        events[0].event,
+        events::PaymentEvent::Spend { evidence_hash: Some(hash), .. } if hash == evidence
+    ));
+}
+
+#[test]
+fn test_authorized_spender_can_spend_on_any_reverie() {
+    let trusted = accounts(2);
+    let spender = accounts(3);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.add_authorized_spender(spender.clone());
+    assert_eq!(contract.get_authorized_spenders(), vec![spender.clone()]);
+    assert!(contract.is_reverie_spender(TEST_REVERIE_ID.to_string(), spender.clone()));
+
+    testing_env!(get_context(user.clone(), 100).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    testing_env!(get_context(spender.clone(), 0).build());
+    contract.record_spend(TEST_REVERIE_ID.to_string(), user.clone(), U128(40), None, None);
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(60));
+
+    testing_env!(get_context(trusted, 0).build());
+    contract.remove_authorized_spender(spender.clone());
+    assert!(!contract.is_authorized_spender(spender));
+}
+
+#[test]
+#[should_panic(expected = "Only the trusted account can manage authorized spenders")]
+fn test_add_authorized_spender_panic_not_trusted() {
+    let mut contract = contract_with_reverie(accounts(2));
+    testing_env!(get_context(accounts(3), 0).build());
+    contract.add_authorized_spender(accounts(3));
+}
+
+fn contract_with_dao_reverie(trusted: AccountId, role: Option<&str>) -> PaymentContract {
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    contract.create_reverie(
+        TEST_REVERIE_ID.to_string(),
+        "type1".to_string(),
+        "desc1".to_string(),
+        AccessCondition::DaoMember { dao_contract: "dao.sputnik-dao.near".parse().unwrap(), role: role.map(str::to_string) },
+        None,
+        None,
+    );
+    contract
+}
+
+fn dao_policy() -> Vec<u8> {
+    near_sdk::serde_json::to_vec(&near_sdk::serde_json::json!({
+        "roles": [
+            {"name": "all", "kind": "Everyone", "permissions": ["*:AddProposal"]},
+            {"name": "council", "kind": {"Group": ["bob"]}, "permissions": ["*:*"]},
+            {"name": "reviewers", "kind": {"Group": ["danny"]}, "permissions": ["*:VoteApprove"]},
+        ],
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_dao_member_access_granted_to_group_members() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_dao_reverie(trusted, None);
+    contract.check_access(TEST_REVERIE_ID.to_string(), accounts(1));
+    assert_eq!(near_sdk::test_utils::get_created_receipts()[0].receiver_id.as_str(), "dao.sputnik-dao.near");
+
+    resolve_access_check(0, near_sdk::PromiseResult::Successful(dao_policy()));
+    assert!(contract.on_dao_policy_checked(TEST_REVERIE_ID.to_string(), accounts(1), None));
+    assert!(contract.has_cached_access(TEST_REVERIE_ID.to_string(), accounts(1)));
+    // Roles open to everyone don't count as membership
+    assert!(!contract.on_dao_policy_checked(TEST_REVERIE_ID.to_string(), accounts(4), None));
+}
+
+#[test]
+fn test_dao_member_access_limited_to_role() {
+    let mut contract = contract_with_dao_reverie(accounts(2), Some("council"));
+    resolve_access_check(0, near_sdk::PromiseResult::Successful(dao_policy()));
+    let role = Some("council".to_string());
+    assert!(contract.on_dao_policy_checked(TEST_REVERIE_ID.to_string(), accounts(1), role.clone()));
+    assert!(!contract.on_dao_policy_checked(TEST_REVERIE_ID.to_string(), accounts(3), role));
+}
+
+#[test]
+fn test_max_reveries_reported_in_limits() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted);
+    contract.set_max_reveries(Some(2));
+    contract.create_reverie("rev2".to_string(), "Type".to_string(), "Other".to_string(), AccessCondition::Ed25519("pk".to_string()), None, None);
+    assert_eq!(contract.get_reverie_limits(), limits::ReverieLimits { reverie_count: 2, max_reveries: Some(2) });
+    // Deleting frees a slot
+    contract.delete_reverie_admin("rev2".to_string());
+    contract.create_reverie("rev3".to_string(), "Type".to_string(), "Other".to_string(), AccessCondition::Ed25519("pk".to_string()), None, None);
+}
+
+#[test]
+#[should_panic(expected = "ERR_MAX_REVERIES_REACHED")]
+fn test_create_reverie_panic_max_reveries_reached() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted);
+    contract.set_max_reveries(Some(1));
+    contract.create_reverie("rev2".to_string(), "Type".to_string(), "Other".to_string(), AccessCondition::Ed25519("pk".to_string()), None, None);
+}
+
+#[test]
+fn test_record_spend_credits_rounds_charge_up() {
+    let trusted = accounts(2);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted.clone());
+    contract.set_credits_per_near(TEST_REVERIE_ID.to_string(), Some(U128(3)));
+    testing_env!(get_context(user.clone(), NearToken::from_near(1).as_yoctonear()).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+    assert_eq!(
+        contract.get_credit_balance(TEST_REVERIE_ID.to_string(), user.clone()),
+        credits::CreditBalance { balance: U128(NearToken::from_near(1).as_yoctonear()), credits: Some(U128(3)) }
+    );
+
+    testing_env!(get_context(trusted, 0).build());
+    let spent = contract.record_spend_credits(TEST_REVERIE_ID.to_string(), user.clone(), U128(1));
+    assert_eq!(spent, U128(333_333_333_333_333_333_333_334));
+    let balance = contract.get_credit_balance(TEST_REVERIE_ID.to_string(), user);
+    assert_eq!(balance.balance, U128(666_666_666_666_666_666_666_666));
+    assert_eq!(balance.credits, Some(U128(1)));
+}
+
+#[test]
+fn test_withdraw_credits_rounds_payout_down() {
+    let trusted = accounts(2);
+    let user = accounts(1);
+    let mut contract = contract_with_reverie(trusted);
+    contract.set_credits_per_near(TEST_REVERIE_ID.to_string(), Some(U128(3)));
+    testing_env!(get_context(user.clone(), NearToken::from_near(1).as_yoctonear()).build());
+    contract.deposit(TEST_REVERIE_ID.to_string());
+
+    testing_env!(get_context(user.clone(), 0).build());
+    contract.withdraw_credits(TEST_REVERIE_ID.to_string(), U128(2));
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), user), U128(333_333_333_333_333_333_333_334));
+}
+
+#[test]
+#[should_panic(expected = "Reverie rev1 has no credit rate")]
+fn test_record_spend_credits_panic_without_rate() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted);
+    contract.record_spend_credits(TEST_REVERIE_ID.to_string(), accounts(1), U128(1));
+}
+
+fn open_registry_config() -> open_registry::OpenRegistryConfig {
+    open_registry::OpenRegistryConfig {
+        id_prefix: "user-".to_string(),
+        reverie_type: "open".to_string(),
+        access_condition: AccessCondition::Ed25519("pubkey1".to_string()),
+    }
+}
+
+#[test]
+fn test_deposit_auto_registers_reverie_under_open_registry_prefix() {
+    let trusted = accounts(2);
+    let user = accounts(1);
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted.clone(), 0).build());
+    contract.set_open_registry(Some(open_registry_config()));
+
+    let deposit = NearToken::from_near(1).as_yoctonear();
+    testing_env!(get_context(user.clone(), deposit).build());
+    contract.deposit("user-alice".to_string());
+    assert_eq!(contract.get_reverie_metadata("user-alice".to_string()).unwrap().reverie_type, "open");
+    assert_eq!(contract.get_reverie_admin("user-alice".to_string()), user);
+    let balance = contract.get_balance("user-alice".to_string(), user.clone()).0;
+    assert!(balance > 0 && balance < deposit);
+
+    // Later deposits credit in full
+    testing_env!(get_context(user.clone(), deposit).build());
+    contract.deposit("user-alice".to_string());
+    assert_eq!(contract.get_balance("user-alice".to_string(), user), U128(balance + deposit));
+}
+
+#[test]
+#[should_panic(expected = "ReverieId team-alice not found in registry")]
+fn test_deposit_outside_open_registry_prefix_panics() {
+    let trusted = accounts(2);
+    let mut contract = new_contract(trusted.clone());
+    testing_env!(get_context(trusted, 0).build());
+    contract.set_open_registry(Some(open_registry_config()));
+    testing_env!(get_context(accounts(1), NearToken::from_near(1).as_yoctonear()).build());
+    contract.deposit("team-alice".to_string());
+}
+
+#[test]
+fn test_dump_reverie_state_pages_over_depositors() {
+    let trusted = accounts(2);
+    let mut contract = contract_with_reverie(trusted.clone());
+    for (user, amount) in [(accounts(1), 100), (accounts(3), 200), (accounts(4), 300)] {
+        testing_env!(get_context(user, amount).build());
+        contract.deposit(TEST_REVERIE_ID.to_string());
+    }
+
+    let page = contract.dump_reverie_state(TEST_REVERIE_ID.to_string(), 1, 10);
+    let dump: state_dump::ReverieStateDump = near_sdk::borsh::from_slice(&page.0).unwrap();
+    assert_eq!(dump.version, state_dump::STATE_DUMP_VERSION);
+    assert_eq!(dump.admin, trusted);
+    assert_eq!(dump.depositor_count, 3);
+    assert_eq!(dump.ledger.total_deposits, U128(600));
+    assert_eq!(dump.balances, vec![(accounts(3), U128(200)), (accounts(4), U128(300))]);
+}
+
+#[test]
+#[should_panic(expected = "ReverieId missing not found in registry")]
+fn test_dump_reverie_state_panic_unknown_reverie() {
+    let contract = contract_with_reverie(accounts(2));
+    contract.dump_reverie_state("missing".to_string(), 0, 10);
+}
+
+// Writes a first-layout contract owned by accounts(2) where accounts(1) holds 100 on TEST_REVERIE_ID
+fn write_first_deployed_layout() {
+    #[derive(near_sdk::borsh::BorshSerialize)]
+    #[borsh(crate = "near_sdk::borsh")]
+    struct PaymentContractV1 {
+        greeting: String,
+        trusted_account: AccountId,
+        reverie_balances: LookupMap<ReverieId, LookupMap<AccountId, u128>>,
+        reverie_ids: Vec<ReverieId>,
+        reverie_metadata: LookupMap<ReverieId, reveries_types::ReverieMetadataV1>,
+    }
+    testing_env!(get_context(accounts(0), 0).build());
+    let mut balances = LookupMap::new(format!("b:{}", TEST_REVERIE_ID).as_bytes());
+    balances.insert(accounts(1), 100u128);
+    balances.flush();
+    let mut reverie_balances = LookupMap::new(b"b");
+    reverie_balances.insert(TEST_REVERIE_ID.to_string(), balances);
+    reverie_balances.flush();
+    let mut reverie_metadata = LookupMap::new(b"r");
+    reverie_metadata.insert(
+        TEST_REVERIE_ID.to_string(),
+        reveries_types::ReverieMetadataV1 {
+            reverie_type: "type1".to_string(),
+            description: "desc1".to_string(),
+            access_condition: AccessCondition::Ed25519("pubkey1".to_string()),
+        },
+    );
+    reverie_metadata.flush();
+    env::state_write(&PaymentContractV1 {
+        greeting: "Hello".to_string(),
+        trusted_account: accounts(2),
+        reverie_balances,
+        reverie_ids: vec![TEST_REVERIE_ID.to_string()],
+        reverie_metadata,
+    });
+}
+
+#[test]
+fn test_migrate_from_first_deployed_layout() {
+    write_first_deployed_layout();
+    let contract = PaymentContract::migrate();
+    assert_eq!(contract.get_trusted_account(), accounts(2));
+    assert_eq!(contract.get_reverie_ids(), vec![TEST_REVERIE_ID.to_string()]);
+    let metadata = contract.get_reverie_metadata(TEST_REVERIE_ID.to_string()).unwrap();
+    assert_eq!(metadata.reverie_type, "type1");
+    assert!(!metadata.frozen);
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), accounts(1)), U128(100));
+}
+
+#[test]
+fn test_shutdown_keeps_reverie_with_untracked_depositors() {
+    write_first_deployed_layout();
+    let mut contract = PaymentContract::migrate();
+    testing_env!(get_context(accounts(2), 0).build());
+    contract.shutdown_reverie(TEST_REVERIE_ID.to_string());
+    assert_eq!(contract.refund_depositors(TEST_REVERIE_ID.to_string(), 10), 0);
+    // accounts(1) deposited before depositors were tracked, so the reverie must outlive the refunds
+    assert!(contract.get_reverie_metadata(TEST_REVERIE_ID.to_string()).is_some());
+    assert_eq!(contract.get_balance(TEST_REVERIE_ID.to_string(), accounts(1)), U128(100));
+}
@@ -0,0 +1,106 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// Umbral keys for a reverie's encrypted content. Proxy nodes use `verifying_pk`
+/// to check kfrags and `delegating_pk` to match capsules to the reverie.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct UmbralPublicKeys {
+    pub delegating_pk: String,
+    pub verifying_pk: String,
+}
+
+/// Authorizes proxy nodes to re-encrypt a reverie's capsules for `user_id`
+/// with the kfrag set identified by `kfrag_hash`.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReencryptionGrant {
+    pub user_id: AccountId,
+    pub kfrag_hash: String,
+    pub granted_at: U64,
+}
+
+#[near]
+impl PaymentContract {
+    pub fn set_umbral_public_keys(&mut self, reverie_id: ReverieId, public_keys: UmbralPublicKeys) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can manage Umbral keys");
+        self.require_umbral_reverie(&reverie_id);
+        self.umbral_public_keys.insert(reverie_id, public_keys);
+    }
+
+    pub fn get_umbral_public_keys(&self, reverie_id: ReverieId) -> Option<UmbralPublicKeys> {
+        self.umbral_public_keys.get(&reverie_id).cloned()
+    }
+
+    /// Grants (or replaces) a user's re-encryption authorization for a reverie.
+    pub fn grant_reencryption(&mut self, reverie_id: ReverieId, user_id: AccountId, kfrag_hash: String) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can grant re-encryption");
+        self.require_umbral_reverie(&reverie_id);
+        assert!(
+            self.umbral_public_keys.get(&reverie_id).is_some(),
+            "Umbral public keys are not set for reverie {}",
+            reverie_id
+        );
+        let grant = ReencryptionGrant {
+            user_id: user_id.clone(),
+            kfrag_hash: kfrag_hash.clone(),
+            granted_at: U64(clock::block_timestamp()),
+        };
+        self.reencryption_grants.insert(self.user_key(&reverie_id, &user_id), grant);
+        if !self.reencryption_grantees.contains_key(&reverie_id) {
+            let grantees = self.new_reencryption_grantees();
+            self.reencryption_grantees.insert(reverie_id.clone(), grantees);
+        }
+        if let Some(grantees) = self.reencryption_grantees.get_mut(&reverie_id) {
+            grantees.insert(user_id.clone());
+        }
+        self.emit_event(events::PaymentEvent::ReencryptionGranted { reverie_id, user_id, kfrag_hash });
+    }
+
+    pub fn revoke_reencryption(&mut self, reverie_id: ReverieId, user_id: AccountId) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can revoke re-encryption");
+        assert!(
+            self.reencryption_grants.remove(&self.user_key(&reverie_id, &user_id)).is_some(),
+            "No re-encryption grant for user {} on reverie {}",
+            user_id,
+            reverie_id
+        );
+        if let Some(grantees) = self.reencryption_grantees.get_mut(&reverie_id) {
+            grantees.remove(&user_id);
+        }
+        self.emit_event(events::PaymentEvent::ReencryptionRevoked { reverie_id, user_id });
+    }
+
+    pub fn get_reencryption_grant(&self, reverie_id: ReverieId, user_id: AccountId) -> Option<ReencryptionGrant> {
+        self.reencryption_grants.get(&self.user_key(&reverie_id, &user_id)).cloned()
+    }
+
+    /// Paginated grants for a reverie, for proxy nodes to poll.
+    pub fn get_reencryption_grants(&self, reverie_id: ReverieId, from_index: u32, limit: u32) -> Vec<ReencryptionGrant> {
+        self.reencryption_grantees
+            .get(&reverie_id)
+            .map(|grantees| {
+                grantees
+                    .iter()
+                    .skip(from_index as usize)
+                    .take(limit as usize)
+                    .filter_map(|user_id| self.get_reencryption_grant(reverie_id.clone(), user_id.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl PaymentContract {
+    fn require_umbral_reverie(&self, reverie_id: &str) {
+        let metadata = self
+            .reverie_metadata
+            .get(reverie_id)
+            .unwrap_or_else(|| env::panic_str(&format!("ReverieId {} not found in registry", reverie_id)));
+        assert!(
+            matches!(metadata.access_condition, AccessCondition::Umbral(_)),
+            "Reverie {} does not use Umbral access",
+            reverie_id
+        );
+    }
+}
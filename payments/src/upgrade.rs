@@ -0,0 +1,34 @@
+use crate::*;
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::Gas;
+
+// Gas kept back for finishing `upgrade` itself; the rest is given to the migration call.
+const GAS_RESERVED_FOR_UPGRADE: Gas = Gas::from_tgas(10);
+
+#[near]
+impl PaymentContract {
+    /// Deploys `code` to this account and then calls `migrate_method` (with `{}` args) if given.
+    /// `code_hash` must be the sha256 of `code`, to avoid deploying the wrong artifact.
+    /// Only the contract account can call this.
+    pub fn upgrade(
+        &mut self,
+        code: Base64VecU8,
+        code_hash: Base58CryptoHash,
+        migrate_method: Option<String>,
+    ) -> Promise {
+        assert_eq!(env::predecessor_account_id(), env::current_account_id(), "Only the contract account can upgrade the contract");
+        let expected: near_sdk::CryptoHash = code_hash.into();
+        assert_eq!(env::sha256_array(&code.0), expected, "Code hash mismatch");
+
+        let promise = Promise::new(env::current_account_id()).deploy_contract(code.0);
+        match migrate_method {
+            Some(method_name) => {
+                let migrate_gas = env::prepaid_gas()
+                    .saturating_sub(env::used_gas())
+                    .saturating_sub(GAS_RESERVED_FOR_UPGRADE);
+                promise.function_call(method_name, b"{}".to_vec(), NearToken::from_yoctonear(0), migrate_gas)
+            }
+            None => promise,
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// A prepaid plan: `amount` vests linearly to the reverie between `start` and `end`
+/// (nanoseconds). `claimed` has already moved to the reverie's revenue.
+#[near(serializers = [borsh, json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct VestingSchedule {
+    pub amount: U128,
+    pub claimed: U128,
+    pub start: U64,
+    pub end: U64,
+}
+
+impl VestingSchedule {
+    pub fn vested_at(&self, now: u64) -> u128 {
+        if now >= self.end.0 {
+            return self.amount.0;
+        }
+        let elapsed = now.saturating_sub(self.start.0) as u128;
+        let period = (self.end.0 - self.start.0) as u128;
+        // amount * elapsed / period without the overflowing product: period and elapsed fit
+        // in u64, so the remainder term stays below u128::MAX
+        let amount = self.amount.0;
+        amount / period * elapsed + (amount % period) * elapsed / period
+    }
+
+    pub fn claimable_at(&self, now: u64) -> u128 {
+        self.vested_at(now) - self.claimed.0
+    }
+}
+
+#[near]
+impl PaymentContract {
+    /// Enables vesting deposits on a NEAR reverie: each `deposit_vesting` vests to the reverie
+    /// linearly over `period_ns`. `None` stops new vesting deposits; running plans continue.
+    pub fn set_vesting_period(&mut self, reverie_id: ReverieId, period_ns: Option<U64>) {
+        assert_eq!(env::predecessor_account_id(), self.trusted_account, "Only the trusted account can set vesting periods");
+        self.require_reverie_exists(&reverie_id);
+        self.assert_near_denominated(&reverie_id);
+        match period_ns {
+            Some(period_ns) => {
+                assert!(period_ns.0 > 0, "Vesting period must be greater than 0");
+                self.vesting_periods.insert(reverie_id, period_ns.0);
+            }
+            None => {
+                self.vesting_periods.remove(&reverie_id);
+            }
+        }
+    }
+
+    pub fn get_vesting_period(&self, reverie_id: ReverieId) -> Option<U64> {
+        self.vesting_periods.get(&reverie_id).map(|period_ns| U64(*period_ns))
+    }
+
+    /// Starts a vesting plan with the attached deposit. Depositing again while a plan is
+    /// running restarts it with the unvested remainder plus the new deposit.
+    #[payable]
+    pub fn deposit_vesting(&mut self, reverie_id: ReverieId) -> VestingSchedule {
+        let period_ns = *self
+            .vesting_periods
+            .get(&reverie_id)
+            .unwrap_or_else(|| env::panic_str(&format!("Reverie {} has no vesting plan", reverie_id)));
+        self.assert_not_frozen(&reverie_id);
+        let user_id = env::predecessor_account_id();
+        let amount = env::attached_deposit().as_yoctonear();
+        assert!(amount > 0, "Deposit amount must be greater than 0");
+
+        let now = clock::block_timestamp();
+        let key = self.user_key(&reverie_id, &user_id);
+        let (unvested, vested) = match self.vesting_schedules.get(&key).cloned() {
+            Some(schedule) => (schedule.amount.0 - schedule.vested_at(now), schedule.claimable_at(now)),
+            None => {
+                let mut users = self.vesting_users.get(&reverie_id).cloned().unwrap_or_default();
+                users.push(user_id.clone());
+                self.vesting_users.insert(reverie_id.clone(), users);
+                (0, 0)
+            }
+        };
+        self.credit_vested(&reverie_id, vested);
+        let schedule = VestingSchedule {
+            amount: U128(unvested + amount),
+            claimed: U128(0),
+            start: U64(now),
+            end: U64(now + period_ns),
+        };
+        self.vesting_schedules.insert(key, schedule.clone());
+        let seq = self.emit_event(events::PaymentEvent::VestingStarted {
+            reverie_id: reverie_id.clone(),
+            user_id: user_id.clone(),
+            amount: schedule.amount,
+            end: schedule.end,
+        });
+        self.record_vesting_entry(&reverie_id, &user_id, ledger::LedgerEntry::Deposit, amount, seq);
+        self.record_vesting_entry(&reverie_id, &user_id, ledger::LedgerEntry::Spend, vested, seq);
+        schedule
+    }
+
+    pub fn get_vesting_schedule(&self, reverie_id: ReverieId, user_id: AccountId) -> Option<VestingSchedule> {
+        self.vesting_schedules.get(&self.user_key(&reverie_id, &user_id)).cloned()
+    }
+
+    /// Vested NEAR not yet claimed by the reverie, across all of its users.
+    pub fn get_claimable_vested(&self, reverie_id: ReverieId) -> U128 {
+        let now = clock::block_timestamp();
+        let users = self.vesting_users.get(&reverie_id).cloned().unwrap_or_default();
+        U128(
+            users
+                .into_iter()
+                .filter_map(|user_id| self.vesting_schedules.get(&self.user_key(&reverie_id, &user_id)))
+                .map(|schedule| schedule.claimable_at(now))
+                .sum(),
+        )
+    }
+
+    /// Moves everything vested so far into the reverie's revenue, withdrawable with
+    /// `withdraw_reverie_revenue`, and drops fully vested plans. Returns the amount claimed.
+    pub fn claim_vested(&mut self, reverie_id: ReverieId) -> U128 {
+        self.assert_reverie_admin(&reverie_id, "Only the reverie admin can claim vested deposits");
+        self.require_reverie_exists(&reverie_id);
+        let now = clock::block_timestamp();
+        let users = self.vesting_users.remove(&reverie_id).unwrap_or_default();
+        let mut remaining_users = Vec::new();
+        let mut claimed = Vec::new();
+        for user_id in users {
+            let key = self.user_key(&reverie_id, &user_id);
+            let Some(schedule) = self.vesting_schedules.get_mut(&key) else {
+                continue;
+            };
+            claimed.push((user_id.clone(), schedule.claimable_at(now)));
+            schedule.claimed = U128(schedule.vested_at(now));
+            if schedule.claimed == schedule.amount {
+                self.vesting_schedules.remove(&key);
+            } else {
+                remaining_users.push(user_id);
+            }
+        }
+        if !remaining_users.is_empty() {
+            self.vesting_users.insert(reverie_id.clone(), remaining_users);
+        }
+        let total = claimed.iter().map(|(_, amount)| amount).sum();
+        self.credit_vested(&reverie_id, total);
+        let seq = self.emit_event(events::PaymentEvent::VestedClaimed { reverie_id: reverie_id.clone(), amount: U128(total) });
+        for (user_id, amount) in claimed {
+            self.record_vesting_entry(&reverie_id, &user_id, ledger::LedgerEntry::Spend, amount, seq);
+        }
+        U128(total)
+    }
+
+    /// Ends the caller's plan: the vested part goes to the reverie and the unvested
+    /// remainder is refunded. Returns the refund.
+    pub fn cancel_vesting(&mut self, reverie_id: ReverieId) -> U128 {
+        let user_id = env::predecessor_account_id();
+        let schedule = self
+            .vesting_schedules
+            .remove(&self.user_key(&reverie_id, &user_id))
+            .unwrap_or_else(|| env::panic_str(&format!("No vesting plan for user {} on reverie {}", user_id, reverie_id)));
+        if let Some(users) = self.vesting_users.get_mut(&reverie_id) {
+            users.retain(|id| id != &user_id);
+            if users.is_empty() {
+                self.vesting_users.remove(&reverie_id);
+            }
+        }
+        let now = clock::block_timestamp();
+        let vested = schedule.claimable_at(now);
+        self.credit_vested(&reverie_id, vested);
+        let refund = schedule.amount.0 - schedule.vested_at(now);
+        if refund > 0 {
+            Promise::new(user_id.clone()).transfer(NearToken::from_yoctonear(refund));
+        }
+        let seq = self.emit_event(events::PaymentEvent::VestingCancelled {
+            reverie_id: reverie_id.clone(),
+            user_id: user_id.clone(),
+            refunded: U128(refund),
+        });
+        self.record_vesting_entry(&reverie_id, &user_id, ledger::LedgerEntry::Spend, vested, seq);
+        self.record_vesting_entry(&reverie_id, &user_id, ledger::LedgerEntry::VestingRefund, refund, seq);
+        U128(refund)
+    }
+}
+
+impl PaymentContract {
+    // internal method keeping vesting in the same bookkeeping as balances: the deposit counts
+    // as a deposit, vested parts as spends and the cancel refund as a withdrawal. Nothing is
+    // recorded once the reverie has been deleted.
+    fn record_vesting_entry(
+        &mut self,
+        reverie_id: &str,
+        user_id: &AccountId,
+        entry: fn(u128) -> ledger::LedgerEntry,
+        amount: u128,
+        seq: u64,
+    ) {
+        if amount == 0 || self.reverie_metadata.get(reverie_id).is_none() {
+            return;
+        }
+        let entry = entry(amount);
+        // Unvested principal is in the ledger but not in any balance
+        let principal = *self.vesting_principal.get(reverie_id).unwrap_or(&0);
+        let principal = match &entry {
+            ledger::LedgerEntry::Deposit(_) => principal + amount,
+            _ => principal.saturating_sub(amount),
+        };
+        self.vesting_principal.insert(reverie_id.to_string(), principal);
+        self.record_daily_stats(reverie_id, user_id, &entry);
+        self.record_ledger_entry(reverie_id, user_id, entry, seq);
+    }
+
+    // internal method returning the vesting deposits of a reverie not yet vested or refunded
+    pub(crate) fn unvested_principal(&self, reverie_id: &str) -> u128 {
+        *self.vesting_principal.get(reverie_id).unwrap_or(&0)
+    }
+
+    // internal method crediting vested NEAR to the reverie's revenue, or to the fee pool once
+    // the reverie has been deleted
+    fn credit_vested(&mut self, reverie_id: &str, amount: u128) {
+        if amount == 0 {
+            return;
+        }
+        if self.reverie_metadata.get(reverie_id).is_none() {
+            self.fee_pool += amount;
+            return;
+        }
+        let total = self.reverie_revenue.get(reverie_id).unwrap_or(&0) + amount;
+        self.reverie_revenue.insert(reverie_id.to_string(), total);
+    }
+}
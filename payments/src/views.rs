@@ -0,0 +1,92 @@
+use crate::*;
+use near_sdk::json_types::U64;
+
+/// Max users per `get_balances`/`can_spend_many` call, to stay within the view gas limit.
+pub const MAX_BULK_QUERY_USERS: usize = 500;
+
+/// Everything a frontend needs to render a reverie, returned by `get_reverie` in one call.
+#[near(serializers = [json])]
+#[derive(Clone, Debug)]
+pub struct ReverieView {
+    pub reverie_id: ReverieId,
+    pub metadata: ReverieMetadata,
+    pub ledger: ledger::LedgerCheckpoint, // aggregate deposits, spends and withdrawals
+    pub spenders: Vec<AccountId>,
+    pub price_oracle: Option<oracle::PriceOracleConfig>,
+}
+
+/// Snapshot a relayer logs when admitting a request, so a later `record_spend` (or a dispute
+/// about it) can be traced back to the balance and event sequence it was priced against.
+#[near(serializers = [json])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdmissionTicket {
+    pub reverie_id: ReverieId,
+    pub user_id: AccountId,
+    pub estimated_cost: U128,
+    pub balance: U128,
+    pub admitted: bool, // balance covers the estimated cost and the reverie isn't frozen
+    pub event_seq: U64, // sequence number of the last emitted PaymentEvent
+    pub block_height: U64,
+    pub block_timestamp: U64,
+}
+
+#[near]
+impl PaymentContract {
+    /// Composite view of a reverie, or `None` if it doesn't exist.
+    pub fn get_reverie(&self, reverie_id: ReverieId) -> Option<ReverieView> {
+        let metadata = self.reverie_metadata.get(&reverie_id)?.clone();
+        Some(ReverieView {
+            ledger: self.ledger_checkpoints.get(&reverie_id).cloned().unwrap_or_default(),
+            spenders: self.reverie_spenders.get(&reverie_id).cloned().unwrap_or_default(),
+            price_oracle: self.price_oracle.clone(),
+            reverie_id,
+            metadata,
+        })
+    }
+
+    /// Balances of many users on a reverie, in the order of `user_ids`.
+    pub fn get_balances(&self, reverie_id: ReverieId, user_ids: Vec<AccountId>) -> Vec<U128> {
+        assert_bulk_query_size(user_ids.len());
+        self.require_reverie_exists(&reverie_id);
+        let user_balances = self.reverie_balances.get(&reverie_id);
+        user_ids
+            .iter()
+            .map(|user_id| U128(user_balances.and_then(|balances| balances.get(user_id)).copied().unwrap_or(0)))
+            .collect()
+    }
+
+    /// `can_spend` with the context a relayer needs to justify admitting the request later.
+    pub fn admit(&self, reverie_id: ReverieId, user_id: AccountId, estimated_cost: U128) -> AdmissionTicket {
+        let balance = self.get_balance(reverie_id.clone(), user_id.clone());
+        let frozen = self.reverie_metadata.get(&reverie_id).map_or(false, |metadata| metadata.frozen);
+        AdmissionTicket {
+            admitted: !frozen && balance >= estimated_cost,
+            reverie_id,
+            user_id,
+            estimated_cost,
+            balance,
+            event_seq: U64(self.event_seq),
+            block_height: U64(env::block_height()),
+            block_timestamp: U64(clock::block_timestamp()),
+        }
+    }
+
+    /// `can_spend` for a batch of (user, amount) checks, in order.
+    pub fn can_spend_many(&self, reverie_id: ReverieId, checks: Vec<(AccountId, U128)>) -> Vec<bool> {
+        assert_bulk_query_size(checks.len());
+        let user_ids = checks.iter().map(|(user_id, _)| user_id.clone()).collect();
+        self.get_balances(reverie_id, user_ids)
+            .into_iter()
+            .zip(checks)
+            .map(|(balance, (_, amount))| balance >= amount)
+            .collect()
+    }
+}
+
+fn assert_bulk_query_size(len: usize) {
+    assert!(
+        len <= MAX_BULK_QUERY_USERS,
+        "Too many users. Max {} per call",
+        MAX_BULK_QUERY_USERS
+    );
+}
@@ -0,0 +1,66 @@
+use serde_json::json;
+use near_workspaces::types::NearToken;
+use reveries_test_utils::gas::assert_within_gas_budget;
+
+// Gas budgets (in TGas) for the payments methods. A change fails the suite when it burns more than
+// the budget plus GAS_REGRESSION_TOLERANCE_PCT percent (default 10), so update these deliberately.
+const CREATE_REVERIE_BUDGET_TGAS: u64 = 4;
+const DEPOSIT_BUDGET_TGAS: u64 = 4;
+const RECORD_SPEND_BUDGET_TGAS: u64 = 4;
+const WITHDRAW_BUDGET_TGAS: u64 = 10;
+
+#[tokio::test]
+async fn test_gas_budgets() -> Result<(), Box<dyn std::error::Error>> {
+    let contract_wasm = near_workspaces::compile_project("./").await?;
+    let sandbox = near_workspaces::sandbox().await?;
+    let contract = sandbox.dev_deploy(&contract_wasm).await?;
+    let trusted_account = sandbox.dev_create_account().await?;
+    let user_account = sandbox.dev_create_account().await?;
+
+    let init_outcome = contract
+        .call("new")
+        .args_json(json!({"trusted_account": trusted_account.id()}))
+        .transact()
+        .await?;
+    assert!(init_outcome.is_success(), "Initialization failed: {:#?}", init_outcome.into_result().unwrap_err());
+
+    let create_reverie_outcome = trusted_account
+        .call(contract.id(), "create_reverie")
+        .args_json(json!({
+            "reverie_id": "rev1",
+            "reverie_type": "type1",
+            "description": "desc1",
+            "access_condition": {"type": "Ed25519", "value": "pubkey1"}
+        }))
+        .transact()
+        .await?;
+    assert_within_gas_budget("create_reverie", &create_reverie_outcome, CREATE_REVERIE_BUDGET_TGAS);
+
+    let deposit_outcome = user_account
+        .call(contract.id(), "deposit")
+        .deposit(NearToken::from_millinear(100))
+        .args_json(json!({"reverie_id": "rev1"}))
+        .transact()
+        .await?;
+    assert_within_gas_budget("deposit", &deposit_outcome, DEPOSIT_BUDGET_TGAS);
+
+    let record_spend_outcome = trusted_account
+        .call(contract.id(), "record_spend")
+        .args_json(json!({
+            "reverie_id": "rev1",
+            "user_id": user_account.id(),
+            "amount_to_spend": "1000"
+        }))
+        .transact()
+        .await?;
+    assert_within_gas_budget("record_spend", &record_spend_outcome, RECORD_SPEND_BUDGET_TGAS);
+
+    let withdraw_outcome = user_account
+        .call(contract.id(), "withdraw")
+        .args_json(json!({"reverie_id": "rev1", "amount": "1000"}))
+        .transact()
+        .await?;
+    assert_within_gas_budget("withdraw", &withdraw_outcome, WITHDRAW_BUDGET_TGAS);
+
+    Ok(())
+}
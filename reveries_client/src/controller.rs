@@ -0,0 +1,167 @@
+use crate::{ContractHandle, Result};
+use near_sdk::json_types::{Base58CryptoHash, U128, U64};
+use near_sdk::PublicKey;
+use near_workspaces::result::ExecutionFinalResult;
+use near_workspaces::types::NearToken;
+use near_workspaces::{Account, AccountId};
+use passkey_controller::batch_plans::BatchExecutionPlan;
+use passkey_controller::borsh_input::DelegatedActionPayload;
+use passkey_controller::execution_tags::ExecutionTag;
+use passkey_controller::receipts::ExecutionReceipt;
+use passkey_controller::security_config::SecurityConfig;
+use reveries_types::SerializableAction;
+use serde_json::json;
+
+/// Client for a deployed `PasskeyController`, signing calls with `account`
+/// (the trusted relayer for delegated executions).
+#[derive(Clone, Debug)]
+pub struct ControllerClient {
+    handle: ContractHandle,
+}
+
+impl ControllerClient {
+    pub fn new(account: Account, contract_id: AccountId) -> Self {
+        Self { handle: ContractHandle { account, contract_id } }
+    }
+
+    pub fn contract_id(&self) -> &AccountId {
+        &self.handle.contract_id
+    }
+
+    pub async fn init(
+        &self,
+        trusted_relayer_account_id: &AccountId,
+        owner_id: &AccountId,
+        initial_passkey_pks: Option<&[PublicKey]>,
+    ) -> Result<()> {
+        let args = json!({
+            "trusted_relayer_account_id": trusted_relayer_account_id,
+            "owner_id": owner_id,
+            "initial_passkey_pks": initial_passkey_pks,
+        });
+        self.handle.call_unit("new", args, NearToken::from_yoctonear(0)).await
+    }
+
+    pub async fn add_passkey_pk(&self, passkey_pk: &PublicKey) -> Result<bool> {
+        self.handle
+            .call_json("add_passkey_pk", json!({ "passkey_pk": passkey_pk }), NearToken::from_yoctonear(0))
+            .await
+    }
+
+    pub async fn set_payments_contract(&self, payments_contract_id: Option<&AccountId>) -> Result<()> {
+        self.handle
+            .call_unit("set_payments_contract", json!({ "payments_contract_id": payments_contract_id }), NearToken::from_yoctonear(0))
+            .await
+    }
+
+    /// Submits a delegated action and returns its request id. The action itself runs in later
+    /// receipts; poll `get_execution_receipt` or use `execute_delegated_outcome` to see its result.
+    pub async fn execute_delegated(&self, passkey_pk: &PublicKey, action: &SerializableAction) -> Result<Base58CryptoHash> {
+        let outcome = self.execute_delegated_outcome(passkey_pk, action).await?;
+        Ok(outcome.into_result()?.json()?)
+    }
+
+    pub async fn execute_delegated_outcome(&self, passkey_pk: &PublicKey, action: &SerializableAction) -> Result<ExecutionFinalResult> {
+        let args = json!({ "passkey_pk_used": passkey_pk, "action_to_execute": action });
+        self.handle
+            .call("execute_delegated_actions", args, NearToken::from_yoctonear(0), delegated_gas(action))
+            .await
+    }
+
+    /// `execute_delegated` with a memo and client id attributing the action to an app.
+    pub async fn execute_delegated_tagged(
+        &self,
+        passkey_pk: &PublicKey,
+        action: &SerializableAction,
+        tag: &ExecutionTag,
+    ) -> Result<Base58CryptoHash> {
+        let args = json!({ "passkey_pk_used": passkey_pk, "action_to_execute": action, "tag": tag });
+        let outcome = self
+            .handle
+            .call("execute_delegated_actions_tagged", args, NearToken::from_yoctonear(0), delegated_gas(action))
+            .await?;
+        Ok(outcome.into_result()?.json()?)
+    }
+
+    /// `execute_delegated` with Borsh-encoded input, cheaper for large actions like DeployContract.
+    pub async fn execute_delegated_borsh(&self, passkey_pk: &PublicKey, action: &SerializableAction) -> Result<Base58CryptoHash> {
+        let payload = near_sdk::borsh::to_vec(&DelegatedActionPayload {
+            passkey_pk_used: passkey_pk.clone(),
+            action_to_execute: action.clone(),
+        })?;
+        let outcome = self
+            .handle
+            .account
+            .call(&self.handle.contract_id, "execute_delegated_actions_borsh")
+            .args_borsh(payload)
+            .gas(delegated_gas(action))
+            .transact()
+            .await?;
+        Ok(outcome.into_result()?.json()?)
+    }
+
+    pub async fn plan_batch(&self, actions: &[SerializableAction]) -> Result<BatchExecutionPlan> {
+        self.handle.view("plan_batch", json!({ "actions": actions })).await
+    }
+
+    /// Executes `actions` as planned by `plan_batch`, returning their request ids in plan order.
+    /// Attaches the plan's `required_gas`, which covers policy checks and result callbacks too.
+    pub async fn execute_delegated_batch(
+        &self,
+        passkey_pk: &PublicKey,
+        actions: &[SerializableAction],
+        plan: &BatchExecutionPlan,
+    ) -> Result<Vec<Base58CryptoHash>> {
+        let args = json!({ "passkey_pk_used": passkey_pk, "actions": actions, "plan_hash": plan.plan_hash });
+        let gas = near_workspaces::types::Gas::from_gas(plan.required_gas.as_gas());
+        let outcome = self
+            .handle
+            .call("execute_delegated_batch", args, NearToken::from_yoctonear(0), gas)
+            .await?;
+        Ok(outcome.into_result()?.json()?)
+    }
+
+    /// Idempotent `execute_delegated` for retries: repeats of `job_id` return the first request id.
+    pub async fn execute_delegated_job(&self, job_id: &str, passkey_pk: &PublicKey, action: &SerializableAction) -> Result<Base58CryptoHash> {
+        let args = json!({ "job_id": job_id, "passkey_pk_used": passkey_pk, "action_to_execute": action });
+        let outcome = self
+            .handle
+            .call("execute_delegated_job", args, NearToken::from_yoctonear(0), delegated_gas(action))
+            .await?;
+        Ok(outcome.into_result()?.json()?)
+    }
+
+    pub async fn fund_controller_for(&self, passkey_pk: &PublicKey, amount: NearToken) -> Result<U128> {
+        self.handle
+            .call_json("fund_controller_for", json!({ "passkey_pk": passkey_pk }), amount)
+            .await
+    }
+
+    pub async fn get_execution_receipt(&self, request_id: Base58CryptoHash) -> Result<Option<ExecutionReceipt>> {
+        self.handle.view("get_execution_receipt", json!({ "request_id": request_id })).await
+    }
+
+    pub async fn get_passkey_nonce(&self, passkey_pk: &PublicKey) -> Result<u64> {
+        let nonce: U64 = self.handle.view("get_passkey_nonce", json!({ "passkey_pk": passkey_pk })).await?;
+        Ok(nonce.0)
+    }
+
+    pub async fn get_prepaid_balance(&self, passkey_pk: &PublicKey) -> Result<u128> {
+        let balance: U128 = self.handle.view("get_prepaid_balance", json!({ "passkey_pk": passkey_pk })).await?;
+        Ok(balance.0)
+    }
+
+    pub async fn get_security_config(&self) -> Result<SecurityConfig> {
+        self.handle.view("get_security_config", json!({})).await
+    }
+
+    pub async fn is_passkey_pk_registered(&self, passkey_pk: &PublicKey) -> Result<bool> {
+        self.handle.view("is_passkey_pk_registered", json!({ "passkey_pk": passkey_pk })).await
+    }
+}
+
+// Gas for the controller's own work plus whatever the action forwards to its receiver
+fn delegated_gas(action: &SerializableAction) -> near_workspaces::types::Gas {
+    let forwarded = action.gas.map_or(0, |gas| gas.as_gas());
+    crate::DEFAULT_CALL_GAS.saturating_add(near_workspaces::types::Gas::from_gas(forwarded))
+}
@@ -0,0 +1,68 @@
+//! Typed clients for the reveries contracts, so relayers and tests call contract methods
+//! with the contracts' own argument and return types instead of hand-written `json!` blobs.
+//!
+//! Each client wraps the near-workspaces `Account` that signs calls and the contract it targets:
+//! ```ignore
+//! let payments = PaymentsClient::new(user_account, payments_id);
+//! payments.deposit("rev1", NearToken::from_near(1)).await?;
+//! let controller = ControllerClient::new(relayer_account, controller_id);
+//! let request_id = controller.execute_delegated(passkey_pk, action).await?;
+//! ```
+
+pub mod controller;
+pub mod payments;
+
+pub use controller::ControllerClient;
+pub use payments::PaymentsClient;
+
+use near_workspaces::result::ExecutionFinalResult;
+use near_workspaces::types::{Gas, NearToken};
+use near_workspaces::{Account, AccountId};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Gas attached to calls that don't set their own.
+pub const DEFAULT_CALL_GAS: Gas = Gas::from_tgas(100);
+
+// Shared by the contract clients: signs calls with `account` against `contract_id`.
+#[derive(Clone, Debug)]
+struct ContractHandle {
+    account: Account,
+    contract_id: AccountId,
+}
+
+impl ContractHandle {
+    async fn call<A: Serialize>(&self, method: &str, args: A, deposit: NearToken, gas: Gas) -> Result<ExecutionFinalResult> {
+        Ok(self
+            .account
+            .call(&self.contract_id, method)
+            .args_json(args)
+            .deposit(deposit)
+            .gas(gas)
+            .transact()
+            .await?)
+    }
+
+    // Calls `method` and decodes its JSON return value, failing if the transaction failed
+    async fn call_json<A: Serialize, T: DeserializeOwned>(&self, method: &str, args: A, deposit: NearToken) -> Result<T> {
+        let outcome = self.call(method, args, deposit, DEFAULT_CALL_GAS).await?;
+        Ok(outcome.into_result()?.json()?)
+    }
+
+    // Calls a method returning nothing, failing if the transaction failed
+    async fn call_unit<A: Serialize>(&self, method: &str, args: A, deposit: NearToken) -> Result<()> {
+        self.call(method, args, deposit, DEFAULT_CALL_GAS).await?.into_result()?;
+        Ok(())
+    }
+
+    async fn view<A: Serialize, T: DeserializeOwned>(&self, method: &str, args: A) -> Result<T> {
+        Ok(self
+            .account
+            .view(&self.contract_id, method)
+            .args_json(args)
+            .await?
+            .json()?)
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{ContractHandle, Result};
+use near_sdk::json_types::{Base58CryptoHash, U128, U64};
+use near_workspaces::result::ExecutionFinalResult;
+use near_workspaces::types::NearToken;
+use near_workspaces::{Account, AccountId};
+use payments::balance_detail::BalanceDetail;
+use payments::credits::CreditBalance;
+use payments::disputes::Dispute;
+use payments::events::RecordedEvent;
+use payments::ledger::LedgerCheckpoint;
+use payments::open_registry::OpenRegistryConfig;
+use payments::storage::StorageCostEstimate;
+use payments::views::AdmissionTicket;
+use reveries_types::{AccessCondition, Denomination, ReverieId, ReverieListing, ReverieMetadata};
+use serde_json::json;
+
+/// Client for a deployed `PaymentContract`, signing calls with `account`.
+#[derive(Clone, Debug)]
+pub struct PaymentsClient {
+    handle: ContractHandle,
+}
+
+impl PaymentsClient {
+    pub fn new(account: Account, contract_id: AccountId) -> Self {
+        Self { handle: ContractHandle { account, contract_id } }
+    }
+
+    pub fn contract_id(&self) -> &AccountId {
+        &self.handle.contract_id
+    }
+
+    pub async fn init(&self, trusted_account: &AccountId) -> Result<()> {
+        self.handle
+            .call_unit("new", json!({ "trusted_account": trusted_account }), NearToken::from_yoctonear(0))
+            .await
+    }
+
+    pub async fn create_reverie(
+        &self,
+        reverie_id: &str,
+        reverie_type: &str,
+        description: &str,
+        access_condition: &AccessCondition,
+        denomination: Option<&Denomination>,
+        listing: Option<&ReverieListing>,
+    ) -> Result<()> {
+        let args = json!({
+            "reverie_id": reverie_id,
+            "reverie_type": reverie_type,
+            "description": description,
+            "access_condition": access_condition,
+            "denomination": denomination,
+            "listing": listing,
+        });
+        self.handle.call_unit("create_reverie", args, NearToken::from_yoctonear(0)).await
+    }
+
+    /// Creates a NEAR reverie and credits `amount` to the caller in one call. Returns the caller's balance.
+    pub async fn create_reverie_with_deposit(
+        &self,
+        reverie_id: &str,
+        reverie_type: &str,
+        description: &str,
+        access_condition: &AccessCondition,
+        listing: Option<&ReverieListing>,
+        amount: NearToken,
+    ) -> Result<u128> {
+        let args = json!({
+            "reverie_id": reverie_id,
+            "reverie_type": reverie_type,
+            "description": description,
+            "access_condition": access_condition,
+            "listing": listing,
+        });
+        let balance: U128 = self.handle.call_json("create_reverie_with_deposit", args, amount).await?;
+        Ok(balance.0)
+    }
+
+    pub async fn create_reverie_derived(
+        &self,
+        reverie_type: &str,
+        description: &str,
+        access_condition: &AccessCondition,
+        denomination: Option<&Denomination>,
+        listing: Option<&ReverieListing>,
+    ) -> Result<ReverieId> {
+        let args = json!({
+            "reverie_type": reverie_type,
+            "description": description,
+            "access_condition": access_condition,
+            "denomination": denomination,
+            "listing": listing,
+        });
+        self.handle.call_json("create_reverie_derived", args, NearToken::from_yoctonear(0)).await
+    }
+
+    pub async fn deposit(&self, reverie_id: &str, amount: NearToken) -> Result<()> {
+        self.handle.call_unit("deposit", json!({ "reverie_id": reverie_id }), amount).await
+    }
+
+    pub async fn deposit_for(&self, reverie_id: &str, user_id: &AccountId, amount: NearToken) -> Result<()> {
+        self.handle
+            .call_unit("deposit_for", json!({ "reverie_id": reverie_id, "user_id": user_id }), amount)
+            .await
+    }
+
+    /// Returns the raw outcome: the payout runs in later receipts, which callers may want to inspect.
+    pub async fn withdraw(&self, reverie_id: &str, amount: u128) -> Result<ExecutionFinalResult> {
+        self.handle
+            .call(
+                "withdraw",
+                json!({ "reverie_id": reverie_id, "amount": U128(amount) }),
+                NearToken::from_yoctonear(0),
+                crate::DEFAULT_CALL_GAS,
+            )
+            .await
+    }
+
+    pub async fn request_withdrawal(&self, reverie_id: &str, amount: u128) -> Result<U64> {
+        self.handle
+            .call_json("request_withdrawal", json!({ "reverie_id": reverie_id, "amount": U128(amount) }), NearToken::from_yoctonear(0))
+            .await
+    }
+
+    pub async fn claim_withdrawal(&self, reverie_id: &str) -> Result<ExecutionFinalResult> {
+        self.handle
+            .call("claim_withdrawal", json!({ "reverie_id": reverie_id }), NearToken::from_yoctonear(0), crate::DEFAULT_CALL_GAS)
+            .await
+    }
+
+    pub async fn record_spend(
+        &self,
+        reverie_id: &str,
+        user_id: &AccountId,
+        amount: u128,
+        category: Option<&str>,
+        evidence_hash: Option<&Base58CryptoHash>,
+    ) -> Result<()> {
+        let args = json!({
+            "reverie_id": reverie_id,
+            "user_id": user_id,
+            "amount_to_spend": U128(amount),
+            "category": category,
+            "evidence_hash": evidence_hash,
+        });
+        self.handle.call_unit("record_spend", args, NearToken::from_yoctonear(0)).await
+    }
+
+    pub async fn set_open_registry(&self, config: Option<&OpenRegistryConfig>) -> Result<()> {
+        self.handle
+            .call_unit("set_open_registry", json!({ "config": config }), NearToken::from_yoctonear(0))
+            .await
+    }
+
+    pub async fn set_credits_per_near(&self, reverie_id: &str, credits_per_near: Option<u128>) -> Result<()> {
+        self.handle
+            .call_unit(
+                "set_credits_per_near",
+                json!({ "reverie_id": reverie_id, "credits_per_near": credits_per_near.map(U128) }),
+                NearToken::from_yoctonear(0),
+            )
+            .await
+    }
+
+    /// Returns the yoctoNEAR charged for `credits`.
+    pub async fn record_spend_credits(&self, reverie_id: &str, user_id: &AccountId, credits: u128) -> Result<u128> {
+        let spent: U128 = self
+            .handle
+            .call_json(
+                "record_spend_credits",
+                json!({ "reverie_id": reverie_id, "user_id": user_id, "credits": U128(credits) }),
+                NearToken::from_yoctonear(0),
+            )
+            .await?;
+        Ok(spent.0)
+    }
+
+    pub async fn withdraw_credits(&self, reverie_id: &str, credits: u128) -> Result<ExecutionFinalResult> {
+        self.handle
+            .call(
+                "withdraw_credits",
+                json!({ "reverie_id": reverie_id, "credits": U128(credits) }),
+                NearToken::from_yoctonear(0),
+                crate::DEFAULT_CALL_GAS,
+            )
+            .await
+    }
+
+    pub async fn get_credit_balance(&self, reverie_id: &str, user_id: &AccountId) -> Result<CreditBalance> {
+        self.handle
+            .view("get_credit_balance", json!({ "reverie_id": reverie_id, "user_id": user_id }))
+            .await
+    }
+
+    pub async fn dispute_spend(&self, spend_id: u64) -> Result<Dispute> {
+        self.handle
+            .call_json("dispute_spend", json!({ "spend_id": U64(spend_id) }), NearToken::from_yoctonear(0))
+            .await
+    }
+
+    pub async fn resolve_dispute(&self, spend_id: u64, refund: bool) -> Result<()> {
+        self.handle
+            .call_unit("resolve_dispute", json!({ "spend_id": U64(spend_id), "refund": refund }), NearToken::from_yoctonear(0))
+            .await
+    }
+
+    pub async fn propose_reverie_transfer(&self, reverie_id: &str, new_admin: &AccountId) -> Result<()> {
+        self.handle
+            .call_unit(
+                "propose_reverie_transfer",
+                json!({ "reverie_id": reverie_id, "new_admin": new_admin }),
+                NearToken::from_yoctonear(0),
+            )
+            .await
+    }
+
+    pub async fn accept_reverie_transfer(&self, reverie_id: &str) -> Result<()> {
+        self.handle
+            .call_unit("accept_reverie_transfer", json!({ "reverie_id": reverie_id }), NearToken::from_yoctonear(0))
+            .await
+    }
+
+    pub async fn get_reverie_admin(&self, reverie_id: &str) -> Result<AccountId> {
+        self.handle.view("get_reverie_admin", json!({ "reverie_id": reverie_id })).await
+    }
+
+    pub async fn get_balance(&self, reverie_id: &str, user_id: &AccountId) -> Result<u128> {
+        let balance: U128 = self
+            .handle
+            .view("get_balance", json!({ "reverie_id": reverie_id, "user_id": user_id }))
+            .await?;
+        Ok(balance.0)
+    }
+
+    pub async fn get_balances(&self, reverie_id: &str, user_ids: &[AccountId]) -> Result<Vec<u128>> {
+        let balances: Vec<U128> = self
+            .handle
+            .view("get_balances", json!({ "reverie_id": reverie_id, "user_ids": user_ids }))
+            .await?;
+        Ok(balances.into_iter().map(|balance| balance.0).collect())
+    }
+
+    pub async fn get_balance_detail(&self, reverie_id: &str, user_id: &AccountId) -> Result<BalanceDetail> {
+        self.handle
+            .view("get_balance_detail", json!({ "reverie_id": reverie_id, "user_id": user_id }))
+            .await
+    }
+
+    pub async fn can_spend(&self, reverie_id: &str, user_id: &AccountId, amount: u128) -> Result<bool> {
+        self.handle
+            .view("can_spend", json!({ "reverie_id": reverie_id, "user_id": user_id, "amount": U128(amount) }))
+            .await
+    }
+
+    /// Admission ticket for a request costing `estimated_cost`, to log before submitting it.
+    pub async fn admit(&self, reverie_id: &str, user_id: &AccountId, estimated_cost: u128) -> Result<AdmissionTicket> {
+        self.handle
+            .view("admit", json!({ "reverie_id": reverie_id, "user_id": user_id, "estimated_cost": U128(estimated_cost) }))
+            .await
+    }
+
+    /// Storage a first deposit by `user_id` would add, and its cost.
+    pub async fn estimate_storage_cost_deposit(&self, reverie_id: &str, user_id: &AccountId) -> Result<StorageCostEstimate> {
+        self.handle
+            .view("estimate_storage_cost_deposit", json!({ "reverie_id": reverie_id, "user_id": user_id }))
+            .await
+    }
+
+    /// Checks a backend's API credential against the hash its reverie anchored on-chain.
+    pub async fn verify_api_key(&self, reverie_id: &str, preimage: &str) -> Result<bool> {
+        self.handle
+            .view("verify_api_key", json!({ "reverie_id": reverie_id, "preimage": preimage }))
+            .await
+    }
+
+    pub async fn shutdown_reverie(&self, reverie_id: &str) -> Result<()> {
+        self.handle
+            .call_unit("shutdown_reverie", json!({ "reverie_id": reverie_id }), NearToken::from_yoctonear(0))
+            .await
+    }
+
+    /// Refunds up to `limit` depositors of a shut down reverie. Returns the number refunded.
+    pub async fn refund_depositors(&self, reverie_id: &str, limit: u32) -> Result<u32> {
+        self.handle
+            .call_json("refund_depositors", json!({ "reverie_id": reverie_id, "limit": limit }), NearToken::from_yoctonear(0))
+            .await
+    }
+
+    pub async fn get_reverie_metadata(&self, reverie_id: &str) -> Result<Option<ReverieMetadata>> {
+        self.handle.view("get_reverie_metadata", json!({ "reverie_id": reverie_id })).await
+    }
+
+    pub async fn get_reverie_ids(&self) -> Result<Vec<ReverieId>> {
+        self.handle.view("get_reverie_ids", json!({})).await
+    }
+
+    pub async fn get_reveries_by_tag(&self, tag: &str, from_index: u32, limit: u32) -> Result<Vec<ReverieId>> {
+        self.handle
+            .view("get_reveries_by_tag", json!({ "tag": tag, "from_index": from_index, "limit": limit }))
+            .await
+    }
+
+    pub async fn get_ledger_checkpoint(&self, reverie_id: &str) -> Result<LedgerCheckpoint> {
+        self.handle.view("get_ledger_checkpoint", json!({ "reverie_id": reverie_id })).await
+    }
+
+    /// Buffered events after `seq`; pass the last `seq` seen to tail the contract.
+    pub async fn get_events_since(&self, seq: u64, limit: u32) -> Result<Vec<RecordedEvent>> {
+        self.handle.view("get_events_since", json!({ "seq": U64(seq), "limit": limit })).await
+    }
+}
@@ -0,0 +1,59 @@
+use near_sdk::json_types::U128;
+use near_sdk::AccountId;
+use reveries_types::{ActionType, SerializableAction};
+
+/// `action_type` with every optional field unset, for struct-update syntax:
+/// `SerializableAction { stake: Some(U128(1)), ..empty_action(ActionType::Stake) }`.
+pub fn empty_action(action_type: ActionType) -> SerializableAction {
+    SerializableAction {
+        action_type,
+        receiver_id: None,
+        amount: None,
+        method_name: None,
+        args: None,
+        deposit: None,
+        gas: None,
+        public_key: None,
+        allowance: None,
+        method_names: None,
+        code: None,
+        stake: None,
+        beneficiary_id: None,
+        initial_deposit_for_new_account: None,
+        public_key_for_new_account: None,
+        reverie_id: None,
+        user_id: None,
+        args_json: None,
+    }
+}
+
+pub fn transfer_action(receiver_id: AccountId, amount: u128) -> SerializableAction {
+    SerializableAction {
+        receiver_id: Some(receiver_id),
+        amount: Some(U128(amount)),
+        ..empty_action(ActionType::Transfer)
+    }
+}
+
+/// FunctionCall without args, deposit or gas.
+pub fn function_call_action(receiver_id: &str, method_name: &str) -> SerializableAction {
+    SerializableAction {
+        receiver_id: Some(receiver_id.parse().unwrap()),
+        method_name: Some(method_name.to_string()),
+        ..empty_action(ActionType::FunctionCall)
+    }
+}
+
+pub fn record_spend_action(reverie_id: &str, user_id: AccountId, amount: u128) -> SerializableAction {
+    SerializableAction {
+        reverie_id: Some(reverie_id.to_string()),
+        user_id: Some(user_id),
+        amount: Some(U128(amount)),
+        ..empty_action(ActionType::RecordSpend)
+    }
+}
+
+/// ChargeReverie carrying its args in the action type; the action's own fields stay unset.
+pub fn charge_reverie_action(reverie_id: &str, user: AccountId, amount: u128) -> SerializableAction {
+    empty_action(ActionType::ChargeReverie { reverie_id: reverie_id.to_string(), user, amount: U128(amount) })
+}
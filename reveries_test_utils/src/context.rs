@@ -0,0 +1,34 @@
+use near_sdk::test_utils::VMContextBuilder;
+use near_sdk::{testing_env, AccountId, NearToken, PromiseResult};
+
+/// Context for a call by `predecessor_account_id`, which also signs it, on `current_account_id`.
+pub fn get_context(predecessor_account_id: AccountId, current_account_id: AccountId) -> VMContextBuilder {
+    let mut builder = VMContextBuilder::new();
+    builder
+        .current_account_id(current_account_id)
+        .signer_account_id(predecessor_account_id.clone())
+        .predecessor_account_id(predecessor_account_id);
+    builder
+}
+
+/// `get_context` with `attached_deposit_yocto` attached, for payable methods.
+pub fn deposit_context(
+    predecessor_account_id: AccountId,
+    current_account_id: AccountId,
+    attached_deposit_yocto: u128,
+) -> VMContextBuilder {
+    let mut builder = get_context(predecessor_account_id, current_account_id);
+    builder.attached_deposit(NearToken::from_yoctonear(attached_deposit_yocto));
+    builder
+}
+
+/// Sets up `context` with the results of the promises a callback is resolving.
+pub fn set_promise_results(context: &VMContextBuilder, promise_results: Vec<PromiseResult>) {
+    testing_env!(
+        context.build(),
+        near_sdk::test_vm_config(),
+        near_sdk::RuntimeFeesConfig::test(),
+        Default::default(),
+        promise_results
+    );
+}
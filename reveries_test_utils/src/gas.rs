@@ -0,0 +1,24 @@
+//! Gas regression checks shared by the contracts' benchmark suites.
+
+use near_workspaces::result::ExecutionFinalResult;
+
+/// Tolerance over a budget when `GAS_REGRESSION_TOLERANCE_PCT` isn't set.
+pub const DEFAULT_GAS_TOLERANCE_PCT: u64 = 10;
+
+/// Asserts `outcome` succeeded and burnt at most `budget_tgas` plus
+/// `GAS_REGRESSION_TOLERANCE_PCT` percent, printing the gas burnt for the benchmark report.
+pub fn assert_within_gas_budget(name: &str, outcome: &ExecutionFinalResult, budget_tgas: u64) {
+    assert!(outcome.is_success(), "{} failed: {:#?}", name, outcome);
+    let tolerance_pct: u64 = std::env::var("GAS_REGRESSION_TOLERANCE_PCT")
+        .ok()
+        .and_then(|pct| pct.parse().ok())
+        .unwrap_or(DEFAULT_GAS_TOLERANCE_PCT);
+    let burnt = outcome.total_gas_burnt.as_gas();
+    let limit = budget_tgas * 1_000_000_000_000 * (100 + tolerance_pct) / 100;
+    println!("{}: {} gas burnt (limit {})", name, burnt, limit);
+    assert!(
+        burnt <= limit,
+        "{} burnt {} gas, over its {} TGas budget by more than {}%",
+        name, burnt, budget_tgas, tolerance_pct
+    );
+}
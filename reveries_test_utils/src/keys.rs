@@ -0,0 +1,37 @@
+use near_sdk::{CurveType, PublicKey};
+
+/// Ed25519 passkey whose secret key is `seed` repeated, so tests get the same key every run.
+pub fn passkey_signing_key(seed: u8) -> ed25519_dalek::SigningKey {
+    ed25519_dalek::SigningKey::from_bytes(&[seed; 32])
+}
+
+pub fn passkey_pk_of(signing_key: &ed25519_dalek::SigningKey) -> PublicKey {
+    PublicKey::from_parts(CurveType::ED25519, signing_key.verifying_key().to_bytes().to_vec()).unwrap()
+}
+
+/// Public key of `passkey_signing_key(seed)`.
+pub fn passkey_pk(seed: u8) -> PublicKey {
+    passkey_pk_of(&passkey_signing_key(seed))
+}
+
+/// secp256k1 passkey whose secret key is `seed` repeated (`seed` must be non-zero).
+pub fn secp256k1_signing_key(seed: u8) -> k256::ecdsa::SigningKey {
+    k256::ecdsa::SigningKey::from_bytes(&[seed; 32].into()).unwrap()
+}
+
+/// SEC1 uncompressed point (65 bytes, 0x04 tag first), as authenticators export it.
+pub fn secp256k1_uncompressed_pk_bytes(signing_key: &k256::ecdsa::SigningKey) -> Vec<u8> {
+    signing_key.verifying_key().to_encoded_point(false).as_bytes().to_vec()
+}
+
+pub fn secp256k1_passkey_pk_of(signing_key: &k256::ecdsa::SigningKey) -> PublicKey {
+    PublicKey::from_parts(CurveType::SECP256K1, secp256k1_uncompressed_pk_bytes(signing_key)[1..].to_vec()).unwrap()
+}
+
+/// 65-byte (r, s, v) signature over a 32-byte hash, the layout `env::ecrecover` takes.
+pub fn secp256k1_sign_prehash(signing_key: &k256::ecdsa::SigningKey, hash: &[u8; 32]) -> Vec<u8> {
+    let (signature, recovery_id) = signing_key.sign_prehash_recoverable(hash).unwrap();
+    let mut bytes = signature.to_bytes().to_vec();
+    bytes.push(recovery_id.to_byte());
+    bytes
+}
@@ -0,0 +1,18 @@
+//! Fixtures shared by the reveries test suites: deterministic passkeys, `SerializableAction`
+//! builders, `VMContextBuilder` presets and, behind the `workspaces` feature, sandbox deployment and gas
+//! budget checks.
+
+pub mod actions;
+pub mod context;
+#[cfg(feature = "workspaces")]
+pub mod gas;
+pub mod keys;
+#[cfg(feature = "workspaces")]
+pub mod sandbox;
+
+pub use actions::{charge_reverie_action, empty_action, function_call_action, record_spend_action, transfer_action};
+pub use context::{deposit_context, get_context, set_promise_results};
+pub use keys::{
+    passkey_pk, passkey_pk_of, passkey_signing_key, secp256k1_passkey_pk_of, secp256k1_sign_prehash,
+    secp256k1_signing_key, secp256k1_uncompressed_pk_bytes,
+};
@@ -0,0 +1,59 @@
+//! Sandbox deployment of the payments contract and passkey controller wired together: the
+//! controller is the payments trusted account and the payments contract is the controller's
+//! target for RecordSpend/ReverieDeposit actions.
+
+use near_sdk::PublicKey;
+use near_workspaces::network::Sandbox;
+use near_workspaces::{Account, Contract, Worker};
+use serde_json::json;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+pub struct WiredContracts {
+    pub worker: Worker<Sandbox>,
+    pub payments: Contract,
+    pub controller: Contract,
+    /// The controller's owner.
+    pub owner: Account,
+    /// The controller's trusted relayer.
+    pub relayer: Account,
+}
+
+/// Deploys and initializes both contracts from their compiled wasm, registering
+/// `initial_passkey_pks` on the controller.
+pub async fn deploy_wired(
+    payments_wasm: &[u8],
+    controller_wasm: &[u8],
+    initial_passkey_pks: &[PublicKey],
+) -> Result<WiredContracts> {
+    let worker = near_workspaces::sandbox().await?;
+    let controller = worker.dev_deploy(controller_wasm).await?;
+    let payments = worker.dev_deploy(payments_wasm).await?;
+    let relayer = worker.dev_create_account().await?;
+    let owner = worker.dev_create_account().await?;
+
+    controller
+        .call("new")
+        .args_json(json!({
+            "trusted_relayer_account_id": relayer.id(),
+            "owner_id": owner.id(),
+            "initial_passkey_pks": initial_passkey_pks,
+        }))
+        .transact()
+        .await?
+        .into_result()?;
+    payments
+        .call("new")
+        .args_json(json!({"trusted_account": controller.id()}))
+        .transact()
+        .await?
+        .into_result()?;
+    owner
+        .call(controller.id(), "set_payments_contract")
+        .args_json(json!({"payments_contract_id": payments.id()}))
+        .transact()
+        .await?
+        .into_result()?;
+
+    Ok(WiredContracts { worker, payments, controller, owner, relayer })
+}
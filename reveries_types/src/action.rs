@@ -0,0 +1,255 @@
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::{AccountId, Allowance, Gas, NearToken, PublicKey};
+use std::num::NonZeroU128;
+
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ActionType {
+    CreateAccount,
+    DeployContract,
+    FunctionCall,
+    Transfer,
+    Stake,
+    AddKey,
+    DeleteKey,
+    DeleteAccount,
+    // Calls into the configured PaymentContract
+    RecordSpend,
+    ReverieDeposit,
+    // Calls into a whitelisted staking pool contract
+    StakeWithPool,
+    UnstakeFromPool,
+    WithdrawFromPool,
+    // Charges `user` `amount` credits on the configured PaymentContract. The controller builds
+    // the `record_spend` call from these typed fields; the action's own fields are ignored
+    ChargeReverie { reverie_id: String, user: AccountId, amount: U128 },
+}
+
+#[near_sdk::near(serializers = [borsh, json])]
+#[derive(Debug, Clone)]
+pub struct SerializableAction {
+    pub action_type: ActionType,
+    // Specific fields for each action type, now optional
+    pub receiver_id: Option<AccountId>,
+    pub method_name: Option<String>,
+    pub args: Option<Base64VecU8>, // JSON string of args, base64 encoded
+    pub deposit: Option<U128>, // yoctoNEAR
+    pub gas: Option<Gas>,
+    // For Transfer
+    pub amount: Option<U128>, // yoctoNEAR
+    // For AddKey/DeleteKey
+    pub public_key: Option<PublicKey>,
+    // For AddKey (FunctionCallAccessKey)
+    pub allowance: Option<U128>, // yoctoNEAR
+    pub method_names: Option<Vec<String>>,
+    // For DeployContract
+    pub code: Option<Base64VecU8>,
+    // For Stake
+    pub stake: Option<U128>, // yoctoNEAR
+    // For DeleteAccount
+    pub beneficiary_id: Option<AccountId>,
+    // For CreateAccount, which may also deploy `code` and call `method_name` with `args`,
+    // `deposit` and `gas` to initialize the new account
+    pub initial_deposit_for_new_account: Option<U128>, // yoctoNEAR
+    pub public_key_for_new_account: Option<PublicKey>,
+    // For RecordSpend/ReverieDeposit and the staking pool actions (amount is taken from `amount`,
+    // the staking pool from `receiver_id`)
+    pub reverie_id: Option<String>,
+    // For RecordSpend/ReverieDeposit, and for delegated AddKey/DeleteKey the account whose
+    // keys are managed (on behalf of the user, defaulting to the controller's own account)
+    pub user_id: Option<AccountId>,
+    // For FunctionCall, a plain JSON alternative to `args`, mutually exclusive with it.
+    // Kept last so Borsh-encoded actions written before it still decode.
+    pub args_json: Option<String>,
+}
+
+// Argument types of the PaymentContract methods called by payments actions.
+#[near_sdk::near(serializers = [json])]
+pub struct RecordSpendArgs {
+    pub reverie_id: String,
+    pub user_id: AccountId,
+    pub amount_to_spend: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+#[near_sdk::near(serializers = [json])]
+pub struct DepositForArgs {
+    pub reverie_id: String,
+    pub user_id: AccountId,
+}
+
+// Argument type of the staking pool `unstake` and `withdraw` methods.
+#[near_sdk::near(serializers = [json])]
+pub struct UnstakeArgs {
+    pub amount: U128,
+}
+
+// Argument types of the key proxy methods on a user's account, for delegated
+// AddKey/DeleteKey actions on behalf of that account.
+#[near_sdk::near(serializers = [json])]
+pub struct AddKeyArgs {
+    pub public_key: PublicKey,
+    pub allowance: Option<U128>, // yoctoNEAR, unlimited if unset
+    pub receiver_id: AccountId,
+    pub method_names: Vec<String>,
+}
+
+#[near_sdk::near(serializers = [json])]
+pub struct DeleteKeyArgs {
+    pub public_key: PublicKey,
+}
+
+impl SerializableAction {
+    pub fn get_action_allowance(&self) -> Allowance {
+        match self.allowance {
+            Some(allowance_amount) if allowance_amount.0 > 0 => {
+                Allowance::Limited(NonZeroU128::new(allowance_amount.0).unwrap_or_else(|| panic!("Allowance must be non-zero if limited")))
+            }
+            _ => Allowance::Unlimited, // Default to Unlimited if None or 0.
+        }
+    }
+
+    /// Bytes of the FunctionCall (or CreateAccount init call) args, from `args` or `args_json`.
+    pub fn call_args(&self) -> Vec<u8> {
+        match (&self.args, &self.args_json) {
+            (Some(_), Some(_)) => panic!("args and args_json are mutually exclusive"),
+            (Some(args), None) => args.0.clone(),
+            (None, Some(args_json)) => {
+                if let Err(err) = near_sdk::serde_json::from_str::<near_sdk::serde_json::Value>(args_json) {
+                    panic!("args_json must be valid JSON: {}", err);
+                }
+                args_json.clone().into_bytes()
+            }
+            (None, None) => vec![],
+        }
+    }
+
+    /// Total yoctoNEAR the action moves out of the executing account.
+    pub fn attached_value(&self) -> u128 {
+        match self.action_type {
+            ActionType::CreateAccount => {
+                self.initial_deposit_for_new_account.map(|d| d.0).unwrap_or(0) + self.deposit.map(|d| d.0).unwrap_or(0)
+            }
+            ActionType::FunctionCall => self.deposit.map(|d| d.0).unwrap_or(0),
+            ActionType::Transfer => self.amount.map(|a| a.0).unwrap_or(0),
+            ActionType::Stake => self.stake.map(|s| s.0).unwrap_or(0),
+            ActionType::ReverieDeposit => self.amount.map(|a| a.0).unwrap_or(0),
+            ActionType::StakeWithPool => self.amount.map(|a| a.0).unwrap_or(0),
+            ActionType::RecordSpend
+            | ActionType::ChargeReverie { .. }
+            | ActionType::UnstakeFromPool
+            | ActionType::WithdrawFromPool
+            | ActionType::DeployContract
+            | ActionType::AddKey
+            | ActionType::DeleteKey
+            | ActionType::DeleteAccount => 0,
+        }
+    }
+
+    /// Method name, JSON args and attached deposit of the PaymentContract call for
+    /// RecordSpend/ChargeReverie/ReverieDeposit actions, so relayers never hand-encode these args.
+    pub fn payments_call(&self) -> (String, Vec<u8>, NearToken) {
+        if let ActionType::ChargeReverie { reverie_id, user, amount } = &self.action_type {
+            return record_spend_call(reverie_id.clone(), user.clone(), *amount);
+        }
+        let reverie_id = self
+            .reverie_id
+            .clone()
+            .unwrap_or_else(|| panic!("reverie_id is required for RecordSpend/ReverieDeposit"));
+        let user_id = self
+            .user_id
+            .clone()
+            .unwrap_or_else(|| panic!("user_id is required for RecordSpend/ReverieDeposit"));
+        let amount = self
+            .amount
+            .unwrap_or_else(|| panic!("amount is required for RecordSpend/ReverieDeposit"));
+        match self.action_type {
+            ActionType::RecordSpend => record_spend_call(reverie_id, user_id, amount),
+            ActionType::ReverieDeposit => (
+                "deposit_for".to_string(),
+                near_sdk::serde_json::to_vec(&DepositForArgs { reverie_id, user_id })
+                    .unwrap_or_else(|_| panic!("ERR_ARGS_SERIALIZATION")),
+                NearToken::from_yoctonear(amount.0),
+            ),
+            _ => panic!("{:?} is not a payments action", self.action_type),
+        }
+    }
+
+    /// Method name, JSON args and attached deposit of the staking pool call for
+    /// StakeWithPool/UnstakeFromPool/WithdrawFromPool actions. WithdrawFromPool withdraws
+    /// `amount` if set, and everything unstaked otherwise.
+    pub fn staking_pool_call(&self) -> (String, Vec<u8>, NearToken) {
+        match self.action_type {
+            ActionType::StakeWithPool => {
+                let amount = self
+                    .amount
+                    .unwrap_or_else(|| panic!("amount is required for StakeWithPool"));
+                assert!(amount.0 > 0, "amount must be greater than 0 for StakeWithPool");
+                ("deposit_and_stake".to_string(), b"{}".to_vec(), NearToken::from_yoctonear(amount.0))
+            }
+            ActionType::UnstakeFromPool => {
+                let amount = self
+                    .amount
+                    .unwrap_or_else(|| panic!("amount is required for UnstakeFromPool"));
+                (
+                    "unstake".to_string(),
+                    near_sdk::serde_json::to_vec(&UnstakeArgs { amount })
+                        .unwrap_or_else(|_| panic!("ERR_ARGS_SERIALIZATION")),
+                    NearToken::from_yoctonear(0),
+                )
+            }
+            ActionType::WithdrawFromPool => match self.amount {
+                Some(amount) => (
+                    "withdraw".to_string(),
+                    near_sdk::serde_json::to_vec(&UnstakeArgs { amount })
+                        .unwrap_or_else(|_| panic!("ERR_ARGS_SERIALIZATION")),
+                    NearToken::from_yoctonear(0),
+                ),
+                None => ("withdraw_all".to_string(), b"{}".to_vec(), NearToken::from_yoctonear(0)),
+            },
+            _ => panic!("{:?} is not a staking pool action", self.action_type),
+        }
+    }
+
+    /// Method name, JSON args and attached deposit of the key proxy call for AddKey/DeleteKey
+    /// actions on behalf of `user_id`. The runtime only lets an account change its own keys,
+    /// so the user's account must run a contract exposing `add_key`/`delete_key`.
+    pub fn key_proxy_call(&self) -> (String, Vec<u8>, NearToken) {
+        let public_key = self
+            .public_key
+            .clone()
+            .unwrap_or_else(|| panic!("public_key is required for {:?}", self.action_type));
+        let (method_name, args) = match self.action_type {
+            ActionType::AddKey => (
+                "add_key",
+                near_sdk::serde_json::to_vec(&AddKeyArgs {
+                    public_key,
+                    allowance: self.allowance.filter(|allowance| allowance.0 > 0),
+                    receiver_id: self
+                        .receiver_id
+                        .clone()
+                        .unwrap_or_else(|| panic!("receiver_id for allowance scope is required for AddKey")),
+                    method_names: self.method_names.clone().unwrap_or_default(),
+                }),
+            ),
+            ActionType::DeleteKey => ("delete_key", near_sdk::serde_json::to_vec(&DeleteKeyArgs { public_key })),
+            _ => panic!("{:?} is not a key management action", self.action_type),
+        };
+        (
+            method_name.to_string(),
+            args.unwrap_or_else(|_| panic!("ERR_ARGS_SERIALIZATION")),
+            NearToken::from_yoctonear(0),
+        )
+    }
+}
+
+// `record_spend` call charging `user_id` `amount` credits on `reverie_id`
+fn record_spend_call(reverie_id: String, user_id: AccountId, amount: U128) -> (String, Vec<u8>, NearToken) {
+    (
+        "record_spend".to_string(),
+        near_sdk::serde_json::to_vec(&RecordSpendArgs { reverie_id, user_id, amount_to_spend: amount, category: None })
+            .unwrap_or_else(|_| panic!("ERR_ARGS_SERIALIZATION")),
+        NearToken::from_yoctonear(0),
+    )
+}
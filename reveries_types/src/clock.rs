@@ -0,0 +1,61 @@
+use near_sdk::env;
+
+/// Overrides for the block timestamp and random seed read by the time-dependent subsystems
+/// of both contracts (quotas, challenges, sessions and the scheduler in the controller;
+/// cooldowns, disputes, vesting, permits, locks and the oracle in payments). Only compiled
+/// with the `testing` feature, so simulations can pin or advance time without rebuilding
+/// the mocked blockchain context.
+#[cfg(any(test, feature = "testing"))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TestHooks {
+    pub block_timestamp: Option<u64>, // nanoseconds
+    pub random_seed: Option<[u8; 32]>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+thread_local! {
+    static TEST_HOOKS: std::cell::RefCell<TestHooks> = std::cell::RefCell::new(TestHooks::default());
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl TestHooks {
+    /// Replaces the hooks of the current thread.
+    pub fn install(self) {
+        TEST_HOOKS.with(|hooks| *hooks.borrow_mut() = self);
+    }
+
+    /// Goes back to the values of the mocked blockchain context.
+    pub fn reset() {
+        TestHooks::default().install();
+    }
+
+    pub fn current() -> TestHooks {
+        TEST_HOOKS.with(|hooks| hooks.borrow().clone())
+    }
+
+    /// Moves the hooked clock forward, starting from the context's timestamp if it isn't pinned yet.
+    pub fn advance_time(ns: u64) {
+        TEST_HOOKS.with(|hooks| {
+            let mut hooks = hooks.borrow_mut();
+            hooks.block_timestamp = Some(hooks.block_timestamp.unwrap_or_else(env::block_timestamp) + ns);
+        });
+    }
+}
+
+/// `env::block_timestamp`, unless pinned by `TestHooks`.
+pub fn block_timestamp() -> u64 {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(timestamp) = TestHooks::current().block_timestamp {
+        return timestamp;
+    }
+    env::block_timestamp()
+}
+
+/// `env::random_seed_array`, unless pinned by `TestHooks`.
+pub fn random_seed() -> [u8; 32] {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(seed) = TestHooks::current().random_seed {
+        return seed;
+    }
+    env::random_seed_array()
+}
@@ -0,0 +1,16 @@
+//! Types shared by the `payments` and `passkey-controller` contracts and by off-chain
+//! relayers, so both sides serialize (JSON and Borsh) and describe (JSON Schema)
+//! reveries and actions from a single definition.
+
+pub mod action;
+pub mod clock;
+pub mod reverie;
+pub mod validation;
+pub mod versioned;
+#[cfg(test)]
+mod tests_reveries_types;
+
+pub use action::{ActionType, AddKeyArgs, DeleteKeyArgs, DepositForArgs, RecordSpendArgs, SerializableAction, UnstakeArgs};
+pub use reverie::{derive_reverie_id, normalize_reverie_id, AccessCondition, Denomination, ReverieId, ReverieListing, ReverieMetadata, ReverieMetadataV1, RoundingPolicy, MAX_REVERIE_ID_LEN};
+pub use validation::{FieldError, ValidationReport};
+pub use versioned::VersionedAction;
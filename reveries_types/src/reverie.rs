@@ -0,0 +1,191 @@
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+use near_sdk::AccountId;
+use near_sdk::json_types::{Base58CryptoHash, U128};
+use near_sdk::serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+
+pub type ReverieId = String;
+
+/// Max length of a `ReverieId`, keeping storage keys bounded.
+pub const MAX_REVERIE_ID_LEN: usize = 64;
+
+/// Lowercases `reverie_id` and checks it is 1..=MAX_REVERIE_ID_LEN characters of
+/// `a-z`, `0-9`, `-`, `_` or `.`, returning the normalized id.
+pub fn normalize_reverie_id(reverie_id: &str) -> Result<ReverieId, String> {
+    let normalized = reverie_id.to_lowercase();
+    if normalized.is_empty() || normalized.len() > MAX_REVERIE_ID_LEN {
+        return Err(format!(
+            "ReverieId must be between 1 and {} characters",
+            MAX_REVERIE_ID_LEN
+        ));
+    }
+    if let Some(c) = normalized
+        .chars()
+        .find(|c| !(c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '-' | '_' | '.')))
+    {
+        return Err(format!("ReverieId contains invalid character '{}'", c));
+    }
+    Ok(normalized)
+}
+
+/// Id `create_reverie_derived` gives a reverie: the base58 sha256 of its Borsh-serialized
+/// metadata, lowercased so it passes `normalize_reverie_id`. Clients can compute it before creation.
+pub fn derive_reverie_id(metadata: &ReverieMetadata) -> ReverieId {
+    let bytes = near_sdk::borsh::to_vec(metadata).unwrap_or_else(|_| panic!("ERR_METADATA_SERIALIZATION"));
+    String::from(&Base58CryptoHash::from(env::sha256_array(&bytes))).to_lowercase()
+}
+
+#[derive(JsonSchema, BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReverieMetadata {
+    pub reverie_type: String,
+    pub description: String,
+    pub access_condition: AccessCondition,
+    #[serde(default)]
+    pub rounding_policy: RoundingPolicy,
+    #[serde(default)]
+    pub denomination: Denomination,
+    #[serde(default, flatten)]
+    pub listing: ReverieListing,
+    /// Set by the payments contract while deposits and spends are blocked; withdrawals stay open.
+    #[serde(default)]
+    pub frozen: bool,
+    /// Integer credits one NEAR buys. When set, spends and withdrawals can be given in credits;
+    /// balances are still held in yoctoNEAR.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credits_per_near: Option<U128>,
+}
+
+/// `ReverieMetadata` as stored by the first deployed payments contract. Payments' `migrate`
+/// rewrites these entries in the current layout.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+pub struct ReverieMetadataV1 {
+    pub reverie_type: String,
+    pub description: String,
+    pub access_condition: AccessCondition,
+}
+
+impl From<ReverieMetadataV1> for ReverieMetadata {
+    fn from(metadata: ReverieMetadataV1) -> Self {
+        Self {
+            reverie_type: metadata.reverie_type,
+            description: metadata.description,
+            access_condition: metadata.access_condition,
+            rounding_policy: RoundingPolicy::default(),
+            denomination: Denomination::default(),
+            listing: ReverieListing::default(),
+            frozen: false,
+            credits_per_near: None,
+        }
+    }
+}
+
+pub const MAX_LISTING_URL_LEN: usize = 256;
+pub const MAX_LISTING_TAGS: usize = 10;
+pub const MAX_LISTING_TAG_LEN: usize = 32;
+
+/// Optional presentation fields for marketplaces. Serialized inline in `ReverieMetadata`'s JSON.
+#[derive(JsonSchema, BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReverieListing {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info_url: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl ReverieListing {
+    /// Checks URL lengths and that there are at most MAX_LISTING_TAGS distinct, non-empty tags
+    /// of at most MAX_LISTING_TAG_LEN characters.
+    pub fn check(&self) -> Result<(), String> {
+        for (field, url) in [("icon_url", &self.icon_url), ("info_url", &self.info_url)] {
+            if url.as_ref().is_some_and(|url| url.len() > MAX_LISTING_URL_LEN) {
+                return Err(format!("{} must be at most {} characters", field, MAX_LISTING_URL_LEN));
+            }
+        }
+        if self.tags.len() > MAX_LISTING_TAGS {
+            return Err(format!("At most {} tags are allowed", MAX_LISTING_TAGS));
+        }
+        for (index, tag) in self.tags.iter().enumerate() {
+            if tag.is_empty() || tag.len() > MAX_LISTING_TAG_LEN {
+                return Err(format!("Tags must be between 1 and {} characters", MAX_LISTING_TAG_LEN));
+            }
+            if self.tags[..index].contains(tag) {
+                return Err(format!("Duplicate tag '{}'", tag));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a reverie's balances are held in: NEAR (attached deposits), or a NEP-141
+/// token deposited with `ft_transfer_call` and withdrawn with `ft_transfer`.
+#[derive(JsonSchema, BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(tag = "type", crate = "near_sdk::serde")]
+pub enum Denomination {
+    #[default]
+    Near,
+    Ft { contract: AccountId },
+}
+
+#[derive(JsonSchema, BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(tag = "type", content = "value", crate = "near_sdk::serde")]
+pub enum AccessCondition {
+    Umbral(String), // Use String for public key serialization
+    Ecdsa(String), // Use String for address serialization
+    Ed25519(String),
+    Contract {
+        address: String,
+        access_function_name: String,
+        access_function_args: String, // Store as JSON string
+    },
+    /// Members of a Sputnik DAO group role, any group role if `role` is unset
+    DaoMember {
+        dao_contract: AccountId,
+        #[serde(default)]
+        role: Option<String>,
+    },
+}
+
+impl AccessCondition {
+    /// The condition's `type` tag, e.g. "Umbral".
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AccessCondition::Umbral(_) => "Umbral",
+            AccessCondition::Ecdsa(_) => "Ecdsa",
+            AccessCondition::Ed25519(_) => "Ed25519",
+            AccessCondition::Contract { .. } => "Contract",
+            AccessCondition::DaoMember { .. } => "DaoMember",
+        }
+    }
+}
+
+/// Per-reverie billing policy. Zero values disable rounding and dust consolidation.
+#[derive(JsonSchema, BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoundingPolicy {
+    pub spend_increment: U128, // spends are rounded up to a multiple of this many yoctoNEAR
+    pub dust_threshold: U128, // balances below this can be moved to the fee pool
+}
+
+impl RoundingPolicy {
+    /// Rounds `amount` up to the nearest multiple of `spend_increment`.
+    pub fn round_spend(&self, amount: u128) -> u128 {
+        let increment = self.spend_increment.0;
+        if increment <= 1 || amount % increment == 0 {
+            return amount;
+        }
+        (amount / increment + 1)
+            .checked_mul(increment)
+            .unwrap_or_else(|| env::panic_str("Rounded spend overflow"))
+    }
+}
@@ -0,0 +1,124 @@
+use super::*;
+use near_sdk::json_types::U128;
+
+#[test]
+fn test_access_condition_json_is_adjacently_tagged() {
+    let condition = AccessCondition::Umbral("pk".to_string());
+    let json = serde_json::to_value(&condition).unwrap();
+    assert_eq!(json, serde_json::json!({"type": "Umbral", "value": "pk"}));
+    assert_eq!(json["type"], condition.kind());
+}
+
+#[test]
+fn test_reverie_metadata_defaults_missing_rounding_policy() {
+    let metadata: ReverieMetadata = serde_json::from_value(serde_json::json!({
+        "reverie_type": "type1",
+        "description": "desc1",
+        "access_condition": {"type": "Ed25519", "value": "pk"}
+    }))
+    .unwrap();
+    assert_eq!(metadata.rounding_policy, RoundingPolicy::default());
+}
+
+#[test]
+fn test_round_spend() {
+    let policy = RoundingPolicy { spend_increment: U128(10), dust_threshold: U128(0) };
+    assert_eq!(policy.round_spend(21), 30);
+    assert_eq!(policy.round_spend(30), 30);
+    assert_eq!(RoundingPolicy::default().round_spend(21), 21);
+}
+
+#[test]
+fn test_normalize_reverie_id() {
+    assert_eq!(normalize_reverie_id("Rev_1.a-b"), Ok("rev_1.a-b".to_string()));
+    assert!(normalize_reverie_id("").is_err());
+    assert!(normalize_reverie_id("rev 1").is_err());
+    assert!(normalize_reverie_id(&"a".repeat(MAX_REVERIE_ID_LEN + 1)).is_err());
+}
+
+#[test]
+fn test_validate_action_reports_missing_fields() {
+    let action: SerializableAction = validation::parse_json(r#"{"action_type": "Transfer"}"#).unwrap();
+    let report = action.validate();
+    assert!(!report.valid);
+    let fields: Vec<_> = report.errors.iter().map(|e| e.field.as_str()).collect();
+    assert_eq!(fields, vec!["receiver_id", "amount"]);
+}
+
+#[test]
+fn test_validate_access_condition() {
+    let condition = AccessCondition::Contract {
+        address: "Not An Account".to_string(),
+        access_function_name: "has_access".to_string(),
+        access_function_args: "{".to_string(),
+    };
+    let report = condition.validate();
+    let fields: Vec<_> = report.errors.iter().map(|e| e.field.as_str()).collect();
+    assert_eq!(fields, vec!["address", "access_function_args"]);
+    assert!(AccessCondition::Ed25519("pk".to_string()).validate().valid);
+
+    let report = validation::parse_json::<AccessCondition>("{\"type\": \"Nope\"}").unwrap_err();
+    assert_eq!(report.errors[0].field, "$");
+}
+
+#[test]
+fn test_derive_reverie_id_is_deterministic_and_valid() {
+    let metadata = ReverieMetadata {
+        reverie_type: "type1".to_string(),
+        description: "desc1".to_string(),
+        access_condition: AccessCondition::Ed25519("pk".to_string()),
+        rounding_policy: RoundingPolicy::default(),
+        denomination: Denomination::Near,
+        listing: ReverieListing::default(),
+        frozen: false,
+        credits_per_near: None,
+    };
+    let reverie_id = derive_reverie_id(&metadata);
+    assert_eq!(reverie_id, derive_reverie_id(&metadata.clone()));
+    assert_eq!(normalize_reverie_id(&reverie_id), Ok(reverie_id.clone()));
+    let other = ReverieMetadata { description: "desc2".to_string(), ..metadata };
+    assert_ne!(derive_reverie_id(&other), reverie_id);
+}
+
+#[test]
+fn test_reverie_listing_is_flattened_into_metadata_json() {
+    let metadata: ReverieMetadata = serde_json::from_value(serde_json::json!({
+        "reverie_type": "type1",
+        "description": "desc1",
+        "access_condition": {"type": "Ed25519", "value": "pk"},
+        "icon_url": "https://example.com/icon.png",
+        "tags": ["ai", "music"]
+    }))
+    .unwrap();
+    assert_eq!(metadata.listing.icon_url.as_deref(), Some("https://example.com/icon.png"));
+    assert_eq!(metadata.listing.tags, vec!["ai".to_string(), "music".to_string()]);
+    let json = serde_json::to_value(&metadata).unwrap();
+    assert_eq!(json["tags"][1], "music");
+    assert!(json.get("info_url").is_none());
+}
+
+#[test]
+fn test_reverie_listing_check() {
+    assert!(ReverieListing::default().check().is_ok());
+    let duplicate = ReverieListing { tags: vec!["ai".to_string(), "ai".to_string()], ..Default::default() };
+    assert!(duplicate.check().is_err());
+    let long_url = ReverieListing { info_url: Some("a".repeat(reverie::MAX_LISTING_URL_LEN + 1)), ..Default::default() };
+    assert!(long_url.check().is_err());
+}
+
+#[test]
+fn test_function_call_args_json() {
+    let action: SerializableAction = validation::parse_json(
+        r#"{"action_type": "FunctionCall", "receiver_id": "app.near", "method_name": "play", "args_json": "{\"level\": 2}"}"#,
+    )
+    .unwrap();
+    assert!(action.validate().valid);
+    assert_eq!(action.call_args(), br#"{"level": 2}"#.to_vec());
+
+    let both = SerializableAction { args: Some(near_sdk::json_types::Base64VecU8(b"{}".to_vec())), ..action.clone() };
+    assert_eq!(both.validate().errors[0].message, "args and args_json are mutually exclusive");
+    let invalid = SerializableAction { args_json: Some("{level".to_string()), ..action };
+    let report = invalid.validate();
+    assert_eq!(report.errors[0].field, "args_json");
+    assert!(report.errors[0].message.starts_with("args_json must be valid JSON"));
+}
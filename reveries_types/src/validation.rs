@@ -0,0 +1,150 @@
+use crate::action::{ActionType, SerializableAction};
+use crate::reverie::AccessCondition;
+use near_sdk::serde::de::DeserializeOwned;
+use near_sdk::AccountId;
+
+/// One problem found in user-provided JSON. `field` is the offending field's name,
+/// or "$" when the JSON couldn't be parsed at all.
+#[near_sdk::near(serializers = [json])]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Result of linting a request before it is submitted on-chain.
+#[near_sdk::near(serializers = [json])]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationReport {
+    pub fn from_errors(errors: Vec<FieldError>) -> Self {
+        Self { valid: errors.is_empty(), errors }
+    }
+
+    pub fn push(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError { field: field.to_string(), message: message.into() });
+        self.valid = false;
+    }
+}
+
+/// Parses `json`, reporting a parse failure as a single "$" error.
+pub fn parse_json<T: DeserializeOwned>(json: &str) -> Result<T, ValidationReport> {
+    near_sdk::serde_json::from_str(json).map_err(|err| {
+        ValidationReport::from_errors(vec![FieldError { field: "$".to_string(), message: err.to_string() }])
+    })
+}
+
+fn require(errors: &mut Vec<FieldError>, present: bool, field: &str, action_type: &str) {
+    if !present {
+        errors.push(FieldError {
+            field: field.to_string(),
+            message: format!("{} is required for {}", field, action_type),
+        });
+    }
+}
+
+impl SerializableAction {
+    /// Checks the fields each action type needs, mirroring the checks made when the
+    /// action is executed.
+    pub fn validate(&self) -> ValidationReport {
+        let mut errors = Vec::new();
+        let errs = &mut errors;
+        match &self.action_type {
+            ActionType::CreateAccount => {
+                require(errs, self.receiver_id.is_some(), "receiver_id", "CreateAccount");
+                if self.method_name.is_some() {
+                    require(errs, self.code.is_some(), "code", "CreateAccount init call");
+                }
+                self.validate_args_json(errs);
+            }
+            ActionType::DeployContract => require(errs, self.code.is_some(), "code", "DeployContract"),
+            ActionType::FunctionCall => {
+                require(errs, self.receiver_id.is_some(), "receiver_id", "FunctionCall");
+                require(errs, self.method_name.as_deref().is_some_and(|m| !m.is_empty()), "method_name", "FunctionCall");
+                if let Some(args) = &self.args {
+                    if near_sdk::serde_json::from_slice::<near_sdk::serde_json::Value>(&args.0).is_err() {
+                        errs.push(FieldError { field: "args".to_string(), message: "args must be base64-encoded JSON".to_string() });
+                    }
+                }
+                self.validate_args_json(errs);
+            }
+            ActionType::Transfer => {
+                require(errs, self.receiver_id.is_some(), "receiver_id", "Transfer");
+                require(errs, self.amount.is_some(), "amount", "Transfer");
+            }
+            ActionType::Stake => {
+                require(errs, self.stake.is_some(), "stake", "Stake");
+                require(errs, self.public_key.is_some(), "public_key", "Stake");
+            }
+            ActionType::AddKey => {
+                require(errs, self.public_key.is_some(), "public_key", "AddKey");
+                require(errs, self.receiver_id.is_some(), "receiver_id", "AddKey");
+            }
+            ActionType::DeleteKey => require(errs, self.public_key.is_some(), "public_key", "DeleteKey"),
+            ActionType::DeleteAccount => require(errs, self.beneficiary_id.is_some(), "beneficiary_id", "DeleteAccount"),
+            ActionType::RecordSpend | ActionType::ReverieDeposit => {
+                require(errs, self.reverie_id.is_some(), "reverie_id", "RecordSpend/ReverieDeposit");
+                require(errs, self.user_id.is_some(), "user_id", "RecordSpend/ReverieDeposit");
+                require(errs, self.amount.is_some(), "amount", "RecordSpend/ReverieDeposit");
+            }
+            ActionType::ChargeReverie { reverie_id, amount, .. } => {
+                require(errs, !reverie_id.is_empty(), "reverie_id", "ChargeReverie");
+                if amount.0 == 0 {
+                    errs.push(FieldError { field: "amount".to_string(), message: "amount must be greater than 0 for ChargeReverie".to_string() });
+                }
+            }
+            ActionType::StakeWithPool | ActionType::UnstakeFromPool => {
+                require(errs, self.receiver_id.is_some(), "receiver_id", "StakeWithPool/UnstakeFromPool");
+                require(errs, self.amount.is_some(), "amount", "StakeWithPool/UnstakeFromPool");
+            }
+            ActionType::WithdrawFromPool => require(errs, self.receiver_id.is_some(), "receiver_id", "WithdrawFromPool"),
+        }
+        ValidationReport::from_errors(errors)
+    }
+
+    fn validate_args_json(&self, errors: &mut Vec<FieldError>) {
+        let Some(args_json) = &self.args_json else {
+            return;
+        };
+        if self.args.is_some() {
+            errors.push(FieldError { field: "args_json".to_string(), message: "args and args_json are mutually exclusive".to_string() });
+        }
+        if let Err(err) = near_sdk::serde_json::from_str::<near_sdk::serde_json::Value>(args_json) {
+            errors.push(FieldError { field: "args_json".to_string(), message: format!("args_json must be valid JSON: {}", err) });
+        }
+    }
+}
+
+impl AccessCondition {
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::from_errors(vec![]);
+        match self {
+            AccessCondition::Umbral(key) | AccessCondition::Ecdsa(key) | AccessCondition::Ed25519(key) => {
+                if key.is_empty() {
+                    report.push("value", format!("{} key must not be empty", self.kind()));
+                }
+            }
+            AccessCondition::Contract { address, access_function_name, access_function_args } => {
+                if address.parse::<AccountId>().is_err() {
+                    report.push("address", format!("{} is not a valid account id", address));
+                }
+                if access_function_name.is_empty() {
+                    report.push("access_function_name", "access_function_name must not be empty");
+                }
+                if near_sdk::serde_json::from_str::<near_sdk::serde_json::Value>(access_function_args).is_err() {
+                    report.push("access_function_args", "access_function_args must be a JSON string");
+                }
+            }
+            AccessCondition::DaoMember { role, .. } => {
+                if role.as_ref().is_some_and(|role| role.is_empty()) {
+                    report.push("role", "role must not be empty");
+                }
+            }
+        }
+        report
+    }
+}
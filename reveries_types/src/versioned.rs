@@ -0,0 +1,63 @@
+use crate::SerializableAction;
+use near_sdk::borsh::{BorshDeserialize, BorshSerialize};
+use near_sdk::serde::de::Error as _;
+use near_sdk::serde::{Deserialize, Deserializer, Serialize};
+use schemars::JsonSchema;
+
+/// Version-tagged `SerializableAction`, so future action schemas can be added
+/// without breaking relayers that still send older versions.
+///
+/// JSON: `{ "version": "v1", "action": { ... } }`. Borsh: a version index byte
+/// (0 for V1) followed by the action. Unknown versions fail to decode with
+/// `unsupported action version` instead of being misread as another version.
+#[derive(Debug, Clone, BorshSerialize, Serialize, JsonSchema)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde", tag = "version", content = "action", rename_all = "snake_case")]
+pub enum VersionedAction {
+    V1(SerializableAction),
+}
+
+impl VersionedAction {
+    /// Upgrades the action to the latest `SerializableAction`.
+    pub fn into_latest(self) -> SerializableAction {
+        match self {
+            VersionedAction::V1(action) => action,
+        }
+    }
+}
+
+impl From<SerializableAction> for VersionedAction {
+    fn from(action: SerializableAction) -> Self {
+        VersionedAction::V1(action)
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionedAction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(crate = "near_sdk::serde")]
+        struct Tagged {
+            version: String,
+            action: near_sdk::serde_json::Value,
+        }
+        let tagged = Tagged::deserialize(deserializer)?;
+        match tagged.version.as_str() {
+            "v1" => near_sdk::serde_json::from_value(tagged.action)
+                .map(VersionedAction::V1)
+                .map_err(D::Error::custom),
+            other => Err(D::Error::custom(format!("unsupported action version: {}", other))),
+        }
+    }
+}
+
+impl BorshDeserialize for VersionedAction {
+    fn deserialize_reader<R: near_sdk::borsh::io::Read>(reader: &mut R) -> near_sdk::borsh::io::Result<Self> {
+        match u8::deserialize_reader(reader)? {
+            0 => Ok(VersionedAction::V1(SerializableAction::deserialize_reader(reader)?)),
+            index => Err(near_sdk::borsh::io::Error::new(
+                near_sdk::borsh::io::ErrorKind::InvalidData,
+                format!("unsupported action version index: {}", index),
+            )),
+        }
+    }
+}
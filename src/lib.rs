@@ -1,2 +1,66 @@
-// pub mod payments; // Temporarily commented out to isolate PasskeyController build
-pub mod passkey_controller;
+//! Client library for off-chain services (relayers, indexers, backends) that talk to
+//! the reveries contracts. Re-exports the contract types so requests and views can be
+//! built and parsed with the same definitions the contracts use.
+//!
+//! The shared types from `reveries-types` are always available at the crate root. Each
+//! contract's own types are behind a feature of the same name, all enabled by default.
+
+#[cfg(target_arch = "wasm32")]
+compile_error!(
+    "near-reveries is an off-chain client library. Build the `payments`, `passkey-controller` or `passkey-wallet` crates for wasm instead."
+);
+
+#[cfg(feature = "payments")]
+pub mod payments {
+    pub use ::payments::access_cache::GrantRecord;
+    pub use ::payments::balance_detail::BalanceDetail;
+    pub use ::payments::disputes::{Dispute, SpendRecord};
+    pub use ::payments::events::{PaymentEvent, RecordedEvent};
+    pub use ::payments::init_config::{InitConfig, ReverieSeed};
+    pub use ::payments::ledger::LedgerCheckpoint;
+    pub use ::payments::membership::{MembershipToken, NFTContractMetadata};
+    pub use ::payments::oracle::PriceOracleConfig;
+    pub use ::payments::stats::DailyStats;
+    pub use ::payments::storage::StorageCostEstimate;
+    pub use ::payments::permits::{SpendPermit, SpendPermitPayload};
+    pub use ::payments::revocations::Revocation;
+    pub use ::payments::rounding::RoundingPolicy;
+    pub use ::payments::umbral::{ReencryptionGrant, UmbralPublicKeys};
+    pub use ::payments::vesting::VestingSchedule;
+    pub use ::payments::views::{AdmissionTicket, ReverieView};
+    pub use ::payments::{AccessCondition, Denomination, ReverieId, ReverieMetadata};
+}
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+#[cfg(feature = "passkey-controller")]
+pub mod passkey_controller {
+    pub use ::passkey_controller::action_caps::ActionCaps;
+    pub use ::passkey_controller::cost_estimate::CostEstimate;
+    pub use ::passkey_controller::envelope::{ActionPayload, SignedActionEnvelope};
+    pub use ::passkey_controller::granted_keys::GrantedKey;
+    pub use ::passkey_controller::init_config::InitConfig;
+    pub use ::passkey_controller::jobs::JobRecord;
+    pub use ::passkey_controller::passkey_keys::{normalize_passkey_pk, validate_passkey_pk, PasskeyKeyError};
+    pub use ::passkey_controller::policy_hooks::PolicyHookConfig;
+    pub use ::passkey_controller::receipts::{compute_request_id, ExecutionReceipt, ExecutionStatus};
+    pub use ::passkey_controller::relayer_failover::RelayerFailoverConfig;
+    pub use ::passkey_controller::relayer_fees::RelayerFeeConfig;
+    pub use ::passkey_controller::result_callbacks::ResultCallback;
+    pub use ::passkey_controller::templates::{CallTemplate, TemplateParams};
+    pub use ::passkey_controller::versioned::VersionedAction;
+    pub use ::passkey_controller::{ActionType, SerializableAction};
+}
+
+#[cfg(feature = "passkey-wallet")]
+pub mod passkey_wallet {
+    pub use ::passkey_wallet::envelope::{SignedWalletRequest, WalletActionPayload};
+    pub use ::passkey_wallet::webauthn::WebAuthnAssertion;
+}
+
+pub use reveries_types::{
+    AccessCondition, ActionType, AddKeyArgs, DeleteKeyArgs, Denomination, DepositForArgs, FieldError,
+    RecordSpendArgs, ReverieId, ReverieListing, ReverieMetadata, RoundingPolicy, SerializableAction, ValidationReport,
+    VersionedAction, derive_reverie_id,
+};
@@ -0,0 +1,40 @@
+//! Receipt assertions for near-workspaces tests of the reveries contracts.
+//!
+//! A delegated action runs in receipts after `execute_delegated_actions` returns, and the
+//! controller's result callback succeeds even when the action itself fails, so the
+//! transaction outcome alone doesn't show whether the action took effect.
+
+use near_workspaces::result::{ExecutionFinalResult, ExecutionOutcome};
+use near_workspaces::AccountId;
+
+/// Receipt outcomes executed on `executor_id`.
+pub fn receipts_on<'a>(outcome: &'a ExecutionFinalResult, executor_id: &AccountId) -> Vec<&'a ExecutionOutcome> {
+    outcome
+        .receipt_outcomes()
+        .iter()
+        .filter(|receipt| &receipt.executor_id == executor_id)
+        .collect()
+}
+
+/// Panics if any receipt of the transaction failed.
+pub fn assert_no_receipt_failures(outcome: &ExecutionFinalResult) {
+    let failures = outcome.receipt_failures();
+    assert!(failures.is_empty(), "{} receipt(s) failed: {:#?}", failures.len(), failures);
+}
+
+/// Panics unless at least one receipt executed on `executor_id` and all of them succeeded.
+pub fn assert_receipts_succeeded_on(outcome: &ExecutionFinalResult, executor_id: &AccountId) {
+    let receipts = receipts_on(outcome, executor_id);
+    assert!(!receipts.is_empty(), "No receipt was executed on {}", executor_id);
+    for receipt in receipts {
+        assert!(receipt.is_success(), "Receipt on {} failed: {:#?}", executor_id, receipt);
+    }
+}
+
+/// Logs emitted by receipts executed on `executor_id`, in execution order.
+pub fn logs_on(outcome: &ExecutionFinalResult, executor_id: &AccountId) -> Vec<String> {
+    receipts_on(outcome, executor_id)
+        .into_iter()
+        .flat_map(|receipt| receipt.logs.clone())
+        .collect()
+}
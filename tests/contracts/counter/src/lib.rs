@@ -0,0 +1,26 @@
+use near_sdk::{env, near, AccountId};
+
+/// Counts calls so tests can check a delegated FunctionCall really changed target state.
+#[near(contract_state)]
+#[derive(Default)]
+pub struct Counter {
+    value: u64,
+    last_caller: Option<AccountId>,
+}
+
+#[near]
+impl Counter {
+    pub fn increment(&mut self, by: Option<u64>) -> u64 {
+        self.value += by.unwrap_or(1);
+        self.last_caller = Some(env::predecessor_account_id());
+        self.value
+    }
+
+    pub fn get_value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn get_last_caller(&self) -> Option<AccountId> {
+        self.last_caller.clone()
+    }
+}
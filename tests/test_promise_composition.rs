@@ -0,0 +1,103 @@
+//! Checks that delegated actions really change state on their targets, by inspecting
+//! receipt outcomes and target state rather than only the transaction status.
+//! Run with `cargo test --features testing --test test_promise_composition`.
+
+use base64::Engine;
+use near_reveries::testing::{assert_no_receipt_failures, assert_receipts_succeeded_on};
+use near_workspaces::types::{KeyType, NearToken, SecretKey};
+use serde_json::json;
+
+#[tokio::test]
+async fn test_delegated_actions_mutate_targets() -> Result<(), Box<dyn std::error::Error>> {
+    let controller_wasm = near_workspaces::compile_project("./passkey_controller").await?;
+    let counter_wasm = near_workspaces::compile_project("./tests/contracts/counter").await?;
+    let sandbox = near_workspaces::sandbox().await?;
+    let controller = sandbox.dev_deploy(&controller_wasm).await?;
+    let counter = sandbox.dev_deploy(&counter_wasm).await?;
+    let relayer = sandbox.dev_create_account().await?;
+    let owner = sandbox.dev_create_account().await?;
+    let user = sandbox.dev_create_account().await?;
+    let passkey_pk = SecretKey::from_random(KeyType::ED25519).public_key().to_string();
+
+    let init_outcome = controller
+        .call("new")
+        .args_json(json!({
+            "trusted_relayer_account_id": relayer.id(),
+            "owner_id": owner.id(),
+            "initial_passkey_pks": [passkey_pk],
+        }))
+        .transact()
+        .await?;
+    assert!(init_outcome.is_success(), "{:#?}", init_outcome.into_result().unwrap_err());
+    let allow_outcome = owner
+        .call(controller.id(), "add_allowed_call")
+        .args_json(json!({"receiver_id": counter.id(), "method_names": ["increment"]}))
+        .transact()
+        .await?;
+    assert!(allow_outcome.is_success());
+
+    // FunctionCall: the counter is incremented, by the controller
+    let outcome = relayer
+        .call(controller.id(), "execute_delegated_actions")
+        .args_json(json!({
+            "passkey_pk_used": passkey_pk,
+            "action_to_execute": {
+                "action_type": "FunctionCall",
+                "receiver_id": counter.id(),
+                "method_name": "increment",
+                "args": base64::engine::general_purpose::STANDARD.encode(json!({"by": 5}).to_string()),
+                "gas": "10000000000000",
+            },
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert_no_receipt_failures(&outcome);
+    assert_receipts_succeeded_on(&outcome, counter.id());
+    assert_eq!(counter.view("get_value").await?.json::<u64>()?, 5);
+    assert_eq!(counter.view("get_last_caller").await?.json::<Option<String>>()?, Some(controller.id().to_string()));
+
+    // Transfer: the receiver's balance grows by exactly the amount
+    let balance_before = user.view_account().await?.balance;
+    let amount = NearToken::from_near(1);
+    let outcome = relayer
+        .call(controller.id(), "execute_delegated_actions")
+        .args_json(json!({
+            "passkey_pk_used": passkey_pk,
+            "action_to_execute": {"action_type": "Transfer", "receiver_id": user.id(), "amount": amount.as_yoctonear().to_string()},
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert_no_receipt_failures(&outcome);
+    assert_receipts_succeeded_on(&outcome, user.id());
+    let balance_after = user.view_account().await?.balance;
+    assert_eq!(balance_after.as_yoctonear() - balance_before.as_yoctonear(), amount.as_yoctonear());
+
+    // AddKey: the function call key is added to the controller account
+    let new_key = SecretKey::from_random(KeyType::ED25519).public_key();
+    let outcome = relayer
+        .call(controller.id(), "execute_delegated_actions")
+        .args_json(json!({
+            "passkey_pk_used": passkey_pk,
+            "action_to_execute": {
+                "action_type": "AddKey",
+                "public_key": new_key.to_string(),
+                "receiver_id": counter.id(),
+                "method_names": ["increment"],
+            },
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert_no_receipt_failures(&outcome);
+    let keys = sandbox.view_access_keys(controller.id()).await?;
+    assert!(
+        keys.iter().any(|key| key.public_key.to_string() == new_key.to_string()),
+        "AddKey did not add {} to {}",
+        new_key,
+        controller.id()
+    );
+
+    Ok(())
+}